@@ -1,16 +1,136 @@
 //! CLI command implementations.
 
-use arbor_graph::compute_centrality;
+use arbor_core::{ArborConfig, ArchitectureRules, GeneratedPolicy, Workspace};
+use arbor_graph::{
+    compute_centrality, parse_unified_diff, ArborGraph, Baseline, BodyQuery, CentralityOptions,
+    ClusterOptions, CollapseOptions, Confidence, CycleOptions, DiffOptions, FilePatch, FindingSet,
+    GraphDiff, GraphStore, MetricsOptions, NodeFilter, QueryLimits, ToFindings,
+};
 use arbor_server::{ArborServer, ServerConfig};
-use arbor_watcher::index_directory;
+use arbor_watcher::{
+    index_directory_with_options, index_revision, index_workspace, load_or_index_revision,
+    FileWatcher, GitRepo, IncrementalIndexer, IndexOptions, IndexPhase, IndexProgress, IndexResult,
+    DEFAULT_DEBOUNCE,
+};
 use colored::Colorize;
 use indicatif::{ProgressBar, ProgressStyle};
 use std::fs;
+use std::io::{IsTerminal, Read};
 use std::path::Path;
 use std::time::Duration;
 
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
+/// How commands with a text and a JSON form print their results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum DataFormat {
+    Text,
+    Json,
+}
+
+/// How report-style commands print their results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    Text,
+    Json,
+    Markdown,
+}
+
+/// How table-like reports print their results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum TableFormat {
+    Text,
+    Json,
+    Csv,
+}
+
+/// How collapsed graphs print.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum GraphFormat {
+    Text,
+    Json,
+    /// Graphviz DOT
+    Dot,
+    /// Mermaid flowchart
+    Mermaid,
+}
+
+/// How commands that report findings print them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ReportFormat {
+    Text,
+    Json,
+    /// SARIF 2.1.0, for code scanning uploads
+    Sarif,
+    /// Checkstyle XML, for CI dashboards
+    Checkstyle,
+}
+
+/// Every `--format` has `--json` as a shorthand for `--format json`.
+macro_rules! json_shorthand {
+    ($($format:ty),*) => {$(
+        impl $format {
+            /// Returns `Json` if `--json` was given, else the `--format` value.
+            pub fn or_json(self, json: bool) -> Self {
+                if json {
+                    Self::Json
+                } else {
+                    self
+                }
+            }
+        }
+    )*};
+}
+
+json_shorthand!(
+    DataFormat,
+    OutputFormat,
+    TableFormat,
+    GraphFormat,
+    ReportFormat
+);
+
+/// Prints a report in a machine-readable format. Returns false for text,
+/// which each command prints itself.
+fn print_report<R: ToFindings + serde::Serialize>(
+    report: &R,
+    format: ReportFormat,
+) -> Result<bool> {
+    let mut findings = FindingSet::new();
+    match format {
+        ReportFormat::Text => return Ok(false),
+        ReportFormat::Json => println!("{}", serde_json::to_string_pretty(report)?),
+        ReportFormat::Sarif => println!(
+            "{}",
+            serde_json::to_string_pretty(&findings.add(report).to_sarif())?
+        ),
+        ReportFormat::Checkstyle => print!("{}", findings.add(report).to_checkstyle()),
+    }
+    Ok(true)
+}
+
+/// Loads `.arbor/config.json` for a project, or the defaults if there is none.
+fn load_config(path: &Path) -> Result<ArborConfig> {
+    Ok(ArborConfig::load(path)?)
+}
+
+/// Indexes a project, or every root if `path` holds a workspace manifest.
+fn index_project(path: &Path, config: &ArborConfig, options: &IndexOptions) -> Result<IndexResult> {
+    match Workspace::load(path)? {
+        Some(workspace) => Ok(index_workspace(&workspace, options)?),
+        None => Ok(index_directory_with_options(path, config, options)?),
+    }
+}
+
+/// Returns the graph of a git revision, from the project store if it was
+/// indexed before. The working tree isn't touched.
+fn revision_graph(path: &Path, rev: &str) -> Result<ArborGraph> {
+    let repo = GitRepo::open(path)?;
+    let store = GraphStore::open(GraphStore::path_for(repo.root()))?;
+    let stored = load_or_index_revision(&repo, rev, &store, &IndexOptions::default())?;
+    Ok(stored.graph)
+}
+
 /// Initialize Arbor in a directory.
 pub fn init(path: &Path) -> Result<()> {
     let arbor_dir = path.join(".arbor");
@@ -20,17 +140,15 @@ pub fn init(path: &Path) -> Result<()> {
         return Ok(());
     }
 
-    fs::create_dir_all(&arbor_dir)?;
-
-    // Create a default config file
-    let config_path = arbor_dir.join("config.json");
-    let default_config = serde_json::json!({
-        "version": "1.0",
-        "languages": ["typescript", "rust", "python"],
-        "ignore": ["node_modules", "target", "dist", "__pycache__"]
-    });
-
-    fs::write(&config_path, serde_json::to_string_pretty(&default_config)?)?;
+    // Create a default config file, listing every language so it's easy to prune
+    let default_config = ArborConfig {
+        languages: arbor_core::languages::supported_languages()
+            .iter()
+            .map(|l| l.to_string())
+            .collect(),
+        ..Default::default()
+    };
+    default_config.save(path)?;
 
     println!("{} Initialized Arbor in {}", "✓".green(), path.display());
     println!("  Run {} to index your codebase", "arbor index".cyan());
@@ -39,7 +157,10 @@ pub fn init(path: &Path) -> Result<()> {
 }
 
 /// Index a directory and build the code graph.
-pub fn index(path: &Path, output: Option<&Path>) -> Result<()> {
+pub fn index(path: &Path, output: Option<&Path>, rev: Option<&str>) -> Result<()> {
+    if let Some(rev) = rev {
+        return index_rev(path, rev, output);
+    }
     println!("{}", "Indexing codebase...".cyan());
 
     let spinner = ProgressBar::new_spinner();
@@ -47,7 +168,23 @@ pub fn index(path: &Path, output: Option<&Path>) -> Result<()> {
     spinner.enable_steady_tick(Duration::from_millis(80));
     spinner.set_message("Scanning files...");
 
-    let result = index_directory(path)?;
+    let config = load_config(path)?;
+    let progress_spinner = spinner.clone();
+    let root = path.to_path_buf();
+    let options = IndexOptions::new().with_progress(move |p: &IndexProgress| {
+        let message = match (p.phase, &p.current_file) {
+            (IndexPhase::Parsing, Some(file)) => format!(
+                "Parsing {}/{} {}",
+                p.files_processed,
+                p.files_total,
+                file.strip_prefix(&root).unwrap_or(file).display()
+            ),
+            (IndexPhase::Linking, _) => "Resolving references...".to_string(),
+            _ => return,
+        };
+        progress_spinner.set_message(message);
+    });
+    let mut result = index_project(path, &config, &options)?;
 
     spinner.finish_and_clear();
 
@@ -60,6 +197,14 @@ pub fn index(path: &Path, output: Option<&Path>) -> Result<()> {
         result.duration_ms
     );
 
+    for root in result.graph.roots() {
+        println!(
+            "  {} {}",
+            format!("{}/", root.namespace).cyan(),
+            root.path.display().to_string().dimmed()
+        );
+    }
+
     // Show any errors
     if !result.errors.is_empty() {
         println!("\n{} files with parse errors:", "⚠".yellow());
@@ -71,24 +216,94 @@ pub fn index(path: &Path, output: Option<&Path>) -> Result<()> {
         }
     }
 
+    if !result.skipped.is_empty() {
+        println!(
+            "{} Skipped {} files (size limit, encoding or generated)",
+            "•".blue(),
+            result.skipped.len()
+        );
+    }
+
+    if !result.generated.is_empty() {
+        let action = match config.generated {
+            GeneratedPolicy::Skip => "skipped",
+            GeneratedPolicy::Tag => "tagged",
+            GeneratedPolicy::Index => "indexed",
+        };
+        println!(
+            "{} {} generated or minified files ({})",
+            "•".blue(),
+            result.generated.len(),
+            action
+        );
+    }
+
+    if !result.transcoded.is_empty() {
+        let lossy = result.transcoded.iter().filter(|t| t.lossy).count();
+        println!(
+            "{} Decoded {} non-UTF-8 files ({} with replaced bytes)",
+            "•".blue(),
+            result.transcoded.len(),
+            lossy
+        );
+    }
+
     // Export if requested
     if let Some(out_path) = output {
-        export_graph(&result.graph, out_path)?;
+        export_graph(&mut result.graph, out_path)?;
+    }
+
+    Ok(())
+}
+
+/// Indexes a git revision into the project store.
+fn index_rev(path: &Path, rev: &str, output: Option<&Path>) -> Result<()> {
+    let repo = GitRepo::open(path)?;
+    println!("{} {}", "Indexing revision".cyan(), rev.cyan().bold());
+
+    let mut indexed = index_revision(&repo, rev, &IndexOptions::default())?;
+    let store = GraphStore::open(GraphStore::path_for(repo.root()))?;
+    store.save_revision(&indexed.info(), &indexed.result.graph)?;
+
+    let result = &indexed.result;
+    println!(
+        "{} Indexed {} files ({} nodes) at {} in {}ms",
+        "✓".green(),
+        result.files_indexed.to_string().cyan(),
+        result.nodes_extracted.to_string().cyan(),
+        indexed.commit[..12.min(indexed.commit.len())].yellow(),
+        result.duration_ms
+    );
+    if !result.errors.is_empty() {
+        println!(
+            "{} {} files with parse errors",
+            "⚠".yellow(),
+            result.errors.len()
+        );
     }
 
+    if let Some(out_path) = output {
+        export_graph(&mut indexed.result.graph, out_path)?;
+    }
     Ok(())
 }
 
-fn export_graph(graph: &arbor_graph::ArborGraph, path: &Path) -> Result<()> {
+fn export_graph(graph: &mut arbor_graph::ArborGraph, path: &Path) -> Result<()> {
+    let scores = compute_centrality(graph, 20, 0.85);
+    graph.set_centrality(scores.into_map());
+    let report = graph.assign_clusters(&ClusterOptions::default());
     let nodes: Vec<_> = graph.nodes().collect();
 
     let export = serde_json::json!({
         "version": "1.0",
         "stats": {
             "nodeCount": graph.node_count(),
-            "edgeCount": graph.edge_count()
+            "edgeCount": graph.edge_count(),
+            "clusterCount": report.clusters.len()
         },
-        "nodes": nodes
+        "nodes": nodes,
+        "clusters": report.clusters,
+        "clusterEdges": report.edges
     });
 
     fs::write(path, serde_json::to_string_pretty(&export)?)?;
@@ -98,27 +313,51 @@ fn export_graph(graph: &arbor_graph::ArborGraph, path: &Path) -> Result<()> {
 }
 
 /// Query the code graph.
-pub fn query(query: &str, limit: usize) -> Result<()> {
+pub fn query(
+    query: &str,
+    limit: Option<usize>,
+    filter: &NodeFilter,
+    rev: Option<&str>,
+) -> Result<()> {
     // For now, we need to re-index. In a real implementation,
     // we'd load from a persisted graph or connect to the server.
     let path = std::env::current_dir()?;
-    let result = index_directory(&path)?;
-
-    let matches: Vec<_> = result.graph.search(query).into_iter().take(limit).collect();
+    let config = load_config(&path)?;
+    let limit = limit.unwrap_or(config.defaults.search_limit);
+    let matcher = filter.compile()?;
+    let mut graph = match rev {
+        Some(rev) => revision_graph(&path, rev)?,
+        None => index_project(&path, &config, &IndexOptions::default())?.graph,
+    };
+    let scores = compute_centrality(&graph, 20, 0.85);
+    graph.set_centrality(scores.into_map());
 
-    if matches.is_empty() {
+    let hits = graph.search_ranked_filtered(query, &matcher);
+    if hits.is_empty() {
         println!("No matches found for \"{}\"", query);
         return Ok(());
     }
 
-    println!("Found {} matches:\n", matches.len());
+    if hits.len() > limit {
+        println!(
+            "Found {} matches, showing the best {}:\n",
+            hits.len(),
+            limit
+        );
+    } else {
+        println!("Found {} matches:\n", hits.len());
+    }
 
-    for node in matches {
+    for hit in hits.iter().take(limit) {
+        let Some(node) = graph.get(hit.id) else {
+            continue;
+        };
         println!(
-            "  {} {} {}",
+            "  {} {} {} {}",
             node.kind.to_string().yellow(),
             node.qualified_name.cyan(),
-            format!("({}:{})", node.file, node.line_start).dimmed()
+            format!("({}:{})", node.file, node.line_start).dimmed(),
+            format!("{:.2}", hit.score).dimmed()
         );
         if let Some(ref sig) = node.signature {
             println!("    {}", sig.dimmed());
@@ -128,6 +367,160 @@ pub fn query(query: &str, limit: usize) -> Result<()> {
     Ok(())
 }
 
+/// Searches symbol bodies in the working tree or a revision. The body
+/// index is built for this run whatever the config says.
+pub fn grep(query: &BodyQuery, rev: Option<&str>, format: DataFormat) -> Result<()> {
+    let path = std::env::current_dir()?;
+    let config = load_config(&path)?;
+    let options = IndexOptions::default().with_body_index();
+    // Stored revisions don't keep file text, so index the commit afresh
+    let graph = match rev {
+        Some(rev) => {
+            index_revision(&GitRepo::open(&path)?, rev, &options)?
+                .result
+                .graph
+        }
+        None => index_project(&path, &config, &options)?.graph,
+    };
+
+    let result = graph.search_bodies(query)?;
+    if format == DataFormat::Json {
+        println!("{}", serde_json::to_string_pretty(&result)?);
+        return Ok(());
+    }
+    if result.matches.is_empty() {
+        println!("No matches found for \"{}\"", query.pattern);
+        return Ok(());
+    }
+
+    println!(
+        "Found {} hits in {} nodes{}:\n",
+        result.total_hits,
+        result.matches.len(),
+        if result.truncated { " (truncated)" } else { "" }
+    );
+    for m in &result.matches {
+        println!(
+            "  {} {} {}",
+            m.node.kind.yellow(),
+            m.node.qualified_name.cyan(),
+            format!("({}:{})", m.node.file, m.node.line_start).dimmed()
+        );
+        for hit in &m.hits {
+            println!(
+                "    {} {}",
+                format!("{:>5} {:<7}", hit.line, hit.region).dimmed(),
+                hit.text
+            );
+        }
+        if m.hit_count > m.hits.len() {
+            println!(
+                "    {}",
+                format!("... {} more", m.hit_count - m.hits.len()).dimmed()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Runs an ArborQL query against the working tree or a revision.
+pub fn ql(
+    query: &str,
+    explain: bool,
+    format: DataFormat,
+    rev: Option<&str>,
+    limits: &QueryLimits,
+) -> Result<()> {
+    let query = if query == "-" {
+        let mut text = String::new();
+        std::io::stdin().read_to_string(&mut text)?;
+        text
+    } else {
+        query.to_string()
+    };
+
+    let path = std::env::current_dir()?;
+    let config = load_config(&path)?;
+    let mut graph = match rev {
+        Some(rev) => revision_graph(&path, rev)?,
+        None => index_project(&path, &config, &IndexOptions::default())?.graph,
+    };
+
+    if explain {
+        print!("{}", graph.explain_query(&query)?);
+        return Ok(());
+    }
+
+    let scores = compute_centrality(&graph, 20, 0.85);
+    graph.set_centrality(scores.into_map());
+    let result = graph.run_query(&query, limits)?;
+
+    if format == DataFormat::Json {
+        println!("{}", serde_json::to_string_pretty(&result)?);
+        return Ok(());
+    }
+
+    let cell = |value: &serde_json::Value| match value {
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Null => "null".to_string(),
+        serde_json::Value::Object(node) => format!(
+            "{} ({}:{})",
+            node["qualified_name"].as_str().unwrap_or_default(),
+            node["file"].as_str().unwrap_or_default(),
+            node["line_start"]
+        ),
+        other => other.to_string(),
+    };
+    let rows: Vec<Vec<String>> = result
+        .rows
+        .iter()
+        .map(|row| row.iter().map(cell).collect())
+        .collect();
+    let widths: Vec<usize> = result
+        .columns
+        .iter()
+        .enumerate()
+        .map(|(i, column)| {
+            rows.iter()
+                .map(|row| row[i].chars().count())
+                .chain([column.chars().count()])
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    let header: Vec<String> = result
+        .columns
+        .iter()
+        .zip(&widths)
+        .map(|(column, width)| format!("{:width$}", column, width = width))
+        .collect();
+    println!("{}", header.join("  ").trim_end().bold());
+    for row in &rows {
+        let line: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(value, width)| format!("{:width$}", value, width = width))
+            .collect();
+        println!("{}", line.join("  ").trim_end());
+    }
+    println!();
+    let note = format!(
+        "{} row{} in {}ms{}",
+        rows.len(),
+        if rows.len() == 1 { "" } else { "s" },
+        result.query_time_ms,
+        if result.truncated {
+            " (truncated; raise --max-results)"
+        } else {
+            ""
+        }
+    );
+    println!("{}", note.dimmed());
+    Ok(())
+}
+
 /// Start the Arbor server.
 pub async fn serve(port: u16, headless: bool, path: &Path) -> Result<()> {
     let bind_addr = if headless { "0.0.0.0" } else { "127.0.0.1" };
@@ -139,12 +532,15 @@ pub async fn serve(port: u16, headless: bool, path: &Path) -> Result<()> {
     }
 
     // Index the codebase first
-    let result = index_directory(path)?;
+    let config = load_config(path)?;
+    let workspace = Workspace::load(path)?;
+    let result = index_project(path, &config, &IndexOptions::default())?;
     let mut graph = result.graph;
 
-    // Compute centrality
+    // Compute centrality, then clusters (key symbols are the central ones)
     let scores = compute_centrality(&graph, 20, 0.85);
     graph.set_centrality(scores.into_map());
+    graph.assign_clusters(&ClusterOptions::default());
 
     println!(
         "{} Indexed {} files ({} nodes)",
@@ -153,9 +549,62 @@ pub async fn serve(port: u16, headless: bool, path: &Path) -> Result<()> {
         result.nodes_extracted
     );
 
+    // Keep the graph current as files change, one watcher per root
+    let watched = match &workspace {
+        Some(workspace) => workspace
+            .roots
+            .iter()
+            .map(|root| {
+                let config = load_config(&root.path)?;
+                let indexer = IncrementalIndexer::for_workspace_root(root)?;
+                Ok((root.path.clone(), config, indexer))
+            })
+            .collect::<Result<Vec<_>>>()?,
+        None => vec![(
+            path.to_path_buf(),
+            config.clone(),
+            IncrementalIndexer::new(path, &config)?,
+        )],
+    };
+    // Each root's store follows the live graph, starting from the initial index
+    let watched = watched
+        .into_iter()
+        .map(|(root, config, indexer)| {
+            let indexer = match GraphStore::open(GraphStore::path_for(&root)) {
+                Ok(store) => indexer.with_store(store),
+                Err(e) => {
+                    eprintln!(
+                        "{} Store unavailable for {}: {}",
+                        "⚠".yellow(),
+                        root.display(),
+                        e
+                    );
+                    indexer
+                }
+            };
+            indexer.seed_store(&graph)?;
+            Ok((root, config, indexer))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
     let addr = format!("{}:{}", bind_addr, port).parse()?;
-    let config = ServerConfig { addr };
-    let server = ArborServer::new(graph, config);
+    let server = ArborServer::new(graph, ServerConfig { addr });
+    for (root, config, mut indexer) in watched {
+        let watcher = FileWatcher::with_config(&root, &config, DEFAULT_DEBOUNCE)?;
+        let shared_graph = server.graph();
+        std::thread::spawn(move || {
+            while let Some(batch) = watcher.recv() {
+                let prepared = indexer.prepare(&batch);
+                if prepared.is_empty() {
+                    continue;
+                }
+                let mut graph = shared_graph.blocking_write();
+                if let Err(e) = indexer.commit(&mut graph, prepared) {
+                    eprintln!("{} Failed to apply changes: {}", "⚠".yellow(), e);
+                }
+            }
+        });
+    }
 
     println!("{} Listening on ws://{}:{}", "✓".green(), bind_addr, port);
     if headless {
@@ -172,45 +621,29 @@ pub async fn serve(port: u16, headless: bool, path: &Path) -> Result<()> {
 pub async fn viz(path: &Path) -> Result<()> {
     println!("{}", "Starting Arbor Visualizer stack...".cyan());
 
-    // 1. Index Codebase
-    let result = index_directory(path)?;
-    let mut graph = result.graph;
-
-    // Compute centrality for better initial layout
-    println!("Computing centrality...");
-    let scores = compute_centrality(&graph, 20, 0.85);
-    graph.set_centrality(scores.into_map());
-
-    println!(
-        "{} Indexed {} files ({} nodes)",
-        "✓".green(),
-        result.files_indexed,
-        result.nodes_extracted
-    );
+    let config = load_config(path)?;
+    let root = path.canonicalize()?;
+    let workspace = Workspace::load(&root)?;
 
-    // 2. Start API Server (JSON-RPC)
+    // 1. Start API Server (JSON-RPC) on an empty graph
     let rpc_port = 7433;
     let rpc_addr = format!("127.0.0.1:{}", rpc_port).parse()?;
     let rpc_config = ServerConfig { addr: rpc_addr };
-    let arbor_server = ArborServer::new(graph, rpc_config);
+    let arbor_server = ArborServer::new(arbor_graph::ArborGraph::new(), rpc_config);
     let shared_graph = arbor_server.graph();
 
-    // 3. Start Sync Server (WebSocket Broadcast)
+    // 2. Start Sync Server (WebSocket Broadcast)
     let sync_port = 8081;
     let sync_addr = format!("127.0.0.1:{}", sync_port).parse()?;
     let sync_config = arbor_server::SyncServerConfig {
         addr: sync_addr,
-        watch_path: path.to_path_buf(),
+        watch_path: root.clone(),
         debounce_ms: 1000,
-        extensions: vec![
-            "ts".to_string(),
-            "tsx".to_string(),
-            "rs".to_string(),
-            "py".to_string(),
-            "dart".to_string(),
-        ],
+        project: config.clone(),
+        workspace,
     };
     let sync_server = arbor_server::SyncServer::new_with_shared(sync_config, shared_graph.clone());
+    let sync_handle = sync_server.handle();
 
     // Spawn servers
     println!("{} RPC Server on port {}", "✓".green(), rpc_port);
@@ -228,6 +661,33 @@ pub async fn viz(path: &Path) -> Result<()> {
         }
     });
 
+    // 3. Index Codebase, streaming progress to connected visualizers
+    let options = IndexOptions::new().with_progress(sync_handle.clone());
+    let index_root = root.clone();
+    let result = tokio::task::spawn_blocking(move || {
+        index_project(&index_root, &config, &options).map_err(|e| e.to_string())
+    })
+    .await??;
+
+    {
+        let mut graph = shared_graph.write().await;
+        *graph = result.graph;
+
+        // Compute centrality for better initial layout
+        println!("Computing centrality...");
+        let scores = compute_centrality(&graph, 20, 0.85);
+        graph.set_centrality(scores.into_map());
+        graph.assign_clusters(&ClusterOptions::default());
+    }
+    sync_handle.publish_graph().await;
+
+    println!(
+        "{} Indexed {} files ({} nodes)",
+        "✓".green(),
+        result.files_indexed,
+        result.nodes_extracted
+    );
+
     // 4. Launch Visualizer
     // Priority 1: Standalone bundled executable (relative to arbor.exe)
     let current_exe = std::env::current_exe()?;
@@ -280,8 +740,9 @@ pub async fn viz(path: &Path) -> Result<()> {
 
 /// Export the graph to JSON.
 pub fn export(path: &Path, output: &Path) -> Result<()> {
-    let result = index_directory(path)?;
-    export_graph(&result.graph, output)?;
+    let config = load_config(path)?;
+    let mut result = index_project(path, &config, &IndexOptions::default())?;
+    export_graph(&mut result.graph, output)?;
     Ok(())
 }
 
@@ -296,14 +757,46 @@ pub fn status(path: &Path) -> Result<()> {
     }
 
     // Quick index to get stats
-    let result = index_directory(path)?;
+    let config = load_config(path)?;
+    let result = index_project(path, &config, &IndexOptions::default())?;
+
+    let languages = if config.languages.is_empty() {
+        "all supported".to_string()
+    } else {
+        config.languages.join(", ")
+    };
 
     println!("{}", "Arbor Status".cyan().bold());
     println!();
     println!("  {} {}", "Files:".dimmed(), result.files_indexed);
     println!("  {} {}", "Nodes:".dimmed(), result.nodes_extracted);
     println!("  {} {}", "Edges:".dimmed(), result.graph.edge_count());
-    println!("  {} TypeScript, Rust, Python", "Languages:".dimmed());
+    println!("  {} {}", "Languages:".dimmed(), languages);
+    if !result.graph.roots().is_empty() {
+        let namespaces: Vec<_> = result
+            .graph
+            .roots()
+            .iter()
+            .map(|r| r.namespace.as_str())
+            .collect();
+        println!("  {} {}", "Roots:".dimmed(), namespaces.join(", "));
+    }
+
+    let store_path = GraphStore::path_for(path);
+    if store_path.exists() {
+        let revisions = GraphStore::open(&store_path)?.revisions()?;
+        if !revisions.is_empty() {
+            println!("  {}", "Revisions:".dimmed());
+        }
+        for rev in revisions {
+            println!(
+                "    {} {} {}",
+                rev.commit[..12.min(rev.commit.len())].yellow(),
+                rev.refs.join(", "),
+                format!("({} files, {} nodes)", rev.files, rev.nodes).dimmed()
+            );
+        }
+    }
 
     Ok(())
 }
@@ -318,40 +811,11 @@ pub async fn bridge(path: &Path, launch_viz: bool) -> Result<()> {
     let graph = arbor_graph::ArborGraph::new();
     let shared_graph = std::sync::Arc::new(tokio::sync::RwLock::new(graph));
 
-    // 2. Run Initial Index (Blocking)
-    let index_path = path.to_path_buf();
-    eprintln!("{} Starting initial index...", "⏳".yellow());
-
-    // Run blocking indexer
-    let result = tokio::task::spawn_blocking(move || index_directory(&index_path)).await?;
-
-    match result {
-        Ok(index_result) => {
-            let mut guard = shared_graph.write().await;
-            *guard = index_result.graph;
-
-            // Compute centrality
-            let scores = compute_centrality(&guard, 20, 0.85);
-            guard.set_centrality(scores.into_map());
-
-            eprintln!(
-                "{} Index Ready: {} files, {} nodes",
-                "✓".green(),
-                index_result.files_indexed,
-                index_result.nodes_extracted
-            );
-        }
-        Err(e) => eprintln!("{} Indexing failed: {}", "⚠".red(), e),
-    }
-
-    // Pass a clone to the background watcher/indexer (which we should start separately if we want continuous updates)
-    // Actually, SyncServer handles the continuous watching!
-    // The previous code had a separate background indexer that seemingly did nothing after the initial index?
-    // No, wait. The previous code ONLY did the initial index.
-    // The SyncServer (lines 355) is what handles *subsequent* file updates via its own watcher.
-    // So this change is strictly correct.
+    let config = load_config(path)?;
+    let root = path.canonicalize()?;
+    let workspace = Workspace::load(&root)?;
 
-    // 3. Start Servers (Background)
+    // 2. Start Servers (Background), so the visualizer can follow the initial index
     let rpc_port = 7433;
     let sync_port = 8081;
 
@@ -363,14 +827,10 @@ pub async fn bridge(path: &Path, launch_viz: bool) -> Result<()> {
 
     let sync_config = arbor_server::SyncServerConfig {
         addr: format!("127.0.0.1:{}", sync_port).parse()?,
-        watch_path: path.to_path_buf(),
+        watch_path: root.clone(),
         debounce_ms: 1000,
-        extensions: vec![
-            "rs".to_string(),
-            "ts".to_string(),
-            "py".to_string(),
-            "dart".to_string(),
-        ],
+        project: config.clone(),
+        workspace,
     };
 
     let sync_server = arbor_server::SyncServer::new_with_shared(sync_config, shared_graph.clone());
@@ -388,6 +848,40 @@ pub async fn bridge(path: &Path, launch_viz: bool) -> Result<()> {
         }
     });
 
+    // 3. Run Initial Index (Blocking), reporting progress to the visualizer
+    let index_path = root.clone();
+    let index_config = config.clone();
+    let options = IndexOptions::new().with_progress(spotlight_handle.clone());
+    eprintln!("{} Starting initial index...", "⏳".yellow());
+
+    let result = tokio::task::spawn_blocking(move || {
+        index_project(&index_path, &index_config, &options).map_err(|e| e.to_string())
+    })
+    .await?;
+
+    match result {
+        Ok(index_result) => {
+            {
+                let mut guard = shared_graph.write().await;
+                *guard = index_result.graph;
+
+                // Compute centrality
+                let scores = compute_centrality(&guard, 20, 0.85);
+                guard.set_centrality(scores.into_map());
+                guard.assign_clusters(&ClusterOptions::default());
+            }
+            spotlight_handle.publish_graph().await;
+
+            eprintln!(
+                "{} Index Ready: {} files, {} nodes",
+                "✓".green(),
+                index_result.files_indexed,
+                index_result.nodes_extracted
+            );
+        }
+        Err(e) => eprintln!("{} Indexing failed: {}", "⚠".red(), e),
+    }
+
     eprintln!(
         "{} Servers Ready (RPC {}, Sync {})",
         "✓".green(),
@@ -396,8 +890,7 @@ pub async fn bridge(path: &Path, launch_viz: bool) -> Result<()> {
     );
     eprintln!("🔦 Spotlight mode active - Visualizer will track AI focus");
 
-    // 3. Optionally launch the visualizer
-    // 3. Optionally launch the visualizer
+    // 4. Optionally launch the visualizer
     if launch_viz {
         // Try to find visualizer in target path or parent (workspace root)
         let viz_dir = if path.join("visualizer").exists() {
@@ -439,7 +932,8 @@ pub async fn bridge(path: &Path, launch_viz: bool) -> Result<()> {
 
     // 3. Start MCP Server (Main Thread) WITH Spotlight capability
     // IMPORTANT: All logging MUST be to stderr from here on.
-    let mcp = McpServer::with_spotlight(shared_graph, spotlight_handle);
+    let mcp =
+        McpServer::with_spotlight(shared_graph, spotlight_handle).with_defaults(config.defaults);
     mcp.run_stdio().await?;
 
     Ok(())
@@ -458,8 +952,6 @@ pub async fn check_health() -> Result<()> {
     let workspace_root = if Path::new("Cargo.toml").exists() && Path::new("../visualizer").exists()
     {
         Path::new("..").to_path_buf()
-    } else if Path::new("crates").exists() {
-        Path::new(".").to_path_buf()
     } else {
         Path::new(".").to_path_buf()
     };
@@ -529,21 +1021,22 @@ pub async fn check_health() -> Result<()> {
 }
 
 /// Preview blast radius before refactoring a node.
-pub fn refactor(target: &str, max_depth: usize, show_why: bool, json_output: bool) -> Result<()> {
+pub fn refactor(
+    target: &str,
+    root: Option<&str>,
+    max_depth: Option<usize>,
+    show_why: bool,
+    json_output: bool,
+) -> Result<()> {
     // Load the graph by indexing current directory
     let path = std::env::current_dir()?;
-    let result = index_directory(&path)?;
+    let config = load_config(&path)?;
+    let max_depth = max_depth.unwrap_or(config.defaults.impact_depth);
+    let result = index_project(&path, &config, &IndexOptions::default())?;
     let graph = result.graph;
 
     // Find the target node
-    let node_idx = graph.get_index(target).or_else(|| {
-        graph
-            .find_by_name(target)
-            .first()
-            .and_then(|n| graph.get_index(&n.id))
-    });
-
-    let node_idx = match node_idx {
+    let node_idx = match graph.find_node(target, root) {
         Some(idx) => idx,
         None => {
             return Err(format!("Node '{}' not found in graph", target).into());
@@ -667,21 +1160,23 @@ pub fn refactor(target: &str, max_depth: usize, show_why: bool, json_output: boo
 }
 
 /// Explain code using graph-backed context.
-pub fn explain(question: &str, max_tokens: usize, show_why: bool, json_output: bool) -> Result<()> {
+pub fn explain(
+    question: &str,
+    root: Option<&str>,
+    max_tokens: Option<usize>,
+    ranking: Option<&CentralityOptions>,
+    show_why: bool,
+    json_output: bool,
+) -> Result<()> {
     // Load the graph by indexing current directory
     let path = std::env::current_dir()?;
-    let result = index_directory(&path)?;
+    let config = load_config(&path)?;
+    let max_tokens = max_tokens.unwrap_or(config.defaults.max_tokens);
+    let result = index_project(&path, &config, &IndexOptions::default())?;
     let graph = result.graph;
 
     // Try to find a node matching the question (could be a function name)
-    let node_idx = graph.get_index(question).or_else(|| {
-        graph
-            .find_by_name(question)
-            .first()
-            .and_then(|n| graph.get_index(&n.id))
-    });
-
-    let node_idx = match node_idx {
+    let node_idx = match graph.find_node(question, root) {
         Some(idx) => idx,
         None => {
             return Err(format!("Node '{}' not found in graph", question).into());
@@ -689,7 +1184,11 @@ pub fn explain(question: &str, max_tokens: usize, show_why: bool, json_output: b
     };
 
     // Slice context around the node
-    let slice = graph.slice_context(node_idx, max_tokens, 2, &[]);
+    let depth = config.defaults.context_depth;
+    let slice = match ranking {
+        Some(ranking) => graph.slice_context_with(node_idx, max_tokens, depth, &[], ranking),
+        None => graph.slice_context(node_idx, max_tokens, depth, &[]),
+    };
 
     if json_output {
         let output = serde_json::json!({
@@ -706,6 +1205,7 @@ pub fn explain(question: &str, max_tokens: usize, show_why: bool, json_output: b
                 "file": n.node_info.file,
                 "depth": n.depth,
                 "token_estimate": n.token_estimate,
+                "centrality": n.node_info.centrality,
                 "pinned": n.pinned
             })).collect::<Vec<_>>(),
             "total_tokens": slice.total_tokens,
@@ -753,3 +1253,992 @@ pub fn explain(question: &str, max_tokens: usize, show_why: bool, json_output: b
 
     Ok(())
 }
+
+/// Compares the graphs of two revisions, or a revision and the working tree.
+pub fn diff(
+    rev_a: &str,
+    rev_b: Option<&str>,
+    structural_only: bool,
+    format: DataFormat,
+) -> Result<()> {
+    let path = std::env::current_dir()?;
+    let old = revision_graph(&path, rev_a)?;
+    let new = match rev_b {
+        Some(rev) => revision_graph(&path, rev)?,
+        None => {
+            let config = load_config(&path)?;
+            index_project(&path, &config, &IndexOptions::default())?.graph
+        }
+    };
+    let to = rev_b.unwrap_or("working tree");
+
+    let options = DiffOptions {
+        structural_only,
+        ..DiffOptions::default()
+    };
+    let diff = GraphDiff::with_options(&old, &new, &options);
+
+    if format == DataFormat::Json {
+        let output = serde_json::json!({
+            "from": rev_a,
+            "to": to,
+            "diff": diff,
+        });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    println!("{} {}..{}", "Graph diff".cyan().bold(), rev_a, to);
+    if diff.is_empty() {
+        println!("  No structural changes");
+        return Ok(());
+    }
+    println!();
+
+    let location = |file: &str, line: u32| format!("({}:{})", file, line).dimmed();
+    for node in &diff.added {
+        println!(
+            "  {} {} {} {}",
+            "+".green(),
+            node.kind.yellow(),
+            node.qualified_name.cyan(),
+            location(&node.file, node.line_start)
+        );
+    }
+    for node in &diff.removed {
+        println!(
+            "  {} {} {} {}",
+            "-".red(),
+            node.kind.yellow(),
+            node.qualified_name.cyan(),
+            location(&node.file, node.line_start)
+        );
+    }
+    for moved in &diff.moved {
+        println!(
+            "  {} {} {} → {}",
+            "→".blue(),
+            moved.node.qualified_name.cyan(),
+            moved.from_file.dimmed(),
+            moved.node.file
+        );
+    }
+    for renamed in &diff.renamed {
+        println!(
+            "  {} {} → {} {}",
+            "~".blue(),
+            renamed.from.qualified_name,
+            renamed.to.qualified_name.cyan(),
+            format!("({:.0}% similar)", renamed.similarity * 100.0).dimmed()
+        );
+    }
+    for change in &diff.signature_changed {
+        println!(
+            "  {} {} signature: {} → {}",
+            "✎".yellow(),
+            change.node.qualified_name.cyan(),
+            change.before.as_deref().unwrap_or("-").dimmed(),
+            change.after.as_deref().unwrap_or("-")
+        );
+    }
+    for change in &diff.visibility_changed {
+        let exported = |v: arbor_core::Visibility, e: bool| {
+            if e {
+                format!("{}, exported", v)
+            } else {
+                v.to_string()
+            }
+        };
+        println!(
+            "  {} {} visibility: {} → {}",
+            "◐".yellow(),
+            change.node.qualified_name.cyan(),
+            exported(change.before, change.exported_before).dimmed(),
+            exported(change.after, change.exported_after)
+        );
+    }
+    for node in &diff.body_changed {
+        println!(
+            "  {} {} body changed {}",
+            "•".dimmed(),
+            node.qualified_name,
+            location(&node.file, node.line_start)
+        );
+    }
+
+    let by_kind = diff.edges_by_kind();
+    if !by_kind.is_empty() {
+        println!();
+        println!(
+            "  {} {} {}",
+            "Edges:".dimmed(),
+            format!("+{}", diff.edges_added.len()).green(),
+            format!("-{}", diff.edges_removed.len()).red()
+        );
+        for (kind, (added, removed)) in by_kind {
+            println!("    {:<16} +{} -{}", kind, added, removed);
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads a patch from `git diff <since>` or, without a ref, from stdin.
+///
+/// Inside a git repository paths are joined onto its root, since git
+/// prints them relative to it whatever directory we're in.
+fn read_patches(repo: Option<&GitRepo>, since: Option<&str>) -> Result<Vec<FilePatch>> {
+    let mut patches = match (since, repo) {
+        (Some(rev), Some(repo)) => parse_unified_diff(&repo.diff_since(rev)?),
+        (Some(_), None) => return Err("--since needs a git repository".into()),
+        (None, _) => {
+            if std::io::stdin().is_terminal() {
+                return Err("pipe a unified diff into stdin or pass --since <ref>".into());
+            }
+            let mut text = String::new();
+            std::io::stdin().read_to_string(&mut text)?;
+            parse_unified_diff(&text)
+        }
+    };
+    if let Some(repo) = repo {
+        let absolute = |path: &str| repo.root().join(path).to_string_lossy().into_owned();
+        for patch in &mut patches {
+            patch.path = absolute(&patch.path);
+            patch.old_path = patch.old_path.as_deref().map(absolute);
+        }
+    }
+    patches.retain(|p| !p.lines.is_empty() || !p.removed.is_empty() || p.deleted);
+    Ok(patches)
+}
+
+/// Shows what a patch touches: changed symbols, their callers and the
+/// tests that reach them. Reads a unified diff from stdin, or diffs the
+/// working tree against `since`.
+pub fn impact(since: Option<&str>, max_depth: Option<usize>, format: OutputFormat) -> Result<()> {
+    let path = std::env::current_dir()?;
+    let config = load_config(&path)?;
+    let max_depth = max_depth.unwrap_or(config.defaults.impact_depth);
+
+    let repo = match since {
+        Some(_) => Some(GitRepo::open(&path)?),
+        None => GitRepo::open(&path).ok(),
+    };
+    let patches = read_patches(repo.as_ref(), since)?;
+
+    let result = index_project(&path, &config, &IndexOptions::default())?;
+    let mut graph = result.graph;
+    let scores = compute_centrality(&graph, 20, 0.85);
+    graph.set_centrality(scores.into_map());
+
+    // Removed lines are numbered as in the version before the patch: the
+    // `--since` revision, or HEAD for a piped diff
+    let base = match (&repo, since) {
+        (Some(_), Some(rev)) => Some(revision_graph(&path, rev)?),
+        (Some(_), None) => revision_graph(&path, "HEAD").ok(),
+        (None, _) => None,
+    };
+    let report = match &base {
+        Some(base) => graph.analyze_change_impact_with_base(base, &patches, max_depth),
+        None => graph.analyze_change_impact(&patches, max_depth),
+    };
+
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+        OutputFormat::Markdown => print!("{}", report.to_markdown()),
+        OutputFormat::Text => {
+            println!("{}", "Change Impact".yellow().bold());
+            println!(
+                "{} changed symbols in {} files, {} affected nodes, {} tests",
+                report.symbols.len().to_string().cyan(),
+                report.files.len(),
+                report.affected.len().to_string().cyan(),
+                report.tests.len().to_string().cyan()
+            );
+            for symbol in &report.symbols {
+                println!();
+                println!(
+                    "  {} {} {}",
+                    symbol.node.kind.yellow(),
+                    symbol.node.qualified_name.cyan(),
+                    format!("({}:{})", symbol.node.file, symbol.node.line_start).dimmed()
+                );
+                for caller in &symbol.callers {
+                    println!(
+                        "    {} {} {}",
+                        "←".dimmed(),
+                        caller.node_info.qualified_name,
+                        format!(
+                            "({} hop{}, {})",
+                            caller.hop_distance,
+                            if caller.hop_distance == 1 { "" } else { "s" },
+                            caller.entry_edge
+                        )
+                        .dimmed()
+                    );
+                }
+                for test in &symbol.tests {
+                    println!("    {} {}", "✓".green(), test.qualified_name);
+                }
+            }
+            let deleted: Vec<&str> = report
+                .files
+                .iter()
+                .filter(|f| f.deleted)
+                .map(|f| f.path.as_str())
+                .collect();
+            if !deleted.is_empty() {
+                println!();
+                println!("  {} {}", "Deleted:".dimmed(), deleted.join(", "));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Lists the tests that reach the given symbols, files or patch.
+pub fn tests_for(
+    targets: &[String],
+    since: Option<&str>,
+    max_depth: Option<usize>,
+    format: DataFormat,
+    commands_only: bool,
+) -> Result<()> {
+    let path = std::env::current_dir()?;
+    let config = load_config(&path)?;
+    let max_depth = max_depth.unwrap_or(config.defaults.impact_depth);
+    let from_patch = targets.is_empty() || since.is_some();
+    let repo = match since {
+        Some(_) => Some(GitRepo::open(&path)?),
+        None => GitRepo::open(&path).ok(),
+    };
+    let patches = if from_patch {
+        read_patches(repo.as_ref(), since)?
+    } else {
+        Vec::new()
+    };
+
+    let result = index_project(&path, &config, &IndexOptions::default())?;
+    let graph = result.graph;
+    let base = match (&repo, since) {
+        (Some(_), Some(rev)) => Some(revision_graph(&path, rev)?),
+        (Some(_), None) if from_patch => revision_graph(&path, "HEAD").ok(),
+        _ => None,
+    };
+
+    let mut seeds = Vec::new();
+    for patch in &patches {
+        seeds.extend(graph.changed_nodes(base.as_ref().unwrap_or(&graph), patch));
+    }
+    for target in targets {
+        let in_file: Vec<_> = graph
+            .find_by_file(target)
+            .into_iter()
+            .filter_map(|n| graph.get_index(&n.id))
+            .collect();
+        if !in_file.is_empty() {
+            seeds.extend(in_file);
+            continue;
+        }
+        match graph.find_node(target, None) {
+            Some(node) => seeds.push(node),
+            None => return Err(format!("no symbol or indexed file named '{}'", target).into()),
+        }
+    }
+
+    let selection = graph.select_tests(&seeds, max_depth);
+
+    if format == DataFormat::Json {
+        println!("{}", serde_json::to_string_pretty(&selection)?);
+        return Ok(());
+    }
+    if commands_only {
+        for command in &selection.commands {
+            println!("{}", command.to_shell());
+        }
+        return Ok(());
+    }
+
+    println!("{}", "Tests to Run".yellow().bold());
+    println!(
+        "{} tests in {} files for {} changed symbols",
+        selection.tests.len().to_string().cyan(),
+        selection.files.len().to_string().cyan(),
+        seeds.len()
+    );
+    if selection.tests.is_empty() {
+        return Ok(());
+    }
+    println!();
+    for test in &selection.tests {
+        println!(
+            "  {} {} {}",
+            "✓".green(),
+            test.qualified_name,
+            format!("({}:{})", test.file, test.line_start).dimmed()
+        );
+    }
+    if !selection.commands.is_empty() {
+        println!();
+        println!("{}", "Commands".yellow().bold());
+        for command in &selection.commands {
+            println!("  {}", command.to_shell());
+        }
+    }
+    Ok(())
+}
+
+/// Prints the circular dependencies at the requested level. Returns whether
+/// any were found, so CI can fail on them.
+pub fn cycles(
+    options: &CycleOptions,
+    limit: Option<usize>,
+    rev: Option<&str>,
+    format: ReportFormat,
+) -> Result<bool> {
+    let path = std::env::current_dir()?;
+    let config = load_config(&path)?;
+    let graph = match rev {
+        Some(rev) => revision_graph(&path, rev)?,
+        None => index_project(&path, &config, &IndexOptions::default())?.graph,
+    };
+
+    let mut report = graph.find_cycles(options);
+    let found = !report.is_acyclic();
+    let total = report.cycles.len();
+    if let Some(limit) = limit {
+        report.cycles.truncate(limit);
+    }
+
+    if print_report(&report, format)? {
+        return Ok(found);
+    }
+
+    println!("{}", "Dependency Cycles".yellow().bold());
+    let kinds: Vec<String> = report.edge_kinds.iter().map(|k| k.to_string()).collect();
+    println!(
+        "{} cycles among {} {}s {}",
+        total.to_string().cyan(),
+        report.units,
+        report.level,
+        format!("(following {})", kinds.join(", ")).dimmed()
+    );
+
+    for (i, cycle) in report.cycles.iter().enumerate() {
+        println!();
+        println!(
+            "  {} {} {}",
+            format!("{}.", i + 1).dimmed(),
+            cycle.path.join(" → ").cyan(),
+            format!("({} members)", cycle.size()).dimmed()
+        );
+        if cycle.size() + 1 > cycle.path.len() {
+            println!("     {} {}", "members:".dimmed(), cycle.members.join(", "));
+        }
+        for edge in &cycle.edges {
+            let also = if edge.occurrences > 1 {
+                format!(" +{} more", edge.occurrences - 1)
+            } else {
+                String::new()
+            };
+            println!(
+                "     {} {} {} {} {}",
+                edge.kind.to_string().yellow(),
+                edge.source,
+                "→".dimmed(),
+                edge.target,
+                format!("({}:{}{})", edge.file, edge.line, also).dimmed()
+            );
+        }
+    }
+    if report.cycles.len() < total {
+        println!();
+        println!(
+            "  {}",
+            format!("… and {} more", total - report.cycles.len()).dimmed()
+        );
+    }
+    Ok(found)
+}
+
+/// Lists unreachable functions and types, grouped by file.
+pub fn deadcode(
+    min_confidence: Confidence,
+    extra_entries: &[String],
+    no_public_api: bool,
+    rev: Option<&str>,
+    format: ReportFormat,
+) -> Result<()> {
+    let path = std::env::current_dir()?;
+    let config = load_config(&path)?;
+    let mut entry = config.entry_points.clone();
+    entry.names.extend(extra_entries.iter().cloned());
+    if no_public_api {
+        entry.public_api = false;
+    }
+
+    let mut graph = match rev {
+        Some(rev) => revision_graph(&path, rev)?,
+        None => index_project(&path, &config, &IndexOptions::default())?.graph,
+    };
+    let scores = compute_centrality(&graph, 20, 0.85);
+    graph.set_centrality(scores.into_map());
+
+    let mut report = graph.find_dead_code(&entry)?;
+    report.retain_confidence(min_confidence);
+
+    if print_report(&report, format)? {
+        return Ok(());
+    }
+
+    println!("{}", "Dead Code".yellow().bold());
+    println!(
+        "{} unreachable symbols in {} files {}",
+        report.dead_count().to_string().cyan(),
+        report.files.len().to_string().cyan(),
+        format!(
+            "({} entry points reach {} of {} nodes)",
+            report.roots,
+            report.reachable,
+            graph.node_count()
+        )
+        .dimmed()
+    );
+
+    let confidence = |c: Confidence| match c {
+        Confidence::High => c.to_string().red(),
+        Confidence::Medium => c.to_string().yellow(),
+        Confidence::Low => c.to_string().dimmed(),
+    };
+    for file in &report.files {
+        println!();
+        if file.whole_file {
+            let imported = file
+                .imported_by
+                .as_ref()
+                .map(|by| format!(" (imported by {})", by))
+                .unwrap_or_default();
+            println!(
+                "  {} {} {}{}",
+                file.file.cyan(),
+                "whole file unused".bold(),
+                confidence(file.confidence),
+                imported.dimmed()
+            );
+        } else {
+            println!("  {}", file.file.cyan());
+        }
+        for symbol in &file.symbols {
+            println!(
+                "    {:>6}  {} {} {}",
+                confidence(symbol.confidence),
+                symbol.node.kind.yellow(),
+                symbol.node.qualified_name,
+                format!(":{} — {}", symbol.node.line_start, symbol.reason).dimmed()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Groups the codebase into architecture clusters.
+pub fn clusters(
+    options: &ClusterOptions,
+    cluster: Option<&str>,
+    rev: Option<&str>,
+    format: OutputFormat,
+) -> Result<()> {
+    let path = std::env::current_dir()?;
+    let config = load_config(&path)?;
+    let mut graph = match rev {
+        Some(rev) => revision_graph(&path, rev)?,
+        None => index_project(&path, &config, &IndexOptions::default())?.graph,
+    };
+    let scores = compute_centrality(&graph, 20, 0.85);
+    graph.set_centrality(scores.into_map());
+
+    let mut report = graph.compute_clusters(options);
+    if let Some(id) = cluster {
+        if report.get(id).is_none() {
+            return Err(format!("unknown cluster '{}'", id).into());
+        }
+        report.clusters.retain(|c| c.id == id);
+        report.edges.retain(|e| e.from == id || e.to == id);
+    }
+
+    match format {
+        OutputFormat::Json => {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+            return Ok(());
+        }
+        OutputFormat::Markdown => {
+            match cluster.and_then(|id| report.summary(id)) {
+                Some(summary) => print!("{}", summary),
+                None => print!("{}", report.to_markdown()),
+            }
+            return Ok(());
+        }
+        OutputFormat::Text => {}
+    }
+
+    println!("{}", "Architecture Clusters".yellow().bold());
+    let files: usize = report.clusters.iter().map(|c| c.files.len()).sum();
+    println!(
+        "{} clusters over {} files {}",
+        report.clusters.len().to_string().cyan(),
+        files,
+        format!("(modularity {:.2})", report.modularity).dimmed()
+    );
+
+    for c in &report.clusters {
+        println!();
+        println!(
+            "  {} {} {}",
+            c.id.cyan().bold(),
+            c.label,
+            format!("({} files, {} symbols)", c.files.len(), c.size).dimmed()
+        );
+        if !c.keywords.is_empty() {
+            println!("     {} {}", "about:".dimmed(), c.keywords.join(", "));
+        }
+        if !c.key_symbols.is_empty() {
+            println!("     {} {}", "key:".dimmed(), c.key_symbols.join(", "));
+        }
+        let depends_on: Vec<String> = report
+            .edges
+            .iter()
+            .filter(|e| e.from == c.id)
+            .map(|e| format!("{} ({})", e.to, e.count))
+            .collect();
+        if !depends_on.is_empty() {
+            println!("     {} {}", "uses:".dimmed(), depends_on.join(", "));
+        }
+    }
+    Ok(())
+}
+
+/// Prints the graph collapsed into files, directories, packages or
+/// clusters, or what depends on one unit and what it depends on.
+pub fn collapse(
+    options: &CollapseOptions,
+    unit: Option<&str>,
+    depth: usize,
+    limit: Option<usize>,
+    rev: Option<&str>,
+    format: GraphFormat,
+) -> Result<()> {
+    let path = std::env::current_dir()?;
+    let config = load_config(&path)?;
+    let mut graph = match rev {
+        Some(rev) => revision_graph(&path, rev)?,
+        None => index_project(&path, &config, &IndexOptions::default())?.graph,
+    };
+    if options.level == arbor_graph::CollapseLevel::Cluster {
+        graph.assign_clusters(&ClusterOptions::default());
+    }
+    let quotient = graph.collapse(options);
+    let unit = match unit {
+        Some(name) => Some(
+            quotient
+                .find_unit(name)
+                .ok_or_else(|| format!("unknown {} '{}'", options.level, name))?,
+        ),
+        None => None,
+    };
+
+    match (format, unit) {
+        (GraphFormat::Dot, Some(unit)) => print!("{}", quotient.neighborhood(unit, depth).to_dot()),
+        (GraphFormat::Dot, None) => print!("{}", quotient.to_dot()),
+        (GraphFormat::Mermaid, Some(unit)) => {
+            print!("{}", quotient.neighborhood(unit, depth).to_mermaid())
+        }
+        (GraphFormat::Mermaid, None) => print!("{}", quotient.to_mermaid()),
+        (GraphFormat::Json, Some(unit)) => println!(
+            "{}",
+            serde_json::to_string_pretty(&quotient.analyze_impact(unit, depth))?
+        ),
+        (GraphFormat::Json, None) => println!("{}", serde_json::to_string_pretty(&quotient)?),
+        (GraphFormat::Text, None) => {
+            println!("{}", "Collapsed Graph".yellow().bold());
+            println!(
+                "{} {}s, {} dependencies between them",
+                quotient.nodes().len().to_string().cyan(),
+                quotient.level,
+                quotient.edges().len()
+            );
+            println!();
+            for edge in quotient.edges().iter().take(limit.unwrap_or(usize::MAX)) {
+                println!(
+                    "  {:>5}  {} {} {} {}",
+                    edge.weight.to_string().cyan(),
+                    edge.from,
+                    "→".dimmed(),
+                    edge.to,
+                    format!("({})", kind_counts(&edge.kinds)).dimmed()
+                );
+            }
+        }
+        (GraphFormat::Text, Some(unit)) => {
+            let node = quotient.node(unit).ok_or("unit disappeared")?;
+            println!(
+                "{} {}",
+                quotient.level.to_string().yellow().bold(),
+                unit.bold()
+            );
+            println!(
+                "{} files, {} symbols",
+                node.files.to_string().cyan(),
+                node.symbols
+            );
+            let impact = quotient
+                .analyze_impact(unit, depth)
+                .ok_or("unit disappeared")?;
+            for (title, affected) in [
+                ("Depended on by", &impact.upstream),
+                ("Depends on", &impact.downstream),
+            ] {
+                println!();
+                println!(
+                    "{} {}",
+                    title.yellow(),
+                    format!("({})", affected.len()).dimmed()
+                );
+                for a in affected.iter().take(limit.unwrap_or(usize::MAX)) {
+                    let via = if a.hop_distance > 1 {
+                        format!(" via {}", a.via)
+                    } else {
+                        String::new()
+                    };
+                    println!(
+                        "  {} {} {}",
+                        format!("{:>2}", a.hop_distance).dimmed(),
+                        a.unit,
+                        format!("({} deps{})", a.weight, via).dimmed()
+                    );
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn kind_counts(kinds: &std::collections::BTreeMap<arbor_graph::EdgeKind, usize>) -> String {
+    kinds
+        .iter()
+        .map(|(kind, count)| format!("{} {}", kind, count))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Prints coupling and cohesion metrics, or how they changed since `compare`.
+pub fn metrics(
+    options: &MetricsOptions,
+    compare: Option<&str>,
+    limit: Option<usize>,
+    rev: Option<&str>,
+    format: TableFormat,
+) -> Result<()> {
+    let path = std::env::current_dir()?;
+    let config = load_config(&path)?;
+    let graph = match rev {
+        Some(rev) => revision_graph(&path, rev)?,
+        None => index_project(&path, &config, &IndexOptions::default())?.graph,
+    };
+    let report = graph.compute_metrics(options);
+
+    if let Some(base) = compare {
+        let before = revision_graph(&path, base)?.compute_metrics(options);
+        let trend = report.compare(&before);
+        match format {
+            TableFormat::Json => println!("{}", serde_json::to_string_pretty(&trend)?),
+            TableFormat::Csv => print!("{}", trend.to_csv()),
+            TableFormat::Text => {
+                println!(
+                    "{}",
+                    format!("Metrics Trend since {}", base).yellow().bold()
+                );
+                println!(
+                    "{} {}s changed, mean distance {:.3} → {:.3}",
+                    trend.units.len().to_string().cyan(),
+                    trend.level,
+                    trend.mean_distance_before,
+                    trend.mean_distance_after
+                );
+                println!();
+                for t in trend.units.iter().take(limit.unwrap_or(usize::MAX)) {
+                    let marker = match t.status {
+                        arbor_graph::TrendStatus::Added => "+".green(),
+                        arbor_graph::TrendStatus::Removed => "-".red(),
+                        arbor_graph::TrendStatus::Changed => "~".yellow(),
+                    };
+                    println!(
+                        "  {} {}  {}",
+                        marker,
+                        t.unit,
+                        format!(
+                            "Ca {:+}  Ce {:+}  I {:+.3}  D {:+.3}",
+                            t.afferent_delta,
+                            t.efferent_delta,
+                            t.instability_delta,
+                            t.distance_delta
+                        )
+                        .dimmed()
+                    );
+                }
+            }
+        }
+        return Ok(());
+    }
+
+    match format {
+        TableFormat::Json => {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+            return Ok(());
+        }
+        TableFormat::Csv => {
+            print!("{}", report.to_csv());
+            return Ok(());
+        }
+        TableFormat::Text => {}
+    }
+
+    println!("{}", "Coupling Metrics".yellow().bold());
+    println!(
+        "{} {}s {}",
+        report.units.len().to_string().cyan(),
+        report.level,
+        format!("(mean distance {:.3})", report.mean_distance()).dimmed()
+    );
+    println!();
+    println!(
+        "  {:<48} {:>4} {:>4} {:>6} {:>6} {:>6} {:>5}",
+        "unit".dimmed(),
+        "Ca".dimmed(),
+        "Ce".dimmed(),
+        "I".dimmed(),
+        "A".dimmed(),
+        "D".dimmed(),
+        "LCOM".dimmed()
+    );
+    let mut units: Vec<_> = report.units.iter().collect();
+    units.sort_by(|a, b| {
+        b.distance
+            .total_cmp(&a.distance)
+            .then(b.fan_in.cmp(&a.fan_in))
+            .then_with(|| a.unit.cmp(&b.unit))
+    });
+    for u in units.iter().take(limit.unwrap_or(usize::MAX)) {
+        println!(
+            "  {:<48} {:>4} {:>4} {:>6.2} {:>6.2} {:>6} {:>5}",
+            u.unit,
+            u.afferent,
+            u.efferent,
+            u.instability,
+            u.abstractness,
+            format!("{:.2}", u.distance).cyan(),
+            u.lcom.map(|l| format!("{:.1}", l)).unwrap_or_default()
+        );
+    }
+
+    let scattered: Vec<_> = report.classes.iter().filter(|c| c.lcom > 1).collect();
+    if !scattered.is_empty() {
+        println!();
+        println!("{}", "Least cohesive classes".yellow().bold());
+        for class in scattered.iter().take(limit.unwrap_or(10)) {
+            println!(
+                "  {:>3}  {} {}",
+                class.lcom.to_string().red(),
+                class.class,
+                format!("({} methods, {}:{})", class.methods, class.file, class.line).dimmed()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// What `arbor check` does with the baseline file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BaselineMode {
+    /// Ignore the violations it lists.
+    Read,
+    /// Replace it with every current violation.
+    Write,
+    /// Drop entries that no longer occur.
+    Update,
+}
+
+/// Checks architecture rules. Returns whether there are new violations.
+pub fn check(
+    rules_path: Option<&Path>,
+    baseline_path: Option<&Path>,
+    mode: BaselineMode,
+    rev: Option<&str>,
+    format: ReportFormat,
+) -> Result<bool> {
+    let path = std::env::current_dir()?;
+    let rules = match rules_path {
+        Some(file) => ArchitectureRules::from_file(file)?,
+        None => ArchitectureRules::load(&path)?.ok_or_else(|| {
+            format!(
+                "no rules found; create {}",
+                ArchitectureRules::path_for(&path).display()
+            )
+        })?,
+    };
+    let config = load_config(&path)?;
+    let graph = match rev {
+        Some(rev) => revision_graph(&path, rev)?,
+        None => index_project(&path, &config, &IndexOptions::default())?.graph,
+    };
+
+    let mut report = graph.check_rules(&rules)?;
+    let baseline_file = baseline_path
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| Baseline::path_for(&path));
+    let baseline = Baseline::load(&baseline_file)?;
+    if let (Some(baseline), false) = (&baseline, mode == BaselineMode::Write) {
+        report.apply_baseline(baseline);
+    }
+    match (mode, &baseline) {
+        (BaselineMode::Write, _) => {
+            Baseline::from_report(&report).save(&baseline_file)?;
+            eprintln!(
+                "{} Recorded {} violations in {}",
+                "✓".green(),
+                report.violations.len(),
+                baseline_file.display()
+            );
+            return Ok(false);
+        }
+        (BaselineMode::Update, Some(baseline)) => {
+            baseline.ratchet(&report).save(&baseline_file)?;
+            eprintln!(
+                "{} Removed {} fixed violations from {}",
+                "✓".green(),
+                report.fixed.len(),
+                baseline_file.display()
+            );
+        }
+        _ => {}
+    }
+
+    if print_report(&report, format)? {
+        return Ok(!report.passed());
+    }
+
+    println!("{}", "Architecture Check".yellow().bold());
+    println!(
+        "{} rules over {} layers, {} dependencies checked",
+        report.rules, report.layers, report.dependencies
+    );
+
+    let mut current_rule: Option<(&str, &str)> = None;
+    let mut new_violations: Vec<_> = report.new_violations().collect();
+    new_violations.sort_by(|a, b| (&a.rule, &a.message).cmp(&(&b.rule, &b.message)));
+    for violation in &new_violations {
+        let group = (violation.rule.as_str(), violation.message.as_str());
+        if current_rule != Some(group) {
+            println!();
+            println!(
+                "{} {}  {}",
+                "✗".red(),
+                violation.rule.red().bold(),
+                violation.message
+            );
+            current_rule = Some(group);
+        }
+        let location = match violation.column {
+            Some(column) => format!("{}:{}:{}", violation.file, violation.line, column + 1),
+            None => format!("{}:{}", violation.file, violation.line),
+        };
+        println!(
+            "    {}  {} {} {} {}",
+            location.dimmed(),
+            violation.source,
+            "→".dimmed(),
+            violation.target,
+            format!("({})", violation.kind).dimmed()
+        );
+    }
+
+    println!();
+    let baselined = report.violations.len() - new_violations.len();
+    let summary = format!(
+        "{} new violations, {} baselined, {} fixed",
+        new_violations.len(),
+        baselined,
+        report.fixed.len()
+    );
+    if report.passed() {
+        println!("{} {}", "✓".green(), summary);
+    } else {
+        println!("{} {}", "✗".red(), summary);
+    }
+    if !report.fixed.is_empty() && mode != BaselineMode::Update {
+        println!(
+            "  {}",
+            "run with --update-baseline to drop fixed violations from the baseline".dimmed()
+        );
+    }
+    Ok(!report.passed())
+}
+
+/// Reports functions over the complexity thresholds; returns whether any are.
+pub fn complexity(
+    max_lines: Option<u32>,
+    max_parameters: Option<usize>,
+    max_fan_out: Option<usize>,
+    rev: Option<&str>,
+    format: ReportFormat,
+) -> Result<bool> {
+    let path = std::env::current_dir()?;
+    let config = load_config(&path)?;
+    let mut thresholds = config.complexity.clone();
+    thresholds.max_lines = max_lines.unwrap_or(thresholds.max_lines);
+    thresholds.max_parameters = max_parameters.unwrap_or(thresholds.max_parameters);
+    thresholds.max_fan_out = max_fan_out.unwrap_or(thresholds.max_fan_out);
+
+    let mut graph = match rev {
+        Some(rev) => revision_graph(&path, rev)?,
+        None => index_project(&path, &config, &IndexOptions::default())?.graph,
+    };
+    let scores = compute_centrality(&graph, 20, 0.85);
+    graph.set_centrality(scores.into_map());
+
+    let report = graph.check_complexity(&thresholds);
+    let found = !report.issues.is_empty();
+    if print_report(&report, format)? {
+        return Ok(found);
+    }
+
+    println!("{}", "Complexity".yellow().bold());
+    println!(
+        "{} issues in {} functions {}",
+        report.issues.len().to_string().cyan(),
+        report.functions,
+        format!(
+            "(max {} lines, {} parameters, {} callees)",
+            thresholds.max_lines, thresholds.max_parameters, thresholds.max_fan_out
+        )
+        .dimmed()
+    );
+
+    let mut current = None;
+    for issue in &report.issues {
+        if current != Some(issue.metric) {
+            println!();
+            println!("  {}", issue.metric.to_string().cyan().bold());
+            current = Some(issue.metric);
+        }
+        println!(
+            "    {:>5}  {} {}",
+            issue.value.to_string().red(),
+            issue.node.qualified_name,
+            format!("({}:{})", issue.node.file, issue.node.line_start).dimmed()
+        );
+    }
+    if !found {
+        println!("{} Every function is within the limits", "✓".green());
+    }
+    Ok(found)
+}
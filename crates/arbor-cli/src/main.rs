@@ -3,6 +3,12 @@
 //! This is the main entry point for users interacting with Arbor.
 //! It provides commands for indexing, querying, and serving the code graph.
 
+use arbor_core::NodeKind;
+use arbor_graph::{
+    BodyQuery, BodyScope, CentralityMeasure, CentralityOptions, ClusterOptions, CollapseLevel,
+    CollapseOptions, Confidence, CycleLevel, CycleOptions, EdgeKind, MetricsLevel, MetricsOptions,
+    NodeFilter, QueryLimits,
+};
 use clap::{Parser, Subcommand};
 use colored::Colorize;
 use std::path::PathBuf;
@@ -42,6 +48,10 @@ enum Commands {
         /// Output file for the graph JSON
         #[arg(short, long)]
         output: Option<PathBuf>,
+
+        /// Index a git commit, branch or tag instead of the working tree
+        #[arg(long)]
+        rev: Option<String>,
     },
 
     /// Search the code graph
@@ -49,9 +59,385 @@ enum Commands {
         /// Search query
         query: String,
 
-        /// Maximum results to return
-        #[arg(short, long, default_value = "10")]
-        limit: usize,
+        /// Maximum results to return (defaults to the project config)
+        #[arg(short, long)]
+        limit: Option<usize>,
+
+        /// Only consider nodes from this workspace root (namespace)
+        #[arg(long)]
+        root: Option<String>,
+
+        /// Search a git commit, branch or tag instead of the working tree
+        #[arg(long)]
+        rev: Option<String>,
+
+        /// Only nodes of this kind (repeatable), e.g. function, struct
+        #[arg(long)]
+        kind: Vec<NodeKind>,
+
+        /// Only nodes in files matching this glob (repeatable)
+        #[arg(long)]
+        file: Vec<String>,
+
+        /// Only exported nodes
+        #[arg(long)]
+        exported: bool,
+    },
+
+    /// Search the text inside symbols and list the nodes it appears in
+    Grep {
+        /// Text to find, or a regular expression with --regex
+        pattern: String,
+
+        /// Treat the pattern as a regular expression
+        #[arg(short = 'e', long)]
+        regex: bool,
+
+        /// Match case exactly
+        #[arg(short = 's', long)]
+        case_sensitive: bool,
+
+        /// Where to look: any, code, strings or comments
+        #[arg(long, default_value = "any")]
+        scope: BodyScope,
+
+        /// Maximum nodes to return
+        #[arg(short, long)]
+        limit: Option<usize>,
+
+        /// Search a git commit, branch or tag instead of the working tree
+        #[arg(long)]
+        rev: Option<String>,
+
+        /// Output format
+        #[arg(long, value_enum, default_value = "text")]
+        format: commands::DataFormat,
+
+        /// Shorthand for --format json
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Run an ArborQL query (see docs/ARBORQL.md); `-` reads it from stdin
+    Ql {
+        /// The query, e.g. "MATCH (f:function)<-[:calls]-(c) RETURN c.name"
+        query: String,
+
+        /// Print the query plan instead of running it
+        #[arg(long)]
+        explain: bool,
+
+        /// Output format
+        #[arg(long, value_enum, default_value = "text")]
+        format: commands::DataFormat,
+
+        /// Shorthand for --format json
+        #[arg(long)]
+        json: bool,
+
+        /// Query a git commit, branch or tag instead of the working tree
+        #[arg(long)]
+        rev: Option<String>,
+
+        /// Intermediate rows a step may produce
+        #[arg(long)]
+        max_rows: Option<usize>,
+
+        /// Result rows to return
+        #[arg(long)]
+        max_results: Option<usize>,
+
+        /// Upper bound for open-ended hop ranges
+        #[arg(long)]
+        max_hops: Option<usize>,
+
+        /// Time budget in milliseconds
+        #[arg(long)]
+        timeout_ms: Option<u64>,
+    },
+
+    /// Compare the graphs of two git revisions
+    Diff {
+        /// Base revision (commit, branch or tag)
+        rev_a: String,
+
+        /// Revision to compare against (defaults to the working tree)
+        rev_b: Option<String>,
+
+        /// Only report API and graph shape changes, not body edits
+        #[arg(long)]
+        structural_only: bool,
+
+        /// Output format
+        #[arg(long, value_enum, default_value = "text")]
+        format: commands::DataFormat,
+
+        /// Shorthand for --format json
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Show what a patch touches: changed symbols, callers and tests
+    Impact {
+        /// Diff the working tree against this git ref instead of reading stdin
+        #[arg(long)]
+        since: Option<String>,
+
+        /// Maximum depth to search (defaults to the project config)
+        #[arg(short, long)]
+        depth: Option<usize>,
+
+        /// Output format
+        #[arg(long, value_enum, default_value = "text")]
+        format: commands::OutputFormat,
+
+        /// Shorthand for --format json
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// List the tests that reach changed symbols, files or a patch
+    TestsFor {
+        /// Symbols or files; reads a unified diff from stdin when empty
+        targets: Vec<String>,
+
+        /// Also include the changes since this git ref
+        #[arg(long)]
+        since: Option<String>,
+
+        /// Maximum depth to search (defaults to the project config)
+        #[arg(short, long)]
+        depth: Option<usize>,
+
+        /// Output format
+        #[arg(long, value_enum, default_value = "text")]
+        format: commands::DataFormat,
+
+        /// Shorthand for --format json
+        #[arg(long)]
+        json: bool,
+
+        /// Print only the runner commands, one per line
+        #[arg(long)]
+        commands: bool,
+    },
+
+    /// Find circular dependencies; exits with status 1 when there are any
+    /// and 2 on errors
+    Cycles {
+        /// Granularity: function, type, file, directory or package
+        #[arg(long, default_value = "file")]
+        level: CycleLevel,
+
+        /// Edge kind to follow (repeatable); defaults to every dependency kind
+        #[arg(long = "edge-kind")]
+        edge_kinds: Vec<EdgeKind>,
+
+        /// Include test code
+        #[arg(long)]
+        tests: bool,
+
+        /// Show at most this many cycles, largest first
+        #[arg(short, long)]
+        limit: Option<usize>,
+
+        /// Check a git commit, branch or tag instead of the working tree
+        #[arg(long)]
+        rev: Option<String>,
+
+        /// Output format
+        #[arg(long, value_enum, default_value = "text")]
+        format: commands::ReportFormat,
+
+        /// Shorthand for --format json
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Report functions and types no entry point reaches, grouped by file
+    Deadcode {
+        /// Only report findings at least this confident: low, medium or high
+        #[arg(long, default_value = "low")]
+        min_confidence: Confidence,
+
+        /// Extra entry points by name or qualified name glob (repeatable)
+        #[arg(long)]
+        entry: Vec<String>,
+
+        /// Don't treat public and exported symbols as entry points
+        #[arg(long)]
+        no_public_api: bool,
+
+        /// Check a git commit, branch or tag instead of the working tree
+        #[arg(long)]
+        rev: Option<String>,
+
+        /// Output format
+        #[arg(long, value_enum, default_value = "text")]
+        format: commands::ReportFormat,
+
+        /// Shorthand for --format json
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Check the architecture rules in .arbor/rules.json; exits 1 on new
+    /// violations and 2 if the check can't run
+    Check {
+        /// Rules file (defaults to .arbor/rules.json)
+        #[arg(long)]
+        rules: Option<PathBuf>,
+
+        /// Known violations that don't fail the check (defaults to
+        /// .arbor/check-baseline.json)
+        #[arg(long)]
+        baseline: Option<PathBuf>,
+
+        /// Record every current violation in the baseline
+        #[arg(long, conflicts_with = "update_baseline")]
+        write_baseline: bool,
+
+        /// Drop fixed violations from the baseline
+        #[arg(long)]
+        update_baseline: bool,
+
+        /// Check a git commit, branch or tag instead of the working tree
+        #[arg(long)]
+        rev: Option<String>,
+
+        /// Output format
+        #[arg(long, value_enum, default_value = "text")]
+        format: commands::ReportFormat,
+
+        /// Shorthand for --format json
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Report functions over the complexity limits in .arbor/config.json;
+    /// exits with status 1 when there are any
+    Complexity {
+        /// Maximum lines per function (0 turns the limit off)
+        #[arg(long)]
+        max_lines: Option<u32>,
+
+        /// Maximum parameters per function
+        #[arg(long = "max-params")]
+        max_parameters: Option<usize>,
+
+        /// Maximum distinct functions called per function
+        #[arg(long)]
+        max_fan_out: Option<usize>,
+
+        /// Check a git commit, branch or tag instead of the working tree
+        #[arg(long)]
+        rev: Option<String>,
+
+        /// Output format
+        #[arg(long, value_enum, default_value = "text")]
+        format: commands::ReportFormat,
+
+        /// Shorthand for --format json
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Group files into architecture clusters and summarize each one
+    Clusters {
+        /// Only this cluster, by ID
+        cluster: Option<String>,
+
+        /// Weight for an edge kind (repeatable), e.g. imports=2 or calls=0
+        #[arg(long = "weight", value_parser = parse_weight)]
+        weights: Vec<(EdgeKind, f64)>,
+
+        /// Higher values give more, smaller clusters
+        #[arg(long, default_value = "1.0")]
+        resolution: f64,
+
+        /// Cluster a git commit, branch or tag instead of the working tree
+        #[arg(long)]
+        rev: Option<String>,
+
+        /// Output format; markdown gives per-cluster onboarding summaries
+        #[arg(long, value_enum, default_value = "text")]
+        format: commands::OutputFormat,
+
+        /// Shorthand for --format json
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Collapse the graph into files, directories, packages or clusters, or
+    /// show what depends on one of them
+    Collapse {
+        /// A unit to show dependents and dependencies of, e.g. billing
+        unit: Option<String>,
+
+        /// Collapse into file, directory, package or cluster
+        #[arg(long, default_value = "package")]
+        level: CollapseLevel,
+
+        /// Edge kind to count (repeatable); defaults to every dependency kind
+        #[arg(long = "edge-kind")]
+        edge_kinds: Vec<EdgeKind>,
+
+        /// Include test code
+        #[arg(long)]
+        tests: bool,
+
+        /// Maximum hops from the unit (0 = unlimited; DOT and Mermaid
+        /// default to 1)
+        #[arg(short, long)]
+        depth: Option<usize>,
+
+        /// Show at most this many rows
+        #[arg(short, long)]
+        limit: Option<usize>,
+
+        /// Collapse a git commit, branch or tag instead of the working tree
+        #[arg(long)]
+        rev: Option<String>,
+
+        /// Output format; dot and mermaid export the graph
+        #[arg(long, value_enum, default_value = "text")]
+        format: commands::GraphFormat,
+
+        /// Shorthand for --format json
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Coupling, instability, abstractness and cohesion by file, directory or
+    /// package
+    Metrics {
+        /// Aggregate by file, directory or package
+        #[arg(long, default_value = "file")]
+        level: MetricsLevel,
+
+        /// Include test code
+        #[arg(long)]
+        tests: bool,
+
+        /// Show how the metrics changed since a git commit, branch or tag
+        #[arg(long)]
+        compare: Option<String>,
+
+        /// Show at most this many rows, furthest from the main sequence first
+        #[arg(short, long)]
+        limit: Option<usize>,
+
+        /// Measure a git commit, branch or tag instead of the working tree
+        #[arg(long)]
+        rev: Option<String>,
+
+        /// Output format
+        #[arg(long, value_enum, default_value = "text")]
+        format: commands::TableFormat,
+
+        /// Shorthand for --format json
+        #[arg(long)]
+        json: bool,
     },
 
     /// Start the Arbor server
@@ -114,9 +500,13 @@ enum Commands {
         /// The node to analyze (function name, class name, or qualified path)
         target: String,
 
-        /// Maximum depth to search (default: 5)
-        #[arg(short, long, default_value = "5")]
-        depth: usize,
+        /// Only consider nodes from this workspace root (namespace)
+        #[arg(long)]
+        root: Option<String>,
+
+        /// Maximum depth to search (defaults to the project config)
+        #[arg(short, long)]
+        depth: Option<usize>,
 
         /// Show detailed reasoning for each affected node
         #[arg(long)]
@@ -132,9 +522,21 @@ enum Commands {
         /// The question or code path to explain
         question: String,
 
-        /// Maximum tokens for context (default: 4000)
-        #[arg(short, long, default_value = "4000")]
-        tokens: usize,
+        /// Only consider nodes from this workspace root (namespace)
+        #[arg(long)]
+        root: Option<String>,
+
+        /// Maximum tokens for context (defaults to the project config)
+        #[arg(short, long)]
+        tokens: Option<usize>,
+
+        /// Order context by this measure, e.g. personalized_page_rank or betweenness
+        #[arg(long)]
+        centrality: Option<CentralityMeasure>,
+
+        /// Edge kind weight for --centrality (repeatable), e.g. imports=0.5
+        #[arg(long = "weight", value_parser = parse_weight, requires = "centrality")]
+        weights: Vec<(EdgeKind, f64)>,
 
         /// Show detailed reasoning for context selection
         #[arg(long)]
@@ -146,6 +548,18 @@ enum Commands {
     },
 }
 
+/// Parses `kind=weight` for `--weight`.
+fn parse_weight(s: &str) -> Result<(EdgeKind, f64), String> {
+    let (kind, weight) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected kind=weight, got '{}'", s))?;
+    let weight: f64 = weight
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid weight '{}'", weight))?;
+    Ok((kind.parse()?, weight))
+}
+
 #[tokio::main]
 async fn main() {
     let cli = Cli::parse();
@@ -163,8 +577,256 @@ async fn main() {
 
     let result = match cli.command {
         Commands::Init { path } => commands::init(&path),
-        Commands::Index { path, output } => commands::index(&path, output.as_deref()),
-        Commands::Query { query, limit } => commands::query(&query, limit),
+        Commands::Index { path, output, rev } => {
+            commands::index(&path, output.as_deref(), rev.as_deref())
+        }
+        Commands::Query {
+            query,
+            limit,
+            root,
+            rev,
+            kind,
+            file,
+            exported,
+        } => {
+            let mut filter = NodeFilter {
+                kinds: kind,
+                files: file,
+                root,
+                ..NodeFilter::default()
+            };
+            if exported {
+                filter = filter.with_exported(true);
+            }
+            commands::query(&query, limit, &filter, rev.as_deref())
+        }
+        Commands::Grep {
+            pattern,
+            regex,
+            case_sensitive,
+            scope,
+            limit,
+            rev,
+            format,
+            json,
+        } => {
+            let query = BodyQuery {
+                pattern,
+                regex,
+                ..BodyQuery::default()
+            }
+            .with_case_sensitive(case_sensitive)
+            .with_scope(scope)
+            .with_limit(limit.unwrap_or(0));
+            commands::grep(&query, rev.as_deref(), format.or_json(json))
+        }
+        Commands::Ql {
+            query,
+            explain,
+            format,
+            json,
+            rev,
+            max_rows,
+            max_results,
+            max_hops,
+            timeout_ms,
+        } => {
+            let mut limits = QueryLimits::default();
+            limits.max_rows = max_rows.unwrap_or(limits.max_rows);
+            limits.max_results = max_results.unwrap_or(limits.max_results);
+            limits.max_hops = max_hops.unwrap_or(limits.max_hops);
+            if let Some(ms) = timeout_ms {
+                limits.timeout = std::time::Duration::from_millis(ms);
+            }
+            commands::ql(
+                &query,
+                explain,
+                format.or_json(json),
+                rev.as_deref(),
+                &limits,
+            )
+        }
+        Commands::Diff {
+            rev_a,
+            rev_b,
+            structural_only,
+            format,
+            json,
+        } => commands::diff(
+            &rev_a,
+            rev_b.as_deref(),
+            structural_only,
+            format.or_json(json),
+        ),
+        Commands::Impact {
+            since,
+            depth,
+            format,
+            json,
+        } => commands::impact(since.as_deref(), depth, format.or_json(json)),
+        Commands::TestsFor {
+            targets,
+            since,
+            depth,
+            format,
+            json,
+            commands,
+        } => commands::tests_for(
+            &targets,
+            since.as_deref(),
+            depth,
+            format.or_json(json),
+            commands,
+        ),
+        Commands::Cycles {
+            level,
+            edge_kinds,
+            tests,
+            limit,
+            rev,
+            format,
+            json,
+        } => {
+            let options = CycleOptions {
+                level,
+                edge_kinds,
+                include_tests: tests,
+            };
+            match commands::cycles(&options, limit, rev.as_deref(), format.or_json(json)) {
+                Ok(true) => std::process::exit(1),
+                Ok(false) => Ok(()),
+                Err(e) => {
+                    eprintln!("{} {}", "error:".red().bold(), e);
+                    std::process::exit(2);
+                }
+            }
+        }
+        Commands::Deadcode {
+            min_confidence,
+            entry,
+            no_public_api,
+            rev,
+            format,
+            json,
+        } => commands::deadcode(
+            min_confidence,
+            &entry,
+            no_public_api,
+            rev.as_deref(),
+            format.or_json(json),
+        ),
+        Commands::Check {
+            rules,
+            baseline,
+            write_baseline,
+            update_baseline,
+            rev,
+            format,
+            json,
+        } => {
+            let baseline_mode = if write_baseline {
+                commands::BaselineMode::Write
+            } else if update_baseline {
+                commands::BaselineMode::Update
+            } else {
+                commands::BaselineMode::Read
+            };
+            match commands::check(
+                rules.as_deref(),
+                baseline.as_deref(),
+                baseline_mode,
+                rev.as_deref(),
+                format.or_json(json),
+            ) {
+                Ok(true) => std::process::exit(1),
+                Ok(false) => Ok(()),
+                Err(e) => {
+                    eprintln!("{} {}", "error:".red().bold(), e);
+                    std::process::exit(2);
+                }
+            }
+        }
+        Commands::Complexity {
+            max_lines,
+            max_parameters,
+            max_fan_out,
+            rev,
+            format,
+            json,
+        } => match commands::complexity(
+            max_lines,
+            max_parameters,
+            max_fan_out,
+            rev.as_deref(),
+            format.or_json(json),
+        ) {
+            Ok(true) => std::process::exit(1),
+            result => result.map(|_| ()),
+        },
+        Commands::Collapse {
+            unit,
+            level,
+            edge_kinds,
+            tests,
+            depth,
+            limit,
+            rev,
+            format,
+            json,
+        } => {
+            let format = format.or_json(json);
+            let options = CollapseOptions {
+                level,
+                edge_kinds,
+                include_tests: tests,
+            };
+            let depth = depth.unwrap_or(match format {
+                commands::GraphFormat::Dot | commands::GraphFormat::Mermaid => 1,
+                _ => 0,
+            });
+            commands::collapse(
+                &options,
+                unit.as_deref(),
+                depth,
+                limit,
+                rev.as_deref(),
+                format,
+            )
+        }
+        Commands::Metrics {
+            level,
+            tests,
+            compare,
+            limit,
+            rev,
+            format,
+            json,
+        } => commands::metrics(
+            &MetricsOptions::new(level).with_tests(tests),
+            compare.as_deref(),
+            limit,
+            rev.as_deref(),
+            format.or_json(json),
+        ),
+        Commands::Clusters {
+            cluster,
+            weights,
+            resolution,
+            rev,
+            format,
+            json,
+        } => {
+            let options = weights.into_iter().fold(
+                ClusterOptions::default().with_resolution(resolution),
+                |options, (kind, weight)| options.with_weight(kind, weight),
+            );
+            commands::clusters(
+                &options,
+                cluster.as_deref(),
+                rev.as_deref(),
+                format.or_json(json),
+            )
+        }
         Commands::Serve {
             port,
             headless,
@@ -177,16 +839,35 @@ async fn main() {
         Commands::CheckHealth => commands::check_health().await,
         Commands::Refactor {
             target,
+            root,
             depth,
             why,
             json,
-        } => commands::refactor(&target, depth, why, json),
+        } => commands::refactor(&target, root.as_deref(), depth, why, json),
         Commands::Explain {
             question,
+            root,
             tokens,
+            centrality,
+            weights,
             why,
             json,
-        } => commands::explain(&question, tokens, why, json),
+        } => {
+            let ranking = centrality.map(|measure| {
+                weights.into_iter().fold(
+                    CentralityOptions::new(measure),
+                    |options, (kind, weight)| options.with_weight(kind, weight),
+                )
+            });
+            commands::explain(
+                &question,
+                root.as_deref(),
+                tokens,
+                ranking.as_ref(),
+                why,
+                json,
+            )
+        }
     };
 
     if let Err(e) = result {
@@ -0,0 +1,823 @@
+//! Project configuration.
+//!
+//! `arbor init` writes `.arbor/config.json`. This module gives that file a
+//! typed shape and compiles it into a [`FileFilter`] so the indexer, the
+//! watcher and the servers all agree on which files belong to the project.
+//!
+//! Every key is optional. A missing file (or a missing key) falls back to
+//! the defaults below, so older configs keep working.
+
+use crate::languages::{self, LanguageParser};
+use crate::paths::ProjectRoot;
+use crate::source::{decode_source, DecodedSource, EncodingPolicy};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Directory that holds Arbor's per-project state.
+pub const CONFIG_DIR: &str = ".arbor";
+
+/// Name of the config file inside [`CONFIG_DIR`].
+pub const CONFIG_FILE: &str = "config.json";
+
+/// How many bytes from the top of a file we scan for generated-file markers.
+const GENERATED_SCAN_BYTES: usize = 2048;
+
+/// The marker reported for Go's generated-code header.
+const GO_GENERATED_MARKER: &str = "// Code generated ... DO NOT EDIT.";
+
+/// How many bytes we sample when checking for minified code.
+const MINIFIED_SCAN_BYTES: usize = 16 * 1024;
+
+/// Things that can go wrong when loading the project config.
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    /// Couldn't read the config file.
+    #[error("failed to read config '{path}': {source}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// The config file isn't valid JSON or has the wrong shape.
+    #[error("invalid config '{path}': {source}")]
+    Json {
+        path: PathBuf,
+        #[source]
+        source: serde_json::Error,
+    },
+
+    /// An include/ignore pattern isn't a valid glob.
+    #[error("invalid glob '{pattern}': {source}")]
+    Glob {
+        pattern: String,
+        #[source]
+        source: globset::Error,
+    },
+
+    /// A language name we don't have a parser for.
+    #[error("unknown language '{0}' (expected one of: {})", languages::supported_languages().join(", "))]
+    UnknownLanguage(String),
+
+    /// The workspace manifest is inconsistent.
+    #[error("invalid workspace: {0}")]
+    Workspace(String),
+
+    /// The architecture rules are inconsistent.
+    #[error("invalid architecture rules: {0}")]
+    Rules(String),
+}
+
+/// Defaults for query commands when the caller doesn't pass a value.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QueryDefaults {
+    /// Hop limit for impact analysis.
+    pub impact_depth: usize,
+    /// Hop limit for context slicing.
+    pub context_depth: usize,
+    /// Token budget for context slicing.
+    pub max_tokens: usize,
+    /// Result limit for searches.
+    pub search_limit: usize,
+}
+
+impl Default for QueryDefaults {
+    fn default() -> Self {
+        Self {
+            impact_depth: 5,
+            context_depth: 2,
+            max_tokens: 4000,
+            search_limit: 10,
+        }
+    }
+}
+
+/// Limits above which a function is reported as too complex. 0 turns a
+/// limit off.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ComplexityThresholds {
+    /// Lines in the body, including the signature.
+    pub max_lines: u32,
+    /// Declared parameters, not counting `self`, `this` or `cls`.
+    pub max_parameters: usize,
+    /// Distinct functions called.
+    pub max_fan_out: usize,
+}
+
+impl Default for ComplexityThresholds {
+    fn default() -> Self {
+        Self {
+            max_lines: 100,
+            max_parameters: 6,
+            max_fan_out: 15,
+        }
+    }
+}
+
+/// What dead code analysis treats as used no matter what calls it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EntryPoints {
+    /// Functions named `main`.
+    pub main: bool,
+
+    /// Exported symbols, and public ones outside Python (where every name
+    /// without a leading underscore is public). Turn off for applications
+    /// to find unused public code.
+    pub public_api: bool,
+
+    /// Test functions and classes.
+    pub tests: bool,
+
+    /// Globs over names or qualified names, e.g. `handle_*` or
+    /// `*Controller.*`.
+    pub names: Vec<String>,
+
+    /// Substrings of decorators, attributes or annotations that mark
+    /// framework handlers, e.g. `@app.route` or `#[get(`.
+    pub attributes: Vec<String>,
+
+    /// Files whose symbols are all entry points, same syntax as `include`.
+    pub files: Vec<String>,
+}
+
+impl Default for EntryPoints {
+    fn default() -> Self {
+        Self {
+            main: true,
+            public_api: true,
+            tests: true,
+            // Dunder methods are called by the Python runtime
+            names: vec!["__*__".to_string()],
+            attributes: [
+                // Python: Flask/FastAPI routes, Celery, Click, pytest
+                "@app.",
+                "@router.",
+                "@blueprint.",
+                ".task",
+                "@click.",
+                "@pytest.fixture",
+                "@receiver",
+                "@property",
+                // Rust
+                "#[test",
+                "#[tokio::main",
+                "#[get(",
+                "#[post(",
+                "#[put(",
+                "#[delete(",
+                "#[no_mangle",
+                // Java: Spring, JUnit
+                "Mapping",
+                "@Bean",
+                "@Override",
+                "@Test",
+                "@Scheduled",
+                "@EventListener",
+                // TypeScript: Angular, NestJS
+                "@Component",
+                "@Injectable",
+                "@Controller",
+                "@Get(",
+                "@Post(",
+                "@Put(",
+                "@Delete(",
+            ]
+            .iter()
+            .map(|s| s.to_string())
+            .collect(),
+            files: Vec::new(),
+        }
+    }
+}
+
+impl EntryPoints {
+    /// Compiles `names` into a glob set matched against names and
+    /// qualified names.
+    pub fn name_globs(&self) -> Result<GlobSet, ConfigError> {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in &self.names {
+            let glob = Glob::new(pattern.trim()).map_err(|source| ConfigError::Glob {
+                pattern: pattern.clone(),
+                source,
+            })?;
+            builder.add(glob);
+        }
+        builder.build().map_err(|source| ConfigError::Glob {
+            pattern: self.names.join(", "),
+            source,
+        })
+    }
+
+    /// Compiles `files` like `include` patterns.
+    pub fn file_globs(&self) -> Result<GlobSet, ConfigError> {
+        build_globset(&self.files)
+    }
+}
+
+/// What to do with generated or minified files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GeneratedPolicy {
+    /// Leave them out of the graph.
+    #[default]
+    Skip,
+    /// Index them, but mark their nodes as generated.
+    Tag,
+    /// Index them like any other file.
+    Index,
+}
+
+/// Why a file was judged to be generated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GeneratedReason {
+    /// A marker such as `@generated` near the top.
+    Marker(String),
+    /// The file name matches a `generated_files` pattern.
+    FileName,
+    /// Lines are far longer than hand-written code.
+    Minified,
+}
+
+impl std::fmt::Display for GeneratedReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Marker(marker) => write!(f, "generated file (marker '{}')", marker),
+            Self::FileName => write!(f, "generated file (file name)"),
+            Self::Minified => write!(f, "minified file"),
+        }
+    }
+}
+
+/// The contents of `.arbor/config.json`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ArborConfig {
+    /// Config format version.
+    pub version: String,
+
+    /// Enabled languages. Empty means every supported language.
+    pub languages: Vec<String>,
+
+    /// Globs a file must match to be indexed. Empty means everything.
+    pub include: Vec<String>,
+
+    /// Globs to skip. A bare name like `node_modules` matches that
+    /// directory or file anywhere in the tree.
+    #[serde(alias = "exclude")]
+    pub ignore: Vec<String>,
+
+    /// Extension → language overrides, e.g. `{"inc": "c"}`.
+    pub extensions: BTreeMap<String, String>,
+
+    /// Files larger than this many bytes are skipped (0 = no limit).
+    pub max_file_size: u64,
+
+    /// Strings that mark a file as generated when found near its top
+    /// (default `@generated`). Go's `// Code generated ... DO NOT EDIT.`
+    /// header is always recognised.
+    pub generated_markers: Vec<String>,
+
+    /// File name globs for generated code, e.g. `*.pb.go`.
+    pub generated_files: Vec<String>,
+
+    /// Average line length above which a file counts as minified (0 = off).
+    pub minified_line_length: usize,
+
+    /// What to do with generated and minified files.
+    pub generated: GeneratedPolicy,
+
+    /// How to read files that aren't plain UTF-8.
+    pub encoding: EncodingPolicy,
+
+    /// Worker threads for parsing (0 = one per CPU).
+    pub threads: usize,
+
+    /// Defaults for query commands.
+    pub defaults: QueryDefaults,
+
+    /// Roots for dead code analysis.
+    pub entry_points: EntryPoints,
+
+    /// Limits for complexity findings.
+    pub complexity: ComplexityThresholds,
+
+    /// Keep the text inside symbols for full-text search. Off by default
+    /// because it holds every indexed file in memory.
+    pub body_index: bool,
+}
+
+impl Default for ArborConfig {
+    fn default() -> Self {
+        Self {
+            version: "1.0".to_string(),
+            languages: Vec::new(),
+            include: Vec::new(),
+            ignore: vec![
+                "node_modules".to_string(),
+                "target".to_string(),
+                "dist".to_string(),
+                "__pycache__".to_string(),
+            ],
+            extensions: BTreeMap::new(),
+            max_file_size: 1024 * 1024,
+            generated_markers: vec!["@generated".to_string()],
+            generated_files: [
+                "*.min.js",
+                "*.min.mjs",
+                "*.bundle.js",
+                "*.pb.go",
+                "*.pb.cc",
+                "*.pb.h",
+                "*_pb2.py",
+                "*_pb2_grpc.py",
+                "*.g.dart",
+                "*.freezed.dart",
+                "*.designer.cs",
+            ]
+            .iter()
+            .map(|s| s.to_string())
+            .collect(),
+            minified_line_length: 500,
+            generated: GeneratedPolicy::Skip,
+            encoding: EncodingPolicy::Auto,
+            threads: 0,
+            defaults: QueryDefaults::default(),
+            entry_points: EntryPoints::default(),
+            complexity: ComplexityThresholds::default(),
+            body_index: false,
+        }
+    }
+}
+
+impl ArborConfig {
+    /// Returns the config path for a project root.
+    pub fn path_for(root: &Path) -> PathBuf {
+        root.join(CONFIG_DIR).join(CONFIG_FILE)
+    }
+
+    /// Loads the config for a project root.
+    ///
+    /// Returns the defaults if the project has no config file yet.
+    pub fn load(root: &Path) -> Result<Self, ConfigError> {
+        let path = Self::path_for(root);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        Self::from_file(&path)
+    }
+
+    /// Reads a config from an explicit path.
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let text = fs::read_to_string(path).map_err(|source| ConfigError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::from_json(&text, path)
+    }
+
+    /// Parses and validates config text. `origin` is only used in errors.
+    pub fn from_json(text: &str, origin: &Path) -> Result<Self, ConfigError> {
+        let config: Self = serde_json::from_str(text).map_err(|source| ConfigError::Json {
+            path: origin.to_path_buf(),
+            source,
+        })?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Writes the config to `<root>/.arbor/config.json`.
+    pub fn save(&self, root: &Path) -> Result<(), ConfigError> {
+        let path = Self::path_for(root);
+        let io_err = |source| ConfigError::Io {
+            path: path.clone(),
+            source,
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(io_err)?;
+        }
+        let text = serde_json::to_string_pretty(self).map_err(|source| ConfigError::Json {
+            path: path.clone(),
+            source,
+        })?;
+        fs::write(&path, text).map_err(io_err)
+    }
+
+    /// Checks that every language name is one we know.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let known = languages::supported_languages();
+        for lang in self.languages.iter().chain(self.extensions.values()) {
+            if !known.contains(&lang.as_str()) {
+                return Err(ConfigError::UnknownLanguage(lang.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the number of parser threads to use.
+    pub fn effective_threads(&self) -> usize {
+        if self.threads > 0 {
+            return self.threads;
+        }
+        std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+    }
+
+    /// Compiles the config into a filter for files under `root`.
+    pub fn file_filter(&self, root: &Path) -> Result<FileFilter, ConfigError> {
+        self.validate()?;
+
+        let include = if self.include.is_empty() {
+            None
+        } else {
+            Some(build_globset(&self.include)?)
+        };
+        let ignore = build_globset(&self.ignore)?;
+
+        let enabled = if self.languages.is_empty() {
+            languages::supported_languages().iter().copied().collect()
+        } else {
+            languages::supported_languages()
+                .iter()
+                .copied()
+                .filter(|l| self.languages.iter().any(|c| c == l))
+                .collect()
+        };
+
+        let overrides = self
+            .extensions
+            .iter()
+            .map(|(ext, lang)| {
+                let lang = languages::supported_languages()
+                    .iter()
+                    .copied()
+                    .find(|l| l == lang)
+                    .ok_or_else(|| ConfigError::UnknownLanguage(lang.clone()))?;
+                Ok((ext.trim_start_matches('.').to_lowercase(), lang))
+            })
+            .collect::<Result<_, ConfigError>>()?;
+
+        Ok(FileFilter {
+            root: root.to_path_buf(),
+            project: ProjectRoot::new(root),
+            include,
+            ignore,
+            enabled,
+            overrides,
+            max_file_size: self.max_file_size,
+            generated_markers: self.generated_markers.clone(),
+            generated_files: build_globset(&self.generated_files)?,
+            minified_line_length: self.minified_line_length,
+            generated: self.generated,
+            encoding: self.encoding,
+            body_index: self.body_index,
+        })
+    }
+}
+
+/// Returns at most `max` bytes of `s`, cut at a char boundary.
+fn truncate_at_char(s: &str, max: usize) -> &str {
+    let mut end = s.len().min(max);
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
+
+/// Whether a line is Go's generated-code header, i.e. matches
+/// `^// Code generated .* DO NOT EDIT\.$`.
+fn is_go_generated_header(line: &str) -> bool {
+    const PREFIX: &str = "// Code generated ";
+    const SUFFIX: &str = " DO NOT EDIT.";
+    line.len() >= PREFIX.len() + SUFFIX.len() && line.starts_with(PREFIX) && line.ends_with(SUFFIX)
+}
+
+/// Expands the user-facing patterns into globs and compiles them.
+///
+/// A pattern without a slash matches at any depth, and every pattern
+/// also matches everything beneath it, so `target` excludes
+/// `crates/foo/target/debug/x.rs`.
+pub(crate) fn build_globset(patterns: &[String]) -> Result<GlobSet, ConfigError> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let trimmed = pattern.trim().trim_start_matches("./");
+        if trimmed.is_empty() {
+            continue;
+        }
+        let base = if let Some(anchored) = trimmed.strip_prefix('/') {
+            anchored.trim_end_matches('/').to_string()
+        } else if trimmed.contains('/') {
+            trimmed.trim_end_matches('/').to_string()
+        } else {
+            format!("**/{}", trimmed)
+        };
+        for glob in [base.clone(), format!("{}/**", base)] {
+            let compiled = Glob::new(&glob).map_err(|source| ConfigError::Glob {
+                pattern: pattern.clone(),
+                source,
+            })?;
+            builder.add(compiled);
+        }
+    }
+    builder.build().map_err(|source| ConfigError::Glob {
+        pattern: patterns.join(", "),
+        source,
+    })
+}
+
+/// A compiled view of [`ArborConfig`] for deciding what to index.
+///
+/// Paths may be absolute or relative to the project root; absolute
+/// paths are made relative before matching.
+#[derive(Debug, Clone)]
+pub struct FileFilter {
+    root: PathBuf,
+    project: ProjectRoot,
+    include: Option<GlobSet>,
+    ignore: GlobSet,
+    enabled: HashSet<&'static str>,
+    overrides: BTreeMap<String, &'static str>,
+    max_file_size: u64,
+    generated_markers: Vec<String>,
+    generated_files: GlobSet,
+    minified_line_length: usize,
+    generated: GeneratedPolicy,
+    encoding: EncodingPolicy,
+    body_index: bool,
+}
+
+impl FileFilter {
+    /// Returns the project root this filter was built for.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Makes a path relative to the project root for glob matching.
+    fn relative<'a>(&self, path: &'a Path) -> &'a Path {
+        if let Ok(rel) = path.strip_prefix(&self.root) {
+            return rel;
+        }
+        if let Ok(rel) = path.strip_prefix(self.project.path()) {
+            return rel;
+        }
+        path
+    }
+
+    /// Returns the normalized project root.
+    pub fn project_root(&self) -> &ProjectRoot {
+        &self.project
+    }
+
+    /// Returns the form of `path` stored in nodes, IDs and the store.
+    pub fn stored_path(&self, path: &Path) -> String {
+        self.project.relative_or_normalized(path)
+    }
+
+    /// Files from this root are stored under `namespace/` and their nodes
+    /// tagged with it. Used for workspace roots.
+    pub fn with_namespace(mut self, namespace: &str) -> Self {
+        self.project = self.project.with_namespace(namespace);
+        self
+    }
+
+    /// Returns the workspace namespace, if this filter is for a workspace root.
+    pub fn namespace(&self) -> Option<&str> {
+        self.project.namespace()
+    }
+
+    /// Whether an ignore pattern matches this path.
+    ///
+    /// Works on directories too, so walkers can prune whole subtrees.
+    pub fn is_ignored(&self, path: &Path) -> bool {
+        let rel = self.relative(path);
+        !rel.as_os_str().is_empty() && self.ignore.is_match(rel)
+    }
+
+    /// Returns the enabled language for a file, honoring overrides.
+    pub fn language_for(&self, path: &Path) -> Option<&'static str> {
+        let ext = path.extension()?.to_str()?.to_lowercase();
+        let lang = match self.overrides.get(&ext) {
+            Some(lang) => *lang,
+            None => languages::language_name(&ext)?,
+        };
+        self.enabled.contains(lang).then_some(lang)
+    }
+
+    /// Returns a parser for a file, honoring overrides.
+    pub fn parser_for(&self, path: &Path) -> Option<Box<dyn LanguageParser>> {
+        languages::get_parser_for_language(self.language_for(path)?)
+    }
+
+    /// Whether a file should be indexed, judged by its path alone.
+    pub fn should_index(&self, path: &Path) -> bool {
+        if self.is_ignored(path) {
+            return false;
+        }
+        if let Some(include) = &self.include {
+            if !include.is_match(self.relative(path)) {
+                return false;
+            }
+        }
+        self.language_for(path).is_some()
+    }
+
+    /// Whether a file of this size is over the configured limit.
+    pub fn exceeds_size_limit(&self, size: u64) -> bool {
+        self.max_file_size > 0 && size > self.max_file_size
+    }
+
+    /// Whether the top of the source contains a generated-file marker.
+    pub fn is_generated(&self, source: &str) -> bool {
+        self.generated_marker(source).is_some()
+    }
+
+    fn generated_marker(&self, source: &str) -> Option<&str> {
+        let head = truncate_at_char(source, GENERATED_SCAN_BYTES);
+        if head.lines().any(is_go_generated_header) {
+            return Some(GO_GENERATED_MARKER);
+        }
+        self.generated_markers
+            .iter()
+            .find(|marker| !marker.is_empty() && head.contains(marker.as_str()))
+            .map(String::as_str)
+    }
+
+    /// Checks the file name, markers and line lengths for generated code.
+    pub fn generated_reason(&self, path: &Path, source: &str) -> Option<GeneratedReason> {
+        if self.generated_files.is_match(self.relative(path)) {
+            return Some(GeneratedReason::FileName);
+        }
+        if let Some(marker) = self.generated_marker(source) {
+            return Some(GeneratedReason::Marker(marker.to_string()));
+        }
+        if self.minified_line_length > 0 {
+            let sample = truncate_at_char(source, MINIFIED_SCAN_BYTES);
+            let lines = sample.lines().count().max(1);
+            if sample.len() >= self.minified_line_length
+                && sample.len() / lines > self.minified_line_length
+            {
+                return Some(GeneratedReason::Minified);
+            }
+        }
+        None
+    }
+
+    /// What to do with generated and minified files.
+    pub fn generated_policy(&self) -> GeneratedPolicy {
+        self.generated
+    }
+
+    /// Whether parsed files should be kept for the body index.
+    pub fn body_index(&self) -> bool {
+        self.body_index
+    }
+
+    /// The configured encoding policy.
+    pub fn encoding(&self) -> EncodingPolicy {
+        self.encoding
+    }
+
+    /// Decodes file contents with the configured encoding policy.
+    ///
+    /// Returns `None` if the policy is strict and the bytes aren't UTF-8.
+    pub fn decode(&self, bytes: &[u8]) -> Option<DecodedSource> {
+        decode_source(bytes, self.encoding)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_missing_config_uses_defaults() {
+        let dir = tempdir().unwrap();
+        let config = ArborConfig::load(dir.path()).unwrap();
+        assert_eq!(config, ArborConfig::default());
+    }
+
+    #[test]
+    fn test_load_partial_config() {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join(CONFIG_DIR)).unwrap();
+        fs::write(
+            ArborConfig::path_for(dir.path()),
+            r#"{ "version": "1.0", "languages": ["rust"], "ignore": ["vendor"] }"#,
+        )
+        .unwrap();
+
+        let config = ArborConfig::load(dir.path()).unwrap();
+        assert_eq!(config.languages, vec!["rust"]);
+        assert_eq!(config.ignore, vec!["vendor"]);
+        assert_eq!(config.defaults, QueryDefaults::default());
+    }
+
+    #[test]
+    fn test_unknown_language_is_rejected() {
+        let config = ArborConfig {
+            languages: vec!["cobol".to_string()],
+            ..Default::default()
+        };
+        assert!(matches!(
+            config.file_filter(Path::new(".")),
+            Err(ConfigError::UnknownLanguage(_))
+        ));
+    }
+
+    #[test]
+    fn test_ignore_matches_at_any_depth() {
+        let filter = ArborConfig::default()
+            .file_filter(Path::new("/repo"))
+            .unwrap();
+
+        assert!(filter.is_ignored(Path::new("/repo/node_modules")));
+        assert!(filter.is_ignored(Path::new("/repo/web/node_modules/x/index.js")));
+        assert!(filter.is_ignored(Path::new("crates/core/target/debug/build.rs")));
+        assert!(!filter.should_index(Path::new("/repo/target/foo.rs")));
+        assert!(filter.should_index(Path::new("/repo/src/main.rs")));
+    }
+
+    #[test]
+    fn test_include_and_languages() {
+        let config = ArborConfig {
+            languages: vec!["python".to_string()],
+            include: vec!["src/**".to_string()],
+            ..Default::default()
+        };
+        let filter = config.file_filter(Path::new("/repo")).unwrap();
+
+        assert!(filter.should_index(Path::new("/repo/src/app.py")));
+        assert!(!filter.should_index(Path::new("/repo/scripts/tool.py")));
+        assert!(!filter.should_index(Path::new("/repo/src/lib.rs")));
+    }
+
+    #[test]
+    fn test_extension_overrides() {
+        let mut config = ArborConfig::default();
+        config.extensions.insert("inc".to_string(), "c".to_string());
+        let filter = config.file_filter(Path::new(".")).unwrap();
+
+        assert_eq!(filter.language_for(Path::new("defs.inc")), Some("c"));
+        assert!(filter.parser_for(Path::new("defs.inc")).is_some());
+        assert_eq!(filter.language_for(Path::new("notes.txt")), None);
+    }
+
+    #[test]
+    fn test_size_limit_and_generated_markers() {
+        let filter = ArborConfig::default().file_filter(Path::new(".")).unwrap();
+
+        assert!(!filter.exceeds_size_limit(10));
+        assert!(filter.exceeds_size_limit(10 * 1024 * 1024));
+        assert!(filter.is_generated("// @generated by build.rs\nfn main() {}"));
+        assert!(filter.is_generated("// Code generated by protoc. DO NOT EDIT.\npackage pb"));
+        assert!(filter
+            .is_generated("// Copyright\r\n\r\n// Code generated by stringer. DO NOT EDIT.\r\n"));
+        assert!(!filter.is_generated("// DO NOT EDIT the order below\nfn main() {}"));
+        assert!(!filter.is_generated("fn main() {}"));
+    }
+
+    #[test]
+    fn test_generated_reason() {
+        let filter = ArborConfig::default().file_filter(Path::new(".")).unwrap();
+        let plain = "function add(a, b) {\n  return a + b;\n}\n";
+
+        assert_eq!(
+            filter.generated_reason(Path::new("web/vendor.min.js"), plain),
+            Some(GeneratedReason::FileName)
+        );
+        assert_eq!(
+            filter.generated_reason(Path::new("api/user.pb.go"), plain),
+            Some(GeneratedReason::FileName)
+        );
+        assert_eq!(
+            filter.generated_reason(Path::new("gen.rs"), "// @generated\nfn f() {}"),
+            Some(GeneratedReason::Marker("@generated".to_string()))
+        );
+
+        let minified = "var a=1;".repeat(200);
+        assert_eq!(
+            filter.generated_reason(Path::new("app.js"), &minified),
+            Some(GeneratedReason::Minified)
+        );
+        assert_eq!(filter.generated_reason(Path::new("app.js"), plain), None);
+    }
+
+    #[test]
+    fn test_save_round_trip() {
+        let dir = tempdir().unwrap();
+        let config = ArborConfig {
+            threads: 4,
+            ..Default::default()
+        };
+        config.save(dir.path()).unwrap();
+        assert_eq!(ArborConfig::load(dir.path()).unwrap(), config);
+    }
+}
@@ -34,6 +34,13 @@ pub enum ParseError {
     #[error("query error: {0}")]
     QueryError(String),
 
+    /// Tree-sitter gave up after the configured parse timeout.
+    #[error("parsing '{path}' timed out after {timeout:?}")]
+    Timeout {
+        path: PathBuf,
+        timeout: std::time::Duration,
+    },
+
     /// The file exists but is empty. Not really an error,
     /// but we surface it so callers can handle it gracefully.
     #[error("file is empty: '{0}'")]
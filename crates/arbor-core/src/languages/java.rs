@@ -140,7 +140,8 @@ fn extract_class(node: &Node, source: &str, file_path: &str) -> Option<CodeNode>
             )
             .with_bytes(node.start_byte() as u32, node.end_byte() as u32)
             .with_column(name_node.start_position().column as u32)
-            .with_visibility(visibility),
+            .with_visibility(visibility)
+            .with_attributes(annotations(node, source)),
     )
 }
 
@@ -158,7 +159,8 @@ fn extract_interface(node: &Node, source: &str, file_path: &str) -> Option<CodeN
             )
             .with_bytes(node.start_byte() as u32, node.end_byte() as u32)
             .with_column(name_node.start_position().column as u32)
-            .with_visibility(visibility),
+            .with_visibility(visibility)
+            .with_attributes(annotations(node, source)),
     )
 }
 
@@ -176,7 +178,8 @@ fn extract_enum(node: &Node, source: &str, file_path: &str) -> Option<CodeNode>
             )
             .with_bytes(node.start_byte() as u32, node.end_byte() as u32)
             .with_column(name_node.start_position().column as u32)
-            .with_visibility(visibility),
+            .with_visibility(visibility)
+            .with_attributes(annotations(node, source)),
     )
 }
 
@@ -209,7 +212,8 @@ fn extract_method(
             .with_column(name_node.start_position().column as u32)
             .with_signature(signature)
             .with_visibility(visibility)
-            .with_references(references),
+            .with_references(references)
+            .with_attributes(annotations(node, source)),
     )
 }
 
@@ -244,7 +248,8 @@ fn extract_constructor(
             .with_bytes(node.start_byte() as u32, node.end_byte() as u32)
             .with_column(name_node.start_position().column as u32)
             .with_signature(signature)
-            .with_visibility(visibility),
+            .with_visibility(visibility)
+            .with_attributes(annotations(node, source)),
     )
 }
 
@@ -356,6 +361,23 @@ fn detect_visibility(node: &Node, source: &str) -> Visibility {
     Visibility::Internal
 }
 
+/// Returns the annotations in a declaration's modifiers, e.g. `@Override`.
+fn annotations(node: &Node, source: &str) -> Vec<String> {
+    let mut cursor = node.walk();
+    let Some(modifiers) = node
+        .children(&mut cursor)
+        .find(|child| child.kind() == "modifiers")
+    else {
+        return Vec::new();
+    };
+    let mut cursor = modifiers.walk();
+    modifiers
+        .children(&mut cursor)
+        .filter(|child| matches!(child.kind(), "marker_annotation" | "annotation"))
+        .map(|child| get_text(&child, source))
+        .collect()
+}
+
 /// Builds a method signature.
 fn build_method_signature(node: &Node, source: &str, name: &str) -> String {
     let return_type = node
@@ -92,3 +92,60 @@ pub fn supported_extensions() -> &'static [&'static str] {
 pub fn is_supported(extension: &str) -> bool {
     get_parser(extension).is_some()
 }
+
+/// Lists the language names accepted in `.arbor/config.json`.
+pub fn supported_languages() -> &'static [&'static str] {
+    &[
+        "typescript",
+        "javascript",
+        "rust",
+        "python",
+        "go",
+        "java",
+        "c",
+        "cpp",
+        "dart",
+    ]
+}
+
+/// Maps a file extension to its language name.
+///
+/// Returns None if we don't support this extension.
+pub fn language_name(extension: &str) -> Option<&'static str> {
+    match extension.to_lowercase().as_str() {
+        "ts" | "tsx" | "mts" | "cts" => Some("typescript"),
+        "js" | "jsx" | "mjs" | "cjs" => Some("javascript"),
+        "rs" => Some("rust"),
+        "py" | "pyi" => Some("python"),
+        "go" => Some("go"),
+        "java" => Some("java"),
+        "c" | "h" => Some("c"),
+        "cpp" | "hpp" | "cc" | "hh" | "cxx" | "hxx" => Some("cpp"),
+        "dart" => Some("dart"),
+        _ => None,
+    }
+}
+
+/// Returns the canonical extension for a language name.
+///
+/// Useful when a file's real extension was overridden in the config
+/// and we still need to pick a grammar for it.
+pub fn primary_extension(language: &str) -> Option<&'static str> {
+    match language {
+        "typescript" => Some("ts"),
+        "javascript" => Some("js"),
+        "rust" => Some("rs"),
+        "python" => Some("py"),
+        "go" => Some("go"),
+        "java" => Some("java"),
+        "c" => Some("c"),
+        "cpp" => Some("cpp"),
+        "dart" => Some("dart"),
+        _ => None,
+    }
+}
+
+/// Gets a parser for the given language name.
+pub fn get_parser_for_language(language: &str) -> Option<Box<dyn LanguageParser>> {
+    get_parser(primary_extension(language)?)
+}
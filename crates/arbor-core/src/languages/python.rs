@@ -127,8 +127,10 @@ fn extract_function(
     let is_async = has_async_keyword(node, source);
 
     // Check for @staticmethod or @classmethod
-    let is_static =
-        has_decorator(node, source, "staticmethod") || has_decorator(node, source, "classmethod");
+    let decorators = decorators(node, source);
+    let is_static = decorators
+        .iter()
+        .any(|d| d == "@staticmethod" || d == "@classmethod");
 
     // Build signature
     let signature = build_function_signature(node, source, &name);
@@ -152,7 +154,8 @@ fn extract_function(
             .with_references(references)
             .with_docstring_if(docstring)
             .with_async_if(is_async)
-            .with_static_if(is_static),
+            .with_static_if(is_static)
+            .with_attributes(decorators),
     )
 }
 
@@ -174,7 +177,8 @@ fn extract_class(node: &Node, source: &str, file_path: &str) -> Option<CodeNode>
             .with_bytes(node.start_byte() as u32, node.end_byte() as u32)
             .with_column(name_node.start_position().column as u32)
             .with_visibility(visibility)
-            .with_docstring_if(docstring),
+            .with_docstring_if(docstring)
+            .with_attributes(decorators(node, source)),
     )
 }
 
@@ -287,19 +291,18 @@ fn has_async_keyword(node: &Node, source: &str) -> bool {
     false
 }
 
-/// Checks if function has a specific decorator.
-fn has_decorator(node: &Node, source: &str, decorator_name: &str) -> bool {
-    for i in 0..node.child_count() {
-        if let Some(child) = node.child(i) {
-            if child.kind() == "decorator" {
-                let text = get_text(&child, source);
-                if text.contains(decorator_name) {
-                    return true;
-                }
-            }
-        }
-    }
-    false
+/// Returns the decorators on a function or class. They live on the
+/// enclosing `decorated_definition`, not the definition itself.
+fn decorators(node: &Node, source: &str) -> Vec<String> {
+    let Some(parent) = node.parent().filter(|p| p.kind() == "decorated_definition") else {
+        return Vec::new();
+    };
+    let mut cursor = parent.walk();
+    parent
+        .children(&mut cursor)
+        .filter(|child| child.kind() == "decorator")
+        .map(|child| get_text(&child, source).trim().to_string())
+        .collect()
 }
 
 /// Builds a function signature.
@@ -174,10 +174,27 @@ fn extract_function(
             .with_signature(signature)
             .with_visibility(visibility)
             .with_references(references)
-            .with_async_if(is_async),
+            .with_async_if(is_async)
+            .with_attributes(outer_attributes(node, source)),
     )
 }
 
+/// Returns the `#[...]` attributes written above an item, in source order.
+fn outer_attributes(node: &Node, source: &str) -> Vec<String> {
+    let mut attributes = Vec::new();
+    let mut sibling = node.prev_named_sibling();
+    while let Some(current) = sibling {
+        match current.kind() {
+            "attribute_item" => attributes.push(get_text(&current, source)),
+            "line_comment" | "block_comment" => {}
+            _ => break,
+        }
+        sibling = current.prev_named_sibling();
+    }
+    attributes.reverse();
+    attributes
+}
+
 /// Extracts a struct definition.
 fn extract_struct(node: &Node, source: &str, file_path: &str) -> Option<CodeNode> {
     let name_node = node.child_by_field_name("name")?;
@@ -192,7 +209,8 @@ fn extract_struct(node: &Node, source: &str, file_path: &str) -> Option<CodeNode
             )
             .with_bytes(node.start_byte() as u32, node.end_byte() as u32)
             .with_column(name_node.start_position().column as u32)
-            .with_visibility(visibility),
+            .with_visibility(visibility)
+            .with_attributes(outer_attributes(node, source)),
     )
 }
 
@@ -210,7 +228,8 @@ fn extract_enum(node: &Node, source: &str, file_path: &str) -> Option<CodeNode>
             )
             .with_bytes(node.start_byte() as u32, node.end_byte() as u32)
             .with_column(name_node.start_position().column as u32)
-            .with_visibility(visibility),
+            .with_visibility(visibility)
+            .with_attributes(outer_attributes(node, source)),
     )
 }
 
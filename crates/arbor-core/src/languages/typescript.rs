@@ -236,7 +236,8 @@ fn extract_class(node: &Node, source: &str, file_path: &str) -> Option<CodeNode>
             } else {
                 Visibility::Private
             })
-            .with_exported_if(is_exported),
+            .with_exported_if(is_exported)
+            .with_attributes(decorators(node, source)),
     )
 }
 
@@ -275,10 +276,31 @@ fn extract_method(
             .with_visibility(visibility)
             .with_references(references)
             .with_async_if(is_async)
-            .with_static_if(is_static),
+            .with_static_if(is_static)
+            .with_attributes(decorators(node, source)),
     )
 }
 
+/// Returns the decorators on a class or method. Method decorators and
+/// those of an exported class precede it as siblings; others are children.
+fn decorators(node: &Node, source: &str) -> Vec<String> {
+    let mut found = Vec::new();
+    let mut sibling = node.prev_named_sibling();
+    while let Some(current) = sibling.filter(|s| s.kind() == "decorator") {
+        found.push(get_text(&current, source));
+        sibling = current.prev_named_sibling();
+    }
+    found.reverse();
+
+    let mut cursor = node.walk();
+    found.extend(
+        node.children(&mut cursor)
+            .filter(|child| child.kind() == "decorator")
+            .map(|child| get_text(&child, source)),
+    );
+    found
+}
+
 /// Extracts an interface declaration.
 fn extract_interface(node: &Node, source: &str, file_path: &str) -> Option<CodeNode> {
     let name_node = node.child_by_field_name("name")?;
@@ -16,14 +16,27 @@
 //! }
 //! ```
 
+pub mod config;
 pub mod error;
 pub mod languages;
 pub mod node;
 pub mod parser;
 pub mod parser_v2;
+pub mod paths;
+pub mod rules;
+pub mod source;
+pub mod workspace;
 
+pub use config::{
+    ArborConfig, ComplexityThresholds, ConfigError, EntryPoints, FileFilter, GeneratedPolicy,
+    GeneratedReason, QueryDefaults,
+};
 pub use error::{ParseError, Result};
 pub use languages::LanguageParser;
 pub use node::{CodeNode, NodeKind, Visibility};
-pub use parser::{detect_language, parse_file, parse_source};
+pub use parser::{detect_language, parse_file, parse_source, parse_source_with_timeout};
 pub use parser_v2::{ArborParser, ParseResult, RelationType, SymbolRelation};
+pub use paths::{normalize_path, ProjectRoot};
+pub use rules::{ArchitectureRules, Layer, LayerMatcher, Rule, RuleKind, RULES_FILE};
+pub use source::{decode_source, DecodedSource, Encoding, EncodingPolicy};
+pub use workspace::{Workspace, WorkspaceRoot, WORKSPACE_FILE};
@@ -6,6 +6,7 @@
 
 use serde::{Deserialize, Serialize};
 use std::hash::{Hash, Hasher};
+use std::str::FromStr;
 
 /// The kind of code entity this node represents.
 ///
@@ -67,6 +68,39 @@ impl std::fmt::Display for NodeKind {
     }
 }
 
+impl NodeKind {
+    /// Every kind, in declaration order.
+    pub const ALL: [NodeKind; 14] = [
+        Self::Function,
+        Self::Method,
+        Self::Class,
+        Self::Interface,
+        Self::Struct,
+        Self::Enum,
+        Self::Variable,
+        Self::Constant,
+        Self::TypeAlias,
+        Self::Module,
+        Self::Import,
+        Self::Export,
+        Self::Constructor,
+        Self::Field,
+    ];
+}
+
+impl FromStr for NodeKind {
+    type Err = String;
+
+    /// Parses the names `Display` prints, e.g. `function` or `type_alias`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let wanted = s.trim().to_lowercase();
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.to_string() == wanted)
+            .ok_or_else(|| format!("unknown node kind '{}'", s))
+    }
+}
+
 /// Visibility of a code entity.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
 #[serde(rename_all = "snake_case")]
@@ -79,6 +113,32 @@ pub enum Visibility {
     Internal,
 }
 
+impl std::fmt::Display for Visibility {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Private => "private",
+            Self::Public => "public",
+            Self::Protected => "protected",
+            Self::Internal => "internal",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl FromStr for Visibility {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "private" => Ok(Self::Private),
+            "public" | "pub" => Ok(Self::Public),
+            "protected" => Ok(Self::Protected),
+            "internal" | "crate" => Ok(Self::Internal),
+            _ => Err(format!("unknown visibility '{}'", s)),
+        }
+    }
+}
+
 /// A code entity extracted from source.
 ///
 /// This is the core data type that flows through Arbor. It's designed
@@ -97,7 +157,8 @@ pub struct CodeNode {
     /// What kind of entity this is.
     pub kind: NodeKind,
 
-    /// Path to the source file, relative to project root.
+    /// Path to the source file, relative to project root. In a workspace
+    /// it starts with the root's namespace.
     pub file: String,
 
     /// Starting line (1-indexed, like editors show).
@@ -134,6 +195,33 @@ pub struct CodeNode {
     /// Entities this node references (call targets, type refs, etc).
     /// These are names, not IDs - resolution happens in the graph crate.
     pub references: Vec<String>,
+
+    /// Comes from a generated or minified file.
+    #[serde(default)]
+    pub generated: bool,
+
+    /// Namespace of the workspace root this node belongs to.
+    #[serde(default)]
+    pub root: Option<String>,
+
+    /// Decorators, attributes or annotations as written, e.g.
+    /// `@app.route("/")`, `#[test]` or `@GetMapping`.
+    #[serde(default)]
+    pub attributes: Vec<String>,
+
+    /// ID of the architecture cluster this node's file belongs to.
+    #[serde(default)]
+    pub cluster: Option<String>,
+
+    /// Hash of the node's source text with its own name blanked out, so
+    /// edits show up even when the length doesn't change. 0 if unknown.
+    #[serde(default)]
+    pub body_hash: u64,
+
+    /// Fields a method or constructor uses through `self.`, `this.` or
+    /// `this->`, sorted and deduplicated. Method calls aren't included.
+    #[serde(default)]
+    pub accessed_fields: Vec<String>,
 }
 
 impl CodeNode {
@@ -182,6 +270,12 @@ impl CodeNode {
             byte_start: 0,
             byte_end: 0,
             references: Vec::new(),
+            generated: false,
+            root: None,
+            attributes: Vec::new(),
+            cluster: None,
+            body_hash: 0,
+            accessed_fields: Vec::new(),
         }
     }
 
@@ -199,6 +293,51 @@ impl CodeNode {
         self
     }
 
+    /// Builder pattern: hash the node's source text into `body_hash` and,
+    /// for methods and constructors, fill `accessed_fields` from it.
+    pub fn with_body(mut self, body: &str) -> Self {
+        self.body_hash = Self::hash_body(&self.name, body);
+        if matches!(self.kind, NodeKind::Method | NodeKind::Constructor) {
+            self.accessed_fields = Self::fields_in(body);
+        }
+        self
+    }
+
+    /// Names following `self.`, `this.` or `this->` that aren't called.
+    pub fn fields_in(body: &str) -> Vec<String> {
+        let mut fields = std::collections::BTreeSet::new();
+        for receiver in ["self.", "this.", "this->"] {
+            for (at, _) in body.match_indices(receiver) {
+                let bounded = body[..at]
+                    .chars()
+                    .next_back()
+                    .is_none_or(|c| !(c.is_alphanumeric() || c == '_' || c == '.'));
+                let rest = &body[at + receiver.len()..];
+                let end = rest
+                    .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                    .unwrap_or(rest.len());
+                let name = &rest[..end];
+                let called = rest[end..].trim_start().starts_with('(');
+                if bounded && !called && !name.is_empty() && !name.starts_with(char::is_numeric) {
+                    fields.insert(name.to_string());
+                }
+            }
+        }
+        fields.into_iter().collect()
+    }
+
+    /// Hashes a node's source text the way `body_hash` expects.
+    ///
+    /// Uses 64-bit FNV-1a, so hashes saved with a revision still compare
+    /// equal after Arbor is rebuilt with another compiler.
+    pub fn hash_body(name: &str, body: &str) -> u64 {
+        if name.is_empty() {
+            fnv1a(body.as_bytes())
+        } else {
+            fnv1a(body.replacen(name, "_", 1).as_bytes())
+        }
+    }
+
     /// Builder pattern: set column.
     pub fn with_column(mut self, column: u32) -> Self {
         self.column = column;
@@ -240,6 +379,18 @@ impl CodeNode {
         self.references = refs;
         self
     }
+
+    /// Builder pattern: set decorators/attributes/annotations.
+    pub fn with_attributes(mut self, attributes: Vec<String>) -> Self {
+        self.attributes = attributes;
+        self
+    }
+
+    /// Whether this node belongs to the workspace root `namespace`.
+    /// Every node matches `None`.
+    pub fn in_root(&self, namespace: Option<&str>) -> bool {
+        namespace.is_none_or(|ns| self.root.as_deref() == Some(ns))
+    }
 }
 
 impl PartialEq for CodeNode {
@@ -255,3 +406,28 @@ impl Hash for CodeNode {
         self.id.hash(state);
     }
 }
+
+/// 64-bit FNV-1a.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+    bytes.iter().fold(OFFSET_BASIS, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(PRIME)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_body_hash_is_stable() {
+        // Reference FNV-1a values; these must never change
+        assert_eq!(CodeNode::hash_body("", ""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(CodeNode::hash_body("", "a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(
+            CodeNode::hash_body("add", "fn add() {}"),
+            CodeNode::hash_body("sum", "fn sum() {}")
+        );
+    }
+}
@@ -6,9 +6,12 @@
 
 use crate::error::{ParseError, Result};
 use crate::languages::{get_parser, LanguageParser};
-use crate::node::CodeNode;
+use crate::node::{CodeNode, NodeKind};
+use crate::paths::normalize_path;
+use crate::source::{decode_source, EncodingPolicy};
 use std::fs;
 use std::path::Path;
+use std::time::Duration;
 
 /// Parses a source file and extracts all code nodes.
 ///
@@ -28,8 +31,11 @@ use std::path::Path;
 /// println!("Found {} nodes", nodes.len());
 /// ```
 pub fn parse_file(path: &Path) -> Result<Vec<CodeNode>> {
-    // Read the source file
-    let source = fs::read_to_string(path).map_err(|e| ParseError::io(path, e))?;
+    // Read the source file, whatever its encoding
+    let bytes = fs::read(path).map_err(|e| ParseError::io(path, e))?;
+    let source = decode_source(&bytes, EncodingPolicy::Auto)
+        .map(|decoded| decoded.text)
+        .unwrap_or_default();
 
     if source.is_empty() {
         return Err(ParseError::EmptyFile(path.to_path_buf()));
@@ -39,10 +45,7 @@ pub fn parse_file(path: &Path) -> Result<Vec<CodeNode>> {
     let parser =
         detect_language(path).ok_or_else(|| ParseError::UnsupportedLanguage(path.to_path_buf()))?;
 
-    // Use the file path as a string for node IDs
-    let file_path = path.to_string_lossy().to_string();
-
-    parse_source(&source, &file_path, parser.as_ref())
+    parse_source(&source, &path.to_string_lossy(), parser.as_ref())
 }
 
 /// Parses source code directly (useful for testing or in-memory content).
@@ -53,20 +56,53 @@ pub fn parse_source(
     source: &str,
     file_path: &str,
     lang_parser: &dyn LanguageParser,
+) -> Result<Vec<CodeNode>> {
+    parse_source_with_timeout(source, file_path, lang_parser, None)
+}
+
+/// Like [`parse_source`], but gives up if Tree-sitter takes longer than `timeout`.
+///
+/// Pathological inputs (huge generated tables, deeply nested literals) can
+/// keep some grammars busy for a very long time.
+///
+/// `file_path` is normalized (forward slashes, no `./`) before it goes into
+/// node IDs. Making it relative to the project root is up to the caller;
+/// see [`ProjectRoot`](crate::ProjectRoot).
+pub fn parse_source_with_timeout(
+    source: &str,
+    file_path: &str,
+    lang_parser: &dyn LanguageParser,
+    timeout: Option<Duration>,
 ) -> Result<Vec<CodeNode>> {
     // Create and configure Tree-sitter parser
     let mut parser = tree_sitter::Parser::new();
     parser
         .set_language(&lang_parser.language())
         .map_err(|e| ParseError::ParserError(format!("Failed to set language: {}", e)))?;
+    if let Some(timeout) = timeout {
+        parser.set_timeout_micros(timeout.as_micros().max(1) as u64);
+    }
 
     // Parse the source
-    let tree = parser
-        .parse(source, None)
-        .ok_or_else(|| ParseError::ParserError("Tree-sitter returned no tree".into()))?;
+    let tree = parser.parse(source, None).ok_or_else(|| match timeout {
+        Some(timeout) => ParseError::Timeout {
+            path: file_path.into(),
+            timeout,
+        },
+        None => ParseError::ParserError("Tree-sitter returned no tree".into()),
+    })?;
 
     // Extract nodes using the language-specific extractor
-    let nodes = lang_parser.extract_nodes(&tree, source, file_path);
+    let file_path = normalize_path(file_path);
+    let mut nodes = lang_parser.extract_nodes(&tree, source, &file_path);
+    for node in &mut nodes {
+        if let Some(body) = source.get(node.byte_start as usize..node.byte_end as usize) {
+            node.body_hash = CodeNode::hash_body(&node.name, body);
+            if matches!(node.kind, NodeKind::Method | NodeKind::Constructor) {
+                node.accessed_fields = CodeNode::fields_in(body);
+            }
+        }
+    }
 
     Ok(nodes)
 }
@@ -82,7 +118,6 @@ pub fn detect_language(path: &Path) -> Option<Box<dyn LanguageParser>> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::node::NodeKind;
 
     #[test]
     fn test_detect_language() {
@@ -138,4 +173,79 @@ mod tests {
             .iter()
             .any(|n| n.name == "UserService" && n.kind == NodeKind::Class));
     }
+
+    #[test]
+    fn test_parse_attributes() {
+        let attributes = |ext: &str, source: &str, name: &str| {
+            let parser = get_parser(ext).unwrap();
+            let nodes = parse_source(source, &format!("test.{}", ext), parser.as_ref()).unwrap();
+            nodes
+                .into_iter()
+                .find(|n| n.name == name)
+                .map(|n| n.attributes)
+                .unwrap()
+        };
+
+        let python = "@app.route('/users')\n@login_required\ndef users():\n    pass\n\nclass A:\n    @staticmethod\n    def make():\n        pass\n";
+        assert_eq!(
+            attributes("py", python, "users"),
+            vec!["@app.route('/users')", "@login_required"]
+        );
+        let parser = get_parser("py").unwrap();
+        let make = parse_source(python, "test.py", parser.as_ref())
+            .unwrap()
+            .into_iter()
+            .find(|n| n.name == "make")
+            .unwrap();
+        assert!(make.is_static);
+
+        let rust =
+            "#[derive(Debug)]\n/// Docs\npub struct S;\n\n#[tokio::main]\nasync fn main() {}\n";
+        assert_eq!(attributes("rs", rust, "S"), vec!["#[derive(Debug)]"]);
+        assert_eq!(attributes("rs", rust, "main"), vec!["#[tokio::main]"]);
+
+        let typescript = "@Controller('cats')\nexport class Cats {\n  @Get()\n  findAll() {}\n}\n";
+        assert_eq!(
+            attributes("ts", typescript, "Cats"),
+            vec!["@Controller('cats')"]
+        );
+        assert_eq!(attributes("ts", typescript, "findAll"), vec!["@Get()"]);
+
+        let java = "class C {\n  @GetMapping(\"/x\")\n  public String x() { return \"\"; }\n}\n";
+        assert_eq!(attributes("java", java, "x"), vec!["@GetMapping(\"/x\")"]);
+    }
+
+    #[test]
+    fn test_accessed_fields() {
+        let python = "class Cart:\n    def add(self, item):\n        self.items.append(item)\n        self.total += item.price\n        self.save()\n";
+        let parser = get_parser("py").unwrap();
+        let add = parse_source(python, "cart.py", parser.as_ref())
+            .unwrap()
+            .into_iter()
+            .find(|n| n.name == "add")
+            .unwrap();
+        assert_eq!(add.accessed_fields, vec!["items", "total"]);
+
+        assert_eq!(
+            CodeNode::fields_in("{ this->count++; return this.name + other.this.x; }"),
+            vec!["count", "name"]
+        );
+    }
+
+    #[test]
+    fn test_parse_timeout() {
+        let source = "def f():\n    return [1, 2, 3]\n".repeat(20_000);
+        let parser = get_parser("py").unwrap();
+
+        let result = parse_source_with_timeout(
+            &source,
+            "big.py",
+            parser.as_ref(),
+            Some(Duration::from_micros(1)),
+        );
+        assert!(matches!(result, Err(ParseError::Timeout { .. })));
+
+        let nodes = parse_source_with_timeout(&source, "big.py", parser.as_ref(), None).unwrap();
+        assert!(!nodes.is_empty());
+    }
 }
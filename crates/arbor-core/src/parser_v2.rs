@@ -9,6 +9,7 @@
 
 use crate::error::{ParseError, Result};
 use crate::node::{CodeNode, NodeKind};
+use crate::paths::normalize_path;
 use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
@@ -183,7 +184,7 @@ impl ArborParser {
             .parse(&source, None)
             .ok_or_else(|| ParseError::ParserError("Tree-sitter returned no tree".into()))?;
 
-        let file_path = path.to_string_lossy().to_string();
+        let file_path = normalize_path(&path.to_string_lossy());
         let file_name = path
             .file_name()
             .and_then(|n| n.to_str())
@@ -0,0 +1,243 @@
+//! File path normalization.
+//!
+//! Node IDs hash the file path, and `file_index` and the store are keyed
+//! by it, so every producer has to spell a file the same way. Paths stored
+//! in the graph are relative to the project root, use forward slashes and
+//! have symlinks resolved. [`ProjectRoot`] converts between those and
+//! absolute paths on disk.
+
+use std::path::{Component, Path, PathBuf};
+
+/// The root that stored file paths are relative to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectRoot {
+    /// Canonical (symlink-free, absolute) root.
+    root: PathBuf,
+    /// Workspace namespace stored paths are prefixed with.
+    namespace: Option<String>,
+}
+
+impl ProjectRoot {
+    /// Creates a root, resolving symlinks. Falls back to an absolute,
+    /// lexically cleaned path if the directory doesn't exist.
+    pub fn new(root: &Path) -> Self {
+        let root = root
+            .canonicalize()
+            .unwrap_or_else(|_| lexical_clean(&absolute(root)));
+        Self {
+            root,
+            namespace: None,
+        }
+    }
+
+    /// Prefixes stored paths with `namespace/`, for workspace roots.
+    pub fn with_namespace(mut self, namespace: &str) -> Self {
+        self.namespace = Some(namespace.to_string());
+        self
+    }
+
+    /// Returns the canonical root.
+    pub fn path(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns the workspace namespace, if any.
+    pub fn namespace(&self) -> Option<&str> {
+        self.namespace.as_deref()
+    }
+
+    /// Returns the stored form of a path: relative to the root, with
+    /// forward slashes. `None` if the path is outside the root.
+    ///
+    /// Relative inputs are taken as relative to the root. Paths that
+    /// no longer exist (deleted files) are resolved through their parent.
+    pub fn relative(&self, path: &Path) -> Option<String> {
+        let full = if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            self.root.join(path)
+        };
+        let resolved = resolve(&full);
+        let rel = to_slash(resolved.strip_prefix(&self.root).ok()?);
+        Some(match &self.namespace {
+            Some(ns) if rel.is_empty() => ns.clone(),
+            Some(ns) => format!("{}/{}", ns, rel),
+            None => rel,
+        })
+    }
+
+    /// Like [`relative`](Self::relative), but falls back to the normalized
+    /// input for paths outside the root instead of failing.
+    pub fn relative_or_normalized(&self, path: &Path) -> String {
+        self.relative(path)
+            .unwrap_or_else(|| normalize_path(&path.to_string_lossy()))
+    }
+
+    /// Maps a stored path back to an absolute one.
+    pub fn absolute(&self, stored: &str) -> PathBuf {
+        let path = Path::new(stored);
+        if path.is_absolute() {
+            return path.to_path_buf();
+        }
+        let rel = match &self.namespace {
+            Some(ns) => stored
+                .strip_prefix(ns.as_str())
+                .and_then(|rest| rest.strip_prefix('/'))
+                .unwrap_or(stored),
+            None => stored,
+        };
+        self.root.join(rel)
+    }
+}
+
+/// Normalizes a path string without touching the filesystem: forward
+/// slashes, no `./` segments, `..` collapsed where possible.
+pub fn normalize_path(path: &str) -> String {
+    let unified = path.replace('\\', "/");
+    let absolute = unified.starts_with('/');
+    let mut parts: Vec<&str> = Vec::new();
+    for part in unified.split('/') {
+        match part {
+            "" | "." => {}
+            ".." if parts.last().is_some_and(|p| *p != "..") => {
+                parts.pop();
+            }
+            ".." if absolute => {}
+            _ => parts.push(part),
+        }
+    }
+    let joined = parts.join("/");
+    if absolute {
+        format!("/{}", joined)
+    } else {
+        joined
+    }
+}
+
+fn absolute(path: &Path) -> PathBuf {
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir()
+            .map(|cwd| cwd.join(path))
+            .unwrap_or_else(|_| path.to_path_buf())
+    }
+}
+
+/// Canonicalizes as much of the path as exists, then appends the rest.
+fn resolve(path: &Path) -> PathBuf {
+    let clean = lexical_clean(path);
+    if let Ok(canonical) = clean.canonicalize() {
+        return canonical;
+    }
+    let mut missing = Vec::new();
+    let mut existing = clean.as_path();
+    while let Some(parent) = existing.parent() {
+        if let Some(name) = existing.file_name() {
+            missing.push(name.to_os_string());
+        }
+        if let Ok(canonical) = parent.canonicalize() {
+            return missing
+                .iter()
+                .rev()
+                .fold(canonical, |acc, name| acc.join(name));
+        }
+        existing = parent;
+    }
+    clean
+}
+
+fn lexical_clean(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !out.pop() {
+                    out.push("..");
+                }
+            }
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+fn to_slash(path: &Path) -> String {
+    path.components()
+        .filter_map(|c| match c {
+            Component::Normal(part) => Some(part.to_string_lossy()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(normalize_path("./src/lib.rs"), "src/lib.rs");
+        assert_eq!(normalize_path("src\\util\\mod.rs"), "src/util/mod.rs");
+        assert_eq!(normalize_path("src/../lib/./a.ts"), "lib/a.ts");
+        assert_eq!(normalize_path("/abs//x.py"), "/abs/x.py");
+        assert_eq!(normalize_path("../up.go"), "../up.go");
+    }
+
+    #[test]
+    fn test_project_root_relative_and_absolute() {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join("src/lib.rs"), "").unwrap();
+        let root = ProjectRoot::new(dir.path());
+        let canonical = dir.path().canonicalize().unwrap();
+
+        let expected = Some("src/lib.rs".to_string());
+        assert_eq!(root.relative(&dir.path().join("src/lib.rs")), expected);
+        assert_eq!(root.relative(&canonical.join("src/./lib.rs")), expected);
+        assert_eq!(root.relative(Path::new("src/lib.rs")), expected);
+        // Deleted files still map
+        assert_eq!(
+            root.relative(&dir.path().join("src/gone.rs")),
+            Some("src/gone.rs".to_string())
+        );
+        assert_eq!(root.relative(Path::new("/definitely/elsewhere.rs")), None);
+
+        assert_eq!(root.absolute("src/lib.rs"), canonical.join("src/lib.rs"));
+
+        let namespaced = ProjectRoot::new(dir.path()).with_namespace("api");
+        assert_eq!(
+            namespaced.relative(&dir.path().join("src/lib.rs")),
+            Some("api/src/lib.rs".to_string())
+        );
+        assert_eq!(
+            namespaced.absolute("api/src/lib.rs"),
+            canonical.join("src/lib.rs")
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinked_root() {
+        let dir = tempdir().unwrap();
+        let real = dir.path().join("real");
+        fs::create_dir_all(real.join("src")).unwrap();
+        fs::write(real.join("src/a.py"), "").unwrap();
+        let link = dir.path().join("link");
+        std::os::unix::fs::symlink(&real, &link).unwrap();
+
+        let root = ProjectRoot::new(&link);
+        assert_eq!(
+            root.relative(&real.join("src/a.py")),
+            Some("src/a.py".to_string())
+        );
+        assert_eq!(
+            root.relative(&link.join("src/a.py")),
+            Some("src/a.py".to_string())
+        );
+    }
+}
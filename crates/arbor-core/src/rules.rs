@@ -0,0 +1,333 @@
+//! Architecture rules.
+//!
+//! `.arbor/rules.json` names layers by path glob or workspace namespace and
+//! declares which dependencies between them are allowed. `arbor check`
+//! evaluates the rules against the graph.
+//!
+//! ```json
+//! {
+//!   "layers": [
+//!     { "name": "ui", "paths": ["src/ui"] },
+//!     { "name": "domain", "paths": ["src/domain"] },
+//!     { "name": "db", "paths": ["src/db"] },
+//!     { "name": "web", "namespace": "frontend" }
+//!   ],
+//!   "rules": [
+//!     { "rule": "forbid", "from": "domain", "to": ["ui", "db"] },
+//!     { "rule": "allow", "from": "ui", "to": "domain" },
+//!     { "rule": "only", "callers": "domain", "target": "db", "kinds": ["calls"] },
+//!     { "rule": "no_internal_imports", "name": "go-internal" }
+//!   ]
+//! }
+//! ```
+//!
+//! A node belongs to the first layer matching its file. Code outside every
+//! layer is only constrained by `only` and `no_internal_imports`.
+
+use crate::config::{build_globset, ConfigError, CONFIG_DIR};
+use globset::GlobSet;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Name of the rules file inside [`CONFIG_DIR`].
+pub const RULES_FILE: &str = "rules.json";
+
+/// A named part of the codebase.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Layer {
+    pub name: String,
+
+    /// Path globs, same syntax as `include` in the project config.
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub paths: Vec<String>,
+
+    /// Workspace namespace whose files all belong to the layer.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+}
+
+/// What a rule checks.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "rule", rename_all = "snake_case")]
+pub enum RuleKind {
+    /// Layers in `from` must not depend on layers in `to`.
+    Forbid {
+        #[serde(deserialize_with = "one_or_many")]
+        from: Vec<String>,
+        #[serde(deserialize_with = "one_or_many")]
+        to: Vec<String>,
+    },
+
+    /// Layers in `from` may only depend on themselves and layers in `to`.
+    Allow {
+        #[serde(deserialize_with = "one_or_many")]
+        from: Vec<String>,
+        #[serde(default, deserialize_with = "one_or_many")]
+        to: Vec<String>,
+    },
+
+    /// Only `callers` (and `target` itself) may depend on `target`.
+    Only {
+        #[serde(deserialize_with = "one_or_many")]
+        callers: Vec<String>,
+        #[serde(deserialize_with = "one_or_many")]
+        target: Vec<String>,
+    },
+
+    /// Code below a directory named like a marker (`internal` by default)
+    /// may only be used from within that directory's parent, as in Go.
+    NoInternalImports {
+        #[serde(default = "default_markers", deserialize_with = "one_or_many")]
+        markers: Vec<String>,
+    },
+}
+
+/// One architecture rule.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rule {
+    /// Rule ID for reports and baselines; derived from the rule if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    #[serde(flatten)]
+    pub kind: RuleKind,
+
+    /// Edge kinds the rule applies to, e.g. `calls` or `imports`. Empty
+    /// means every dependency.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub kinds: Vec<String>,
+
+    /// Explanation shown with each violation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+impl Rule {
+    /// The rule's name, or one built from its kind and layers.
+    pub fn id(&self) -> String {
+        if let Some(name) = &self.name {
+            return name.clone();
+        }
+        match &self.kind {
+            RuleKind::Forbid { from, to } => {
+                format!("forbid:{}->{}", from.join(","), to.join(","))
+            }
+            RuleKind::Allow { from, to } => format!("allow:{}->{}", from.join(","), to.join(",")),
+            RuleKind::Only { callers, target } => {
+                format!("only:{}->{}", callers.join(","), target.join(","))
+            }
+            RuleKind::NoInternalImports { .. } => "no_internal_imports".to_string(),
+        }
+    }
+
+    /// Layer names the rule mentions.
+    fn layers(&self) -> Vec<&String> {
+        match &self.kind {
+            RuleKind::Forbid { from, to } | RuleKind::Allow { from, to } => {
+                from.iter().chain(to).collect()
+            }
+            RuleKind::Only { callers, target } => callers.iter().chain(target).collect(),
+            RuleKind::NoInternalImports { .. } => Vec::new(),
+        }
+    }
+}
+
+/// The contents of `.arbor/rules.json`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ArchitectureRules {
+    pub layers: Vec<Layer>,
+    pub rules: Vec<Rule>,
+
+    /// Whether test code is checked. Tests often reach across layers.
+    pub include_tests: bool,
+}
+
+impl ArchitectureRules {
+    /// Returns the rules path for a project directory.
+    pub fn path_for(dir: &Path) -> PathBuf {
+        dir.join(CONFIG_DIR).join(RULES_FILE)
+    }
+
+    /// Loads the rules in `dir`, if there are any.
+    pub fn load(dir: &Path) -> Result<Option<Self>, ConfigError> {
+        let path = Self::path_for(dir);
+        if !path.exists() {
+            return Ok(None);
+        }
+        Self::from_file(&path).map(Some)
+    }
+
+    /// Loads and validates a rules file.
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let text = fs::read_to_string(path).map_err(|source| ConfigError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let rules: Self = serde_json::from_str(&text).map_err(|source| ConfigError::Json {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        rules.validate()?;
+        Ok(rules)
+    }
+
+    /// Checks layer and rule names are unique, every layer has paths or a
+    /// namespace and rules only mention known layers.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut names = HashSet::new();
+        for layer in &self.layers {
+            if !names.insert(layer.name.as_str()) {
+                return Err(ConfigError::Rules(format!(
+                    "duplicate layer '{}'",
+                    layer.name
+                )));
+            }
+            if layer.paths.is_empty() && layer.namespace.is_none() {
+                return Err(ConfigError::Rules(format!(
+                    "layer '{}' needs paths or a namespace",
+                    layer.name
+                )));
+            }
+        }
+        let mut ids = HashSet::new();
+        for rule in &self.rules {
+            let id = rule.id();
+            if !ids.insert(id.clone()) {
+                return Err(ConfigError::Rules(format!("duplicate rule '{}'", id)));
+            }
+            if let Some(unknown) = rule
+                .layers()
+                .into_iter()
+                .find(|l| !names.contains(l.as_str()))
+            {
+                return Err(ConfigError::Rules(format!(
+                    "rule '{}' mentions unknown layer '{}'",
+                    id, unknown
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Compiles the layer globs.
+    pub fn layer_matcher(&self) -> Result<LayerMatcher, ConfigError> {
+        let layers = self
+            .layers
+            .iter()
+            .map(|layer| {
+                Ok((
+                    layer.name.clone(),
+                    build_globset(&layer.paths)?,
+                    layer.namespace.clone(),
+                ))
+            })
+            .collect::<Result<_, ConfigError>>()?;
+        Ok(LayerMatcher { layers })
+    }
+}
+
+/// Finds the layer a file belongs to.
+#[derive(Debug, Clone)]
+pub struct LayerMatcher {
+    layers: Vec<(String, GlobSet, Option<String>)>,
+}
+
+impl LayerMatcher {
+    /// The first layer whose globs or namespace match a stored file path.
+    pub fn layer_of(&self, file: &str) -> Option<&str> {
+        self.layers
+            .iter()
+            .find(|(_, globs, namespace)| {
+                globs.is_match(file)
+                    || namespace
+                        .as_deref()
+                        .is_some_and(|ns| file.strip_prefix(ns).is_some_and(|r| r.starts_with('/')))
+            })
+            .map(|(name, _, _)| name.as_str())
+    }
+}
+
+fn default_markers() -> Vec<String> {
+    vec!["internal".to_string()]
+}
+
+/// Accepts `"a"` as well as `["a", "b"]`.
+fn one_or_many<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(one) => vec![one],
+        OneOrMany::Many(many) => many,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    const RULES: &str = r#"{
+        "layers": [
+            { "name": "ui", "paths": ["src/ui"] },
+            { "name": "db", "paths": "src/db/**" },
+            { "name": "web", "namespace": "frontend" }
+        ],
+        "rules": [
+            { "rule": "forbid", "from": "ui", "to": ["db"], "message": "go through services" },
+            { "rule": "only", "name": "db-access", "callers": "ui", "target": "db", "kinds": ["calls"] },
+            { "rule": "no_internal_imports" }
+        ]
+    }"#;
+
+    #[test]
+    fn test_load_rules() {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join(CONFIG_DIR)).unwrap();
+        fs::write(ArchitectureRules::path_for(dir.path()), RULES).unwrap();
+
+        let rules = ArchitectureRules::load(dir.path()).unwrap().unwrap();
+        assert_eq!(rules.layers[1].paths, vec!["src/db/**"]);
+        assert_eq!(rules.rules[0].id(), "forbid:ui->db");
+        assert_eq!(rules.rules[1].id(), "db-access");
+        assert_eq!(rules.rules[1].kinds, vec!["calls"]);
+        assert_eq!(
+            rules.rules[2].kind,
+            RuleKind::NoInternalImports {
+                markers: vec!["internal".to_string()]
+            }
+        );
+
+        let matcher = rules.layer_matcher().unwrap();
+        assert_eq!(matcher.layer_of("src/ui/button.ts"), Some("ui"));
+        assert_eq!(matcher.layer_of("src/db/pool.rs"), Some("db"));
+        assert_eq!(matcher.layer_of("frontend/app.ts"), Some("web"));
+        assert_eq!(matcher.layer_of("frontendx/app.ts"), None);
+        assert_eq!(matcher.layer_of("src/core.rs"), None);
+
+        assert!(ArchitectureRules::load(&dir.path().join("missing"))
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_validate_rules() {
+        let unknown: ArchitectureRules = serde_json::from_str(
+            r#"{"layers": [{"name": "ui", "paths": ["ui"]}],
+                "rules": [{"rule": "forbid", "from": "ui", "to": "db"}]}"#,
+        )
+        .unwrap();
+        let err = unknown.validate().unwrap_err().to_string();
+        assert!(err.contains("unknown layer 'db'"), "{}", err);
+
+        let empty: ArchitectureRules =
+            serde_json::from_str(r#"{"layers": [{"name": "ui"}]}"#).unwrap();
+        assert!(empty.validate().is_err());
+    }
+}
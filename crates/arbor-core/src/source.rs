@@ -0,0 +1,256 @@
+//! Reading source text.
+//!
+//! Source files aren't always UTF-8. Older codebases have Latin-1 or
+//! Windows-1252 comments, some Windows tools write UTF-16, and plenty of
+//! editors add a byte-order mark. This module turns raw bytes into text
+//! so those files still make it into the graph.
+//!
+//! Node byte offsets always refer to the decoded UTF-8 text.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// How many leading bytes we sample to spot BOM-less UTF-16.
+const UTF16_SNIFF_BYTES: usize = 512;
+
+/// A text encoding we know how to decode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Encoding {
+    Utf8,
+    /// UTF-8 with a leading byte-order mark.
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+    /// Also covers Latin-1, which it's a superset of for printable text.
+    Windows1252,
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Self::Utf8 => "utf-8",
+            Self::Utf8Bom => "utf-8 (bom)",
+            Self::Utf16Le => "utf-16le",
+            Self::Utf16Be => "utf-16be",
+            Self::Windows1252 => "windows-1252",
+        };
+        f.write_str(s)
+    }
+}
+
+/// What to do with files that aren't plain UTF-8.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EncodingPolicy {
+    /// Detect BOMs and UTF-16, fall back to Windows-1252.
+    #[default]
+    Auto,
+    /// Treat everything as UTF-8, replacing invalid bytes.
+    Utf8Lossy,
+    /// Only accept valid UTF-8 (a BOM is fine); skip anything else.
+    Strict,
+}
+
+/// Source text and how it was decoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedSource {
+    pub text: String,
+    pub encoding: Encoding,
+    /// Some bytes couldn't be decoded and were replaced with U+FFFD.
+    pub lossy: bool,
+}
+
+impl DecodedSource {
+    /// Returns true if the file wasn't plain UTF-8.
+    pub fn was_transcoded(&self) -> bool {
+        self.encoding != Encoding::Utf8 || self.lossy
+    }
+}
+
+/// Decodes raw file contents according to `policy`.
+///
+/// Returns `None` only under [`EncodingPolicy::Strict`], for input that
+/// isn't valid UTF-8.
+pub fn decode_source(bytes: &[u8], policy: EncodingPolicy) -> Option<DecodedSource> {
+    if let Some(rest) = bytes.strip_prefix(b"\xEF\xBB\xBF") {
+        return decode_utf8(rest, Encoding::Utf8Bom, policy);
+    }
+
+    match policy {
+        EncodingPolicy::Strict | EncodingPolicy::Utf8Lossy => {
+            decode_utf8(bytes, Encoding::Utf8, policy)
+        }
+        EncodingPolicy::Auto => {
+            if let Some(rest) = bytes.strip_prefix(b"\xFF\xFE") {
+                return Some(decode_utf16(rest, Encoding::Utf16Le));
+            }
+            if let Some(rest) = bytes.strip_prefix(b"\xFE\xFF") {
+                return Some(decode_utf16(rest, Encoding::Utf16Be));
+            }
+            if let Some(encoding) = sniff_utf16(bytes) {
+                return Some(decode_utf16(bytes, encoding));
+            }
+            if let Ok(text) = std::str::from_utf8(bytes) {
+                return Some(DecodedSource {
+                    text: text.to_string(),
+                    encoding: Encoding::Utf8,
+                    lossy: false,
+                });
+            }
+            Some(decode_windows_1252(bytes))
+        }
+    }
+}
+
+fn decode_utf8(bytes: &[u8], encoding: Encoding, policy: EncodingPolicy) -> Option<DecodedSource> {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => Some(DecodedSource {
+            text: text.to_string(),
+            encoding,
+            lossy: false,
+        }),
+        Err(_) if policy == EncodingPolicy::Strict => None,
+        Err(_) => Some(DecodedSource {
+            text: String::from_utf8_lossy(bytes).into_owned(),
+            encoding,
+            lossy: true,
+        }),
+    }
+}
+
+/// Guesses BOM-less UTF-16 from the NUL bytes mostly-ASCII text has.
+fn sniff_utf16(bytes: &[u8]) -> Option<Encoding> {
+    let sample = &bytes[..bytes.len().min(UTF16_SNIFF_BYTES)];
+    if sample.len() < 4 {
+        return None;
+    }
+
+    let pairs = sample.len() / 2;
+    let even_zeros = sample.iter().step_by(2).filter(|&&b| b == 0).count();
+    let odd_zeros = sample
+        .iter()
+        .skip(1)
+        .step_by(2)
+        .filter(|&&b| b == 0)
+        .count();
+
+    // Nearly every code unit of ASCII text has one zero byte, on one side
+    if odd_zeros * 10 >= pairs * 7 && even_zeros * 10 <= pairs {
+        Some(Encoding::Utf16Le)
+    } else if even_zeros * 10 >= pairs * 7 && odd_zeros * 10 <= pairs {
+        Some(Encoding::Utf16Be)
+    } else {
+        None
+    }
+}
+
+fn decode_utf16(bytes: &[u8], encoding: Encoding) -> DecodedSource {
+    let units = bytes.chunks_exact(2).map(|pair| match encoding {
+        Encoding::Utf16Be => u16::from_be_bytes([pair[0], pair[1]]),
+        _ => u16::from_le_bytes([pair[0], pair[1]]),
+    });
+
+    let mut lossy = !bytes.len().is_multiple_of(2);
+    let text = char::decode_utf16(units)
+        .map(|c| {
+            c.unwrap_or_else(|_| {
+                lossy = true;
+                char::REPLACEMENT_CHARACTER
+            })
+        })
+        .collect();
+
+    DecodedSource {
+        text,
+        encoding,
+        lossy,
+    }
+}
+
+/// Windows-1252 code points for 0x80..=0x9F. Zero marks an unassigned byte.
+const WINDOWS_1252_HIGH: [u16; 32] = [
+    0x20AC, 0, 0x201A, 0x0192, 0x201E, 0x2026, 0x2020, 0x2021, 0x02C6, 0x2030, 0x0160, 0x2039,
+    0x0152, 0, 0x017D, 0, 0, 0x2018, 0x2019, 0x201C, 0x201D, 0x2022, 0x2013, 0x2014, 0x02DC,
+    0x2122, 0x0161, 0x203A, 0x0153, 0, 0x017E, 0x0178,
+];
+
+fn decode_windows_1252(bytes: &[u8]) -> DecodedSource {
+    let mut lossy = false;
+    let text = bytes
+        .iter()
+        .map(|&b| match b {
+            0x80..=0x9F => match WINDOWS_1252_HIGH[(b - 0x80) as usize] {
+                0 => {
+                    lossy = true;
+                    char::REPLACEMENT_CHARACTER
+                }
+                cp => char::from_u32(cp as u32).unwrap_or(char::REPLACEMENT_CHARACTER),
+            },
+            // Every other byte maps straight to the same code point
+            _ => b as char,
+        })
+        .collect();
+
+    DecodedSource {
+        text,
+        encoding: Encoding::Windows1252,
+        lossy,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_common_encodings() {
+        let utf8 = decode_source("fn main() {}".as_bytes(), EncodingPolicy::Auto).unwrap();
+        assert_eq!(utf8.encoding, Encoding::Utf8);
+        assert!(!utf8.was_transcoded());
+
+        let bom = decode_source(b"\xEF\xBB\xBFdef f(): pass", EncodingPolicy::Auto).unwrap();
+        assert_eq!(bom.encoding, Encoding::Utf8Bom);
+        assert_eq!(bom.text, "def f(): pass");
+
+        // "# café" in Latin-1
+        let latin1 = decode_source(b"# caf\xE9\n\x93quoted\x94", EncodingPolicy::Auto).unwrap();
+        assert_eq!(latin1.encoding, Encoding::Windows1252);
+        assert_eq!(latin1.text, "# café\n\u{201C}quoted\u{201D}");
+        assert!(!latin1.lossy);
+
+        let utf16: Vec<u8> = "x = 1\n"
+            .encode_utf16()
+            .flat_map(|u| u.to_le_bytes())
+            .collect();
+        let mut with_bom = vec![0xFF, 0xFE];
+        with_bom.extend(&utf16);
+        for bytes in [&with_bom, &utf16] {
+            let decoded = decode_source(bytes, EncodingPolicy::Auto).unwrap();
+            assert_eq!(decoded.encoding, Encoding::Utf16Le);
+            assert_eq!(decoded.text, "x = 1\n");
+        }
+
+        let utf16be: Vec<u8> = "y = 2\n"
+            .encode_utf16()
+            .flat_map(|u| u.to_be_bytes())
+            .collect();
+        let decoded = decode_source(&utf16be, EncodingPolicy::Auto).unwrap();
+        assert_eq!(decoded.encoding, Encoding::Utf16Be);
+        assert_eq!(decoded.text, "y = 2\n");
+    }
+
+    #[test]
+    fn test_policies() {
+        let latin1 = b"# caf\xE9";
+        assert!(decode_source(latin1, EncodingPolicy::Strict).is_none());
+
+        let lossy = decode_source(latin1, EncodingPolicy::Utf8Lossy).unwrap();
+        assert!(lossy.lossy);
+        assert_eq!(lossy.text, "# caf\u{FFFD}");
+
+        let unassigned = decode_source(b"a\x81b\xFF", EncodingPolicy::Auto).unwrap();
+        assert!(unassigned.lossy);
+        assert_eq!(unassigned.text, "a\u{FFFD}b\u{FF}");
+    }
+}
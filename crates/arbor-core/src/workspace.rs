@@ -0,0 +1,192 @@
+//! Multi-root workspaces.
+//!
+//! A workspace manifest (`.arbor/workspace.json`) lists several project
+//! roots, typically separate repositories, that should be indexed into one
+//! graph. Each root gets a namespace. Files from a root are stored as
+//! `<namespace>/<path relative to the root>`, so the namespace works like
+//! a mount point and IDs never collide between roots.
+//!
+//! ```json
+//! {
+//!   "roots": [
+//!     { "namespace": "backend", "path": "../backend", "depends_on": ["protos"] },
+//!     { "namespace": "protos", "path": "../shared-protos" },
+//!     { "namespace": "web", "path": "../frontend", "depends_on": ["backend"] }
+//!   ]
+//! }
+//! ```
+//!
+//! Root paths are relative to the directory holding `.arbor/`. Each root
+//! keeps its own `.arbor/config.json`.
+
+use crate::config::{ConfigError, CONFIG_DIR};
+use crate::paths::ProjectRoot;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Name of the workspace manifest inside [`CONFIG_DIR`].
+pub const WORKSPACE_FILE: &str = "workspace.json";
+
+/// One root of a workspace.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkspaceRoot {
+    /// Prefix for this root's file paths. Letters, digits, `-` and `_`.
+    pub namespace: String,
+
+    /// Directory of the root. Relative paths are resolved against the
+    /// workspace directory when the manifest is loaded.
+    pub path: PathBuf,
+
+    /// Namespaces this root's references may resolve into. `None` lets it
+    /// see every root; an empty list keeps it to itself.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub depends_on: Option<Vec<String>>,
+}
+
+impl WorkspaceRoot {
+    /// Whether references from this root may resolve to nodes in `namespace`.
+    pub fn can_see(&self, namespace: &str) -> bool {
+        self.namespace == namespace
+            || self
+                .depends_on
+                .as_ref()
+                .is_none_or(|deps| deps.iter().any(|d| d == namespace))
+    }
+}
+
+/// The contents of `.arbor/workspace.json`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Workspace {
+    pub roots: Vec<WorkspaceRoot>,
+}
+
+impl Workspace {
+    /// Returns the manifest path for a workspace directory.
+    pub fn path_for(dir: &Path) -> PathBuf {
+        dir.join(CONFIG_DIR).join(WORKSPACE_FILE)
+    }
+
+    /// Loads the manifest in `dir`, if there is one.
+    ///
+    /// Root paths come back absolute, with symlinks resolved.
+    pub fn load(dir: &Path) -> Result<Option<Self>, ConfigError> {
+        let path = Self::path_for(dir);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let text = fs::read_to_string(&path).map_err(|source| ConfigError::Io {
+            path: path.clone(),
+            source,
+        })?;
+        let mut workspace: Self =
+            serde_json::from_str(&text).map_err(|source| ConfigError::Json {
+                path: path.clone(),
+                source,
+            })?;
+        workspace.validate()?;
+        for root in &mut workspace.roots {
+            root.path = ProjectRoot::new(&dir.join(&root.path)).path().to_path_buf();
+        }
+        Ok(Some(workspace))
+    }
+
+    /// Checks namespaces are valid and unique, and dependencies exist.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.roots.is_empty() {
+            return Err(ConfigError::Workspace("no roots listed".to_string()));
+        }
+        let mut seen = HashSet::new();
+        for root in &self.roots {
+            let ns = &root.namespace;
+            let valid = !ns.is_empty()
+                && ns
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+            if !valid {
+                return Err(ConfigError::Workspace(format!(
+                    "invalid namespace '{}' (use letters, digits, '-' and '_')",
+                    ns
+                )));
+            }
+            if !seen.insert(ns.as_str()) {
+                return Err(ConfigError::Workspace(format!(
+                    "duplicate namespace '{}'",
+                    ns
+                )));
+            }
+        }
+        for root in &self.roots {
+            for dep in root.depends_on.iter().flatten() {
+                if !seen.contains(dep.as_str()) {
+                    return Err(ConfigError::Workspace(format!(
+                        "root '{}' depends on unknown namespace '{}'",
+                        root.namespace, dep
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Finds a root by namespace.
+    pub fn root(&self, namespace: &str) -> Option<&WorkspaceRoot> {
+        self.roots.iter().find(|r| r.namespace == namespace)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn write_manifest(dir: &Path, json: &str) {
+        fs::create_dir_all(dir.join(CONFIG_DIR)).unwrap();
+        fs::write(Workspace::path_for(dir), json).unwrap();
+    }
+
+    #[test]
+    fn test_load_resolves_roots() {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("api")).unwrap();
+        fs::create_dir_all(dir.path().join("web")).unwrap();
+        write_manifest(
+            dir.path(),
+            r#"{"roots": [
+                {"namespace": "api", "path": "api"},
+                {"namespace": "web", "path": "./web", "depends_on": ["api"]}
+            ]}"#,
+        );
+
+        let workspace = Workspace::load(dir.path()).unwrap().unwrap();
+        let canonical = dir.path().canonicalize().unwrap();
+        assert_eq!(workspace.roots[0].path, canonical.join("api"));
+        assert_eq!(workspace.roots[1].path, canonical.join("web"));
+
+        let web = workspace.root("web").unwrap();
+        assert!(web.can_see("api"));
+        assert!(web.can_see("web"));
+        assert!(workspace.root("api").unwrap().depends_on.is_none());
+        assert!(workspace.root("api").unwrap().can_see("web"));
+
+        assert!(Workspace::load(&dir.path().join("api")).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_invalid_manifests() {
+        let dir = tempdir().unwrap();
+        for json in [
+            r#"{"roots": []}"#,
+            r#"{"roots": [{"namespace": "a/b", "path": "."}]}"#,
+            r#"{"roots": [{"namespace": "a", "path": "."}, {"namespace": "a", "path": "x"}]}"#,
+            r#"{"roots": [{"namespace": "a", "path": ".", "depends_on": ["nope"]}]}"#,
+        ] {
+            write_manifest(dir.path(), json);
+            assert!(matches!(
+                Workspace::load(dir.path()),
+                Err(ConfigError::Workspace(_))
+            ));
+        }
+    }
+}
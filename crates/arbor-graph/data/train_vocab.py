@@ -0,0 +1,149 @@
+#!/usr/bin/env python3
+"""Trains the bundled byte-level BPE vocabulary (code_bpe.tiktoken).
+
+The output uses the tiktoken file format, one `base64(token) rank` pair
+per line, and the corpus is split with the cl100k_base pre-tokenizer, so
+the Rust loader reads this file and OpenAI's cl100k_base.tiktoken alike.
+The vocabulary itself is much smaller and learned from source code only.
+
+    python3 train_vocab.py --size 16384 --per-language 4000000 \
+        -o code_bpe.tiktoken DIR...
+
+Needs the `regex` module for `\\p{L}`. Files are read in sorted order and
+ties are broken by the pair's bytes, so the same corpus gives the same
+file.
+"""
+
+import argparse
+import base64
+import heapq
+import os
+import sys
+from collections import Counter, defaultdict
+
+import regex
+
+PATTERN = regex.compile(
+    r"""(?i:'s|'t|'re|'ve|'m|'ll|'d)|[^\r\n\p{L}\p{N}]?\p{L}+|\p{N}{1,3}"""
+    r"""| ?[^\s\p{L}\p{N}]+[\r\n]*|\s*[\r\n]+|\s+(?!\S)|\s+"""
+)
+
+EXTENSIONS = {
+    ".rs": "rust", ".py": "python", ".js": "javascript", ".jsx": "javascript",
+    ".ts": "typescript", ".tsx": "typescript", ".go": "go", ".java": "java",
+    ".c": "c", ".h": "c", ".cpp": "cpp", ".hpp": "cpp", ".cc": "cpp",
+    ".cs": "csharp", ".rb": "ruby", ".php": "php", ".kt": "kotlin",
+    ".swift": "swift", ".dart": "dart", ".md": "markdown",
+}
+
+
+def corpus(dirs, per_language, max_file):
+    """Yields file texts, at most `per_language` bytes per language."""
+    paths = defaultdict(list)
+    for top in dirs:
+        for root, subdirs, files in os.walk(top):
+            subdirs[:] = sorted(d for d in subdirs if not d.startswith(".")
+                                and d not in ("target", "node_modules"))
+            for name in sorted(files):
+                language = EXTENSIONS.get(os.path.splitext(name)[1])
+                if language:
+                    paths[language].append(os.path.join(root, name))
+    for language in sorted(paths):
+        used = 0
+        for path in paths[language]:
+            if used >= per_language:
+                break
+            try:
+                if os.path.getsize(path) > max_file:
+                    continue
+                with open(path, encoding="utf-8") as f:
+                    text = f.read()
+            except (OSError, UnicodeDecodeError):
+                continue
+            used += len(text)
+            yield text
+        print(f"{language}: {used} bytes", file=sys.stderr)
+
+
+def train(words, size):
+    """Learns merges until the vocabulary has `size` tokens."""
+    vocab = [bytes([b]) for b in range(256)]
+    ids = {token: i for i, token in enumerate(vocab)}
+    words = [(list(word), count) for word, count in words.items()]
+
+    pairs = Counter()
+    where = defaultdict(set)
+    for w, (symbols, count) in enumerate(words):
+        for pair in zip(symbols, symbols[1:]):
+            pairs[pair] += count
+            where[pair].add(w)
+
+    def key(pair):
+        return (-pairs[pair], vocab[pair[0]] + vocab[pair[1]])
+
+    heap = [(key(pair), pair) for pair in pairs]
+    heapq.heapify(heap)
+
+    while len(vocab) < size and heap:
+        item, pair = heapq.heappop(heap)
+        if pairs[pair] <= 0 or item != key(pair):
+            continue
+        merged = vocab[pair[0]] + vocab[pair[1]]
+        new = ids.get(merged)
+        if new is None:
+            new = len(vocab)
+            vocab.append(merged)
+            ids[merged] = new
+
+        changed = set()
+        for w in where.pop(pair, ()):
+            symbols, count = words[w]
+            i, out = 0, []
+            while i < len(symbols):
+                if i + 1 < len(symbols) and (symbols[i], symbols[i + 1]) == pair:
+                    out.append(new)
+                    i += 2
+                else:
+                    out.append(symbols[i])
+                    i += 1
+            for old in zip(symbols, symbols[1:]):
+                pairs[old] -= count
+                changed.add(old)
+            for fresh in zip(out, out[1:]):
+                pairs[fresh] += count
+                where[fresh].add(w)
+                changed.add(fresh)
+            words[w] = (out, count)
+        for p in changed:
+            if pairs[p] > 0:
+                heapq.heappush(heap, (key(p), p))
+            else:
+                pairs.pop(p, None)
+    return vocab
+
+
+def main():
+    parser = argparse.ArgumentParser(description=__doc__.splitlines()[0])
+    parser.add_argument("dirs", nargs="+")
+    parser.add_argument("--size", type=int, default=16384)
+    parser.add_argument("--per-language", type=int, default=4_000_000)
+    parser.add_argument("--max-file", type=int, default=200_000)
+    parser.add_argument("--min-count", type=int, default=2)
+    parser.add_argument("-o", "--output", default="code_bpe.tiktoken")
+    args = parser.parse_args()
+
+    words = Counter()
+    for text in corpus(args.dirs, args.per_language, args.max_file):
+        words.update(m.encode("utf-8") for m in PATTERN.findall(text))
+    words = {w: c for w, c in words.items() if c >= args.min_count}
+    print(f"{len(words)} distinct pieces", file=sys.stderr)
+
+    vocab = train(words, args.size)
+    with open(args.output, "w") as f:
+        for rank, token in enumerate(vocab):
+            f.write(f"{base64.b64encode(token).decode()} {rank}\n")
+    print(f"wrote {len(vocab)} tokens to {args.output}", file=sys.stderr)
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,663 @@
+//! Full-text search over symbol bodies.
+//!
+//! The body index keeps the decoded text of each indexed file, the
+//! string literal and comment regions found by a small lexer, and the
+//! words each file contains. Literal queries only scan files holding
+//! every whole word of the pattern; regex queries scan every file. Each
+//! match is mapped to the innermost node whose range covers it, so
+//! results are nodes that feed straight into impact analysis and
+//! context slicing.
+
+use crate::graph::{ArborGraph, NodeId};
+use crate::query::NodeInfo;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use thiserror::Error;
+
+/// Hits kept per node; the rest are only counted.
+const MAX_HITS_PER_NODE: usize = 20;
+
+/// Longest line excerpt shown for a hit, in bytes.
+const MAX_EXCERPT_LEN: usize = 160;
+
+/// What kind of text a match is in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextRegion {
+    Code,
+    String,
+    Comment,
+}
+
+impl std::fmt::Display for TextRegion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Code => "code",
+            Self::String => "string",
+            Self::Comment => "comment",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// Which regions a body search looks at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BodyScope {
+    #[default]
+    Any,
+    Code,
+    Strings,
+    Comments,
+}
+
+impl BodyScope {
+    fn admits(self, region: TextRegion) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Code => region == TextRegion::Code,
+            Self::Strings => region == TextRegion::String,
+            Self::Comments => region == TextRegion::Comment,
+        }
+    }
+}
+
+impl std::fmt::Display for BodyScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Any => "any",
+            Self::Code => "code",
+            Self::Strings => "strings",
+            Self::Comments => "comments",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl std::str::FromStr for BodyScope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "any" => Ok(Self::Any),
+            "code" => Ok(Self::Code),
+            "strings" | "string" => Ok(Self::Strings),
+            "comments" | "comment" => Ok(Self::Comments),
+            _ => Err(format!(
+                "unknown scope '{}' (expected any, code, strings or comments)",
+                s
+            )),
+        }
+    }
+}
+
+/// A body search.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct BodyQuery {
+    pub pattern: String,
+    /// Treat `pattern` as a regular expression instead of a literal.
+    pub regex: bool,
+    pub case_sensitive: bool,
+    pub scope: BodyScope,
+    /// Most nodes to return (0 = no limit).
+    pub limit: usize,
+}
+
+impl BodyQuery {
+    /// Searches for `pattern` literally, ignoring case.
+    pub fn literal(pattern: impl Into<String>) -> Self {
+        Self {
+            pattern: pattern.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Searches for the regular expression `pattern`, ignoring case.
+    pub fn regex(pattern: impl Into<String>) -> Self {
+        Self {
+            pattern: pattern.into(),
+            regex: true,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_case_sensitive(mut self, case_sensitive: bool) -> Self {
+        self.case_sensitive = case_sensitive;
+        self
+    }
+
+    pub fn with_scope(mut self, scope: BodyScope) -> Self {
+        self.scope = scope;
+        self
+    }
+
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    fn compile(&self) -> Result<Regex, BodyQueryError> {
+        if self.pattern.is_empty() {
+            return Err(BodyQueryError::EmptyPattern);
+        }
+        let source = if self.regex {
+            self.pattern.clone()
+        } else {
+            regex::escape(&self.pattern)
+        };
+        Ok(RegexBuilder::new(&source)
+            .case_insensitive(!self.case_sensitive)
+            .multi_line(true)
+            .build()?)
+    }
+
+    /// Lowercased words the pattern contains whole, which every matching
+    /// file must contain too. Empty for regexes.
+    fn required_words(&self) -> Vec<String> {
+        if self.regex {
+            return Vec::new();
+        }
+        let words: Vec<&str> = self.pattern.split(|c| !is_word_char(c)).collect();
+        // The first and last pieces may be cut off mid-word
+        let inner = if words.len() > 2 {
+            &words[1..words.len() - 1]
+        } else {
+            &[][..]
+        };
+        inner
+            .iter()
+            .filter(|w| !w.is_empty())
+            .map(|w| w.to_lowercase())
+            .collect()
+    }
+}
+
+/// Errors from body searches.
+#[derive(Debug, Error)]
+pub enum BodyQueryError {
+    #[error("the body index is off; set \"body_index\": true in .arbor/config.json")]
+    NotIndexed,
+
+    #[error("empty search pattern")]
+    EmptyPattern,
+
+    #[error("invalid regex: {0}")]
+    Regex(#[from] regex::Error),
+}
+
+/// One occurrence of the pattern.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BodyHit {
+    pub line: u32,
+    /// Byte offset within the line, 0-based like node columns.
+    pub column: u32,
+    pub region: TextRegion,
+    /// The line, trimmed and shortened.
+    pub text: String,
+}
+
+/// A node whose body matched.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BodyMatch {
+    #[serde(flatten)]
+    pub node: NodeInfo,
+    /// Occurrences in this node and not in a node nested inside it.
+    pub hit_count: usize,
+    pub hits: Vec<BodyHit>,
+}
+
+/// Nodes matching a body search, in file and line order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BodySearchResult {
+    pub pattern: String,
+    pub matches: Vec<BodyMatch>,
+    /// Occurrences in scope, including those outside any node.
+    pub total_hits: usize,
+    pub files_searched: usize,
+    /// More nodes matched than the limit allowed.
+    pub truncated: bool,
+}
+
+impl BodySearchResult {
+    /// Indexes of the matched nodes.
+    pub fn node_ids<'a>(&'a self, graph: &'a ArborGraph) -> impl Iterator<Item = NodeId> + 'a {
+        self.matches
+            .iter()
+            .filter_map(|m| graph.get_index(&m.node.id))
+    }
+}
+
+/// The text of one file.
+#[derive(Debug, Clone)]
+struct FileText {
+    text: String,
+    line_starts: Vec<usize>,
+    /// String and comment regions, sorted and disjoint.
+    regions: Vec<(usize, usize, TextRegion)>,
+}
+
+impl FileText {
+    fn region_at(&self, offset: usize) -> TextRegion {
+        let i = self
+            .regions
+            .partition_point(|&(start, _, _)| start <= offset);
+        match i.checked_sub(1).map(|i| self.regions[i]) {
+            Some((_, end, region)) if offset < end => region,
+            _ => TextRegion::Code,
+        }
+    }
+
+    /// 1-based line and 0-based column of a byte offset.
+    fn position(&self, offset: usize) -> (u32, u32) {
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        (line as u32 + 1, (offset - self.line_starts[line]) as u32)
+    }
+
+    fn excerpt(&self, line: u32) -> String {
+        let start = self.line_starts[line as usize - 1];
+        let end = self
+            .line_starts
+            .get(line as usize)
+            .copied()
+            .unwrap_or(self.text.len());
+        let text = self.text[start..end].trim();
+        let mut cut = text.len().min(MAX_EXCERPT_LEN);
+        while !text.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        text[..cut].to_string()
+    }
+}
+
+/// Files kept for body search, plus a word index to narrow literal
+/// queries.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct BodyIndex {
+    enabled: bool,
+    files: HashMap<String, FileText>,
+    /// Lowercased word -> files containing it.
+    words: HashMap<String, HashSet<String>>,
+}
+
+impl BodyIndex {
+    pub(crate) fn insert(&mut self, file: &str, text: String) {
+        self.remove(file);
+        self.enabled = true;
+        for word in text.split(|c| !is_word_char(c)).filter(|w| !w.is_empty()) {
+            let files = self.words.entry(word.to_lowercase()).or_default();
+            if !files.contains(file) {
+                files.insert(file.to_string());
+            }
+        }
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        let regions = lex_regions(&text, file);
+        self.files.insert(
+            file.to_string(),
+            FileText {
+                text,
+                line_starts,
+                regions,
+            },
+        );
+    }
+
+    pub(crate) fn text(&self, file: &str) -> Option<&str> {
+        self.files.get(file).map(|f| f.text.as_str())
+    }
+
+    pub(crate) fn remove(&mut self, file: &str) {
+        let Some(old) = self.files.remove(file) else {
+            return;
+        };
+        let words: HashSet<String> = old
+            .text
+            .split(|c| !is_word_char(c))
+            .filter(|w| !w.is_empty())
+            .map(str::to_lowercase)
+            .collect();
+        for word in words {
+            if let Some(files) = self.words.get_mut(&word) {
+                files.remove(file);
+                if files.is_empty() {
+                    self.words.remove(&word);
+                }
+            }
+        }
+    }
+
+    /// Files that can match, sorted.
+    fn candidates(&self, query: &BodyQuery) -> Vec<&String> {
+        let mut files: Vec<&String> = match query
+            .required_words()
+            .iter()
+            .map(|w| self.words.get(w))
+            .collect::<Option<Vec<_>>>()
+        {
+            None => Vec::new(),
+            Some(sets) if sets.is_empty() => self.files.keys().collect(),
+            Some(sets) => {
+                let smallest = sets.iter().min_by_key(|s| s.len()).unwrap();
+                smallest
+                    .iter()
+                    .filter(|file| sets.iter().all(|s| s.contains(*file)))
+                    .collect()
+            }
+        };
+        files.sort();
+        files
+    }
+}
+
+impl ArborGraph {
+    /// Adds a file's text to the body index, replacing any earlier
+    /// version. `file` is the path stored in the file's nodes.
+    pub fn index_body(&mut self, file: &str, text: String) {
+        self.bodies.insert(file, text);
+    }
+
+    /// Returns true once any file text has been indexed.
+    pub fn has_body_index(&self) -> bool {
+        self.bodies.enabled
+    }
+
+    /// Finds the nodes whose bodies contain the pattern.
+    ///
+    /// Each occurrence belongs to the innermost node covering it, so a
+    /// hit inside a method is reported for the method, not its class.
+    /// Occurrences outside every node count towards `total_hits` only.
+    pub fn search_bodies(&self, query: &BodyQuery) -> Result<BodySearchResult, BodyQueryError> {
+        if !self.has_body_index() {
+            return Err(BodyQueryError::NotIndexed);
+        }
+        let regex = query.compile()?;
+        let candidates = self.bodies.candidates(query);
+
+        let mut matches = Vec::new();
+        let mut total_hits = 0;
+        for file in &candidates {
+            let body = &self.bodies.files[*file];
+            let spans = self.spans(file, body);
+            let mut by_node: HashMap<NodeId, (usize, Vec<BodyHit>)> = HashMap::new();
+
+            for found in regex.find_iter(&body.text) {
+                let region = body.region_at(found.start());
+                if !query.scope.admits(region) {
+                    continue;
+                }
+                total_hits += 1;
+                let (line, column) = body.position(found.start());
+                let Some(node) = innermost(&spans, found.start()) else {
+                    continue;
+                };
+                let (count, hits) = by_node.entry(node).or_default();
+                *count += 1;
+                if hits.len() < MAX_HITS_PER_NODE {
+                    hits.push(BodyHit {
+                        line,
+                        column,
+                        region,
+                        text: body.excerpt(line),
+                    });
+                }
+            }
+
+            let mut found: Vec<BodyMatch> = by_node
+                .into_iter()
+                .filter_map(|(id, (hit_count, hits))| {
+                    let mut node = NodeInfo::from(self.get(id)?);
+                    node.centrality = self.centrality(id);
+                    Some(BodyMatch {
+                        node,
+                        hit_count,
+                        hits,
+                    })
+                })
+                .collect();
+            found.sort_by_key(|m| (m.hits[0].line, m.hits[0].column));
+            matches.extend(found);
+        }
+
+        let truncated = query.limit > 0 && matches.len() > query.limit;
+        if truncated {
+            matches.truncate(query.limit);
+        }
+        Ok(BodySearchResult {
+            pattern: query.pattern.clone(),
+            matches,
+            total_hits,
+            files_searched: candidates.len(),
+            truncated,
+        })
+    }
+
+    /// Byte and line ranges of a file's nodes. Nodes without a byte
+    /// range fall back to their lines.
+    fn spans(&self, file: &str, body: &FileText) -> Vec<Span> {
+        self.nodes_in_file(file)
+            .iter()
+            .filter_map(|&id| {
+                let node = self.get(id)?;
+                let (start, end) = if node.byte_end > node.byte_start {
+                    (node.byte_start as usize, node.byte_end as usize)
+                } else {
+                    let line = |l: u32| body.line_starts.get(l.saturating_sub(1) as usize);
+                    let start = *line(node.line_start)?;
+                    let end = line(node.line_end + 1).copied().unwrap_or(body.text.len());
+                    (start, end)
+                };
+                Some(Span { id, start, end })
+            })
+            .collect()
+    }
+}
+
+struct Span {
+    id: NodeId,
+    start: usize,
+    end: usize,
+}
+
+/// The smallest span covering `offset`.
+fn innermost(spans: &[Span], offset: usize) -> Option<NodeId> {
+    spans
+        .iter()
+        .filter(|s| s.start <= offset && offset < s.end)
+        .min_by_key(|s| s.end - s.start)
+        .map(|s| s.id)
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Finds string literal and comment regions.
+///
+/// A lexer that knows quotes, escapes and comment markers, not a
+/// grammar: `#` starts a comment in Python, Ruby, shell and similar
+/// files, `//` and `/* */` everywhere else. Ordinary string literals end
+/// at the line's end; backtick and triple-quoted strings may span lines.
+/// In Rust a single quote only opens a char literal, never a lifetime.
+fn lex_regions(text: &str, file: &str) -> Vec<(usize, usize, TextRegion)> {
+    let extension = file.rsplit('.').next().unwrap_or("");
+    let hash_comments = matches!(
+        extension,
+        "py" | "pyi"
+            | "rb"
+            | "sh"
+            | "bash"
+            | "zsh"
+            | "pl"
+            | "r"
+            | "yaml"
+            | "yml"
+            | "toml"
+            | "ex"
+            | "exs"
+    );
+    let rust = extension == "rs";
+    let bytes = text.as_bytes();
+    let mut regions = Vec::new();
+    let mut i = 0;
+
+    let line_end = |from: usize| {
+        bytes[from..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(bytes.len(), |p| from + p)
+    };
+    // Index just past the closing `quote`, honouring backslash escapes
+    let close = |from: usize, quote: &[u8], multiline: bool| {
+        let mut j = from;
+        while j < bytes.len() {
+            if bytes[j] == b'\\' {
+                j += 2;
+                continue;
+            }
+            if !multiline && bytes[j] == b'\n' {
+                return j;
+            }
+            if bytes[j..].starts_with(quote) {
+                return j + quote.len();
+            }
+            j += 1;
+        }
+        bytes.len()
+    };
+
+    while i < bytes.len() {
+        let rest = &bytes[i..];
+        let line_comment = if hash_comments {
+            rest[0] == b'#'
+        } else {
+            rest.starts_with(b"//")
+        };
+        let (end, region) = if line_comment {
+            (line_end(i), TextRegion::Comment)
+        } else if !hash_comments && rest.starts_with(b"/*") {
+            let end = text[i + 2..]
+                .find("*/")
+                .map_or(bytes.len(), |p| i + 2 + p + 2);
+            (end, TextRegion::Comment)
+        } else if rest.starts_with(b"\"\"\"") || rest.starts_with(b"'''") {
+            (close(i + 3, &rest[..3], true), TextRegion::String)
+        } else if rest[0] == b'`' {
+            (close(i + 1, b"`", true), TextRegion::String)
+        } else if rest[0] == b'"' {
+            (close(i + 1, b"\"", false), TextRegion::String)
+        } else if rest[0] == b'\'' {
+            let char_literal = rest.get(1) == Some(&b'\\')
+                || (rest.len() > 2 && rest[2] == b'\'')
+                || text[i + 1..].chars().nth(1) == Some('\'');
+            if rust && !char_literal {
+                i += 1;
+                continue;
+            }
+            (close(i + 1, b"'", false), TextRegion::String)
+        } else {
+            i += 1;
+            continue;
+        };
+        regions.push((i, end, region));
+        i = end.max(i + 1);
+    }
+    regions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arbor_core::{CodeNode, NodeKind};
+
+    const SOURCE: &str = r#"const MAX_RETRIES: u32 = 3;
+
+fn send<'a>(req: &'a Request) {
+    // Tag with X-Request-Id
+    req.header("X-Request-Id", id());
+    for _ in 0..MAX_RETRIES {}
+}
+
+fn other() {
+    let c = 'x';
+}
+"#;
+
+    fn graph() -> (ArborGraph, NodeId, NodeId) {
+        let mut graph = ArborGraph::new();
+        let start = SOURCE.find("fn send").unwrap() as u32;
+        let end = SOURCE.find("fn other").unwrap() as u32 - 1;
+        let send = graph.add_node(
+            CodeNode::new("send", "send", NodeKind::Function, "src/net.rs")
+                .with_lines(3, 7)
+                .with_bytes(start, end),
+        );
+        // No byte range: matched by lines
+        let constant = graph.add_node(
+            CodeNode::new(
+                "MAX_RETRIES",
+                "MAX_RETRIES",
+                NodeKind::Constant,
+                "src/net.rs",
+            )
+            .with_lines(1, 1),
+        );
+        graph.index_body("src/net.rs", SOURCE.to_string());
+        (graph, send, constant)
+    }
+
+    #[test]
+    fn test_literal_and_scopes() {
+        let (mut graph, send, constant) = graph();
+        let ids = |result: &BodySearchResult| -> Vec<String> {
+            result.matches.iter().map(|m| m.node.name.clone()).collect()
+        };
+
+        let header = graph
+            .search_bodies(&BodyQuery::literal("x-request-id"))
+            .unwrap();
+        assert_eq!(ids(&header), vec!["send"]);
+        assert_eq!(header.matches[0].hit_count, 2);
+        assert_eq!(header.matches[0].hits[1].line, 5);
+        assert_eq!(header.matches[0].hits[1].column, 16);
+        assert_eq!(header.matches[0].hits[1].region, TextRegion::String);
+
+        let strings = BodyQuery::literal("X-Request-Id").with_scope(BodyScope::Strings);
+        assert_eq!(graph.search_bodies(&strings).unwrap().total_hits, 1);
+        let comments = BodyQuery::literal("X-Request-Id").with_scope(BodyScope::Comments);
+        assert_eq!(graph.search_bodies(&comments).unwrap().total_hits, 1);
+
+        let retries = BodyQuery::literal("MAX_RETRIES").with_case_sensitive(true);
+        let result = graph.search_bodies(&retries).unwrap();
+        assert_eq!(ids(&result), vec!["MAX_RETRIES", "send"]);
+        assert!(result.node_ids(&graph).eq([constant, send]));
+
+        // The lifetime doesn't open a string, so `Request` is code
+        let code = BodyQuery::literal("Request)").with_scope(BodyScope::Code);
+        assert_eq!(graph.search_bodies(&code).unwrap().total_hits, 1);
+
+        graph.remove_file("src/net.rs");
+        let gone = graph.search_bodies(&BodyQuery::literal("send")).unwrap();
+        assert!(gone.matches.is_empty());
+        assert_eq!(gone.files_searched, 0);
+    }
+
+    #[test]
+    fn test_regex_and_errors() {
+        let (graph, _, _) = graph();
+        let result = graph
+            .search_bodies(&BodyQuery::regex(r"header\(\s*\x22[A-Z]"))
+            .unwrap();
+        assert_eq!(result.matches.len(), 1);
+        assert!(matches!(
+            graph.search_bodies(&BodyQuery::regex("(")),
+            Err(BodyQueryError::Regex(_))
+        ));
+        assert!(matches!(
+            ArborGraph::new().search_bodies(&BodyQuery::literal("x")),
+            Err(BodyQueryError::NotIndexed)
+        ));
+    }
+}
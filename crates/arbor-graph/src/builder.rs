@@ -6,7 +6,7 @@
 use crate::edge::{Edge, EdgeKind};
 use crate::graph::{ArborGraph, NodeId};
 use crate::symbol_table::SymbolTable;
-use arbor_core::CodeNode;
+use arbor_core::{CodeNode, WorkspaceRoot};
 use std::collections::HashMap;
 use std::path::PathBuf;
 
@@ -39,6 +39,12 @@ impl GraphBuilder {
         }
     }
 
+    /// Sets the workspace roots, so references only cross roots where
+    /// `depends_on` allows.
+    pub fn set_roots(&mut self, roots: Vec<WorkspaceRoot>) {
+        self.graph.set_roots(roots);
+    }
+
     /// Adds nodes from a file to the graph.
     ///
     /// Call this for each parsed file, then call `resolve_edges`
@@ -73,6 +79,7 @@ impl GraphBuilder {
 
         // Collect indices to avoid borrowing self.graph during iteration
         let node_indices: Vec<NodeId> = self.graph.node_indexes().collect();
+        let workspace = !self.graph.roots().is_empty();
 
         for from_idx in node_indices {
             // Get references by cloning to release borrow on graph
@@ -82,6 +89,16 @@ impl GraphBuilder {
             };
 
             for reference in references {
+                // Workspaces resolve through the graph, which knows the roots
+                if workspace {
+                    if let Some(to_idx) = self.graph.resolve_reference_from(from_idx, &reference) {
+                        if from_idx != to_idx {
+                            edges_to_add.push((from_idx, to_idx, reference));
+                        }
+                    }
+                    continue;
+                }
+
                 let mut found = false;
 
                 // 1. Try resolving via Symbol Table (FQN)
@@ -183,4 +200,27 @@ mod tests {
             "Should resolve cross-file edge via FQN"
         );
     }
+
+    #[test]
+    fn test_find_by_file_accepts_client_paths() {
+        let mut builder = GraphBuilder::new();
+        builder.add_nodes(vec![CodeNode::new(
+            "run",
+            "run",
+            NodeKind::Function,
+            "src/app.py",
+        )]);
+        let mut graph = builder.build();
+        graph.set_root(PathBuf::from("/work/proj"));
+
+        assert_eq!(graph.find_by_file("src/app.py").len(), 1);
+        assert_eq!(graph.find_by_file("./src/app.py").len(), 1);
+        assert_eq!(graph.find_by_file("src\\app.py").len(), 1);
+        assert_eq!(graph.find_by_file("/work/proj/src/app.py").len(), 1);
+        assert!(graph.find_by_file("/elsewhere/src/app.py").is_empty());
+        assert_eq!(
+            graph.absolute_path("src/app.py"),
+            PathBuf::from("/work/proj/src/app.py")
+        );
+    }
 }
@@ -0,0 +1,484 @@
+//! Impact analysis for a whole patch.
+//!
+//! Maps the lines a patch changed onto the innermost nodes covering them,
+//! then runs [`ArborGraph::analyze_impact`] on each and merges the
+//! results. Answers "what does this PR touch" rather than "what does
+//! this one symbol touch".
+
+use crate::graph::{ArborGraph, NodeId};
+use crate::impact::AffectedNode;
+use crate::patch::{FilePatch, LineRange};
+use crate::query::NodeInfo;
+use arbor_core::{CodeNode, NodeKind};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt::Write as _;
+use std::time::Instant;
+
+/// A file touched by the patch.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangedFile {
+    /// Path as stored in the graph.
+    pub path: String,
+    pub deleted: bool,
+    /// Number of symbols the changed lines fall in.
+    pub symbols: usize,
+}
+
+/// A symbol whose lines the patch changed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangedSymbol {
+    pub node: NodeInfo,
+    /// Upstream nodes: callers, importers and so on, nearest first.
+    pub callers: Vec<AffectedNode>,
+    /// Tests among the callers, or the symbol itself if it's a test.
+    pub tests: Vec<NodeInfo>,
+}
+
+/// The combined impact of a patch.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangeImpact {
+    pub files: Vec<ChangedFile>,
+    pub symbols: Vec<ChangedSymbol>,
+    /// Every upstream node of any changed symbol, once, at its smallest
+    /// distance. Changed symbols themselves are left out.
+    pub affected: Vec<AffectedNode>,
+    /// Every test that reaches a changed symbol, once.
+    pub tests: Vec<NodeInfo>,
+    pub max_depth: usize,
+    pub query_time_ms: u64,
+}
+
+impl ArborGraph {
+    /// Returns the innermost nodes covering any of the given lines.
+    ///
+    /// Imports are skipped, and modules only count when nothing smaller
+    /// covers the line.
+    pub fn nodes_in_lines(&self, file: &str, lines: &[LineRange]) -> Vec<NodeId> {
+        let candidates: Vec<&CodeNode> = self
+            .find_by_file(file)
+            .into_iter()
+            .filter(|n| n.kind != NodeKind::Import)
+            .collect();
+        let mut found = BTreeSet::new();
+        for range in lines {
+            for line in range.start..=range.end {
+                let innermost = candidates
+                    .iter()
+                    .filter(|n| n.line_start <= line && line <= n.line_end)
+                    .min_by_key(|n| (n.kind == NodeKind::Module, n.line_end - n.line_start));
+                if let Some(index) = innermost.and_then(|n| self.get_index(&n.id)) {
+                    found.insert(index);
+                }
+            }
+        }
+        found.into_iter().collect()
+    }
+
+    /// Analyzes everything a patch touches.
+    ///
+    /// `patches` paths may be stored paths or absolute paths under the
+    /// graph root. `max_depth` bounds the upstream search (0 = unlimited).
+    /// Removed lines are looked up in this graph too, which is right when
+    /// it's the version the patch applies to; otherwise use
+    /// [`analyze_change_impact_with_base`](Self::analyze_change_impact_with_base).
+    pub fn analyze_change_impact(&self, patches: &[FilePatch], max_depth: usize) -> ChangeImpact {
+        self.analyze_change_impact_with_base(self, patches, max_depth)
+    }
+
+    /// Analyzes a patch that has already been applied to this graph.
+    ///
+    /// Added lines are looked up here and removed lines in `base`, the
+    /// version before the patch. Symbols the patch deleted outright are
+    /// reported from `base`, with their callers that still exist here.
+    pub fn analyze_change_impact_with_base(
+        &self,
+        base: &ArborGraph,
+        patches: &[FilePatch],
+        max_depth: usize,
+    ) -> ChangeImpact {
+        let start = Instant::now();
+        let mut files = Vec::new();
+        let mut changed = Vec::new();
+        let mut gone = Vec::new();
+        for patch in patches {
+            let (nodes, deleted) = self.patch_nodes(base, patch);
+            gone.extend(deleted);
+            files.push(ChangedFile {
+                path: self.file_key(&patch.path),
+                deleted: patch.deleted,
+                symbols: nodes.len(),
+            });
+            changed.extend(nodes);
+        }
+        let changed: Vec<(&ArborGraph, NodeId)> = changed
+            .into_iter()
+            .map(|index| (self, index))
+            .chain(gone.into_iter().map(|index| (base, index)))
+            .collect();
+
+        let changed_set: HashSet<NodeId> = changed
+            .iter()
+            .filter(|(graph, _)| std::ptr::eq(*graph, self))
+            .map(|(_, index)| *index)
+            .collect();
+        let mut symbols = Vec::new();
+        let mut affected: HashMap<NodeId, AffectedNode> = HashMap::new();
+        let mut tests: HashMap<String, NodeInfo> = HashMap::new();
+
+        for (graph, index) in changed {
+            let Some(node) = graph.get(index) else {
+                continue;
+            };
+            let analysis = graph.analyze_impact(index, max_depth);
+            let mut callers = self.carry_over(graph, analysis.upstream);
+            callers.sort_by(|a, b| {
+                (a.hop_distance, &a.node_info.file, a.node_info.line_start).cmp(&(
+                    b.hop_distance,
+                    &b.node_info.file,
+                    b.node_info.line_start,
+                ))
+            });
+
+            let mut symbol_tests: Vec<NodeInfo> = callers
+                .iter()
+                .filter(|c| self.get(c.node_id).is_some_and(|n| self.is_test(n)))
+                .map(|c| c.node_info.clone())
+                .collect();
+            if self.is_test(node) {
+                symbol_tests.insert(0, NodeInfo::from(node));
+            }
+            for test in &symbol_tests {
+                tests.entry(test.id.clone()).or_insert_with(|| test.clone());
+            }
+
+            for caller in &callers {
+                if changed_set.contains(&caller.node_id) {
+                    continue;
+                }
+                affected
+                    .entry(caller.node_id)
+                    .and_modify(|seen| {
+                        if caller.hop_distance < seen.hop_distance {
+                            *seen = caller.clone();
+                        }
+                    })
+                    .or_insert_with(|| caller.clone());
+            }
+
+            let mut info = NodeInfo::from(node);
+            info.centrality = graph.centrality(index);
+            symbols.push(ChangedSymbol {
+                node: info,
+                callers,
+                tests: symbol_tests,
+            });
+        }
+
+        let mut affected: Vec<AffectedNode> = affected.into_values().collect();
+        affected.sort_by(|a, b| {
+            (a.hop_distance, &a.node_info.file, a.node_info.line_start).cmp(&(
+                b.hop_distance,
+                &b.node_info.file,
+                b.node_info.line_start,
+            ))
+        });
+        let mut tests: Vec<NodeInfo> = tests.into_values().collect();
+        tests.sort_by(|a, b| (&a.file, a.line_start).cmp(&(&b.file, b.line_start)));
+
+        ChangeImpact {
+            files,
+            symbols,
+            affected,
+            tests,
+            max_depth,
+            query_time_ms: start.elapsed().as_millis() as u64,
+        }
+    }
+}
+
+impl ArborGraph {
+    /// Returns the nodes here that a patch changed: those covering its
+    /// added lines, plus those whose removed lines `base` places in them.
+    pub fn changed_nodes(&self, base: &ArborGraph, patch: &FilePatch) -> Vec<NodeId> {
+        self.patch_nodes(base, patch).0
+    }
+
+    /// Nodes here a patch changed, and nodes of `base` it deleted outright.
+    fn patch_nodes(&self, base: &ArborGraph, patch: &FilePatch) -> (Vec<NodeId>, Vec<NodeId>) {
+        let mut nodes = if patch.deleted {
+            Vec::new()
+        } else {
+            self.nodes_in_lines(&patch.path, &patch.lines)
+        };
+        let mut gone = Vec::new();
+        let old_path = patch.old_path.as_deref().unwrap_or(&patch.path);
+        for index in base.nodes_in_lines(old_path, &patch.removed) {
+            let Some(node) = base.get(index) else {
+                continue;
+            };
+            let current = match self.counterpart(base, node) {
+                Some(current) => Some(current),
+                None => {
+                    gone.push(index);
+                    // Whatever held it changed too
+                    self.surviving_parent(base, node)
+                }
+            };
+            if let Some(current) = current.filter(|c| !nodes.contains(c)) {
+                nodes.push(current);
+            }
+        }
+        (nodes, gone)
+    }
+
+    /// The innermost node of `base` around `node` that still exists here.
+    fn surviving_parent(&self, base: &ArborGraph, node: &CodeNode) -> Option<NodeId> {
+        base.find_by_file(&node.file)
+            .into_iter()
+            .filter(|n| n.id != node.id && n.kind != NodeKind::Import)
+            .filter(|n| n.line_start <= node.line_start && node.line_end <= n.line_end)
+            .filter_map(|n| Some((n, self.counterpart(base, n)?)))
+            .min_by_key(|(n, _)| (n.kind == NodeKind::Module, n.line_end - n.line_start))
+            .map(|(_, current)| current)
+    }
+
+    /// Finds the node in this graph that `node`, from `other`, stands for:
+    /// same file on disk, qualified name and kind. The two graphs may
+    /// store paths relative to different roots.
+    fn counterpart(&self, other: &ArborGraph, node: &CodeNode) -> Option<NodeId> {
+        if std::ptr::eq(other, self) {
+            return self.get_index(&node.id);
+        }
+        let file = other.absolute_path(&node.file);
+        self.find_by_file(&file.to_string_lossy())
+            .into_iter()
+            .find(|n| n.qualified_name == node.qualified_name && n.kind == node.kind)
+            .and_then(|n| self.get_index(&n.id))
+    }
+
+    /// Maps nodes found in `other` onto this graph, dropping the ones
+    /// that no longer exist here.
+    fn carry_over(&self, other: &ArborGraph, nodes: Vec<AffectedNode>) -> Vec<AffectedNode> {
+        if std::ptr::eq(other, self) {
+            return nodes;
+        }
+        nodes
+            .into_iter()
+            .filter_map(|mut affected| {
+                let node = other.get(affected.node_id)?;
+                let index = self.counterpart(other, node)?;
+                affected.node_id = index;
+                affected.node_info = NodeInfo::from(self.get(index)?);
+                Some(affected)
+            })
+            .collect()
+    }
+}
+
+impl ChangeImpact {
+    /// Renders the report as Markdown, e.g. for a review comment.
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "## Change impact\n");
+        let _ = writeln!(
+            out,
+            "{} changed symbols in {} files, {} affected nodes, {} tests.\n",
+            self.symbols.len(),
+            self.files.len(),
+            self.affected.len(),
+            self.tests.len()
+        );
+
+        if !self.symbols.is_empty() {
+            let _ = writeln!(out, "| Symbol | Kind | Location | Callers | Tests |");
+            let _ = writeln!(out, "|---|---|---|---|---|");
+            for symbol in &self.symbols {
+                let _ = writeln!(
+                    out,
+                    "| `{}` | {} | `{}:{}` | {} | {} |",
+                    symbol.node.qualified_name,
+                    symbol.node.kind,
+                    symbol.node.file,
+                    symbol.node.line_start,
+                    symbol.callers.len(),
+                    symbol.tests.len()
+                );
+            }
+            out.push('\n');
+        }
+
+        for symbol in self.symbols.iter().filter(|s| !s.callers.is_empty()) {
+            let _ = writeln!(out, "### `{}`\n", symbol.node.qualified_name);
+            for caller in &symbol.callers {
+                let _ = writeln!(
+                    out,
+                    "- `{}` ({}, {} hop{}) — `{}:{}`",
+                    caller.node_info.qualified_name,
+                    caller.entry_edge,
+                    caller.hop_distance,
+                    if caller.hop_distance == 1 { "" } else { "s" },
+                    caller.node_info.file,
+                    caller.node_info.line_start
+                );
+            }
+            out.push('\n');
+        }
+
+        if !self.tests.is_empty() {
+            let _ = writeln!(out, "### Tests to run\n");
+            for test in &self.tests {
+                let _ = writeln!(
+                    out,
+                    "- `{}` — `{}:{}`",
+                    test.qualified_name, test.file, test.line_start
+                );
+            }
+            out.push('\n');
+        }
+
+        let deleted: Vec<&str> = self
+            .files
+            .iter()
+            .filter(|f| f.deleted)
+            .map(|f| f.path.as_str())
+            .collect();
+        if !deleted.is_empty() {
+            let _ = writeln!(out, "Deleted files: {}", deleted.join(", "));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::GraphBuilder;
+    use crate::patch::parse_unified_diff;
+
+    fn func(name: &str, file: &str, lines: (u32, u32), refs: &[&str]) -> CodeNode {
+        CodeNode::new(name, name, NodeKind::Function, file)
+            .with_lines(lines.0, lines.1)
+            .with_references(refs.iter().map(|r| r.to_string()).collect())
+    }
+
+    fn graph() -> ArborGraph {
+        let mut builder = GraphBuilder::new();
+        builder.add_nodes(vec![
+            CodeNode::new("Service", "Service", NodeKind::Class, "app.py").with_lines(1, 20),
+            func("validate", "app.py", (2, 6), &[]),
+            func("save", "app.py", (8, 12), &["validate"]),
+            func("handler", "api.py", (1, 5), &["save"]),
+            func("test_save", "tests/test_app.py", (1, 4), &["save"]),
+            func("test_handler", "tests/test_api.py", (1, 4), &["handler"]),
+        ]);
+        builder.build()
+    }
+
+    #[test]
+    fn test_nodes_in_lines_picks_innermost() {
+        let graph = graph();
+        let names = |lines: &[LineRange]| -> Vec<String> {
+            graph
+                .nodes_in_lines("app.py", lines)
+                .into_iter()
+                .map(|i| graph.get(i).unwrap().name.clone())
+                .collect()
+        };
+        assert_eq!(names(&[LineRange { start: 3, end: 3 }]), vec!["validate"]);
+        assert_eq!(names(&[LineRange { start: 7, end: 7 }]), vec!["Service"]);
+        let mut both = names(&[LineRange { start: 5, end: 9 }]);
+        both.sort();
+        assert_eq!(both, vec!["Service", "save", "validate"]);
+    }
+
+    #[test]
+    fn test_change_impact_merges_and_finds_tests() {
+        let graph = graph();
+        let patch = "\
+--- a/app.py
++++ b/app.py
+@@ -3,1 +3,1 @@
+-    return False
++    return True
+@@ -10,1 +10,2 @@
+-    db.save()
++    db.save()
++    log()
+";
+        let impact = graph.analyze_change_impact(&parse_unified_diff(patch), 0);
+        assert_eq!(impact.files.len(), 1);
+        assert_eq!(impact.files[0].symbols, 2);
+
+        let names: Vec<&str> = impact
+            .symbols
+            .iter()
+            .map(|s| s.node.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["validate", "save"]);
+
+        // save is a changed symbol itself, so only appears once as a symbol
+        let affected: Vec<&str> = impact
+            .affected
+            .iter()
+            .map(|a| a.node_info.name.as_str())
+            .collect();
+        assert!(!affected.contains(&"save"));
+        assert!(affected.contains(&"handler"));
+        assert_eq!(
+            affected.iter().filter(|n| **n == "test_save").count(),
+            1,
+            "deduplicated"
+        );
+
+        let tests: Vec<&str> = impact.tests.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(tests, vec!["test_handler", "test_save"]);
+        assert_eq!(impact.symbols[1].tests.len(), 2);
+
+        let markdown = impact.to_markdown();
+        assert!(markdown.contains("| `validate` | function | `app.py:2` |"));
+        assert!(markdown.contains("### Tests to run"));
+    }
+
+    #[test]
+    fn test_removed_lines_map_onto_base() {
+        let mut builder = GraphBuilder::new();
+        builder.add_nodes(vec![
+            CodeNode::new("Service", "Service", NodeKind::Class, "app.py").with_lines(1, 24),
+            func("validate", "app.py", (2, 6), &[]),
+            func("save", "app.py", (8, 12), &["validate"]),
+            func("cleanup", "app.py", (14, 18), &[]),
+            func("handler", "api.py", (1, 5), &["save", "cleanup"]),
+        ]);
+        let base = builder.build();
+        let current = graph();
+
+        // Drops save's first line and all of cleanup
+        let patch = "\
+--- a/app.py
++++ b/app.py
+@@ -8 +7,0 @@
+-    # save it
+@@ -14,5 +12,0 @@
+-def cleanup():
+-    pass
+-
+-
+-
+";
+        let impact = current.analyze_change_impact_with_base(&base, &parse_unified_diff(patch), 0);
+        let names: Vec<&str> = impact
+            .symbols
+            .iter()
+            .map(|s| s.node.name.as_str())
+            .collect();
+        // The class lost a method, so it changed too
+        assert_eq!(names, vec!["save", "Service", "cleanup"]);
+
+        let cleanup = &impact.symbols[2];
+        assert_eq!(cleanup.callers.len(), 1);
+        assert_eq!(cleanup.callers[0].node_info.name, "handler");
+        let handler = current.get(cleanup.callers[0].node_id).unwrap();
+        assert_eq!(handler.file, "api.py");
+    }
+}
@@ -0,0 +1,788 @@
+//! Architecture clustering.
+//!
+//! Files are grouped into communities with the Louvain method: edges
+//! between symbols, and imports resolved to files, become weighted links
+//! between files, and files in the same directory get a weaker link so
+//! that code nothing calls stays with its neighbours. Each cluster is
+//! labelled by the deepest directory holding most of its symbols; its ID
+//! is a slug of that label, so it survives re-indexing as long as the
+//! layout does. Every node joins its file's cluster.
+
+use crate::edge::EdgeKind;
+use crate::graph::{ArborGraph, NodeId};
+use crate::layout::directory_of;
+use crate::resolve::{is_type, ImportResolver};
+use arbor_core::NodeKind;
+use petgraph::visit::{EdgeRef, IntoEdgeReferences};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write;
+
+/// Local-move passes per Louvain level before giving up on convergence.
+const MAX_PASSES: usize = 32;
+
+/// Smallest modularity gain worth moving a file for.
+const MIN_GAIN: f64 = 1e-9;
+
+/// Key symbols listed per cluster.
+const KEY_SYMBOLS: usize = 5;
+
+/// Name tokens used as keywords per cluster.
+const KEYWORDS: usize = 3;
+
+/// Tokens too common to say anything about a cluster.
+const STOPWORDS: [&str; 16] = [
+    "get", "set", "new", "test", "tests", "init", "self", "the", "from", "with", "for", "and",
+    "impl", "default", "main", "mod",
+];
+
+/// How the graph is turned into weighted links.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClusterOptions {
+    /// Weight per edge kind. Unlisted kinds weigh 1, except `flows_to`
+    /// and `data_dependency`, which stay inside a function and weigh 0.
+    pub weights: HashMap<EdgeKind, f64>,
+
+    /// Weight linking files in the same directory.
+    pub directory_weight: f64,
+
+    /// Higher values give more, smaller clusters.
+    pub resolution: f64,
+}
+
+impl Default for ClusterOptions {
+    fn default() -> Self {
+        Self {
+            weights: HashMap::new(),
+            directory_weight: 0.5,
+            resolution: 1.0,
+        }
+    }
+}
+
+impl ClusterOptions {
+    /// Sets the weight of an edge kind; 0 ignores it.
+    pub fn with_weight(mut self, kind: EdgeKind, weight: f64) -> Self {
+        self.weights.insert(kind, weight);
+        self
+    }
+
+    /// Sets the Louvain resolution.
+    pub fn with_resolution(mut self, resolution: f64) -> Self {
+        self.resolution = resolution;
+        self
+    }
+
+    /// The weight edges of this kind contribute.
+    pub fn weight(&self, kind: EdgeKind) -> f64 {
+        self.weights.get(&kind).copied().unwrap_or(match kind {
+            EdgeKind::FlowsTo | EdgeKind::DataDependency => 0.0,
+            _ => 1.0,
+        })
+    }
+}
+
+/// A group of files that mostly talk to each other.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Cluster {
+    /// Stable ID, a slug of the label.
+    pub id: String,
+    /// Dominant directory, plus a keyword when two clusters share one.
+    pub label: String,
+    /// The deepest directory holding at least half of the symbols.
+    pub directory: String,
+    /// The most frequent tokens in symbol names.
+    pub keywords: Vec<String>,
+    /// Number of nodes.
+    pub size: usize,
+    /// Member files, sorted.
+    pub files: Vec<String>,
+    /// The most central functions and types, by qualified name.
+    pub key_symbols: Vec<String>,
+    /// Dependencies that stay inside the cluster.
+    pub internal_edges: usize,
+}
+
+/// Dependencies from one cluster to another.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClusterEdge {
+    /// The dependent cluster's ID.
+    pub from: String,
+    /// The ID of the cluster it depends on.
+    pub to: String,
+    /// Calls, references and resolved imports between them.
+    pub count: usize,
+}
+
+/// The clusters of a graph and how they depend on each other.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ClusterReport {
+    /// Clusters, largest first.
+    pub clusters: Vec<Cluster>,
+    /// Inter-cluster dependencies, heaviest first.
+    pub edges: Vec<ClusterEdge>,
+    /// Modularity of the partition of the file graph, from -0.5 to 1.
+    pub modularity: f64,
+}
+
+impl ClusterReport {
+    /// Looks up a cluster by ID.
+    pub fn get(&self, id: &str) -> Option<&Cluster> {
+        self.clusters.iter().find(|c| c.id == id)
+    }
+
+    /// A Markdown overview of every cluster, for onboarding context.
+    pub fn to_markdown(&self) -> String {
+        let files: usize = self.clusters.iter().map(|c| c.files.len()).sum();
+        let mut out = format!(
+            "# Architecture\n\n{} clusters over {} files (modularity {:.2}).\n",
+            self.clusters.len(),
+            files,
+            self.modularity
+        );
+        for cluster in &self.clusters {
+            out.push('\n');
+            out.push_str(&self.cluster_markdown(cluster));
+        }
+        out
+    }
+
+    /// A Markdown summary of one cluster: what's in it, its key symbols
+    /// and which clusters it depends on and is used by.
+    pub fn summary(&self, id: &str) -> Option<String> {
+        self.get(id).map(|cluster| self.cluster_markdown(cluster))
+    }
+
+    fn cluster_markdown(&self, cluster: &Cluster) -> String {
+        let mut out = format!("## {} (`{}`)\n\n", cluster.label, cluster.id);
+        let _ = write!(
+            out,
+            "{} files, {} symbols",
+            cluster.files.len(),
+            cluster.size
+        );
+        if !cluster.keywords.is_empty() {
+            let _ = write!(out, "; about {}", cluster.keywords.join(", "));
+        }
+        out.push_str(".\n\n");
+
+        if !cluster.key_symbols.is_empty() {
+            let symbols: Vec<String> = cluster
+                .key_symbols
+                .iter()
+                .map(|s| format!("`{}`", s))
+                .collect();
+            let _ = writeln!(out, "- Key symbols: {}", symbols.join(", "));
+        }
+        let shown: Vec<&str> = cluster.files.iter().take(8).map(String::as_str).collect();
+        let _ = write!(out, "- Files: {}", shown.join(", "));
+        if cluster.files.len() > shown.len() {
+            let _ = write!(out, " and {} more", cluster.files.len() - shown.len());
+        }
+        out.push('\n');
+
+        let neighbours = |outgoing: bool| -> Vec<String> {
+            self.edges
+                .iter()
+                .filter_map(|e| {
+                    let (this, other) = if outgoing {
+                        (&e.from, &e.to)
+                    } else {
+                        (&e.to, &e.from)
+                    };
+                    (*this == cluster.id).then(|| format!("`{}` ({})", other, e.count))
+                })
+                .collect()
+        };
+        let depends_on = neighbours(true);
+        if !depends_on.is_empty() {
+            let _ = writeln!(out, "- Depends on: {}", depends_on.join(", "));
+        }
+        let used_by = neighbours(false);
+        if !used_by.is_empty() {
+            let _ = writeln!(out, "- Used by: {}", used_by.join(", "));
+        }
+        out
+    }
+}
+
+impl ArborGraph {
+    /// Groups files into clusters. Doesn't change the graph; see
+    /// [`ArborGraph::assign_clusters`].
+    pub fn compute_clusters(&self, options: &ClusterOptions) -> ClusterReport {
+        let deps = FileDeps::new(self, options);
+        let files = &deps.files;
+
+        // Undirected, weighted file graph
+        let mut adjacency: Vec<BTreeMap<usize, f64>> = vec![BTreeMap::new(); files.len()];
+        let mut link = |a: usize, b: usize, weight: f64| {
+            if a != b && weight > 0.0 {
+                *adjacency[a].entry(b).or_default() += weight;
+                *adjacency[b].entry(a).or_default() += weight;
+            }
+        };
+        for (&(from, to), &(_, weight)) in &deps.links {
+            link(from, to, weight);
+        }
+        if options.directory_weight > 0.0 {
+            let mut previous: HashMap<&str, usize> = HashMap::new();
+            for (position, file) in files.iter().enumerate() {
+                if let Some(sibling) = previous.insert(directory_of(file), position) {
+                    link(sibling, position, options.directory_weight);
+                }
+            }
+        }
+
+        let membership = louvain(&adjacency, options.resolution);
+        let modularity = modularity(&adjacency, &membership, options.resolution);
+
+        let count = membership.iter().max().map_or(0, |m| m + 1);
+        let mut groups: Vec<Vec<usize>> = vec![Vec::new(); count];
+        for (file, &community) in membership.iter().enumerate() {
+            groups[community].push(file);
+        }
+
+        let mut clusters: Vec<(Cluster, Vec<usize>)> = groups
+            .into_iter()
+            .map(|members| (self.describe_cluster(files, &members), members))
+            .collect();
+        clusters.sort_by(|(a, _), (b, _)| {
+            b.size
+                .cmp(&a.size)
+                .then_with(|| a.directory.cmp(&b.directory))
+                .then_with(|| a.files.cmp(&b.files))
+        });
+        name_clusters(clusters.iter_mut().map(|(cluster, _)| cluster));
+
+        let mut cluster_of = vec![0; files.len()];
+        for (position, (_, members)) in clusters.iter().enumerate() {
+            for &file in members {
+                cluster_of[file] = position;
+            }
+        }
+        let mut between: BTreeMap<(usize, usize), usize> = BTreeMap::new();
+        for (&(from, to), &(edges, _)) in &deps.links {
+            let (from, to) = (cluster_of[from], cluster_of[to]);
+            if from == to {
+                clusters[from].0.internal_edges += edges;
+            } else {
+                *between.entry((from, to)).or_default() += edges;
+            }
+        }
+        for (&file, &edges) in &deps.internal {
+            clusters[cluster_of[file]].0.internal_edges += edges;
+        }
+
+        let mut edges: Vec<ClusterEdge> = between
+            .into_iter()
+            .map(|((from, to), count)| ClusterEdge {
+                from: clusters[from].0.id.clone(),
+                to: clusters[to].0.id.clone(),
+                count,
+            })
+            .collect();
+        edges.sort_by_key(|e| std::cmp::Reverse(e.count));
+
+        ClusterReport {
+            clusters: clusters.into_iter().map(|(cluster, _)| cluster).collect(),
+            edges,
+            modularity,
+        }
+    }
+
+    /// Computes clusters and records each node's membership in
+    /// `CodeNode::cluster`. Nodes added later join their file's cluster;
+    /// see `refresh_clusters` for new files.
+    pub fn assign_clusters(&mut self, options: &ClusterOptions) -> ClusterReport {
+        let report = self.compute_clusters(options);
+        self.set_clusters(report.clusters.clone());
+        report
+    }
+
+    fn describe_cluster(&self, files: &[&str], members: &[usize]) -> Cluster {
+        let mut files: Vec<String> = members.iter().map(|&f| files[f].to_string()).collect();
+        files.sort();
+
+        let nodes: Vec<NodeId> = files
+            .iter()
+            .flat_map(|file| self.nodes_in_file(file).iter().copied())
+            .collect();
+
+        // Deepest directory (or ancestor) covering half the symbols
+        let mut per_directory: HashMap<&str, usize> = HashMap::new();
+        for file in &files {
+            let weight = self.nodes_in_file(file).len().max(1);
+            let mut directory = directory_of(file);
+            loop {
+                *per_directory.entry(directory).or_default() += weight;
+                if directory == "." {
+                    break;
+                }
+                directory = directory_of(directory);
+            }
+        }
+        let total = per_directory.get(".").copied().unwrap_or(0);
+        let directory = per_directory
+            .iter()
+            .filter(|(_, &count)| count * 2 >= total)
+            .max_by(|(a, _), (b, _)| {
+                depth(a)
+                    .cmp(&depth(b))
+                    .then_with(|| per_directory[*a].cmp(&per_directory[*b]))
+                    .then_with(|| b.cmp(a))
+            })
+            .map_or(".", |(directory, _)| *directory)
+            .to_string();
+
+        let mut tokens: HashMap<String, usize> = HashMap::new();
+        for node in nodes.iter().filter_map(|&i| self.get(i)) {
+            if matches!(node.kind, NodeKind::Import | NodeKind::Export) {
+                continue;
+            }
+            for token in name_tokens(&node.name) {
+                *tokens.entry(token).or_default() += 1;
+            }
+        }
+        let mut keywords: Vec<(String, usize)> = tokens.into_iter().collect();
+        keywords.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        let mut symbols: Vec<NodeId> = nodes
+            .iter()
+            .copied()
+            .filter(|&i| {
+                self.get(i).is_some_and(|n| {
+                    is_type(n.kind)
+                        || matches!(
+                            n.kind,
+                            NodeKind::Function | NodeKind::Method | NodeKind::Constructor
+                        )
+                })
+            })
+            .collect();
+        symbols.sort_by(|&a, &b| {
+            self.centrality(b)
+                .total_cmp(&self.centrality(a))
+                .then_with(|| self.get(a).map(|n| &n.id).cmp(&self.get(b).map(|n| &n.id)))
+        });
+
+        Cluster {
+            id: String::new(),
+            label: String::new(),
+            directory,
+            keywords: keywords
+                .into_iter()
+                .take(KEYWORDS)
+                .map(|(token, _)| token)
+                .collect(),
+            size: nodes.len(),
+            files,
+            key_symbols: symbols
+                .into_iter()
+                .take(KEY_SYMBOLS)
+                .filter_map(|i| self.get(i))
+                .map(|n| n.qualified_name.clone())
+                .collect(),
+            internal_edges: 0,
+        }
+    }
+}
+
+/// Dependencies between files, as (edge count, weight) per ordered pair.
+struct FileDeps<'a> {
+    files: Vec<&'a str>,
+    links: BTreeMap<(usize, usize), (usize, f64)>,
+    /// Dependencies within a single file.
+    internal: BTreeMap<usize, usize>,
+}
+
+impl<'a> FileDeps<'a> {
+    fn new(graph: &'a ArborGraph, options: &ClusterOptions) -> Self {
+        let mut files: Vec<&str> = graph.nodes().map(|n| n.file.as_str()).collect();
+        files.sort_unstable();
+        files.dedup();
+        let position: HashMap<&str, usize> =
+            files.iter().enumerate().map(|(i, &f)| (f, i)).collect();
+
+        let mut deps = Self {
+            files,
+            links: BTreeMap::new(),
+            internal: BTreeMap::new(),
+        };
+        let mut add = |from: &str, to: &str, weight: f64| {
+            let (Some(&from), Some(&to)) = (position.get(from), position.get(to)) else {
+                return;
+            };
+            if from == to {
+                *deps.internal.entry(from).or_default() += 1;
+            } else {
+                let entry = deps.links.entry((from, to)).or_default();
+                entry.0 += 1;
+                entry.1 += weight;
+            }
+        };
+
+        for edge in graph.graph.edge_references() {
+            let weight = options.weight(edge.weight().kind);
+            if weight <= 0.0 {
+                continue;
+            }
+            let (Some(source), Some(target)) = (graph.get(edge.source()), graph.get(edge.target()))
+            else {
+                continue;
+            };
+            add(&source.file, &target.file, weight);
+        }
+
+        let weight = options.weight(EdgeKind::Imports);
+        if weight > 0.0 {
+            let resolver = ImportResolver::new(graph);
+            let mut seen: HashSet<(&str, &str)> = HashSet::new();
+            for &index in graph.nodes_of_kind(NodeKind::Import) {
+                let Some(import) = graph.get(index) else {
+                    continue;
+                };
+                for target in resolver.resolve(import) {
+                    if seen.insert((import.file.as_str(), target)) {
+                        add(&import.file, target, weight);
+                    }
+                }
+            }
+        }
+        deps
+    }
+}
+
+/// Runs Louvain to convergence. Returns each node's community, numbered
+/// in order of first appearance.
+fn louvain(adjacency: &[BTreeMap<usize, f64>], resolution: f64) -> Vec<usize> {
+    let mut membership: Vec<usize> = (0..adjacency.len()).collect();
+    let mut level = adjacency.to_vec();
+    loop {
+        let communities = local_moves(&level, resolution);
+        let count = communities.iter().max().map_or(0, |m| m + 1);
+        for community in membership.iter_mut() {
+            *community = communities[*community];
+        }
+        if count == level.len() {
+            renumber(&mut membership);
+            return membership;
+        }
+
+        // Collapse each community into one node; internal weight becomes
+        // a self-loop so degrees are preserved
+        let mut next: Vec<BTreeMap<usize, f64>> = vec![BTreeMap::new(); count];
+        for (node, links) in level.iter().enumerate() {
+            for (&other, &weight) in links {
+                *next[communities[node]]
+                    .entry(communities[other])
+                    .or_default() += weight;
+            }
+        }
+        level = next;
+    }
+}
+
+/// One Louvain phase: moves each node to the neighbouring community with
+/// the best modularity gain until nothing moves.
+fn local_moves(adjacency: &[BTreeMap<usize, f64>], resolution: f64) -> Vec<usize> {
+    let degree: Vec<f64> = adjacency.iter().map(|l| l.values().sum()).collect();
+    let total_weight: f64 = degree.iter().sum();
+    let mut community: Vec<usize> = (0..adjacency.len()).collect();
+    if total_weight <= 0.0 {
+        return community;
+    }
+
+    let mut totals = degree.clone();
+    for _ in 0..MAX_PASSES {
+        let mut moved = false;
+        for node in 0..adjacency.len() {
+            let current = community[node];
+            totals[current] -= degree[node];
+
+            let mut links: BTreeMap<usize, f64> = BTreeMap::new();
+            for (&other, &weight) in &adjacency[node] {
+                if other != node {
+                    *links.entry(community[other]).or_default() += weight;
+                }
+            }
+            let gain = |c: usize, weight: f64| {
+                weight - resolution * totals[c] * degree[node] / total_weight
+            };
+
+            let mut best = current;
+            let mut best_gain = gain(current, links.get(&current).copied().unwrap_or(0.0));
+            for (&candidate, &weight) in &links {
+                let candidate_gain = gain(candidate, weight);
+                if candidate_gain > best_gain + MIN_GAIN {
+                    best = candidate;
+                    best_gain = candidate_gain;
+                }
+            }
+
+            totals[best] += degree[node];
+            if best != current {
+                community[node] = best;
+                moved = true;
+            }
+        }
+        if !moved {
+            break;
+        }
+    }
+    renumber(&mut community);
+    community
+}
+
+/// Renumbers communities 0.. in order of first appearance.
+fn renumber(community: &mut [usize]) {
+    let mut ids: HashMap<usize, usize> = HashMap::new();
+    for c in community.iter_mut() {
+        let next = ids.len();
+        *c = *ids.entry(*c).or_insert(next);
+    }
+}
+
+fn modularity(adjacency: &[BTreeMap<usize, f64>], membership: &[usize], resolution: f64) -> f64 {
+    let total_weight: f64 = adjacency.iter().flat_map(|l| l.values()).sum();
+    if total_weight <= 0.0 {
+        return 0.0;
+    }
+    let mut inside: HashMap<usize, f64> = HashMap::new();
+    let mut totals: HashMap<usize, f64> = HashMap::new();
+    for (node, links) in adjacency.iter().enumerate() {
+        for (&other, &weight) in links {
+            *totals.entry(membership[node]).or_default() += weight;
+            if membership[node] == membership[other] {
+                *inside.entry(membership[node]).or_default() += weight;
+            }
+        }
+    }
+    totals
+        .iter()
+        .map(|(c, total)| {
+            inside.get(c).copied().unwrap_or(0.0) / total_weight
+                - resolution * (total / total_weight).powi(2)
+        })
+        .sum()
+}
+
+/// Sets labels and IDs. Clusters sharing a directory are told apart by
+/// their first keyword no other one shares, then by number.
+fn name_clusters<'a>(clusters: impl Iterator<Item = &'a mut Cluster>) {
+    let clusters: Vec<&mut Cluster> = clusters.collect();
+    let mut per_directory: HashMap<String, Vec<String>> = HashMap::new();
+    for cluster in &clusters {
+        per_directory
+            .entry(cluster.directory.clone())
+            .or_default()
+            .extend(cluster.keywords.iter().cloned());
+    }
+
+    let mut taken: HashSet<String> = HashSet::new();
+    for cluster in clusters {
+        let directory = if cluster.directory == "." {
+            "(root)".to_string()
+        } else {
+            cluster.directory.clone()
+        };
+        let mut label = directory.clone();
+        if taken.contains(&slug(&label)) {
+            let siblings = &per_directory[&cluster.directory];
+            let distinct = cluster
+                .keywords
+                .iter()
+                .find(|k| siblings.iter().filter(|s| s == k).count() == 1)
+                .or(cluster.keywords.first());
+            if let Some(keyword) = distinct {
+                label = format!("{} · {}", directory, keyword);
+            }
+        }
+        let mut id = slug(&label);
+        let mut suffix = 2;
+        while taken.contains(&id) {
+            id = format!("{}-{}", slug(&label), suffix);
+            suffix += 1;
+        }
+        if id != slug(&label) {
+            label = format!("{} #{}", label, suffix - 1);
+        }
+        taken.insert(id.clone());
+        cluster.id = id;
+        cluster.label = label;
+    }
+}
+
+/// Lowercase alphanumerics joined by dashes; `root` for the top level.
+fn slug(label: &str) -> String {
+    let words: Vec<String> = label
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_ascii_lowercase)
+        .collect();
+    if words.is_empty() {
+        "root".to_string()
+    } else {
+        words.join("-")
+    }
+}
+
+fn depth(directory: &str) -> usize {
+    if directory == "." {
+        0
+    } else {
+        directory.split('/').count()
+    }
+}
+
+/// Splits `parseHttpRequest` or `parse_http_request` into lowercase words,
+/// dropping short and common ones.
+fn name_tokens(name: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut previous_lower = false;
+    for c in name.chars() {
+        if !c.is_alphanumeric() {
+            previous_lower = false;
+            words.push(std::mem::take(&mut current));
+            continue;
+        }
+        if c.is_uppercase() && previous_lower {
+            words.push(std::mem::take(&mut current));
+        }
+        previous_lower = c.is_lowercase() || c.is_ascii_digit();
+        current.extend(c.to_lowercase());
+    }
+    words.push(current);
+    words
+        .into_iter()
+        .filter(|w| w.len() >= 3 && !STOPWORDS.contains(&w.as_str()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::GraphBuilder;
+    use arbor_core::CodeNode;
+
+    fn func(name: &str, file: &str, refs: &[&str]) -> CodeNode {
+        CodeNode::new(name, name, NodeKind::Function, file)
+            .with_references(refs.iter().map(|r| r.to_string()).collect())
+    }
+
+    /// Two tightly knit groups with a single call between them.
+    fn two_groups() -> ArborGraph {
+        let mut builder = GraphBuilder::new();
+        builder.add_nodes(vec![
+            func(
+                "login_user",
+                "src/auth/login.rs",
+                &["check_token", "load_session"],
+            ),
+            func("check_token", "src/auth/token.rs", &["load_session"]),
+            func("load_session", "src/auth/session.rs", &["check_token"]),
+            func("query_rows", "src/db/query.rs", &["open_pool", "row_cache"]),
+            func("open_pool", "src/db/pool.rs", &["row_cache"]),
+            func("row_cache", "src/db/cache.rs", &["open_pool", "query_rows"]),
+            func("save_session", "src/auth/session.rs", &["query_rows"]),
+        ]);
+        builder.build()
+    }
+
+    #[test]
+    fn test_clusters_follow_structure() {
+        let graph = two_groups();
+        let report = graph.compute_clusters(&ClusterOptions::default());
+
+        assert_eq!(report.clusters.len(), 2);
+        assert!(report.modularity > 0.2);
+        let auth = report.get("src-auth").expect("auth cluster");
+        assert_eq!(auth.label, "src/auth");
+        assert_eq!(auth.size, 4);
+        assert_eq!(auth.files.len(), 3);
+        assert!(auth.keywords.contains(&"session".to_string()));
+        let db = report.get("src-db").expect("db cluster");
+        assert_eq!(db.files.len(), 3);
+
+        assert_eq!(report.edges.len(), 1);
+        assert_eq!(report.edges[0].from, "src-auth");
+        assert_eq!(report.edges[0].to, "src-db");
+        assert_eq!(report.edges[0].count, 1);
+
+        let summary = report.summary("src-auth").unwrap();
+        assert!(summary.contains("Depends on: `src-db` (1)"));
+        assert!(report.to_markdown().starts_with("# Architecture"));
+
+        // Deterministic
+        assert_eq!(report, graph.compute_clusters(&ClusterOptions::default()));
+    }
+
+    #[test]
+    fn test_assign_clusters_marks_nodes() {
+        let mut graph = two_groups();
+        graph.assign_clusters(&ClusterOptions::default());
+        assert_eq!(graph.clusters().len(), 2);
+
+        let node = graph.find_by_name("open_pool")[0];
+        assert_eq!(node.cluster.as_deref(), Some("src-db"));
+
+        // Re-added nodes join their file's cluster
+        graph.remove_file("src/db/pool.rs");
+        graph.add_node(func("open_pool", "src/db/pool.rs", &[]));
+        let node = graph.find_by_name("open_pool")[0];
+        assert_eq!(node.cluster.as_deref(), Some("src-db"));
+    }
+
+    #[test]
+    fn test_refresh_clusters_places_new_files() {
+        let mut graph = two_groups();
+        graph.assign_clusters(&ClusterOptions::default());
+        assert!(!graph.refresh_clusters());
+
+        // Joins the cluster it calls into, wherever it lives
+        let index = graph.add_node(func("migrate", "tools/migrate.rs", &["open_pool"]));
+        graph.relink(index);
+        // Nothing links this one, so its directory decides
+        graph.add_node(func("oauth_flow", "src/auth/oauth.rs", &[]));
+        graph.remove_file("src/db/cache.rs");
+        assert!(graph.refresh_clusters());
+
+        let node = graph.find_by_name("migrate")[0];
+        assert_eq!(node.cluster.as_deref(), Some("src-db"));
+        let node = graph.find_by_name("oauth_flow")[0];
+        assert_eq!(node.cluster.as_deref(), Some("src-auth"));
+        let db = graph.clusters().iter().find(|c| c.id == "src-db").unwrap();
+        assert_eq!(
+            db.files,
+            vec!["src/db/pool.rs", "src/db/query.rs", "tools/migrate.rs"]
+        );
+        assert_eq!(db.size, 3);
+    }
+
+    #[test]
+    fn test_weights_and_names() {
+        let graph = two_groups();
+        // Without any links every file stands alone
+        let options = ClusterOptions {
+            directory_weight: 0.0,
+            ..ClusterOptions::default()
+        }
+        .with_weight(EdgeKind::Calls, 0.0);
+        let report = graph.compute_clusters(&options);
+        assert_eq!(report.clusters.len(), 6);
+        assert!(report.edges.is_empty());
+        assert_eq!(report.modularity, 0.0);
+        let ids: HashSet<&str> = report.clusters.iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(ids.len(), 6);
+        assert!(ids.contains("src-auth"));
+
+        assert_eq!(
+            name_tokens("parseHTTPRequest_v2"),
+            vec!["parse", "httprequest"]
+        );
+        assert_eq!(name_tokens("get_user_name"), vec!["user", "name"]);
+        assert_eq!(slug("src/auth · token"), "src-auth-token");
+        assert_eq!(slug("(root)"), "root");
+    }
+}
@@ -0,0 +1,233 @@
+//! Complexity thresholds.
+//!
+//! Without function bodies in the graph we measure what the nodes and
+//! edges tell us: how long a function is, how many parameters its
+//! signature declares and how many distinct functions it calls. Each
+//! measurement above its configured limit is reported.
+
+use crate::edge::EdgeKind;
+use crate::graph::ArborGraph;
+use crate::query::NodeInfo;
+use arbor_core::{CodeNode, ComplexityThresholds, NodeKind};
+use petgraph::visit::EdgeRef;
+use petgraph::Direction;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// What a complexity finding measures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ComplexityMetric {
+    Lines,
+    Parameters,
+    FanOut,
+}
+
+impl std::fmt::Display for ComplexityMetric {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Lines => "lines",
+            Self::Parameters => "parameters",
+            Self::FanOut => "fan_out",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// A function over one limit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComplexityIssue {
+    #[serde(flatten)]
+    pub node: NodeInfo,
+    pub metric: ComplexityMetric,
+    pub value: usize,
+    pub limit: usize,
+}
+
+/// Every function over a limit, worst first within each metric.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComplexityReport {
+    pub thresholds: ComplexityThresholds,
+    /// Functions, methods and constructors measured.
+    pub functions: usize,
+    pub issues: Vec<ComplexityIssue>,
+}
+
+impl ArborGraph {
+    /// Measures functions against the thresholds. Generated code is
+    /// skipped.
+    pub fn check_complexity(&self, thresholds: &ComplexityThresholds) -> ComplexityReport {
+        let mut functions = 0;
+        let mut issues = Vec::new();
+        for index in self.node_indexes() {
+            let Some(node) = self.get(index).filter(|n| {
+                !n.generated
+                    && matches!(
+                        n.kind,
+                        NodeKind::Function | NodeKind::Method | NodeKind::Constructor
+                    )
+            }) else {
+                continue;
+            };
+            functions += 1;
+
+            let callees: HashSet<_> = self
+                .graph
+                .edges_directed(index, Direction::Outgoing)
+                .filter(|e| e.weight().kind == EdgeKind::Calls)
+                .map(|e| e.target())
+                .collect();
+            let measured = [
+                (
+                    ComplexityMetric::Lines,
+                    (node.line_end.saturating_sub(node.line_start) + 1) as usize,
+                    thresholds.max_lines as usize,
+                ),
+                (
+                    ComplexityMetric::Parameters,
+                    parameter_count(node),
+                    thresholds.max_parameters,
+                ),
+                (
+                    ComplexityMetric::FanOut,
+                    callees.len(),
+                    thresholds.max_fan_out,
+                ),
+            ];
+            for (metric, value, limit) in measured {
+                if limit > 0 && value > limit {
+                    let mut info = NodeInfo::from(node);
+                    info.centrality = self.centrality(index);
+                    issues.push(ComplexityIssue {
+                        node: info,
+                        metric,
+                        value,
+                        limit,
+                    });
+                }
+            }
+        }
+        issues.sort_by(|a, b| {
+            (a.metric as u8)
+                .cmp(&(b.metric as u8))
+                .then(b.value.cmp(&a.value))
+                .then_with(|| a.node.file.cmp(&b.node.file))
+                .then(a.node.line_start.cmp(&b.node.line_start))
+        });
+
+        ComplexityReport {
+            thresholds: thresholds.clone(),
+            functions,
+            issues,
+        }
+    }
+}
+
+/// Counts the parameters between the signature's outermost parentheses.
+/// The `>` of `->` and `=>` is an arrow, not a closing bracket.
+fn parameter_count(node: &CodeNode) -> usize {
+    let Some(signature) = &node.signature else {
+        return 0;
+    };
+    let Some(open) = signature.find('(') else {
+        return 0;
+    };
+    let mut depth = 0;
+    let mut params = Vec::new();
+    let mut current = String::new();
+    let mut previous = '(';
+    for c in signature[open + 1..].chars() {
+        match c {
+            '(' | '[' | '{' | '<' => depth += 1,
+            ')' if depth == 0 => break,
+            '>' if matches!(previous, '-' | '=') => {}
+            ')' | ']' | '}' | '>' => depth -= 1,
+            ',' if depth == 0 => {
+                params.push(std::mem::take(&mut current));
+                previous = c;
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+        previous = c;
+    }
+    params.push(current);
+    params
+        .iter()
+        .map(|p| p.trim())
+        .filter(|p| !p.is_empty())
+        .filter(|p| {
+            let name = p.trim_start_matches(['&', '*']).trim_start_matches("mut ");
+            let name = name.split([':', ' ']).next().unwrap_or(name);
+            !matches!(name, "self" | "this" | "cls" | "'a")
+        })
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::GraphBuilder;
+
+    fn func(name: &str, lines: (u32, u32), signature: &str, refs: &[&str]) -> CodeNode {
+        CodeNode::new(name, name, NodeKind::Function, "src/lib.rs")
+            .with_lines(lines.0, lines.1)
+            .with_signature(signature)
+            .with_references(refs.iter().map(|r| r.to_string()).collect())
+    }
+
+    #[test]
+    fn test_complexity_thresholds() {
+        let mut builder = GraphBuilder::new();
+        builder.add_nodes(vec![
+            func("long", (1, 150), "fn long()", &["a", "b", "c"]),
+            func(
+                "wide",
+                (200, 205),
+                "fn wide(&self, a: u8, b: HashMap<u8, u8>, c: (u8, u8), d: u8)",
+                &[],
+            ),
+            func("a", (300, 301), "fn a()", &[]),
+            func("b", (302, 303), "fn b()", &[]),
+            func("c", (304, 305), "fn c()", &[]),
+        ]);
+        let graph = builder.build();
+
+        let thresholds = ComplexityThresholds {
+            max_lines: 100,
+            max_parameters: 3,
+            max_fan_out: 2,
+        };
+        let report = graph.check_complexity(&thresholds);
+        assert_eq!(report.functions, 5);
+        let found: Vec<(&str, ComplexityMetric, usize)> = report
+            .issues
+            .iter()
+            .map(|i| (i.node.name.as_str(), i.metric, i.value))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("long", ComplexityMetric::Lines, 150),
+                ("wide", ComplexityMetric::Parameters, 4),
+                ("long", ComplexityMetric::FanOut, 3),
+            ]
+        );
+
+        let off = ComplexityThresholds {
+            max_lines: 0,
+            max_parameters: 0,
+            max_fan_out: 0,
+        };
+        assert!(graph.check_complexity(&off).issues.is_empty());
+    }
+
+    #[test]
+    fn test_arrows_in_parameter_types() {
+        let count = |signature: &str| parameter_count(&func("f", (1, 2), signature, &[]));
+        assert_eq!(count("function f(cb: () => void, retries: number)"), 2);
+        assert_eq!(count("fn f(f: impl Fn(u8) -> u8, g: Vec<u8>, n: u8)"), 3);
+        assert_eq!(count("fn f(m: HashMap<u8, Box<dyn Fn() -> u8>>, n: u8)"), 2);
+    }
+}
@@ -0,0 +1,497 @@
+//! Architecture conformance checks.
+//!
+//! Evaluates [`ArchitectureRules`] against every dependency in the graph:
+//! edges between symbols, plus import statements resolved to the files
+//! they load. Each dependency that breaks a rule becomes a [`Violation`]
+//! with the file and line that creates it.
+//!
+//! A [`Baseline`] records known violations so a check only fails on new
+//! ones. Entries ignore line numbers, so unrelated edits don't invalidate
+//! them; fixed entries can be dropped to ratchet the baseline down.
+
+use crate::edge::EdgeKind;
+use crate::graph::ArborGraph;
+use crate::resolve::ImportResolver;
+use crate::testing::is_test_file;
+use arbor_core::config::CONFIG_DIR;
+use arbor_core::{ArchitectureRules, CodeNode, ConfigError, NodeKind, RuleKind};
+use petgraph::visit::{EdgeRef, IntoEdgeReferences};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Name of the default baseline file inside [`CONFIG_DIR`].
+pub const BASELINE_FILE: &str = "check-baseline.json";
+
+/// A dependency that breaks a rule.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Violation {
+    /// ID of the broken rule.
+    pub rule: String,
+    /// The rule's message, or a description of what it forbids.
+    pub message: String,
+    /// Qualified name of the dependent symbol.
+    pub source: String,
+    /// Qualified name of the symbol depended on, or the imported file.
+    pub target: String,
+    pub kind: EdgeKind,
+    pub from_layer: Option<String>,
+    pub to_layer: Option<String>,
+    /// Where the dependency is created.
+    pub file: String,
+    pub line: u32,
+    /// 0-based; unknown when only the line of the dependency is recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub column: Option<u32>,
+    /// Already recorded in the baseline.
+    #[serde(default)]
+    pub baselined: bool,
+}
+
+impl Violation {
+    /// The baseline entry matching this violation.
+    pub fn entry(&self) -> BaselineEntry {
+        BaselineEntry {
+            rule: self.rule.clone(),
+            source: self.source.clone(),
+            target: self.target.clone(),
+            kind: self.kind,
+            file: self.file.clone(),
+        }
+    }
+}
+
+/// The outcome of checking rules against a graph.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CheckReport {
+    /// Number of rules evaluated.
+    pub rules: usize,
+    /// Number of layers defined.
+    pub layers: usize,
+    /// Dependencies checked.
+    pub dependencies: usize,
+    /// Violations sorted by file and line.
+    pub violations: Vec<Violation>,
+    /// Baseline entries that no longer occur.
+    pub fixed: Vec<BaselineEntry>,
+}
+
+impl CheckReport {
+    /// Violations the baseline doesn't cover.
+    pub fn new_violations(&self) -> impl Iterator<Item = &Violation> {
+        self.violations.iter().filter(|v| !v.baselined)
+    }
+
+    /// Whether there are no new violations.
+    pub fn passed(&self) -> bool {
+        self.new_violations().next().is_none()
+    }
+
+    /// Marks violations the baseline records and lists the entries that
+    /// were fixed.
+    pub fn apply_baseline(&mut self, baseline: &Baseline) {
+        let mut remaining: HashMap<&BaselineEntry, usize> = HashMap::new();
+        for entry in &baseline.entries {
+            *remaining.entry(entry).or_default() += 1;
+        }
+        for violation in &mut self.violations {
+            if let Some(count) = remaining.get_mut(&violation.entry()) {
+                if *count > 0 {
+                    *count -= 1;
+                    violation.baselined = true;
+                }
+            }
+        }
+        self.fixed = baseline
+            .entries
+            .iter()
+            .filter(|entry| {
+                remaining.get_mut(entry).is_some_and(|count| {
+                    let fixed = *count > 0;
+                    *count = count.saturating_sub(1);
+                    fixed
+                })
+            })
+            .cloned()
+            .collect();
+    }
+}
+
+/// Identifies a violation independently of its line.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct BaselineEntry {
+    pub rule: String,
+    pub source: String,
+    pub target: String,
+    pub kind: EdgeKind,
+    pub file: String,
+}
+
+/// Known violations that don't fail a check.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Baseline {
+    pub version: u32,
+    pub entries: Vec<BaselineEntry>,
+}
+
+impl Baseline {
+    /// Returns the default baseline path for a project directory.
+    pub fn path_for(dir: &Path) -> PathBuf {
+        dir.join(CONFIG_DIR).join(BASELINE_FILE)
+    }
+
+    /// Records every violation in a report.
+    pub fn from_report(report: &CheckReport) -> Self {
+        let mut entries: Vec<BaselineEntry> =
+            report.violations.iter().map(Violation::entry).collect();
+        entries.sort();
+        Self {
+            version: 1,
+            entries,
+        }
+    }
+
+    /// Keeps only the entries a report still violates. Never adds any,
+    /// so the baseline can only shrink.
+    pub fn ratchet(&self, report: &CheckReport) -> Self {
+        let mut entries: Vec<BaselineEntry> = report
+            .violations
+            .iter()
+            .filter(|v| v.baselined)
+            .map(Violation::entry)
+            .collect();
+        entries.sort();
+        Self {
+            version: self.version,
+            entries,
+        }
+    }
+
+    /// Loads a baseline, if the file exists.
+    pub fn load(path: &Path) -> Result<Option<Self>, ConfigError> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let text = fs::read_to_string(path).map_err(|source| ConfigError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        serde_json::from_str(&text)
+            .map(Some)
+            .map_err(|source| ConfigError::Json {
+                path: path.to_path_buf(),
+                source,
+            })
+    }
+
+    /// Writes the baseline as pretty-printed JSON.
+    pub fn save(&self, path: &Path) -> Result<(), ConfigError> {
+        let io = |source| ConfigError::Io {
+            path: path.to_path_buf(),
+            source,
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(io)?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|source| ConfigError::Json {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        fs::write(path, json + "\n").map_err(io)
+    }
+}
+
+/// One dependency, wherever it came from.
+pub(crate) struct Dependency<'a> {
+    pub(crate) source: &'a CodeNode,
+    pub(crate) target: &'a str,
+    pub(crate) target_file: &'a str,
+    pub(crate) kind: EdgeKind,
+    pub(crate) file: &'a str,
+    pub(crate) line: u32,
+    pub(crate) column: Option<u32>,
+}
+
+impl ArborGraph {
+    /// Evaluates architecture rules against the graph's dependencies.
+    pub fn check_rules(&self, rules: &ArchitectureRules) -> Result<CheckReport, ConfigError> {
+        rules.validate()?;
+        let layers = rules.layer_matcher()?;
+        let dependencies = self.dependencies(rules.include_tests);
+
+        let mut violations = Vec::new();
+        for rule in &rules.rules {
+            let kinds = rule
+                .kinds
+                .iter()
+                .map(|k| k.parse::<EdgeKind>().map_err(ConfigError::Rules))
+                .collect::<Result<HashSet<_>, _>>()?;
+            let id = rule.id();
+            for dep in &dependencies {
+                if !kinds.is_empty() && !kinds.contains(&dep.kind) {
+                    continue;
+                }
+                let from = layers.layer_of(&dep.source.file);
+                let to = layers.layer_of(dep.target_file);
+                let Some(reason) = breaks(&rule.kind, dep, from, to) else {
+                    continue;
+                };
+                violations.push(Violation {
+                    rule: id.clone(),
+                    message: rule.message.clone().unwrap_or(reason),
+                    source: dep.source.qualified_name.clone(),
+                    target: dep.target.to_string(),
+                    kind: dep.kind,
+                    from_layer: from.map(str::to_string),
+                    to_layer: to.map(str::to_string),
+                    file: dep.file.to_string(),
+                    line: dep.line,
+                    column: dep.column,
+                    baselined: false,
+                });
+            }
+        }
+        violations.sort_by(|a, b| {
+            (&a.file, a.line, &a.rule, &a.target).cmp(&(&b.file, b.line, &b.rule, &b.target))
+        });
+
+        Ok(CheckReport {
+            rules: rules.rules.len(),
+            layers: rules.layers.len(),
+            dependencies: dependencies.len(),
+            violations,
+            fixed: Vec::new(),
+        })
+    }
+
+    /// Every edge except containment and intra-function flow, plus
+    /// imports resolved to files.
+    pub(crate) fn dependencies(&self, include_tests: bool) -> Vec<Dependency<'_>> {
+        let skip =
+            |node: &CodeNode| !include_tests && (is_test_file(&node.file) || self.is_test(node));
+
+        let mut dependencies = Vec::new();
+        for edge in self.graph.edge_references() {
+            let kind = edge.weight().kind;
+            if matches!(
+                kind,
+                EdgeKind::Contains | EdgeKind::FlowsTo | EdgeKind::DataDependency
+            ) {
+                continue;
+            }
+            let (Some(source), Some(target)) = (self.get(edge.source()), self.get(edge.target()))
+            else {
+                continue;
+            };
+            if skip(source) {
+                continue;
+            }
+            let located = edge.weight().line.is_some();
+            dependencies.push(Dependency {
+                source,
+                target: &target.qualified_name,
+                target_file: &target.file,
+                kind,
+                file: edge.weight().file.as_deref().unwrap_or(&source.file),
+                line: edge.weight().line.unwrap_or(source.line_start),
+                column: (!located).then_some(source.column),
+            });
+        }
+
+        let resolver = ImportResolver::new(self);
+        for &index in self.nodes_of_kind(NodeKind::Import) {
+            let Some(import) = self.get(index).filter(|n| !skip(n)) else {
+                continue;
+            };
+            for file in resolver.resolve(import) {
+                dependencies.push(Dependency {
+                    source: import,
+                    target: file,
+                    target_file: file,
+                    kind: EdgeKind::Imports,
+                    file: &import.file,
+                    line: import.line_start,
+                    column: Some(import.column),
+                });
+            }
+        }
+        dependencies
+    }
+}
+
+/// Why a dependency breaks a rule, if it does.
+fn breaks(
+    rule: &RuleKind,
+    dep: &Dependency,
+    from: Option<&str>,
+    to: Option<&str>,
+) -> Option<String> {
+    let listed = |layers: &[String], layer: Option<&str>| {
+        layer.is_some_and(|l| layers.iter().any(|x| x == l))
+    };
+    match rule {
+        RuleKind::Forbid { from: f, to: t } => {
+            let (from, to) = (from?, to?);
+            (listed(f, Some(from)) && listed(t, Some(to)))
+                .then(|| format!("{} must not depend on {}", from, to))
+        }
+        RuleKind::Allow { from: f, to: t } => {
+            let (from, to) = (from?, to?);
+            (listed(f, Some(from)) && from != to && !listed(t, Some(to))).then(|| {
+                if t.is_empty() {
+                    format!("{} must not depend on other layers", from)
+                } else {
+                    format!("{} may only depend on {}", from, t.join(", "))
+                }
+            })
+        }
+        RuleKind::Only { callers, target } => {
+            let to = to.filter(|&to| listed(target, Some(to)))?;
+            (!listed(target, from) && !listed(callers, from))
+                .then(|| format!("only {} may depend on {}", callers.join(", "), to))
+        }
+        RuleKind::NoInternalImports { markers } => {
+            let owner = internal_owner(dep.target_file, markers)?;
+            let inside = owner.is_empty()
+                || dep
+                    .source
+                    .file
+                    .strip_prefix(owner)
+                    .is_some_and(|rest| rest.starts_with('/'));
+            (!inside).then(|| format!("{} is internal to {}", dep.target_file, owner))
+        }
+    }
+}
+
+/// The directory an internal file is private to: the parent of its
+/// outermost marker directory.
+fn internal_owner<'a>(file: &'a str, markers: &[String]) -> Option<&'a str> {
+    let mut offset = 0;
+    let segments: Vec<&str> = file.split('/').collect();
+    // The last segment is the file name, not a directory
+    for segment in &segments[..segments.len().saturating_sub(1)] {
+        if markers.iter().any(|m| m == segment) {
+            return Some(file[..offset].trim_end_matches('/'));
+        }
+        offset += segment.len() + 1;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::GraphBuilder;
+
+    fn func(name: &str, file: &str, refs: &[&str]) -> CodeNode {
+        CodeNode::new(name, name, NodeKind::Function, file)
+            .with_lines(3, 5)
+            .with_references(refs.iter().map(|r| r.to_string()).collect())
+    }
+
+    fn graph() -> ArborGraph {
+        let mut builder = GraphBuilder::new();
+        builder.add_nodes(vec![
+            func("render", "src/ui/view.rs", &["query", "place_order"]),
+            func("place_order", "src/domain/order.rs", &["query", "hash"]),
+            func("query", "src/db/pool.rs", &["hash"]),
+            func("report", "src/tools/report.rs", &["query"]),
+            func("hash", "src/db/internal/hash.rs", &[]),
+            func("test_render", "tests/ui_test.rs", &["query"]),
+        ]);
+        builder.build()
+    }
+
+    fn rules(json: &str) -> ArchitectureRules {
+        let layers = r#"[
+            { "name": "ui", "paths": ["src/ui"] },
+            { "name": "domain", "paths": ["src/domain"] },
+            { "name": "db", "paths": ["src/db"] }
+        ]"#;
+        serde_json::from_str(&format!(r#"{{"layers": {}, "rules": {}}}"#, layers, json)).unwrap()
+    }
+
+    fn broken(report: &CheckReport) -> Vec<(&str, &str, &str)> {
+        report
+            .violations
+            .iter()
+            .map(|v| (v.rule.as_str(), v.source.as_str(), v.target.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_layer_rules() {
+        let graph = graph();
+        let report = graph
+            .check_rules(&rules(
+                r#"[
+                    { "rule": "forbid", "from": "ui", "to": "db", "message": "use the domain" },
+                    { "rule": "allow", "from": "domain", "to": "db" },
+                    { "rule": "only", "name": "db-owners", "callers": "domain", "target": "db" }
+                ]"#,
+            ))
+            .unwrap();
+
+        // allow domain -> db holds; test code is skipped
+        assert_eq!(
+            broken(&report),
+            vec![
+                ("db-owners", "report", "query"),
+                ("db-owners", "render", "query"),
+                ("forbid:ui->db", "render", "query"),
+            ]
+        );
+        let forbidden = &report.violations[2];
+        assert_eq!(forbidden.message, "use the domain");
+        assert_eq!(forbidden.file, "src/ui/view.rs");
+        assert_eq!(forbidden.line, 3);
+        assert_eq!(forbidden.from_layer.as_deref(), Some("ui"));
+        assert!(!report.passed());
+    }
+
+    #[test]
+    fn test_internal_imports_and_baseline() {
+        let graph = graph();
+        let mut report = graph
+            .check_rules(&rules(r#"[{ "rule": "no_internal_imports" }]"#))
+            .unwrap();
+        // Only place_order reaches into src/db/internal from outside src/db
+        assert_eq!(
+            broken(&report),
+            vec![("no_internal_imports", "place_order", "hash")]
+        );
+        assert_eq!(
+            report.violations[0].message,
+            "src/db/internal/hash.rs is internal to src/db"
+        );
+
+        let baseline = Baseline::from_report(&report);
+        report.apply_baseline(&baseline);
+        assert!(report.passed());
+        assert!(report.fixed.is_empty());
+
+        // A baseline entry that no longer occurs is reported as fixed
+        let mut stale = baseline.clone();
+        stale.entries.push(BaselineEntry {
+            rule: "no_internal_imports".to_string(),
+            source: "render".to_string(),
+            target: "hash".to_string(),
+            kind: EdgeKind::Calls,
+            file: "src/ui/view.rs".to_string(),
+        });
+        let mut report = graph
+            .check_rules(&rules(r#"[{ "rule": "no_internal_imports" }]"#))
+            .unwrap();
+        report.apply_baseline(&stale);
+        assert!(report.passed());
+        assert_eq!(report.fixed.len(), 1);
+        assert_eq!(stale.ratchet(&report), baseline);
+
+        assert_eq!(
+            internal_owner("internal/x.go", &["internal".into()]),
+            Some("")
+        );
+        assert_eq!(internal_owner("a/internal", &["internal".into()]), None);
+    }
+}
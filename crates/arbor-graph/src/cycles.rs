@@ -0,0 +1,553 @@
+//! Circular dependency detection.
+//!
+//! Nodes are grouped into units (functions, types, files, directories or
+//! packages),
+//! graph edges of the selected kinds become dependencies between units,
+//! and Tarjan's algorithm finds the strongly connected components. Each
+//! component with more than one unit is a cycle; we report its members,
+//! the shortest cycle through them and the source locations of each hop.
+//!
+//! The builder doesn't link import statements to anything, so at file,
+//! directory and package level `imports` dependencies come from resolving import nodes
+//! to indexed files: dotted Python modules (absolute or relative) and
+//! `./`/`../` specifiers for JavaScript and TypeScript.
+
+use crate::edge::EdgeKind;
+use crate::graph::{ArborGraph, NodeId};
+use crate::layout::{directory_of, package_of};
+use crate::resolve::{ImportResolver, TypeIndex};
+use arbor_core::{CodeNode, NodeKind};
+use petgraph::algo::tarjan_scc;
+use petgraph::graph::DiGraph;
+use petgraph::visit::{EdgeRef, IntoEdgeReferences};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+/// How many members of a large component we search for a shortest cycle.
+const MAX_CYCLE_STARTS: usize = 64;
+
+/// The granularity cycles are detected at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CycleLevel {
+    /// Functions, methods and constructors.
+    Function,
+    /// Classes, structs, interfaces, enums and type aliases. Members count
+    /// as their enclosing type.
+    Type,
+    /// Source files.
+    #[default]
+    File,
+    /// Directories, i.e. Python packages or Go packages.
+    Directory,
+    /// The directory above a `src`, `lib`, `pkg` or `internal` directory,
+    /// like a crate, npm or Go module; otherwise the top-level directory.
+    Package,
+}
+
+impl std::fmt::Display for CycleLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Function => "function",
+            Self::Type => "type",
+            Self::File => "file",
+            Self::Directory => "directory",
+            Self::Package => "package",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl std::str::FromStr for CycleLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "function" | "fn" => Ok(Self::Function),
+            "type" | "class" => Ok(Self::Type),
+            "file" | "module" => Ok(Self::File),
+            "directory" | "dir" => Ok(Self::Directory),
+            "package" | "pkg" => Ok(Self::Package),
+            _ => Err(format!(
+                "unknown cycle level '{}' (expected function, type, file, directory or package)",
+                s
+            )),
+        }
+    }
+}
+
+/// What to look for cycles in.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CycleOptions {
+    /// The units cycles are made of.
+    pub level: CycleLevel,
+
+    /// Edge kinds that count as dependencies. Empty means every kind
+    /// except `contains`, `flows_to` and `data_dependency`.
+    pub edge_kinds: Vec<EdgeKind>,
+
+    /// Whether test code takes part. Tests rarely matter for layering.
+    pub include_tests: bool,
+}
+
+impl CycleOptions {
+    /// Options for the given level with default edge kinds.
+    pub fn new(level: CycleLevel) -> Self {
+        Self {
+            level,
+            ..Self::default()
+        }
+    }
+
+    /// Adds an edge kind to follow.
+    pub fn with_edge_kind(mut self, kind: EdgeKind) -> Self {
+        self.edge_kinds.push(kind);
+        self
+    }
+
+    /// Includes test files and test functions.
+    pub fn with_tests(mut self, include: bool) -> Self {
+        self.include_tests = include;
+        self
+    }
+
+    /// The edge kinds actually followed.
+    pub fn effective_edge_kinds(&self) -> Vec<EdgeKind> {
+        if !self.edge_kinds.is_empty() {
+            return self.edge_kinds.clone();
+        }
+        EdgeKind::ALL
+            .into_iter()
+            .filter(|k| {
+                !matches!(
+                    k,
+                    EdgeKind::Contains | EdgeKind::FlowsTo | EdgeKind::DataDependency
+                )
+            })
+            .collect()
+    }
+}
+
+/// One hop of a cycle and the code that creates it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CycleEdge {
+    /// The dependent unit.
+    pub from: String,
+    /// The unit it depends on.
+    pub to: String,
+    /// Kind of the edge shown.
+    pub kind: EdgeKind,
+    /// Symbol the dependency starts from.
+    pub source: String,
+    /// Symbol it points at, or the imported module.
+    pub target: String,
+    /// Where the dependency is written.
+    pub file: String,
+    pub line: u32,
+    /// How many edges connect the two units in this direction.
+    pub occurrences: usize,
+}
+
+/// A strongly connected component with more than one unit.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Cycle {
+    /// Every unit in the component, sorted.
+    pub members: Vec<String>,
+    /// The shortest cycle found, starting and ending at the same unit.
+    pub path: Vec<String>,
+    /// One entry per hop of `path`. The last one closes the cycle.
+    pub edges: Vec<CycleEdge>,
+}
+
+impl Cycle {
+    /// Number of units in the component.
+    pub fn size(&self) -> usize {
+        self.members.len()
+    }
+}
+
+/// The cycles in a graph at one level.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CycleReport {
+    pub level: CycleLevel,
+    pub edge_kinds: Vec<EdgeKind>,
+    /// Units considered.
+    pub units: usize,
+    /// Dependencies between distinct units.
+    pub dependencies: usize,
+    /// Largest components first.
+    pub cycles: Vec<Cycle>,
+}
+
+impl CycleReport {
+    /// Whether the graph is free of cycles at this level.
+    pub fn is_acyclic(&self) -> bool {
+        self.cycles.is_empty()
+    }
+}
+
+/// A dependency edge between two units and what it's made of.
+#[derive(Default)]
+struct Witnesses {
+    first: Option<CycleEdgeSite>,
+    count: usize,
+}
+
+struct CycleEdgeSite {
+    kind: EdgeKind,
+    source: String,
+    target: String,
+    file: String,
+    line: u32,
+}
+
+impl ArborGraph {
+    /// Finds circular dependencies at the level and over the edge kinds
+    /// in `options`. Self-dependencies such as direct recursion or calls
+    /// within one file are not cycles.
+    pub fn find_cycles(&self, options: &CycleOptions) -> CycleReport {
+        let kinds = options.effective_edge_kinds();
+        let units = self.cycle_units(options);
+
+        let mut names: Vec<&str> = units.values().map(String::as_str).collect();
+        names.sort_unstable();
+        names.dedup();
+        let position: HashMap<&str, usize> =
+            names.iter().enumerate().map(|(i, n)| (*n, i)).collect();
+
+        let mut deps: BTreeMap<(usize, usize), Witnesses> = BTreeMap::new();
+        let mut add = |from: usize, to: usize, site: CycleEdgeSite| {
+            if from == to {
+                return;
+            }
+            let entry = deps.entry((from, to)).or_default();
+            entry.count += 1;
+            let better = entry
+                .first
+                .as_ref()
+                .is_none_or(|f| (&site.file, site.line) < (&f.file, f.line));
+            if better {
+                entry.first = Some(site);
+            }
+        };
+
+        for edge in self.graph.edge_references() {
+            let kind = edge.weight().kind;
+            if !kinds.contains(&kind) {
+                continue;
+            }
+            let (Some(from), Some(to)) = (units.get(&edge.source()), units.get(&edge.target()))
+            else {
+                continue;
+            };
+            let (source, target) = (&self.graph[edge.source()], &self.graph[edge.target()]);
+            add(
+                position[from.as_str()],
+                position[to.as_str()],
+                CycleEdgeSite {
+                    kind,
+                    source: source.qualified_name.clone(),
+                    target: target.qualified_name.clone(),
+                    file: edge
+                        .weight()
+                        .file
+                        .clone()
+                        .unwrap_or_else(|| source.file.clone()),
+                    line: edge.weight().line.unwrap_or(source.line_start),
+                },
+            );
+        }
+
+        if kinds.contains(&EdgeKind::Imports)
+            && matches!(
+                options.level,
+                CycleLevel::File | CycleLevel::Directory | CycleLevel::Package
+            )
+        {
+            let resolver = ImportResolver::new(self);
+            for &index in self.nodes_of_kind(NodeKind::Import) {
+                let Some(import) = self.get(index) else {
+                    continue;
+                };
+                if !options.include_tests && crate::testing::is_test_file(&import.file) {
+                    continue;
+                }
+                let Some(from) = unit_name(options.level, import, None) else {
+                    continue;
+                };
+                for file in resolver.resolve(import) {
+                    let Some(to) = unit_name(options.level, &file_stub(file), None) else {
+                        continue;
+                    };
+                    let (Some(&from), Some(&to)) =
+                        (position.get(from.as_str()), position.get(to.as_str()))
+                    else {
+                        continue;
+                    };
+                    add(
+                        from,
+                        to,
+                        CycleEdgeSite {
+                            kind: EdgeKind::Imports,
+                            source: import.file.clone(),
+                            target: import.name.clone(),
+                            file: import.file.clone(),
+                            line: import.line_start,
+                        },
+                    );
+                }
+            }
+        }
+
+        let mut dep_graph: DiGraph<usize, ()> = DiGraph::new();
+        let handles: Vec<_> = (0..names.len()).map(|i| dep_graph.add_node(i)).collect();
+        for &(from, to) in deps.keys() {
+            dep_graph.add_edge(handles[from], handles[to], ());
+        }
+
+        let mut cycles: Vec<Cycle> = tarjan_scc(&dep_graph)
+            .into_iter()
+            .filter(|component| component.len() > 1)
+            .map(|component| {
+                let mut members: Vec<usize> = component.iter().map(|h| dep_graph[*h]).collect();
+                members.sort_unstable();
+                let path = shortest_cycle(&members, &deps);
+                let edges = path
+                    .windows(2)
+                    .map(|hop| {
+                        let witnesses = &deps[&(hop[0], hop[1])];
+                        let site = witnesses.first.as_ref().expect("recorded with the edge");
+                        CycleEdge {
+                            from: names[hop[0]].to_string(),
+                            to: names[hop[1]].to_string(),
+                            kind: site.kind,
+                            source: site.source.clone(),
+                            target: site.target.clone(),
+                            file: site.file.clone(),
+                            line: site.line,
+                            occurrences: witnesses.count,
+                        }
+                    })
+                    .collect();
+                Cycle {
+                    members: members.iter().map(|&m| names[m].to_string()).collect(),
+                    path: path.iter().map(|&m| names[m].to_string()).collect(),
+                    edges,
+                }
+            })
+            .collect();
+        cycles.sort_by(|a, b| {
+            b.size()
+                .cmp(&a.size())
+                .then_with(|| a.members.cmp(&b.members))
+        });
+
+        CycleReport {
+            level: options.level,
+            edge_kinds: kinds,
+            units: names.len(),
+            dependencies: deps.len(),
+            cycles,
+        }
+    }
+
+    /// Maps every node that belongs to a unit at this level to the unit's name.
+    fn cycle_units(&self, options: &CycleOptions) -> HashMap<NodeId, String> {
+        let types = match options.level {
+            CycleLevel::Type => Some(TypeIndex::new(self)),
+            _ => None,
+        };
+        self.node_indexes()
+            .filter_map(|index| {
+                let node = self.get(index)?;
+                if !options.include_tests && crate::testing::is_test_node(node) {
+                    return None;
+                }
+                unit_name(options.level, node, types.as_ref()).map(|name| (index, name))
+            })
+            .collect()
+    }
+}
+
+/// The unit a node belongs to, if any.
+fn unit_name(level: CycleLevel, node: &CodeNode, types: Option<&TypeIndex>) -> Option<String> {
+    match level {
+        CycleLevel::Function => matches!(
+            node.kind,
+            NodeKind::Function | NodeKind::Method | NodeKind::Constructor
+        )
+        .then(|| node.qualified_name.clone()),
+        CycleLevel::Type => types?.enclosing(node).map(str::to_string),
+        CycleLevel::File => Some(node.file.clone()),
+        CycleLevel::Directory => Some(directory_of(&node.file).to_string()),
+        CycleLevel::Package => Some(package_of(&node.file).to_string()),
+    }
+}
+
+/// A placeholder node standing for a whole file.
+fn file_stub(file: &str) -> CodeNode {
+    CodeNode::new(file, file, NodeKind::Module, file)
+}
+
+/// The shortest cycle through the component, as unit positions with the
+/// first repeated at the end. Searches from the first few members.
+fn shortest_cycle(members: &[usize], deps: &BTreeMap<(usize, usize), Witnesses>) -> Vec<usize> {
+    let inside: HashSet<usize> = members.iter().copied().collect();
+    let mut successors: HashMap<usize, Vec<usize>> = HashMap::new();
+    for &(from, to) in deps.keys() {
+        if inside.contains(&from) && inside.contains(&to) {
+            successors.entry(from).or_default().push(to);
+        }
+    }
+
+    let mut best: Option<Vec<usize>> = None;
+    for &start in members.iter().take(MAX_CYCLE_STARTS) {
+        let mut parent: HashMap<usize, usize> = HashMap::new();
+        let mut queue = VecDeque::from([start]);
+        let mut closing = None;
+        while let Some(current) = queue.pop_front() {
+            for &next in successors.get(&current).into_iter().flatten() {
+                if next == start {
+                    closing = Some(current);
+                    break;
+                }
+                if let std::collections::hash_map::Entry::Vacant(e) = parent.entry(next) {
+                    e.insert(current);
+                    queue.push_back(next);
+                }
+            }
+            if closing.is_some() {
+                break;
+            }
+        }
+        let Some(mut current) = closing else {
+            continue;
+        };
+        let mut chain = Vec::new();
+        while current != start {
+            chain.push(current);
+            current = parent[&current];
+        }
+        let path: Vec<usize> = std::iter::once(start)
+            .chain(chain.into_iter().rev())
+            .chain(std::iter::once(start))
+            .collect();
+        if best.as_ref().is_none_or(|b| path.len() < b.len()) {
+            best = Some(path);
+        }
+    }
+    best.unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::GraphBuilder;
+
+    fn func(name: &str, file: &str, refs: &[&str]) -> CodeNode {
+        CodeNode::new(name, name, NodeKind::Function, file)
+            .with_references(refs.iter().map(|r| r.to_string()).collect())
+    }
+
+    fn import(module: &str, file: &str, line: u32) -> CodeNode {
+        CodeNode::new(module, module, NodeKind::Import, file).with_lines(line, line)
+    }
+
+    #[test]
+    fn test_function_and_file_cycles() {
+        let mut builder = GraphBuilder::new();
+        builder.add_nodes(vec![
+            func("a", "src/a.rs", &["b"]),
+            func("b", "src/b.rs", &["c"]),
+            func("c", "src/c.rs", &["a", "d"]),
+            func("d", "src/c.rs", &["d2"]),
+            func("d2", "src/c.rs", &["d"]),
+            func("leaf", "src/d.rs", &[]),
+        ]);
+        let graph = builder.build();
+
+        let functions = graph.find_cycles(&CycleOptions::new(CycleLevel::Function));
+        assert_eq!(functions.cycles.len(), 2);
+        let big = &functions.cycles[0];
+        assert_eq!(big.members, vec!["a", "b", "c"]);
+        assert_eq!(big.path, vec!["a", "b", "c", "a"]);
+        assert_eq!(big.edges.len(), 3);
+        assert_eq!(big.edges[2].source, "c");
+        assert_eq!(big.edges[2].target, "a");
+        assert_eq!(big.edges[2].file, "src/c.rs");
+        assert_eq!(functions.cycles[1].members, vec!["d", "d2"]);
+
+        // d <-> d2 is within one file, so only the three-file cycle remains
+        let files = graph.find_cycles(&CycleOptions::new(CycleLevel::File));
+        assert_eq!(files.cycles.len(), 1);
+        assert_eq!(
+            files.cycles[0].members,
+            vec!["src/a.rs", "src/b.rs", "src/c.rs"]
+        );
+        assert!(graph
+            .find_cycles(&CycleOptions::new(CycleLevel::Package))
+            .is_acyclic());
+
+        // Only following imports, there's nothing to find
+        let imports_only = CycleOptions::new(CycleLevel::File).with_edge_kind(EdgeKind::Imports);
+        assert!(graph.find_cycles(&imports_only).is_acyclic());
+    }
+
+    #[test]
+    fn test_python_package_import_cycle() {
+        let mut builder = GraphBuilder::new();
+        builder.add_nodes(vec![
+            func("run", "app/billing/invoice.py", &[]),
+            import("app.users.models", "app/billing/invoice.py", 1),
+            func("User", "app/users/models.py", &[]),
+            import("..billing.invoice", "app/users/models.py", 3),
+            import("os", "app/users/models.py", 1),
+            func("helper", "app/users/__init__.py", &[]),
+            import(".models, json as j", "app/users/__init__.py", 1),
+        ]);
+        let graph = builder.build();
+
+        let directories = graph.find_cycles(&CycleOptions::new(CycleLevel::Directory));
+        assert_eq!(directories.cycles.len(), 1);
+        let cycle = &directories.cycles[0];
+        assert_eq!(cycle.members, vec!["app/billing", "app/users"]);
+        assert_eq!(cycle.path, vec!["app/billing", "app/users", "app/billing"]);
+        assert_eq!(cycle.edges[0].kind, EdgeKind::Imports);
+        assert_eq!(cycle.edges[0].target, "app.users.models");
+        assert_eq!(cycle.edges[1].file, "app/users/models.py");
+        assert_eq!(cycle.edges[1].line, 3);
+
+        let files = graph.find_cycles(&CycleOptions::new(CycleLevel::File));
+        assert_eq!(
+            files.cycles[0].members,
+            vec!["app/billing/invoice.py", "app/users/models.py"]
+        );
+
+        // Both directories belong to the `app` package
+        assert!(graph
+            .find_cycles(&CycleOptions::new(CycleLevel::Package))
+            .is_acyclic());
+    }
+
+    #[test]
+    fn test_type_level_lifts_members() {
+        let mut builder = GraphBuilder::new();
+        builder.add_nodes(vec![
+            CodeNode::new("Order", "Order", NodeKind::Class, "shop.py").with_lines(1, 10),
+            CodeNode::new("total", "Order.total", NodeKind::Method, "shop.py")
+                .with_lines(2, 4)
+                .with_references(vec!["Cart.items".to_string()]),
+            CodeNode::new("Cart", "Cart", NodeKind::Class, "shop.py").with_lines(12, 20),
+            CodeNode::new("items", "Cart.items", NodeKind::Method, "shop.py").with_lines(13, 14),
+            CodeNode::new("checkout", "checkout", NodeKind::Function, "shop.py")
+                .with_lines(15, 18)
+                .with_references(vec!["Order.total".to_string()]),
+        ]);
+        let graph = builder.build();
+
+        let types = graph.find_cycles(&CycleOptions::new(CycleLevel::Type));
+        assert_eq!(types.cycles.len(), 1);
+        assert_eq!(types.cycles[0].members, vec!["Cart", "Order"]);
+    }
+}
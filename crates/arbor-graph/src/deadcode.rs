@@ -0,0 +1,474 @@
+//! Dead code detection.
+//!
+//! Everything reachable from the configured entry points is live: `main`,
+//! the public API, tests, framework handlers picked out by decorator or
+//! name, and whole files. Functions and types nothing live reaches are
+//! reported, grouped by file, with a confidence level. Calls we couldn't
+//! resolve and dynamic dispatch both hide real uses, so a symbol a live
+//! node mentions by name, or a method, is never reported with high
+//! confidence. Neither is a type: only calls are followed, so a type used
+//! as a field, parameter or return type looks unreachable.
+
+use crate::graph::{ArborGraph, NodeId};
+use crate::query::NodeInfo;
+use crate::resolve::{is_type, ImportResolver, TypeIndex};
+use arbor_core::{CodeNode, ConfigError, EntryPoints, NodeKind, Visibility};
+use globset::GlobSet;
+use petgraph::Direction;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+/// How sure we are that a symbol is unused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Confidence {
+    /// Something live mentions it by name, or its file is imported.
+    Low,
+    /// A method, a type, a public symbol, or only used by other dead code.
+    Medium,
+    /// Private and never referenced.
+    High,
+}
+
+impl std::fmt::Display for Confidence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Low => "low",
+            Self::Medium => "medium",
+            Self::High => "high",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl std::str::FromStr for Confidence {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "low" => Ok(Self::Low),
+            "medium" => Ok(Self::Medium),
+            "high" => Ok(Self::High),
+            _ => Err(format!(
+                "unknown confidence '{}' (expected low, medium or high)",
+                s
+            )),
+        }
+    }
+}
+
+/// An unreachable function or type.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadSymbol {
+    #[serde(flatten)]
+    pub node: NodeInfo,
+    pub confidence: Confidence,
+    /// Why the confidence is what it is.
+    pub reason: String,
+}
+
+/// The dead symbols in one file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadFile {
+    pub file: String,
+    /// Every function and type in the file is unreachable.
+    pub whole_file: bool,
+    /// The lowest confidence among the file's symbols, or low for a whole
+    /// file that a live file imports.
+    pub confidence: Confidence,
+    /// A live file that imports this one.
+    pub imported_by: Option<String>,
+    /// Members of dead types are left out; the type stands for them.
+    pub symbols: Vec<DeadSymbol>,
+}
+
+/// The result of a dead code pass.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadCodeReport {
+    /// Nodes matching an entry point.
+    pub roots: usize,
+    /// Nodes reachable from them.
+    pub reachable: usize,
+    /// Functions and types checked.
+    pub symbols: usize,
+    /// Files with dead symbols, sorted by path.
+    pub files: Vec<DeadFile>,
+}
+
+impl DeadCodeReport {
+    /// Number of dead symbols reported.
+    pub fn dead_count(&self) -> usize {
+        self.files.iter().map(|f| f.symbols.len()).sum()
+    }
+
+    /// Drops symbols below `min`, and files left with none.
+    pub fn retain_confidence(&mut self, min: Confidence) {
+        for file in &mut self.files {
+            file.symbols.retain(|s| s.confidence >= min);
+            file.refresh_confidence();
+        }
+        self.files.retain(|f| !f.symbols.is_empty());
+    }
+}
+
+impl DeadFile {
+    fn refresh_confidence(&mut self) {
+        let lowest = self.symbols.iter().map(|s| s.confidence).min();
+        self.confidence = match (self.whole_file && self.imported_by.is_some(), lowest) {
+            (true, _) | (_, None) => Confidence::Low,
+            (false, Some(lowest)) => lowest,
+        };
+    }
+}
+
+/// Compiled entry point rules.
+struct Roots<'a> {
+    entry: &'a EntryPoints,
+    names: GlobSet,
+    files: GlobSet,
+}
+
+impl Roots<'_> {
+    fn matches(&self, graph: &ArborGraph, node: &CodeNode) -> bool {
+        let entry = self.entry;
+        if entry.main && node.name == "main" && node.kind == NodeKind::Function {
+            return true;
+        }
+        if entry.public_api && is_public_api(node) {
+            return true;
+        }
+        if entry.tests && graph.is_test(node) {
+            return true;
+        }
+        self.names.is_match(&node.name)
+            || self.names.is_match(&node.qualified_name)
+            || self.files.is_match(&node.file)
+            || node
+                .attributes
+                .iter()
+                .any(|a| entry.attributes.iter().any(|p| a.contains(p.as_str())))
+    }
+}
+
+/// Exported, or declared public in a language where that's explicit.
+fn is_public_api(node: &CodeNode) -> bool {
+    let by_convention = node.file.ends_with(".py") || node.file.ends_with(".pyi");
+    node.is_exported || (node.visibility == Visibility::Public && !by_convention)
+}
+
+/// The kinds dead code is reported for.
+fn is_checked(kind: NodeKind) -> bool {
+    matches!(
+        kind,
+        NodeKind::Function | NodeKind::Method | NodeKind::Constructor
+    ) || is_type(kind)
+}
+
+impl ArborGraph {
+    /// Finds functions and types that no entry point reaches.
+    pub fn find_dead_code(&self, entry: &EntryPoints) -> Result<DeadCodeReport, ConfigError> {
+        let roots = Roots {
+            entry,
+            names: entry.name_globs()?,
+            files: entry.file_globs()?,
+        };
+
+        // Each member's enclosing type, so a used method keeps its class
+        let types = TypeIndex::new(self);
+        let mut owner: HashMap<NodeId, NodeId> = HashMap::new();
+        for index in self.node_indexes() {
+            let Some(node) = self.get(index).filter(|n| !is_type(n.kind)) else {
+                continue;
+            };
+            let found = types.enclosing(node).and_then(|qualified| {
+                self.nodes_qualified(qualified)
+                    .iter()
+                    .copied()
+                    .find(|&t| self.get(t).is_some_and(|t| t.file == node.file))
+            });
+            if let Some(found) = found {
+                owner.insert(index, found);
+            }
+        }
+
+        let root_nodes: Vec<NodeId> = self
+            .node_indexes()
+            .filter(|&i| self.get(i).is_some_and(|n| roots.matches(self, n)))
+            .collect();
+
+        let mut live: HashSet<NodeId> = root_nodes.iter().copied().collect();
+        let mut queue: VecDeque<NodeId> = root_nodes.iter().copied().collect();
+        while let Some(current) = queue.pop_front() {
+            let next = self
+                .graph
+                .neighbors_directed(current, Direction::Outgoing)
+                .chain(owner.get(&current).copied());
+            for next in next {
+                if live.insert(next) {
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        let checked: Vec<NodeId> = self
+            .node_indexes()
+            .filter(|&i| {
+                self.get(i)
+                    .is_some_and(|n| is_checked(n.kind) && !n.generated)
+            })
+            .collect();
+        let checked_set: HashSet<NodeId> = checked.iter().copied().collect();
+
+        let mut by_file: BTreeMap<&str, (usize, Vec<DeadSymbol>)> = BTreeMap::new();
+        for &index in &checked {
+            let node = &self.graph[index];
+            let entry = by_file.entry(node.file.as_str()).or_default();
+            entry.0 += 1;
+            if live.contains(&index) {
+                continue;
+            }
+            // A dead type stands for its members
+            if owner.get(&index).is_some_and(|t| !live.contains(t)) {
+                continue;
+            }
+            let (confidence, reason) = self.dead_confidence(index, &live);
+            let mut info = NodeInfo::from(node);
+            info.centrality = self.centrality(index);
+            entry.1.push(DeadSymbol {
+                node: info,
+                confidence,
+                reason,
+            });
+        }
+
+        // Files that live files import aren't dead as a whole
+        let resolver = ImportResolver::new(self);
+        let mut imported_by_live: HashMap<&str, &str> = HashMap::new();
+        for &index in self.nodes_of_kind(NodeKind::Import) {
+            let Some(import) = self.get(index) else {
+                continue;
+            };
+            let file_live = self
+                .nodes_in_file(&import.file)
+                .iter()
+                .any(|i| live.contains(i));
+            if file_live {
+                for target in resolver.resolve(import) {
+                    imported_by_live.entry(target).or_insert(&import.file);
+                }
+            }
+        }
+
+        let files = by_file
+            .into_iter()
+            .filter(|(_, (_, dead))| !dead.is_empty())
+            .map(|(file, (total, mut symbols))| {
+                symbols.sort_by_key(|s| s.node.line_start);
+                let dead_here = self
+                    .nodes_in_file(file)
+                    .iter()
+                    .filter(|i| checked_set.contains(i) && !live.contains(i))
+                    .count();
+                let mut dead_file = DeadFile {
+                    file: file.to_string(),
+                    whole_file: dead_here == total,
+                    confidence: Confidence::Low,
+                    imported_by: imported_by_live.get(file).map(|f| f.to_string()),
+                    symbols,
+                };
+                dead_file.refresh_confidence();
+                dead_file
+            })
+            .collect();
+
+        Ok(DeadCodeReport {
+            roots: root_nodes.len(),
+            reachable: live.len(),
+            symbols: checked.len(),
+            files,
+        })
+    }
+
+    /// How sure we are that an unreachable node is unused, and why.
+    fn dead_confidence(&self, index: NodeId, live: &HashSet<NodeId>) -> (Confidence, String) {
+        let node = &self.graph[index];
+        let names: HashSet<String> = [node.name.clone(), node.qualified_name.clone()].into();
+        let mut mentions: Vec<NodeId> = self.find_referencing(&names);
+        mentions.retain(|&i| i != index);
+        let callers: Vec<NodeId> = self
+            .graph
+            .neighbors_directed(index, Direction::Incoming)
+            .collect();
+
+        if let Some(user) = mentions.iter().find(|i| live.contains(i)) {
+            let user = &self.graph[*user];
+            return (
+                Confidence::Low,
+                format!(
+                    "live `{}` refers to `{}`, which may resolve here",
+                    user.qualified_name, node.name
+                ),
+            );
+        }
+        if matches!(node.kind, NodeKind::Method | NodeKind::Constructor) {
+            return (
+                Confidence::Medium,
+                "method; may be called through an interface, a trait or dynamic dispatch"
+                    .to_string(),
+            );
+        }
+        if is_type(node.kind) {
+            return (
+                Confidence::Medium,
+                "type; uses in fields, parameters and return types aren't followed".to_string(),
+            );
+        }
+        if !callers.is_empty() || !mentions.is_empty() {
+            return (
+                Confidence::Medium,
+                "only used by unreachable code".to_string(),
+            );
+        }
+        if is_public_api(node) {
+            return (
+                Confidence::Medium,
+                "public; may be used from outside the indexed code".to_string(),
+            );
+        }
+        (Confidence::High, "never referenced".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::GraphBuilder;
+
+    fn func(name: &str, file: &str, refs: &[&str]) -> CodeNode {
+        CodeNode::new(name, name, NodeKind::Function, file)
+            .with_visibility(Visibility::Private)
+            .with_references(refs.iter().map(|r| r.to_string()).collect())
+    }
+
+    fn graph() -> ArborGraph {
+        let mut builder = GraphBuilder::new();
+        builder.add_nodes(vec![
+            func("main", "src/main.rs", &["run"]),
+            func("run", "src/main.rs", &["helper"]),
+            func("helper", "src/util.rs", &[]),
+            func("orphan", "src/util.rs", &[]),
+            func("old_entry", "src/legacy.rs", &["old_helper"]),
+            func("old_helper", "src/legacy.rs", &[]),
+            CodeNode::new("Report", "Report", NodeKind::Struct, "src/report.rs")
+                .with_lines(1, 20)
+                .with_visibility(Visibility::Private),
+            CodeNode::new("render", "Report.render", NodeKind::Method, "src/report.rs")
+                .with_lines(5, 10)
+                .with_visibility(Visibility::Private),
+            func("index", "src/web.rs", &[]).with_attributes(vec!["#[get(\"/\")]".to_string()]),
+            func("test_helper", "tests/util.rs", &["helper"]),
+        ]);
+        builder.build()
+    }
+
+    fn dead(report: &DeadCodeReport) -> Vec<(&str, Confidence)> {
+        report
+            .files
+            .iter()
+            .flat_map(|f| &f.symbols)
+            .map(|s| (s.node.name.as_str(), s.confidence))
+            .collect()
+    }
+
+    #[test]
+    fn test_reachability_from_entry_points() {
+        let graph = graph();
+        let report = graph.find_dead_code(&EntryPoints::default()).unwrap();
+
+        assert_eq!(
+            dead(&report),
+            vec![
+                ("old_entry", Confidence::High),
+                ("old_helper", Confidence::Medium),
+                ("Report", Confidence::Medium),
+                ("orphan", Confidence::High),
+            ]
+        );
+        let legacy = report
+            .files
+            .iter()
+            .find(|f| f.file == "src/legacy.rs")
+            .unwrap();
+        assert!(legacy.whole_file);
+        let util = report
+            .files
+            .iter()
+            .find(|f| f.file == "src/util.rs")
+            .unwrap();
+        assert!(!util.whole_file);
+
+        let mut high = report.clone();
+        high.retain_confidence(Confidence::High);
+        assert_eq!(high.dead_count(), 2);
+    }
+
+    #[test]
+    fn test_configurable_roots() {
+        let graph = graph();
+        let entry = EntryPoints {
+            names: vec!["old_*".to_string()],
+            files: vec!["src/report.rs".to_string()],
+            ..EntryPoints::default()
+        };
+        let report = graph.find_dead_code(&entry).unwrap();
+        assert_eq!(dead(&report), vec![("orphan", Confidence::High)]);
+
+        // Without tests or attributes as roots, their code is dead too
+        let entry = EntryPoints {
+            tests: false,
+            attributes: Vec::new(),
+            ..EntryPoints::default()
+        };
+        let report = graph.find_dead_code(&entry).unwrap();
+        let names: Vec<&str> = dead(&report).into_iter().map(|(name, _)| name).collect();
+        assert!(names.contains(&"index"));
+        assert!(names.contains(&"test_helper"));
+    }
+
+    #[test]
+    fn test_types_are_never_high_confidence() {
+        let mut builder = GraphBuilder::new();
+        builder.add_nodes(vec![
+            func("main", "src/main.rs", &["load"]),
+            func("load", "src/config.rs", &[])
+                .with_lines(6, 8)
+                .with_signature("fn load() -> Config"),
+            CodeNode::new("Config", "Config", NodeKind::Struct, "src/config.rs")
+                .with_lines(1, 4)
+                .with_visibility(Visibility::Private),
+        ]);
+        let graph = builder.build();
+
+        let report = graph.find_dead_code(&EntryPoints::default()).unwrap();
+        assert_eq!(dead(&report), vec![("Config", Confidence::Medium)]);
+    }
+
+    #[test]
+    fn test_ambiguous_reference_lowers_confidence() {
+        let mut builder = GraphBuilder::new();
+        builder.add_nodes(vec![
+            func("main", "a.py", &["process"]),
+            CodeNode::new("process", "Worker.process", NodeKind::Method, "b.py"),
+            CodeNode::new("process", "Job.process", NodeKind::Method, "d.py"),
+            func("process_all", "c.py", &[]),
+        ]);
+        let graph = builder.build();
+
+        // "process" resolves to one method; the other might be the real target
+        let report = graph.find_dead_code(&EntryPoints::default()).unwrap();
+        let found = dead(&report);
+        assert!(found.contains(&("process", Confidence::Low)));
+        assert!(found.contains(&("process_all", Confidence::High)));
+        assert_eq!(found.len(), 2);
+    }
+}
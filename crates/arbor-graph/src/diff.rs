@@ -0,0 +1,554 @@
+//! Structural diff between two graph snapshots.
+//!
+//! Nodes are matched in three passes: by ID (same file, qualified name and
+//! kind), then by qualified name and kind across files (moves), then by a
+//! similarity score within each kind (renames). Edges are compared after
+//! mapping matched nodes onto each other, so renaming a function doesn't
+//! show up as all of its edges being removed and added again.
+
+use crate::edge::EdgeKind;
+use crate::graph::ArborGraph;
+use crate::query::NodeInfo;
+use crate::search_index::{edit_distance, identifier_tokens};
+use arbor_core::{CodeNode, NodeKind, Visibility};
+use petgraph::visit::{EdgeRef, IntoEdgeReferences};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Names less alike than this can only pair up as a rename when their
+/// bodies are identical.
+const MIN_NAME_SIMILARITY: f64 = 0.5;
+
+/// Knobs for [`GraphDiff::with_options`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DiffOptions {
+    /// Leave out body-only changes; report just what affects the API
+    /// and the shape of the graph.
+    pub structural_only: bool,
+
+    /// Minimum similarity (0.0 to 1.0) for two nodes to count as a rename.
+    pub rename_threshold: f64,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        Self {
+            structural_only: false,
+            rename_threshold: 0.6,
+        }
+    }
+}
+
+/// A node that now lives in a different file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MovedNode {
+    pub node: NodeInfo,
+    pub from_file: String,
+}
+
+/// A node that was most likely renamed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenamedNode {
+    pub from: NodeInfo,
+    pub to: NodeInfo,
+    /// How alike the two nodes are, from 0.0 to 1.0.
+    pub similarity: f64,
+}
+
+/// A signature that changed, beyond a rename.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignatureChange {
+    pub node: NodeInfo,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+/// A change in visibility or export status.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VisibilityChange {
+    pub node: NodeInfo,
+    pub before: Visibility,
+    pub after: Visibility,
+    pub exported_before: bool,
+    pub exported_after: bool,
+}
+
+/// An edge that appeared or disappeared.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EdgeChange {
+    pub kind: EdgeKind,
+    pub source: String,
+    pub source_file: String,
+    pub target: String,
+    pub target_file: String,
+}
+
+/// Everything that differs between two graphs.
+///
+/// Node entries describe the node as it is in the new graph, except for
+/// `removed` and `RenamedNode::from`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GraphDiff {
+    pub added: Vec<NodeInfo>,
+    pub removed: Vec<NodeInfo>,
+    pub moved: Vec<MovedNode>,
+    pub renamed: Vec<RenamedNode>,
+    pub signature_changed: Vec<SignatureChange>,
+    pub visibility_changed: Vec<VisibilityChange>,
+    /// Same signature and visibility, different body. Empty when
+    /// [`DiffOptions::structural_only`] is set.
+    pub body_changed: Vec<NodeInfo>,
+    pub edges_added: Vec<EdgeChange>,
+    pub edges_removed: Vec<EdgeChange>,
+}
+
+impl GraphDiff {
+    /// Diffs `old` against `new` with the default options.
+    pub fn between(old: &ArborGraph, new: &ArborGraph) -> Self {
+        Self::with_options(old, new, &DiffOptions::default())
+    }
+
+    /// Diffs `old` against `new`.
+    pub fn with_options(old: &ArborGraph, new: &ArborGraph, options: &DiffOptions) -> Self {
+        let matching = match_nodes(old, new, options.rename_threshold);
+        let mut diff = GraphDiff::default();
+
+        for node in old.nodes().filter(|n| !matching.pairs.contains_key(&n.id)) {
+            diff.removed.push(NodeInfo::from(node));
+        }
+        let matched_new: HashSet<&str> = matching.pairs.values().map(String::as_str).collect();
+        for node in new.nodes().filter(|n| !matched_new.contains(n.id.as_str())) {
+            diff.added.push(NodeInfo::from(node));
+        }
+
+        for (old_id, new_id) in &matching.pairs {
+            let (Some(before), Some(after)) = (old.get_by_id(old_id), new.get_by_id(new_id)) else {
+                continue;
+            };
+            let info = NodeInfo::from(after);
+
+            if let Some(&similarity) = matching.renames.get(old_id) {
+                diff.renamed.push(RenamedNode {
+                    from: NodeInfo::from(before),
+                    to: info.clone(),
+                    similarity,
+                });
+            } else if before.file != after.file {
+                diff.moved.push(MovedNode {
+                    node: info.clone(),
+                    from_file: before.file.clone(),
+                });
+            }
+
+            let signature_changed = normalized_signature(before) != normalized_signature(after);
+            if signature_changed {
+                diff.signature_changed.push(SignatureChange {
+                    node: info.clone(),
+                    before: before.signature.clone(),
+                    after: after.signature.clone(),
+                });
+            }
+            let visibility_changed =
+                before.visibility != after.visibility || before.is_exported != after.is_exported;
+            if visibility_changed {
+                diff.visibility_changed.push(VisibilityChange {
+                    node: info.clone(),
+                    before: before.visibility,
+                    after: after.visibility,
+                    exported_before: before.is_exported,
+                    exported_after: after.is_exported,
+                });
+            }
+            if !options.structural_only
+                && !signature_changed
+                && !visibility_changed
+                && body_changed(before, after)
+            {
+                diff.body_changed.push(info);
+            }
+        }
+
+        let old_edges = edge_set(old, |id| {
+            matching
+                .pairs
+                .get(id)
+                .cloned()
+                .unwrap_or_else(|| format!("old:{}", id))
+        });
+        let new_edges = edge_set(new, |id| id.to_string());
+        diff.edges_removed = old_edges
+            .iter()
+            .filter(|(key, _)| !new_edges.contains_key(*key))
+            .map(|(_, change)| change.clone())
+            .collect();
+        diff.edges_added = new_edges
+            .iter()
+            .filter(|(key, _)| !old_edges.contains_key(*key))
+            .map(|(_, change)| change.clone())
+            .collect();
+
+        diff.sort();
+        diff
+    }
+
+    /// Whether the two graphs are equivalent.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.moved.is_empty()
+            && self.renamed.is_empty()
+            && self.signature_changed.is_empty()
+            && self.visibility_changed.is_empty()
+            && self.body_changed.is_empty()
+            && self.edges_added.is_empty()
+            && self.edges_removed.is_empty()
+    }
+
+    /// Counts added and removed edges per kind.
+    pub fn edges_by_kind(&self) -> BTreeMap<String, (usize, usize)> {
+        let mut counts: BTreeMap<String, (usize, usize)> = BTreeMap::new();
+        for edge in &self.edges_added {
+            counts.entry(edge.kind.to_string()).or_default().0 += 1;
+        }
+        for edge in &self.edges_removed {
+            counts.entry(edge.kind.to_string()).or_default().1 += 1;
+        }
+        counts
+    }
+
+    fn sort(&mut self) {
+        let by_location = |a: &NodeInfo, b: &NodeInfo| {
+            (&a.file, a.line_start, &a.qualified_name).cmp(&(
+                &b.file,
+                b.line_start,
+                &b.qualified_name,
+            ))
+        };
+        self.added.sort_by(by_location);
+        self.removed.sort_by(by_location);
+        self.body_changed.sort_by(by_location);
+        self.moved.sort_by(|a, b| by_location(&a.node, &b.node));
+        self.renamed.sort_by(|a, b| by_location(&a.to, &b.to));
+        self.signature_changed
+            .sort_by(|a, b| by_location(&a.node, &b.node));
+        self.visibility_changed
+            .sort_by(|a, b| by_location(&a.node, &b.node));
+        let by_edge = |a: &EdgeChange, b: &EdgeChange| {
+            (&a.source_file, &a.source, &a.target, a.kind.to_string()).cmp(&(
+                &b.source_file,
+                &b.source,
+                &b.target,
+                b.kind.to_string(),
+            ))
+        };
+        self.edges_added.sort_by(by_edge);
+        self.edges_removed.sort_by(by_edge);
+    }
+}
+
+/// Old node ID → new node ID, plus which of those pairs are renames.
+struct Matching {
+    pairs: HashMap<String, String>,
+    renames: HashMap<String, f64>,
+}
+
+fn match_nodes(old: &ArborGraph, new: &ArborGraph, rename_threshold: f64) -> Matching {
+    let mut pairs = HashMap::new();
+    let mut renames = HashMap::new();
+
+    // 1. Same ID: same file, qualified name and kind
+    for node in old.nodes() {
+        if new.get_by_id(&node.id).is_some() {
+            pairs.insert(node.id.clone(), node.id.clone());
+        }
+    }
+
+    // 2. Same qualified name and kind in another file
+    let mut unmatched_new: HashMap<(&str, NodeKind), Vec<&CodeNode>> = HashMap::new();
+    for node in new.nodes().filter(|n| old.get_by_id(&n.id).is_none()) {
+        unmatched_new
+            .entry((node.qualified_name.as_str(), node.kind))
+            .or_default()
+            .push(node);
+    }
+    let mut unmatched_old: Vec<&CodeNode> =
+        old.nodes().filter(|n| !pairs.contains_key(&n.id)).collect();
+    unmatched_old.sort_by(|a, b| (&a.file, a.line_start).cmp(&(&b.file, b.line_start)));
+    for candidates in unmatched_new.values_mut() {
+        candidates.sort_by(|a, b| (&b.file, b.line_start).cmp(&(&a.file, a.line_start)));
+    }
+    unmatched_old.retain(|node| {
+        let key = (node.qualified_name.as_str(), node.kind);
+        match unmatched_new.get_mut(&key).and_then(Vec::pop) {
+            Some(moved) => {
+                pairs.insert(node.id.clone(), moved.id.clone());
+                false
+            }
+            None => true,
+        }
+    });
+
+    // 3. Renames: best-scoring pairs of the same kind, greedily
+    let remaining_new: Vec<&CodeNode> = unmatched_new.into_values().flatten().collect();
+    let mut candidates = Vec::new();
+    for before in &unmatched_old {
+        for after in remaining_new.iter().filter(|n| n.kind == before.kind) {
+            let score = similarity(before, after);
+            if score >= rename_threshold {
+                candidates.push((score, *before, *after));
+            }
+        }
+    }
+    candidates.sort_by(|a, b| {
+        b.0.total_cmp(&a.0)
+            .then_with(|| a.1.qualified_name.cmp(&b.1.qualified_name))
+            .then_with(|| a.2.qualified_name.cmp(&b.2.qualified_name))
+    });
+    let mut taken = HashSet::new();
+    for (score, before, after) in candidates {
+        if pairs.contains_key(&before.id) || taken.contains(&after.id) {
+            continue;
+        }
+        pairs.insert(before.id.clone(), after.id.clone());
+        renames.insert(before.id.clone(), (score * 100.0).round() / 100.0);
+        taken.insert(after.id.clone());
+    }
+
+    Matching { pairs, renames }
+}
+
+/// Scores how likely `after` is `before` under a new name.
+///
+/// Returns 0 unless the names are alike or the bodies match: a shared
+/// file and signature alone don't make two functions the same one.
+fn similarity(before: &CodeNode, after: &CodeNode) -> f64 {
+    let same_body = before.body_hash != 0 && before.body_hash == after.body_hash;
+    if !same_body && name_similarity(&before.name, &after.name) < MIN_NAME_SIMILARITY {
+        return 0.0;
+    }
+
+    let mut score = 0.0;
+    if before.signature.is_some() && normalized_signature(before) == normalized_signature(after) {
+        score += 0.35;
+    }
+    if before.file == after.file {
+        score += 0.2;
+    }
+    if parent_scope(&before.qualified_name) == parent_scope(&after.qualified_name) {
+        score += 0.1;
+    }
+
+    // No references on either side says nothing either way
+    let refs_before: HashSet<&String> = before.references.iter().collect();
+    let refs_after: HashSet<&String> = after.references.iter().collect();
+    let union = refs_before.union(&refs_after).count();
+    if union > 0 {
+        let shared = refs_before.intersection(&refs_after).count() as f64 / union as f64;
+        score += 0.2 * shared;
+    }
+
+    let (a, b) = (body_len(before), body_len(after));
+    if same_body {
+        score += 0.15;
+    } else if a.max(b) > 0 {
+        score += 0.15 * a.min(b) as f64 / a.max(b) as f64;
+    }
+    score
+}
+
+/// How alike two names are, from 0.0 to 1.0: the better of shared
+/// identifier words (`get_user` / `fetch_user`) and edit distance
+/// (`helper` / `helpers`).
+fn name_similarity(a: &str, b: &str) -> f64 {
+    let words_a: HashSet<String> = identifier_tokens(a).into_iter().collect();
+    let words_b: HashSet<String> = identifier_tokens(b).into_iter().collect();
+    let total = words_a.len() + words_b.len();
+    let words = if total == 0 {
+        0.0
+    } else {
+        2.0 * words_a.intersection(&words_b).count() as f64 / total as f64
+    };
+
+    let (a, b) = (a.to_lowercase(), b.to_lowercase());
+    let longest = a.chars().count().max(b.chars().count());
+    let chars = match edit_distance(&a, &b, longest) {
+        Some(distance) if longest > 0 => 1.0 - distance as f64 / longest as f64,
+        _ => 0.0,
+    };
+    words.max(chars)
+}
+
+/// The signature with the node's own name blanked out, so renames
+/// don't count as signature changes.
+fn normalized_signature(node: &CodeNode) -> Option<String> {
+    node.signature
+        .as_ref()
+        .map(|sig| sig.replacen(node.name.as_str(), "_", 1))
+}
+
+fn parent_scope(qualified_name: &str) -> &str {
+    qualified_name
+        .rsplit_once(['.', ':'])
+        .map_or("", |(parent, _)| parent.trim_end_matches(':'))
+}
+
+fn body_len(node: &CodeNode) -> u32 {
+    node.byte_end.saturating_sub(node.byte_start)
+}
+
+/// Compares body hashes, or lengths for nodes indexed without them.
+fn body_changed(before: &CodeNode, after: &CodeNode) -> bool {
+    if before.body_hash != 0 && after.body_hash != 0 {
+        before.body_hash != after.body_hash
+    } else {
+        body_len(before) != body_len(after)
+    }
+}
+
+/// Edges keyed by (source key, target key, kind), where `key` maps a
+/// node ID onto the shared identity space.
+fn edge_set(
+    graph: &ArborGraph,
+    key: impl Fn(&str) -> String,
+) -> HashMap<(String, String, EdgeKind), EdgeChange> {
+    let mut edges = HashMap::new();
+    for edge in graph.graph.edge_references() {
+        let (Some(source), Some(target)) = (
+            graph.graph.node_weight(edge.source()),
+            graph.graph.node_weight(edge.target()),
+        ) else {
+            continue;
+        };
+        let kind = edge.weight().kind;
+        edges.insert(
+            (key(&source.id), key(&target.id), kind),
+            EdgeChange {
+                kind,
+                source: source.qualified_name.clone(),
+                source_file: source.file.clone(),
+                target: target.qualified_name.clone(),
+                target_file: target.file.clone(),
+            },
+        );
+    }
+    edges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::GraphBuilder;
+
+    fn func(name: &str, file: &str, sig: &str, refs: &[&str]) -> CodeNode {
+        CodeNode::new(name, name, NodeKind::Function, file)
+            .with_signature(sig)
+            .with_bytes(0, 40)
+            .with_body(&format!("{}: {}", sig, refs.join(", ")))
+            .with_references(refs.iter().map(|r| r.to_string()).collect())
+    }
+
+    fn build(nodes: Vec<CodeNode>) -> ArborGraph {
+        let mut builder = GraphBuilder::new();
+        builder.add_nodes(nodes);
+        builder.build()
+    }
+
+    #[test]
+    fn test_identical_graphs() {
+        let nodes = vec![
+            func("a", "a.py", "def a()", &["b"]),
+            func("b", "a.py", "def b()", &[]),
+        ];
+        let diff = GraphDiff::between(&build(nodes.clone()), &build(nodes));
+        assert!(diff.is_empty());
+    }
+
+    #[test]
+    fn test_added_removed_and_changed() {
+        let old = build(vec![
+            func("keep", "a.py", "def keep(x)", &["gone"]),
+            func("gone", "a.py", "def gone()", &[]),
+            func("hidden", "a.py", "def hidden()", &[]),
+        ]);
+        let new = build(vec![
+            func("keep", "a.py", "def keep(x, y)", &["fresh"]),
+            func("fresh", "b.py", "def fresh(a, b, c) -> Dict[str, int]", &[]),
+            func("hidden", "a.py", "def hidden()", &[]).with_visibility(Visibility::Public),
+        ]);
+        let diff = GraphDiff::between(&old, &new);
+
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.added[0].name, "fresh");
+        assert_eq!(diff.removed.len(), 1);
+        assert_eq!(diff.removed[0].name, "gone");
+        assert_eq!(diff.signature_changed.len(), 1);
+        assert_eq!(
+            diff.signature_changed[0].after.as_deref(),
+            Some("def keep(x, y)")
+        );
+        assert_eq!(diff.visibility_changed[0].node.name, "hidden");
+        assert_eq!(diff.visibility_changed[0].after, Visibility::Public);
+
+        assert_eq!(diff.edges_removed.len(), 1);
+        assert_eq!(diff.edges_removed[0].target, "gone");
+        assert_eq!(diff.edges_added.len(), 1);
+        assert_eq!(diff.edges_added[0].target, "fresh");
+        assert_eq!(diff.edges_by_kind()["calls"], (1, 1));
+    }
+
+    #[test]
+    fn test_moves_and_renames_keep_edges() {
+        let old = build(vec![
+            func("caller", "a.py", "def caller()", &["helper", "util"]),
+            func("helper", "a.py", "def helper(x)", &["print"]),
+            func("util", "a.py", "def util()", &[]),
+        ]);
+        let new = build(vec![
+            func("caller", "a.py", "def caller()", &["assist", "util"]),
+            func("assist", "a.py", "def assist(x)", &["print"]),
+            func("util", "lib/util.py", "def util()", &[]),
+        ]);
+        let diff = GraphDiff::between(&old, &new);
+
+        assert!(diff.added.is_empty() && diff.removed.is_empty());
+        assert_eq!(diff.renamed.len(), 1);
+        assert_eq!(diff.renamed[0].from.name, "helper");
+        assert_eq!(diff.renamed[0].to.name, "assist");
+        assert!(diff.signature_changed.is_empty());
+        assert_eq!(diff.moved.len(), 1);
+        assert_eq!(diff.moved[0].from_file, "a.py");
+        assert_eq!(diff.moved[0].node.file, "lib/util.py");
+        assert!(diff.edges_added.is_empty() && diff.edges_removed.is_empty());
+    }
+
+    #[test]
+    fn test_structural_only_skips_body_changes() {
+        let old = build(vec![func("a", "a.py", "def a()", &[])]);
+        let new = build(vec![
+            func("a", "a.py", "def a()", &[]).with_body("def a(): return 2")
+        ]);
+
+        // Same length, different text
+        let diff = GraphDiff::between(&old, &new);
+        assert_eq!(diff.body_changed.len(), 1);
+
+        let options = DiffOptions {
+            structural_only: true,
+            ..DiffOptions::default()
+        };
+        assert!(GraphDiff::with_options(&old, &new, &options).is_empty());
+    }
+
+    #[test]
+    fn test_unrelated_functions_are_not_renames() {
+        let old = build(vec![func("start", "a.py", "def start()", &[])]);
+        let new = build(vec![
+            func("shutdown", "a.py", "def shutdown()", &[]).with_body("def shutdown(): exit()")
+        ]);
+
+        let diff = GraphDiff::between(&old, &new);
+        assert!(diff.renamed.is_empty());
+        assert_eq!(diff.removed[0].name, "start");
+        assert_eq!(diff.added[0].name, "shutdown");
+    }
+}
@@ -7,7 +7,7 @@
 use serde::{Deserialize, Serialize};
 
 /// The type of relationship between two code entities.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum EdgeKind {
     /// Function A calls function B.
@@ -57,6 +57,34 @@ impl std::fmt::Display for EdgeKind {
     }
 }
 
+impl EdgeKind {
+    /// Every kind, in declaration order.
+    pub const ALL: [EdgeKind; 9] = [
+        Self::Calls,
+        Self::Imports,
+        Self::Extends,
+        Self::Implements,
+        Self::UsesType,
+        Self::References,
+        Self::Contains,
+        Self::FlowsTo,
+        Self::DataDependency,
+    ];
+}
+
+impl std::str::FromStr for EdgeKind {
+    type Err = String;
+
+    /// Parses the names `Display` prints, e.g. `calls` or `uses_type`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let wanted = s.trim().to_lowercase();
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.to_string() == wanted)
+            .ok_or_else(|| format!("unknown edge kind '{}'", s))
+    }
+}
+
 /// An edge in the code graph with location info.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Edge {
@@ -0,0 +1,431 @@
+//! Composable node filters.
+//!
+//! A [`NodeFilter`] is plain data, so it can come straight from JSON-RPC
+//! params or CLI flags. Compiling it into a [`NodeMatcher`] validates the
+//! globs and language names once; the matcher is then cheap to apply to
+//! every candidate. All conditions must hold; an empty filter matches
+//! everything.
+
+use crate::graph::{ArborGraph, NodeId};
+use crate::search_index::SearchHit;
+use arbor_core::languages::{language_name, supported_languages};
+use arbor_core::{CodeNode, NodeKind, Visibility};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// A filter that can't be compiled.
+#[derive(Error, Debug)]
+pub enum FilterError {
+    /// A file pattern isn't a valid glob.
+    #[error("invalid file glob '{pattern}': {source}")]
+    Glob {
+        pattern: String,
+        #[source]
+        source: globset::Error,
+    },
+
+    /// A language name we don't have a parser for.
+    #[error("unknown language '{0}' (expected one of: {})", supported_languages().join(", "))]
+    UnknownLanguage(String),
+}
+
+/// A flag a node can carry besides kind and visibility.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeAttribute {
+    /// Static or class-level.
+    Static,
+    /// From a generated or minified file.
+    Generated,
+    /// Has a docstring or leading comment.
+    Documented,
+    /// A test function, method or class (see [`ArborGraph::is_test`]).
+    Test,
+}
+
+impl std::str::FromStr for NodeAttribute {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "static" => Ok(Self::Static),
+            "generated" => Ok(Self::Generated),
+            "documented" => Ok(Self::Documented),
+            "test" => Ok(Self::Test),
+            _ => Err(format!("unknown attribute '{}'", s)),
+        }
+    }
+}
+
+/// Conditions a node must meet. Unset fields don't constrain.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NodeFilter {
+    /// Any of these kinds.
+    pub kinds: Vec<NodeKind>,
+    /// Any of these visibilities.
+    pub visibility: Vec<Visibility>,
+    pub exported: Option<bool>,
+    #[serde(rename = "async")]
+    pub is_async: Option<bool>,
+    /// File globs, matched against stored paths; any may match. A bare
+    /// name like `*.rs` matches in every directory.
+    pub files: Vec<String>,
+    /// Any of these languages, by file extension.
+    pub languages: Vec<String>,
+    /// All of these attributes.
+    pub attributes: Vec<NodeAttribute>,
+    pub min_centrality: Option<f64>,
+    /// Workspace root (namespace) the node must belong to.
+    pub root: Option<String>,
+}
+
+impl NodeFilter {
+    /// A filter that matches every node.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builder pattern: also allow this kind.
+    pub fn with_kind(mut self, kind: NodeKind) -> Self {
+        self.kinds.push(kind);
+        self
+    }
+
+    /// Builder pattern: also allow this visibility.
+    pub fn with_visibility(mut self, visibility: Visibility) -> Self {
+        self.visibility.push(visibility);
+        self
+    }
+
+    /// Builder pattern: require `is_exported` to be `exported`.
+    pub fn with_exported(mut self, exported: bool) -> Self {
+        self.exported = Some(exported);
+        self
+    }
+
+    /// Builder pattern: require `is_async` to be `is_async`.
+    pub fn with_async(mut self, is_async: bool) -> Self {
+        self.is_async = Some(is_async);
+        self
+    }
+
+    /// Builder pattern: also allow files matching this glob.
+    pub fn with_file(mut self, pattern: impl Into<String>) -> Self {
+        self.files.push(pattern.into());
+        self
+    }
+
+    /// Builder pattern: also allow this language.
+    pub fn with_language(mut self, language: impl Into<String>) -> Self {
+        self.languages.push(language.into());
+        self
+    }
+
+    /// Builder pattern: require this attribute.
+    pub fn with_attribute(mut self, attribute: NodeAttribute) -> Self {
+        self.attributes.push(attribute);
+        self
+    }
+
+    /// Builder pattern: require at least this centrality.
+    pub fn with_min_centrality(mut self, min: f64) -> Self {
+        self.min_centrality = Some(min);
+        self
+    }
+
+    /// Builder pattern: restrict to a workspace root.
+    pub fn with_root(mut self, root: impl Into<String>) -> Self {
+        self.root = Some(root.into());
+        self
+    }
+
+    /// Whether the filter constrains anything.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Validates the filter and prepares it for matching.
+    pub fn compile(&self) -> Result<NodeMatcher, FilterError> {
+        let files = if self.files.is_empty() {
+            None
+        } else {
+            let mut builder = GlobSetBuilder::new();
+            for pattern in &self.files {
+                let trimmed = pattern.trim().trim_start_matches("./");
+                let glob = if trimmed.contains('/') {
+                    trimmed.to_string()
+                } else {
+                    format!("**/{}", trimmed)
+                };
+                let compiled = Glob::new(&glob).map_err(|source| FilterError::Glob {
+                    pattern: pattern.clone(),
+                    source,
+                })?;
+                builder.add(compiled);
+            }
+            Some(builder.build().map_err(|source| FilterError::Glob {
+                pattern: self.files.join(", "),
+                source,
+            })?)
+        };
+
+        let mut languages = Vec::with_capacity(self.languages.len());
+        for language in &self.languages {
+            let lower = language.trim().to_lowercase();
+            match supported_languages().iter().find(|l| **l == lower) {
+                Some(known) => languages.push(*known),
+                None => return Err(FilterError::UnknownLanguage(language.clone())),
+            }
+        }
+
+        Ok(NodeMatcher {
+            filter: self.clone(),
+            files,
+            languages,
+        })
+    }
+}
+
+/// A compiled [`NodeFilter`].
+#[derive(Debug, Clone)]
+pub struct NodeMatcher {
+    filter: NodeFilter,
+    files: Option<GlobSet>,
+    languages: Vec<&'static str>,
+}
+
+impl NodeMatcher {
+    /// The filter this was compiled from.
+    pub fn filter(&self) -> &NodeFilter {
+        &self.filter
+    }
+
+    /// Checks the conditions that only need the node itself.
+    pub fn matches_node(&self, node: &CodeNode) -> bool {
+        let f = &self.filter;
+        if !f.kinds.is_empty() && !f.kinds.contains(&node.kind) {
+            return false;
+        }
+        if !f.visibility.is_empty() && !f.visibility.contains(&node.visibility) {
+            return false;
+        }
+        if f.exported.is_some_and(|e| e != node.is_exported) {
+            return false;
+        }
+        if f.is_async.is_some_and(|a| a != node.is_async) {
+            return false;
+        }
+        if !node.in_root(f.root.as_deref()) {
+            return false;
+        }
+        if let Some(files) = &self.files {
+            if !files.is_match(&node.file) {
+                return false;
+            }
+        }
+        if !self.languages.is_empty() {
+            let language = node
+                .file
+                .rsplit_once('.')
+                .and_then(|(_, ext)| language_name(ext));
+            if !language.is_some_and(|l| self.languages.contains(&l)) {
+                return false;
+            }
+        }
+        f.attributes.iter().all(|attribute| match attribute {
+            NodeAttribute::Static => node.is_static,
+            NodeAttribute::Generated => node.generated,
+            NodeAttribute::Documented => node.docstring.is_some(),
+            // Needs the graph; see `matches`
+            NodeAttribute::Test => true,
+        })
+    }
+
+    /// Checks every condition, including those that need the graph.
+    pub fn matches(&self, graph: &ArborGraph, index: NodeId) -> bool {
+        let Some(node) = graph.get(index) else {
+            return false;
+        };
+        if !self.matches_node(node) {
+            return false;
+        }
+        if self
+            .filter
+            .min_centrality
+            .is_some_and(|min| graph.centrality(index) < min)
+        {
+            return false;
+        }
+        if self.filter.attributes.contains(&NodeAttribute::Test) && !graph.is_test(node) {
+            return false;
+        }
+        true
+    }
+}
+
+impl ArborGraph {
+    /// Returns the nodes a filter matches, in index order.
+    ///
+    /// Starts from the kind index when the filter names kinds.
+    pub fn filter_nodes(&self, filter: &NodeFilter) -> Result<Vec<NodeId>, FilterError> {
+        let matcher = filter.compile()?;
+        let mut found: Vec<NodeId> = if filter.kinds.is_empty() {
+            self.node_indexes()
+                .filter(|&i| matcher.matches(self, i))
+                .collect()
+        } else {
+            filter
+                .kinds
+                .iter()
+                .flat_map(|&kind| self.nodes_of_kind(kind).iter().copied())
+                .filter(|&i| matcher.matches(self, i))
+                .collect()
+        };
+        found.sort();
+        found.dedup();
+        Ok(found)
+    }
+
+    /// Like [`search`](Self::search), keeping only nodes the matcher accepts.
+    pub fn search_filtered(&self, query: &str, matcher: &NodeMatcher) -> Vec<&CodeNode> {
+        self.search_ranked_filtered(query, matcher)
+            .iter()
+            .filter_map(|hit| self.get(hit.id))
+            .collect()
+    }
+
+    /// Like [`search_ranked`](Self::search_ranked), keeping only nodes the
+    /// matcher accepts.
+    pub fn search_ranked_filtered(&self, query: &str, matcher: &NodeMatcher) -> Vec<SearchHit> {
+        self.search_ranked(query)
+            .into_iter()
+            .filter(|hit| matcher.matches(self, hit.id))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::GraphBuilder;
+    use std::collections::HashMap;
+
+    fn graph() -> ArborGraph {
+        let mut exported = CodeNode::new("fetch", "fetch", NodeKind::Function, "web/api.ts");
+        exported.is_exported = true;
+        exported.is_async = true;
+        let mut public = CodeNode::new("Parser", "Parser", NodeKind::Struct, "src/parse.rs");
+        public.visibility = Visibility::Public;
+        public.docstring = Some("Parses things.".to_string());
+        let mut builder = GraphBuilder::new();
+        builder.add_nodes(vec![
+            exported,
+            public,
+            CodeNode::new("helper", "helper", NodeKind::Function, "src/parse.rs"),
+            CodeNode::new(
+                "test_parse",
+                "test_parse",
+                NodeKind::Function,
+                "tests/parse.rs",
+            ),
+            CodeNode::new("Config", "Config", NodeKind::Class, "app/config.py"),
+        ]);
+        builder.build()
+    }
+
+    fn names(graph: &ArborGraph, filter: &NodeFilter) -> Vec<String> {
+        let mut names: Vec<String> = graph
+            .filter_nodes(filter)
+            .unwrap()
+            .into_iter()
+            .map(|i| graph.get(i).unwrap().name.clone())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_kind_index_tracks_removal() {
+        let mut graph = graph();
+        assert_eq!(graph.find_by_kind(NodeKind::Function).len(), 3);
+        graph.remove_file("src/parse.rs");
+        assert_eq!(graph.find_by_kind(NodeKind::Function).len(), 2);
+        assert!(graph.find_by_kind(NodeKind::Struct).is_empty());
+    }
+
+    #[test]
+    fn test_filters_compose() {
+        let graph = graph();
+        assert_eq!(names(&graph, &NodeFilter::new()).len(), 5);
+
+        let functions = NodeFilter::new().with_kind(NodeKind::Function);
+        assert_eq!(
+            names(&graph, &functions),
+            vec!["fetch", "helper", "test_parse"]
+        );
+        assert_eq!(
+            names(&graph, &functions.clone().with_file("src/**")),
+            vec!["helper"]
+        );
+        assert_eq!(
+            names(
+                &graph,
+                &functions.clone().with_exported(true).with_async(true)
+            ),
+            vec!["fetch"]
+        );
+        assert_eq!(
+            names(&graph, &functions.with_attribute(NodeAttribute::Test)),
+            vec!["test_parse"]
+        );
+
+        let rust_public = NodeFilter::new()
+            .with_language("rust")
+            .with_visibility(Visibility::Public)
+            .with_attribute(NodeAttribute::Documented);
+        assert_eq!(names(&graph, &rust_public), vec!["Parser"]);
+        assert_eq!(
+            names(&graph, &NodeFilter::new().with_file("*.py")),
+            vec!["Config"]
+        );
+    }
+
+    #[test]
+    fn test_centrality_and_errors() {
+        let mut graph = graph();
+        let config = graph
+            .get_index(&graph.find_by_name("Config")[0].id)
+            .unwrap();
+        graph.set_centrality(HashMap::from([(config, 0.9)]));
+        let central = NodeFilter::new().with_min_centrality(0.5);
+        assert_eq!(names(&graph, &central), vec!["Config"]);
+
+        let matcher = central.compile().unwrap();
+        let found: Vec<&str> = graph
+            .search_filtered("Conf", &matcher)
+            .iter()
+            .map(|n| n.name.as_str())
+            .collect();
+        assert_eq!(found, vec!["Config"]);
+
+        assert!(matches!(
+            NodeFilter::new().with_language("cobol").compile(),
+            Err(FilterError::UnknownLanguage(_))
+        ));
+        assert!(matches!(
+            NodeFilter::new().with_file("src/[").compile(),
+            Err(FilterError::Glob { .. })
+        ));
+
+        let parsed: NodeFilter =
+            serde_json::from_str(r#"{"kinds": ["struct"], "async": false}"#).unwrap();
+        assert_eq!(
+            parsed,
+            NodeFilter::new()
+                .with_kind(NodeKind::Struct)
+                .with_async(false)
+        );
+    }
+}
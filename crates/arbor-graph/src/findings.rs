@@ -0,0 +1,566 @@
+//! Findings shared by every analysis.
+//!
+//! Architecture violations, dead code, cycles and complexity issues all
+//! convert into [`Finding`]s, which a [`FindingSet`] writes out as SARIF
+//! 2.1.0 for code scanning or as Checkstyle XML for CI dashboards.
+
+use crate::complexity::{ComplexityMetric, ComplexityReport};
+use crate::conformance::CheckReport;
+use crate::cycles::CycleReport;
+use crate::deadcode::{Confidence, DeadCodeReport};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+const INFORMATION_URI: &str = "https://github.com/Anandb71/arbor";
+
+/// How serious a finding is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Note,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    /// The SARIF `level`.
+    pub fn sarif_level(self) -> &'static str {
+        match self {
+            Self::Note => "note",
+            Self::Warning => "warning",
+            Self::Error => "error",
+        }
+    }
+
+    /// The Checkstyle `severity`.
+    pub fn checkstyle_severity(self) -> &'static str {
+        match self {
+            Self::Note => "info",
+            Self::Warning => "warning",
+            Self::Error => "error",
+        }
+    }
+}
+
+/// A place in the source. Lines and columns are 1-based.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Location {
+    pub file: String,
+    pub line: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub column: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_line: Option<u32>,
+}
+
+impl Location {
+    /// A location from a graph line and 0-based column. Unknown lines
+    /// (0) point at the start of the file.
+    pub fn new(file: impl Into<String>, line: u32, column: Option<u32>) -> Self {
+        Self {
+            file: file.into(),
+            line: line.max(1),
+            column: column.map(|c| c + 1),
+            end_line: None,
+        }
+    }
+
+    pub fn with_end_line(mut self, end_line: u32) -> Self {
+        self.end_line = (end_line > self.line).then_some(end_line);
+        self
+    }
+}
+
+/// A check a finding can come from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FindingRule {
+    /// Stable ID, e.g. `dead-code/symbol`.
+    pub id: String,
+    pub description: String,
+    pub severity: Severity,
+}
+
+impl FindingRule {
+    pub fn new(id: impl Into<String>, description: impl Into<String>, severity: Severity) -> Self {
+        Self {
+            id: id.into(),
+            description: description.into(),
+            severity,
+        }
+    }
+}
+
+/// One problem reported by an analysis.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Finding {
+    pub rule_id: String,
+    pub severity: Severity,
+    pub message: String,
+    pub location: Location,
+    /// Qualified name of the symbol at fault, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,
+    /// Other places involved, such as the hops of a cycle.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub related: Vec<Location>,
+    /// Accepted in a baseline; kept in SARIF as suppressed, left out of
+    /// Checkstyle.
+    #[serde(default)]
+    pub suppressed: bool,
+}
+
+/// An analysis result that can be reported as findings.
+pub trait ToFindings {
+    /// Every rule the analysis can report, found or not.
+    fn rules(&self) -> Vec<FindingRule>;
+
+    fn to_findings(&self) -> Vec<Finding>;
+}
+
+/// Findings from one or more analyses.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FindingSet {
+    pub rules: Vec<FindingRule>,
+    pub findings: Vec<Finding>,
+}
+
+impl FindingSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an analysis's rules and findings.
+    pub fn add(&mut self, report: &impl ToFindings) -> &mut Self {
+        for rule in report.rules() {
+            if !self.rules.iter().any(|r| r.id == rule.id) {
+                self.rules.push(rule);
+            }
+        }
+        self.findings.extend(report.to_findings());
+        self
+    }
+
+    /// Findings that are not suppressed.
+    pub fn active(&self) -> impl Iterator<Item = &Finding> {
+        self.findings.iter().filter(|f| !f.suppressed)
+    }
+
+    /// A SARIF 2.1.0 log with one run.
+    pub fn to_sarif(&self) -> Value {
+        let rules: Vec<Value> = self
+            .rules
+            .iter()
+            .map(|rule| {
+                json!({
+                    "id": rule.id,
+                    "shortDescription": { "text": rule.description },
+                    "defaultConfiguration": { "level": rule.severity.sarif_level() },
+                })
+            })
+            .collect();
+        let results: Vec<Value> = self
+            .findings
+            .iter()
+            .map(|finding| {
+                let mut result = json!({
+                    "ruleId": finding.rule_id,
+                    "level": finding.severity.sarif_level(),
+                    "message": { "text": finding.message },
+                    "locations": [sarif_location(&finding.location, finding.symbol.as_deref())],
+                });
+                if let Some(index) = self.rules.iter().position(|r| r.id == finding.rule_id) {
+                    result["ruleIndex"] = json!(index);
+                }
+                if !finding.related.is_empty() {
+                    result["relatedLocations"] = finding
+                        .related
+                        .iter()
+                        .enumerate()
+                        .map(|(id, location)| {
+                            let mut value = sarif_location(location, None);
+                            value["id"] = json!(id);
+                            value
+                        })
+                        .collect();
+                }
+                if finding.suppressed {
+                    result["suppressions"] = json!([{ "kind": "external" }]);
+                }
+                result
+            })
+            .collect();
+
+        json!({
+            "$schema": SARIF_SCHEMA,
+            "version": "2.1.0",
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": "arbor",
+                        "version": env!("CARGO_PKG_VERSION"),
+                        "informationUri": INFORMATION_URI,
+                        "rules": rules,
+                    }
+                },
+                "results": results,
+            }]
+        })
+    }
+
+    /// A Checkstyle XML report, grouped by file. Suppressed findings are
+    /// left out.
+    pub fn to_checkstyle(&self) -> String {
+        let mut files: Vec<&str> = Vec::new();
+        for finding in self.active() {
+            if !files.contains(&finding.location.file.as_str()) {
+                files.push(&finding.location.file);
+            }
+        }
+        files.sort_unstable();
+
+        let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        out.push_str("<checkstyle version=\"4.3\">\n");
+        for file in files {
+            out.push_str(&format!("  <file name=\"{}\">\n", xml_escape(file)));
+            let mut findings: Vec<&Finding> =
+                self.active().filter(|f| f.location.file == file).collect();
+            findings.sort_by_key(|f| (f.location.line, f.location.column));
+            for finding in findings {
+                out.push_str(&format!("    <error line=\"{}\"", finding.location.line));
+                if let Some(column) = finding.location.column {
+                    out.push_str(&format!(" column=\"{}\"", column));
+                }
+                out.push_str(&format!(
+                    " severity=\"{}\" message=\"{}\" source=\"arbor.{}\"/>\n",
+                    finding.severity.checkstyle_severity(),
+                    xml_escape(&finding.message),
+                    xml_escape(&finding.rule_id),
+                ));
+            }
+            out.push_str("  </file>\n");
+        }
+        out.push_str("</checkstyle>\n");
+        out
+    }
+}
+
+fn sarif_location(location: &Location, symbol: Option<&str>) -> Value {
+    let mut region = json!({ "startLine": location.line });
+    if let Some(column) = location.column {
+        region["startColumn"] = json!(column);
+    }
+    if let Some(end_line) = location.end_line {
+        region["endLine"] = json!(end_line);
+    }
+    let mut value = json!({
+        "physicalLocation": {
+            "artifactLocation": { "uri": location.file },
+            "region": region,
+        }
+    });
+    if let Some(symbol) = symbol {
+        value["logicalLocations"] = json!([{ "fullyQualifiedName": symbol }]);
+    }
+    value
+}
+
+fn xml_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            '\n' => out.push_str("&#10;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+impl ToFindings for CheckReport {
+    fn rules(&self) -> Vec<FindingRule> {
+        let mut ids: Vec<&str> = self.violations.iter().map(|v| v.rule.as_str()).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        ids.into_iter()
+            .map(|id| {
+                FindingRule::new(
+                    format!("architecture/{}", id),
+                    format!("Architecture rule '{}'", id),
+                    Severity::Error,
+                )
+            })
+            .collect()
+    }
+
+    fn to_findings(&self) -> Vec<Finding> {
+        self.violations
+            .iter()
+            .map(|v| Finding {
+                rule_id: format!("architecture/{}", v.rule),
+                severity: Severity::Error,
+                message: v.message.clone(),
+                location: Location::new(&v.file, v.line, v.column),
+                symbol: Some(v.source.clone()),
+                related: Vec::new(),
+                suppressed: v.baselined,
+            })
+            .collect()
+    }
+}
+
+impl ToFindings for DeadCodeReport {
+    fn rules(&self) -> Vec<FindingRule> {
+        vec![
+            FindingRule::new(
+                "dead-code/symbol",
+                "Symbol unreachable from any entry point",
+                Severity::Warning,
+            ),
+            FindingRule::new(
+                "dead-code/file",
+                "File with no reachable symbols",
+                Severity::Warning,
+            ),
+        ]
+    }
+
+    fn to_findings(&self) -> Vec<Finding> {
+        let severity = |confidence| match confidence {
+            Confidence::High => Severity::Warning,
+            _ => Severity::Note,
+        };
+        let mut findings = Vec::new();
+        for file in &self.files {
+            if file.whole_file {
+                findings.push(Finding {
+                    rule_id: "dead-code/file".to_string(),
+                    severity: severity(file.confidence),
+                    message: format!("{} is unused ({} confidence)", file.file, file.confidence),
+                    location: Location::new(&file.file, 1, None),
+                    symbol: None,
+                    related: Vec::new(),
+                    suppressed: false,
+                });
+            }
+            for symbol in &file.symbols {
+                let node = &symbol.node;
+                findings.push(Finding {
+                    rule_id: "dead-code/symbol".to_string(),
+                    severity: severity(symbol.confidence),
+                    message: format!(
+                        "{} {} is unused: {} ({} confidence)",
+                        node.kind, node.name, symbol.reason, symbol.confidence
+                    ),
+                    location: Location::new(&node.file, node.line_start, Some(node.column))
+                        .with_end_line(node.line_end),
+                    symbol: Some(node.qualified_name.clone()),
+                    related: Vec::new(),
+                    suppressed: false,
+                });
+            }
+        }
+        findings
+    }
+}
+
+impl ToFindings for CycleReport {
+    fn rules(&self) -> Vec<FindingRule> {
+        vec![FindingRule::new(
+            format!("cycle/{}", self.level),
+            format!("Dependency cycle between {}s", self.level),
+            Severity::Warning,
+        )]
+    }
+
+    fn to_findings(&self) -> Vec<Finding> {
+        self.cycles
+            .iter()
+            .map(|cycle| {
+                let hops: Vec<Location> = cycle
+                    .edges
+                    .iter()
+                    .map(|edge| Location::new(&edge.file, edge.line, None))
+                    .collect();
+                let location = hops.first().cloned().unwrap_or_else(|| {
+                    Location::new(cycle.members.first().cloned().unwrap_or_default(), 1, None)
+                });
+                Finding {
+                    rule_id: format!("cycle/{}", self.level),
+                    severity: Severity::Warning,
+                    message: format!(
+                        "{} cycle of {}: {}",
+                        self.level,
+                        cycle.members.len(),
+                        cycle.path.join(" → ")
+                    ),
+                    location,
+                    symbol: None,
+                    related: hops,
+                    suppressed: false,
+                }
+            })
+            .collect()
+    }
+}
+
+impl ToFindings for ComplexityReport {
+    fn rules(&self) -> Vec<FindingRule> {
+        vec![
+            FindingRule::new(
+                "complexity/lines",
+                "Function longer than the line limit",
+                Severity::Warning,
+            ),
+            FindingRule::new(
+                "complexity/parameters",
+                "Function with more parameters than the limit",
+                Severity::Warning,
+            ),
+            FindingRule::new(
+                "complexity/fan-out",
+                "Function calling more functions than the limit",
+                Severity::Warning,
+            ),
+        ]
+    }
+
+    fn to_findings(&self) -> Vec<Finding> {
+        self.issues
+            .iter()
+            .map(|issue| {
+                let (id, what) = match issue.metric {
+                    ComplexityMetric::Lines => ("lines", "lines long"),
+                    ComplexityMetric::Parameters => ("parameters", "parameters"),
+                    ComplexityMetric::FanOut => ("fan-out", "distinct callees"),
+                };
+                let node = &issue.node;
+                Finding {
+                    rule_id: format!("complexity/{}", id),
+                    severity: Severity::Warning,
+                    message: format!(
+                        "{} has {} {} (limit {})",
+                        node.name, issue.value, what, issue.limit
+                    ),
+                    location: Location::new(&node.file, node.line_start, Some(node.column))
+                        .with_end_line(node.line_end),
+                    symbol: Some(node.qualified_name.clone()),
+                    related: Vec::new(),
+                    suppressed: false,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Fixed(Vec<Finding>);
+
+    impl ToFindings for Fixed {
+        fn rules(&self) -> Vec<FindingRule> {
+            vec![
+                FindingRule::new("test/a", "A", Severity::Error),
+                FindingRule::new("test/b", "B", Severity::Note),
+            ]
+        }
+
+        fn to_findings(&self) -> Vec<Finding> {
+            self.0.clone()
+        }
+    }
+
+    fn finding(rule: &str, file: &str, line: u32, suppressed: bool) -> Finding {
+        Finding {
+            rule_id: rule.to_string(),
+            severity: Severity::Error,
+            message: format!("<{}> & \"{}\"", rule, line),
+            location: Location::new(file, line, Some(4)).with_end_line(line + 2),
+            symbol: Some("mod::f".to_string()),
+            related: vec![Location::new("b.rs", 9, None)],
+            suppressed,
+        }
+    }
+
+    fn sample() -> FindingSet {
+        let mut set = FindingSet::new();
+        set.add(&Fixed(vec![
+            finding("test/b", "src/a.rs", 7, false),
+            finding("test/a", "src/a.rs", 3, true),
+        ]));
+        set.add(&Fixed(vec![finding("test/a", "src/b.rs", 0, false)]));
+        set
+    }
+
+    #[test]
+    fn test_sarif_output() {
+        let sarif = sample().to_sarif();
+        assert_eq!(sarif["version"], "2.1.0");
+        let run = &sarif["runs"][0];
+        assert_eq!(run["tool"]["driver"]["name"], "arbor");
+        assert_eq!(run["tool"]["driver"]["rules"].as_array().unwrap().len(), 2);
+
+        let results = run["results"].as_array().unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0]["ruleId"], "test/b");
+        assert_eq!(results[0]["ruleIndex"], 1);
+        assert_eq!(results[0]["level"], "error");
+        let region = &results[0]["locations"][0]["physicalLocation"]["region"];
+        assert_eq!(region["startLine"], 7);
+        assert_eq!(region["startColumn"], 5);
+        assert_eq!(region["endLine"], 9);
+        assert_eq!(
+            results[0]["relatedLocations"][0]["physicalLocation"]["artifactLocation"]["uri"],
+            "b.rs"
+        );
+        assert!(results[0].get("suppressions").is_none());
+        assert_eq!(results[1]["suppressions"][0]["kind"], "external");
+        assert_eq!(
+            results[2]["locations"][0]["physicalLocation"]["region"]["startLine"],
+            1
+        );
+
+        // Column 0 is the first column, not an unknown one
+        let mut set = FindingSet::new();
+        set.add(&CheckReport {
+            rules: 1,
+            layers: 0,
+            dependencies: 1,
+            violations: vec![crate::conformance::Violation {
+                rule: "no-db".to_string(),
+                message: "no".to_string(),
+                source: "api::f".to_string(),
+                target: "db".to_string(),
+                kind: crate::edge::EdgeKind::Imports,
+                from_layer: None,
+                to_layer: None,
+                file: "src/api.rs".to_string(),
+                line: 0,
+                column: Some(0),
+                baselined: false,
+            }],
+            fixed: Vec::new(),
+        });
+        let sarif = set.to_sarif();
+        let region = &sarif["runs"][0]["results"][0]["locations"][0]["physicalLocation"]["region"];
+        assert_eq!(region["startColumn"], 1);
+    }
+
+    #[test]
+    fn test_checkstyle_output() {
+        let xml = sample().to_checkstyle();
+        assert!(xml.starts_with("<?xml"));
+        assert_eq!(xml.matches("<file ").count(), 2);
+        assert_eq!(xml.matches("<error ").count(), 2);
+        assert!(xml.contains(
+            r#"<error line="7" column="5" severity="error" message="&lt;test/b&gt; &amp; &quot;7&quot;" source="arbor.test/b"/>"#
+        ));
+        assert!(!xml.contains(r#"line="3""#));
+    }
+}
@@ -3,17 +3,28 @@
 //! The ArborGraph wraps petgraph and adds indexes for fast lookups.
 //! It's the central data structure that everything else works with.
 
+use crate::body_index::BodyIndex;
+use crate::clusters::Cluster;
 use crate::edge::{Edge, EdgeKind, GraphEdge};
-use crate::search_index::SearchIndex;
-use arbor_core::CodeNode;
-use petgraph::graph::{DiGraph, NodeIndex};
-use petgraph::visit::EdgeRef; // For edge_references
+use crate::layout::directory_of;
+use crate::search_index::{SearchHit, SearchIndex};
+use crate::tokens::TokenCounts;
+use arbor_core::{normalize_path, CodeNode, EncodingPolicy, NodeKind, WorkspaceRoot};
+use petgraph::stable_graph::{NodeIndex, StableDiGraph};
+use petgraph::visit::{EdgeRef, IntoEdgeReferences}; // For edge_references
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 
 /// Unique identifier for a node in the graph.
 pub type NodeId = NodeIndex;
 
+/// How much a centrality of 1.0 multiplies a search score by, minus one.
+pub(crate) const CENTRALITY_BOOST: f64 = 0.5;
+
+/// Search score multiplier for imports and exports.
+const RESTATEMENT_PENALTY: f64 = 0.5;
+
 /// The code relationship graph.
 ///
 /// This is the heart of Arbor. It stores all code entities as nodes
@@ -21,7 +32,7 @@ pub type NodeId = NodeIndex;
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ArborGraph {
     /// The underlying petgraph graph.
-    pub(crate) graph: DiGraph<CodeNode, Edge>,
+    pub(crate) graph: StableDiGraph<CodeNode, Edge>,
 
     /// Maps string IDs to graph node indexes.
     id_index: HashMap<String, NodeId>,
@@ -29,15 +40,61 @@ pub struct ArborGraph {
     /// Maps node names to node IDs (for search).
     name_index: HashMap<String, Vec<NodeId>>,
 
+    /// Maps qualified names to node IDs (for reference resolution).
+    #[serde(default)]
+    qualified_index: HashMap<String, Vec<NodeId>>,
+
+    /// Maps referenced names to the nodes referencing them.
+    #[serde(default)]
+    reference_index: HashMap<String, Vec<NodeId>>,
+
     /// Maps file paths to node IDs (for incremental updates).
     file_index: HashMap<String, Vec<NodeId>>,
 
+    /// Maps node kinds to node IDs (for filtering by type).
+    #[serde(default)]
+    kind_index: HashMap<NodeKind, Vec<NodeId>>,
+
     /// Centrality scores for ranking.
     centrality: HashMap<NodeId, f64>,
 
     /// Search index for fast substring queries.
     #[serde(skip)]
     search_index: SearchIndex,
+
+    /// File text for full-text search, when enabled. Not persisted.
+    #[serde(skip)]
+    pub(crate) bodies: BodyIndex,
+
+    /// Token counter and cached counts for context budgets.
+    #[serde(skip)]
+    pub(crate) tokens: TokenCounts,
+
+    /// Project root that node file paths are relative to.
+    #[serde(default)]
+    root: Option<PathBuf>,
+
+    /// How files read back from disk are decoded, as when indexing.
+    #[serde(default)]
+    encoding: EncodingPolicy,
+
+    /// Encoding policies of workspace roots, by namespace. Roots not
+    /// listed use `encoding`.
+    #[serde(default)]
+    root_encodings: HashMap<String, EncodingPolicy>,
+
+    /// Workspace roots, when the graph spans several. Node paths start
+    /// with their root's namespace.
+    #[serde(default)]
+    roots: Vec<WorkspaceRoot>,
+
+    /// Architecture clusters from the last `assign_clusters`.
+    #[serde(default)]
+    clusters: Vec<Cluster>,
+
+    /// Maps file paths to cluster IDs, so re-parsed nodes keep theirs.
+    #[serde(default)]
+    file_clusters: HashMap<String, String>,
 }
 
 impl Default for ArborGraph {
@@ -50,30 +107,160 @@ impl ArborGraph {
     /// Creates a new empty graph.
     pub fn new() -> Self {
         Self {
-            graph: DiGraph::new(),
+            graph: StableDiGraph::new(),
             id_index: HashMap::new(),
             name_index: HashMap::new(),
+            qualified_index: HashMap::new(),
+            reference_index: HashMap::new(),
             file_index: HashMap::new(),
+            kind_index: HashMap::new(),
             centrality: HashMap::new(),
             search_index: SearchIndex::new(),
+            bodies: BodyIndex::default(),
+            tokens: TokenCounts::default(),
+            root: None,
+            encoding: EncodingPolicy::default(),
+            root_encodings: HashMap::new(),
+            roots: Vec::new(),
+            clusters: Vec::new(),
+            file_clusters: HashMap::new(),
         }
     }
 
+    /// Returns the project root node paths are relative to, if known.
+    pub fn root(&self) -> Option<&Path> {
+        self.root.as_deref()
+    }
+
+    /// Records the project root node paths are relative to.
+    pub fn set_root(&mut self, root: PathBuf) {
+        self.root = Some(root);
+    }
+
+    /// Returns the encoding policy files are decoded with.
+    pub fn encoding(&self) -> EncodingPolicy {
+        self.encoding
+    }
+
+    /// Decodes files read back from disk with `policy`, the one they
+    /// were indexed with, so byte offsets line up.
+    pub fn set_encoding(&mut self, policy: EncodingPolicy) {
+        self.encoding = policy;
+    }
+
+    /// Decodes files from the workspace root `namespace` with `policy`.
+    pub fn set_root_encoding(&mut self, namespace: &str, policy: EncodingPolicy) {
+        self.root_encodings.insert(namespace.to_string(), policy);
+    }
+
+    /// Returns the encoding policy a stored file was indexed with.
+    pub fn encoding_for(&self, file: &str) -> EncodingPolicy {
+        self.roots
+            .iter()
+            .find(|ws_root| strip_dir(file, &ws_root.namespace).is_some())
+            .and_then(|ws_root| self.root_encodings.get(&ws_root.namespace))
+            .copied()
+            .unwrap_or(self.encoding)
+    }
+
+    /// Returns the workspace roots, empty for a single-root graph.
+    pub fn roots(&self) -> &[WorkspaceRoot] {
+        &self.roots
+    }
+
+    /// Records the workspace roots. Call before resolving references, since
+    /// `depends_on` decides which cross-root edges are allowed.
+    pub fn set_roots(&mut self, roots: Vec<WorkspaceRoot>) {
+        self.roots = roots;
+    }
+
+    /// Maps a node's stored file path back to an absolute path.
+    ///
+    /// Returns the stored path unchanged if no root is set.
+    pub fn absolute_path(&self, file: &str) -> PathBuf {
+        if Path::new(file).is_absolute() {
+            return PathBuf::from(file);
+        }
+        for ws_root in &self.roots {
+            if let Some(rel) = strip_dir(file, &ws_root.namespace) {
+                return ws_root.path.join(rel);
+            }
+        }
+        match &self.root {
+            Some(root) => root.join(file),
+            None => PathBuf::from(file),
+        }
+    }
+
+    /// Converts a path as a client might send it (absolute, `./`-prefixed,
+    /// backslashes) into the form used in `file_index`.
+    pub fn file_key(&self, file: &str) -> String {
+        let normalized = normalize_path(file);
+        for ws_root in &self.roots {
+            let dir = normalize_path(&ws_root.path.to_string_lossy());
+            if let Some(rel) = strip_dir(&normalized, &dir) {
+                return format!("{}/{}", ws_root.namespace, rel);
+            }
+        }
+        if let Some(root) = &self.root {
+            let root = normalize_path(&root.to_string_lossy());
+            if let Some(rel) = strip_dir(&normalized, &root) {
+                return rel.to_string();
+            }
+        }
+        normalized
+    }
+
+    /// Finds a node by ID, or by name within an optional workspace root.
+    pub fn find_node(&self, id_or_name: &str, namespace: Option<&str>) -> Option<NodeId> {
+        if let Some(index) = self.get_index(id_or_name) {
+            return Some(index);
+        }
+        self.find_by_name(id_or_name)
+            .into_iter()
+            .find(|node| node.in_root(namespace))
+            .and_then(|node| self.get_index(&node.id))
+    }
+
     /// Adds a code node to the graph.
     ///
     /// Returns the node's index for adding edges later.
-    pub fn add_node(&mut self, node: CodeNode) -> NodeId {
+    pub fn add_node(&mut self, mut node: CodeNode) -> NodeId {
+        match &node.cluster {
+            Some(cluster) => {
+                self.file_clusters
+                    .insert(node.file.clone(), cluster.clone());
+            }
+            None => node.cluster = self.file_clusters.get(&node.file).cloned(),
+        }
+
         let id = node.id.clone();
         let name = node.name.clone();
+        let qualified = node.qualified_name.clone();
+        let references = node.references.clone();
         let file = node.file.clone();
+        let kind = node.kind;
 
         let index = self.graph.add_node(node);
 
         // Update indexes
         self.id_index.insert(id, index);
         self.name_index.entry(name.clone()).or_default().push(index);
+        if !qualified.is_empty() {
+            self.qualified_index
+                .entry(qualified)
+                .or_default()
+                .push(index);
+        }
+        for reference in references {
+            let referrers = self.reference_index.entry(reference).or_default();
+            if !referrers.contains(&index) {
+                referrers.push(index);
+            }
+        }
         self.file_index.entry(file).or_default().push(index);
-        self.search_index.insert(&name, index);
+        self.kind_index.entry(kind).or_default().push(index);
+        self.search_index.insert_node(&self.graph[index], index);
 
         index
     }
@@ -108,9 +295,11 @@ impl ArborGraph {
     }
 
     /// Finds all nodes in a file.
+    ///
+    /// Accepts absolute paths under the root as well as stored ones.
     pub fn find_by_file(&self, file: &str) -> Vec<&CodeNode> {
         self.file_index
-            .get(file)
+            .get(&self.file_key(file))
             .map(|indexes| {
                 indexes
                     .iter()
@@ -120,18 +309,91 @@ impl ArborGraph {
             .unwrap_or_default()
     }
 
-    /// Searches for nodes whose name contains the query.
+    /// Lists the indexed files inside a directory, at any depth.
+    pub fn files_under(&self, dir: &str) -> Vec<String> {
+        let key = self.file_key(dir);
+        let key = key.trim_end_matches('/');
+        let mut files: Vec<String> = self
+            .file_index
+            .keys()
+            .filter(|file| strip_dir(file, key).is_some())
+            .cloned()
+            .collect();
+        files.sort();
+        files
+    }
+
+    /// Finds all nodes of a kind.
+    pub fn find_by_kind(&self, kind: NodeKind) -> Vec<&CodeNode> {
+        self.nodes_of_kind(kind)
+            .iter()
+            .filter_map(|idx| self.graph.node_weight(*idx))
+            .collect()
+    }
+
+    /// Indexes of all nodes of a kind.
+    pub fn nodes_of_kind(&self, kind: NodeKind) -> &[NodeId] {
+        self.kind_index.get(&kind).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Indexes of nodes with this exact name.
+    pub(crate) fn nodes_named(&self, name: &str) -> &[NodeId] {
+        self.name_index.get(name).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Indexes of nodes with this exact qualified name.
+    pub(crate) fn nodes_qualified(&self, qualified_name: &str) -> &[NodeId] {
+        self.qualified_index
+            .get(qualified_name)
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+    }
+
+    /// Indexes of nodes in a file, accepting the same paths as `find_by_file`.
+    pub(crate) fn nodes_in_file(&self, file: &str) -> &[NodeId] {
+        self.file_index
+            .get(&self.file_key(file))
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+    }
+
+    /// Searches for nodes matching the query, most relevant first.
     ///
-    /// Uses the search index for fast O(k) lookups where k is the number of matches,
-    /// instead of O(n) linear scan over all nodes.
+    /// See [`search_ranked`](Self::search_ranked) for how matches are
+    /// scored.
     pub fn search(&self, query: &str) -> Vec<&CodeNode> {
-        self.search_index
-            .search(query)
+        self.search_ranked(query)
             .iter()
-            .filter_map(|id| self.graph.node_weight(*id))
+            .filter_map(|hit| self.graph.node_weight(hit.id))
             .collect()
     }
 
+    /// Scores nodes against the query, best first.
+    ///
+    /// Text relevance comes from the search index: identifier tokens,
+    /// prefixes, acronyms and typos across name, qualified name,
+    /// signature and docstring. Centrality then boosts it by up to half
+    /// again, and imports and exports count half. Nodes from generated
+    /// files come last so they don't crowd out hand-written matches.
+    pub fn search_ranked(&self, query: &str) -> Vec<SearchHit> {
+        let mut hits = self.search_index.rank(query);
+        for hit in &mut hits {
+            hit.score *= 1.0 + CENTRALITY_BOOST * self.centrality(hit.id);
+            // Imports and exports restate a symbol defined elsewhere
+            if matches!(self.graph[hit.id].kind, NodeKind::Import | NodeKind::Export) {
+                hit.score *= RESTATEMENT_PENALTY;
+            }
+        }
+        hits.sort_by(|a, b| {
+            let generated = |hit: &SearchHit| self.graph[hit.id].generated;
+            generated(a)
+                .cmp(&generated(b))
+                .then(b.score.total_cmp(&a.score))
+                .then(a.id.cmp(&b.id))
+        });
+        hits
+    }
+
     /// Gets nodes that call the given node.
     pub fn get_callers(&self, index: NodeId) -> Vec<&CodeNode> {
         self.graph
@@ -196,8 +458,12 @@ impl ArborGraph {
     }
 
     /// Removes all nodes from a file. Used for incremental updates.
+    ///
+    /// Accepts absolute paths under the root as well as stored ones.
     pub fn remove_file(&mut self, file: &str) {
-        if let Some(indexes) = self.file_index.remove(file) {
+        let file = self.file_key(file);
+        self.bodies.remove(&file);
+        if let Some(indexes) = self.file_index.remove(&file) {
             for index in indexes {
                 if let Some(node) = self.graph.node_weight(index) {
                     // Remove from name index
@@ -205,16 +471,187 @@ impl ArborGraph {
                     if let Some(name_list) = self.name_index.get_mut(&name) {
                         name_list.retain(|&idx| idx != index);
                     }
+                    if let Some(list) = self.qualified_index.get_mut(&node.qualified_name) {
+                        list.retain(|&idx| idx != index);
+                    }
+                    if let Some(list) = self.kind_index.get_mut(&node.kind) {
+                        list.retain(|&idx| idx != index);
+                    }
+                    for reference in &node.references {
+                        if let Some(list) = self.reference_index.get_mut(reference) {
+                            list.retain(|&idx| idx != index);
+                        }
+                    }
                     // Remove from id index
                     self.id_index.remove(&node.id);
                     // Remove from search index
                     self.search_index.remove(&name, index);
                 }
+                self.centrality.remove(&index);
                 self.graph.remove_node(index);
             }
         }
     }
 
+    /// Resolves a referenced name to the node it most likely means.
+    ///
+    /// Qualified names win over simple names. When several nodes match,
+    /// the most recently added one is used, like `GraphBuilder` does.
+    pub fn resolve_reference(&self, reference: &str) -> Option<NodeId> {
+        self.qualified_index
+            .get(reference)
+            .and_then(|list| list.last())
+            .or_else(|| self.name_index.get(reference).and_then(|list| list.last()))
+            .copied()
+    }
+
+    /// Resolves a reference made by `from`, honouring workspace roots.
+    ///
+    /// Targets in the same root win. Other roots are only considered if the
+    /// source root's `depends_on` allows them. Without workspace roots this
+    /// is [`resolve_reference`](Self::resolve_reference).
+    pub fn resolve_reference_from(&self, from: NodeId, reference: &str) -> Option<NodeId> {
+        if self.roots.is_empty() {
+            return self.resolve_reference(reference);
+        }
+        let source = self.graph.node_weight(from)?.root.as_deref();
+        let source_root = source.and_then(|ns| self.roots.iter().find(|r| r.namespace == ns));
+
+        [
+            self.qualified_index.get(reference),
+            self.name_index.get(reference),
+        ]
+        .into_iter()
+        .flatten()
+        .find_map(|candidates| {
+            let mut visible = None;
+            for &index in candidates.iter().rev() {
+                let Some(node) = self.graph.node_weight(index) else {
+                    continue;
+                };
+                let target = node.root.as_deref();
+                if target == source {
+                    return Some(index);
+                }
+                let allowed = match (source_root, target) {
+                    (Some(root), Some(ns)) => root.can_see(ns),
+                    _ => true,
+                };
+                if allowed && visible.is_none() {
+                    visible = Some(index);
+                }
+            }
+            visible
+        })
+    }
+
+    /// Returns the nodes that reference any of the given names.
+    pub fn find_referencing(&self, names: &HashSet<String>) -> Vec<NodeId> {
+        let mut result: Vec<NodeId> = names
+            .iter()
+            .filter_map(|name| self.reference_index.get(name))
+            .flatten()
+            .copied()
+            .collect();
+        result.sort();
+        result.dedup();
+        result
+    }
+
+    /// Re-resolves a node's references into edges.
+    ///
+    /// Drops the node's outgoing edges that came from its references and
+    /// links every reference that resolves. An edge to a target that was
+    /// linked before keeps its kind; new ones are calls. Returns the
+    /// previous targets.
+    pub fn relink(&mut self, index: NodeId) -> Vec<NodeId> {
+        let Some(node) = self.graph.node_weight(index) else {
+            return Vec::new();
+        };
+        let references = node.references.clone();
+        let referenced: HashSet<&str> = references.iter().map(String::as_str).collect();
+
+        let old_edges: Vec<_> = self
+            .graph
+            .edges_directed(index, petgraph::Direction::Outgoing)
+            .filter(|e| {
+                e.weight().kind == EdgeKind::Calls
+                    || self.graph.node_weight(e.target()).is_some_and(|target| {
+                        referenced.contains(target.name.as_str())
+                            || referenced.contains(target.qualified_name.as_str())
+                    })
+            })
+            .map(|e| (e.id(), e.target(), e.weight().kind))
+            .collect();
+        let previous = old_edges.iter().map(|(_, target, _)| *target).collect();
+        let mut kinds: HashMap<NodeId, EdgeKind> = HashMap::new();
+        for (edge, target, kind) in old_edges {
+            if kind != EdgeKind::Calls {
+                kinds.insert(target, kind);
+            }
+            self.graph.remove_edge(edge);
+        }
+
+        for reference in references {
+            if let Some(target) = self.resolve_reference_from(index, &reference) {
+                if target != index {
+                    let kind = kinds.get(&target).copied().unwrap_or(EdgeKind::Calls);
+                    self.graph.add_edge(index, target, Edge::new(kind));
+                }
+            }
+        }
+
+        previous
+    }
+
+    /// Returns the edges touching a file's nodes, other than calls, as
+    /// (source ID, target ID, edge).
+    ///
+    /// `relink` only rebuilds calls from references, so save these before
+    /// `remove_file` and put them back with `restore_edges`.
+    pub fn file_edges(&self, file: &str) -> Vec<(String, String, Edge)> {
+        let mut seen = HashSet::new();
+        let mut edges = Vec::new();
+        for &index in self.nodes_in_file(file) {
+            let touching = self
+                .graph
+                .edges_directed(index, petgraph::Direction::Outgoing)
+                .chain(
+                    self.graph
+                        .edges_directed(index, petgraph::Direction::Incoming),
+                );
+            for edge in touching {
+                if edge.weight().kind == EdgeKind::Calls || !seen.insert(edge.id()) {
+                    continue;
+                }
+                let (Some(source), Some(target)) = (
+                    self.graph.node_weight(edge.source()),
+                    self.graph.node_weight(edge.target()),
+                ) else {
+                    continue;
+                };
+                edges.push((source.id.clone(), target.id.clone(), edge.weight().clone()));
+            }
+        }
+        edges
+    }
+
+    /// Re-adds edges saved by `file_edges` whose ends both still exist.
+    pub fn restore_edges(&mut self, edges: Vec<(String, String, Edge)>) {
+        for (source, target, edge) in edges {
+            let (Some(from), Some(to)) = (self.get_index(&source), self.get_index(&target)) else {
+                continue;
+            };
+            let exists = self
+                .graph
+                .edges_connecting(from, to)
+                .any(|e| e.weight().kind == edge.kind);
+            if !exists {
+                self.graph.add_edge(from, to, edge);
+            }
+        }
+    }
+
     /// Gets the centrality score for a node.
     pub fn centrality(&self, index: NodeId) -> f64 {
         self.centrality.get(&index).copied().unwrap_or(0.0)
@@ -225,6 +662,112 @@ impl ArborGraph {
         self.centrality = scores;
     }
 
+    /// Returns the architecture clusters, empty until assigned.
+    pub fn clusters(&self) -> &[Cluster] {
+        &self.clusters
+    }
+
+    /// Records clusters and moves every node into its file's cluster.
+    pub(crate) fn set_clusters(&mut self, clusters: Vec<Cluster>) {
+        self.file_clusters = clusters
+            .iter()
+            .flat_map(|c| c.files.iter().map(|f| (f.clone(), c.id.clone())))
+            .collect();
+        for node in self.graph.node_weights_mut() {
+            node.cluster = self.file_clusters.get(&node.file).cloned();
+        }
+        self.clusters = clusters;
+    }
+
+    /// Brings assigned clusters up to date after incremental changes
+    /// without re-running Louvain. Files that are gone leave their
+    /// cluster; a new file joins the cluster it has the most edges to, or
+    /// else the one with the most files in its nearest directory. Returns
+    /// true if any cluster changed.
+    pub fn refresh_clusters(&mut self) -> bool {
+        if self.clusters.is_empty() {
+            return false;
+        }
+
+        let file_index = &self.file_index;
+        self.file_clusters
+            .retain(|file, _| file_index.contains_key(file));
+        let mut new_files: Vec<String> = file_index
+            .keys()
+            .filter(|file| !self.file_clusters.contains_key(*file))
+            .cloned()
+            .collect();
+        new_files.sort();
+        for file in new_files {
+            if let Some(id) = self.cluster_for_new_file(&file) {
+                for &index in &self.file_index[&file] {
+                    if let Some(node) = self.graph.node_weight_mut(index) {
+                        node.cluster = Some(id.clone());
+                    }
+                }
+                self.file_clusters.insert(file, id);
+            }
+        }
+
+        let mut members: HashMap<&str, Vec<String>> = HashMap::new();
+        for (file, id) in &self.file_clusters {
+            members.entry(id).or_default().push(file.clone());
+        }
+        let mut changed = false;
+        for cluster in &mut self.clusters {
+            let mut files = members.remove(cluster.id.as_str()).unwrap_or_default();
+            files.sort();
+            let size = files.iter().map(|f| self.file_index[f].len()).sum();
+            if files != cluster.files || size != cluster.size {
+                cluster.files = files;
+                cluster.size = size;
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// Picks the cluster for a file indexed after `assign_clusters`.
+    fn cluster_for_new_file(&self, file: &str) -> Option<String> {
+        let mut links: HashMap<&str, usize> = HashMap::new();
+        for &index in self.nodes_in_file(file) {
+            for neighbor in self.graph.neighbors_undirected(index) {
+                let cluster = self
+                    .get(neighbor)
+                    .and_then(|n| self.file_clusters.get(&n.file));
+                if let Some(cluster) = cluster {
+                    *links.entry(cluster).or_default() += 1;
+                }
+            }
+        }
+        let heaviest = links
+            .into_iter()
+            .max_by(|(a, x), (b, y)| x.cmp(y).then_with(|| b.cmp(a)));
+        if let Some((id, _)) = heaviest {
+            return Some(id.to_string());
+        }
+
+        let mut directory = directory_of(file);
+        loop {
+            let mut counts: HashMap<&str, usize> = HashMap::new();
+            for (member, id) in &self.file_clusters {
+                if directory == "." || strip_dir(member, directory).is_some() {
+                    *counts.entry(id).or_default() += 1;
+                }
+            }
+            let nearest = counts
+                .into_iter()
+                .max_by(|(a, x), (b, y)| x.cmp(y).then_with(|| b.cmp(a)));
+            if let Some((id, _)) = nearest {
+                return Some(id.to_string());
+            }
+            if directory == "." {
+                return None;
+            }
+            directory = directory_of(directory);
+        }
+    }
+
     /// Returns the number of nodes.
     pub fn node_count(&self) -> usize {
         self.graph.node_count()
@@ -320,3 +863,8 @@ impl ArborGraph {
         }
     }
 }
+
+/// Strips `dir/` from the front of a slash-separated path.
+fn strip_dir<'a>(path: &'a str, dir: &str) -> Option<&'a str> {
+    path.strip_prefix(dir)?.strip_prefix('/')
+}
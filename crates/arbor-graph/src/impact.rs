@@ -188,8 +188,10 @@ impl ArborGraph {
                         file: String::new(),
                         line_start: 0,
                         line_end: 0,
+                        column: 0,
                         signature: None,
                         centrality: 0.0,
+                        score: None,
                     },
                     upstream: Vec::new(),
                     downstream: Vec::new(),
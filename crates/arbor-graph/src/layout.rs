@@ -0,0 +1,57 @@
+//! Where a file sits in a project: its directory and its package.
+//!
+//! Cycles, metrics, clusters and collapsed graphs all group files this
+//! way, so a "package" means the same thing in every report.
+
+/// Directory names that mark the source root of a package.
+const SOURCE_ROOTS: [&str; 4] = ["src", "lib", "pkg", "internal"];
+
+/// The directory part of a file path, `.` for top-level files.
+pub(crate) fn directory_of(file: &str) -> &str {
+    match file.rsplit_once('/') {
+        Some((dir, _)) => dir,
+        None => ".",
+    }
+}
+
+/// The directory above the first source root, like a crate, npm or Go
+/// module; otherwise the top-level directory, `.` for top-level files.
+pub(crate) fn package_of(file: &str) -> &str {
+    let mut offset = 0;
+    for segment in file.split('/') {
+        if offset > 0 && SOURCE_ROOTS.contains(&segment) {
+            return &file[..offset - 1];
+        }
+        offset += segment.len() + 1;
+    }
+    if SOURCE_ROOTS
+        .iter()
+        .any(|root| file.starts_with(&format!("{}/", root)))
+    {
+        return ".";
+    }
+    match file.split_once('/') {
+        Some((top, _)) => top,
+        None => ".",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_directories_and_packages() {
+        assert_eq!(directory_of("app/users/models.py"), "app/users");
+        assert_eq!(directory_of("setup.py"), ".");
+
+        assert_eq!(
+            package_of("crates/arbor-graph/src/lib.rs"),
+            "crates/arbor-graph"
+        );
+        assert_eq!(package_of("src/ui/view.py"), ".");
+        assert_eq!(package_of("scripts/build.py"), "scripts");
+        assert_eq!(package_of("app/users/models.py"), "app");
+        assert_eq!(package_of("setup.py"), ".");
+    }
+}
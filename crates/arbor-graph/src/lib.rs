@@ -27,26 +27,75 @@
 //! let matches = graph.find_by_name("validate");
 //! ```
 
+mod body_index;
 mod builder;
+mod change_impact;
+mod clusters;
+mod complexity;
+mod conformance;
+mod cycles;
+mod deadcode;
+mod diff;
 mod edge;
+mod filter;
+mod findings;
 mod graph;
 mod impact;
+mod layout;
+mod metrics;
+mod patch;
 mod query;
+mod quotient;
 mod ranking;
+mod resolve;
 mod search_index;
 mod slice;
+mod task;
+mod testing;
+mod tokens;
 
+pub mod ql;
 pub mod store;
 pub mod symbol_table;
 
-pub use search_index::SearchIndex;
+pub use search_index::{identifier_tokens, SearchHit, SearchIndex};
 
+pub use body_index::{
+    BodyHit, BodyMatch, BodyQuery, BodyQueryError, BodyScope, BodySearchResult, TextRegion,
+};
 pub use builder::GraphBuilder;
+pub use change_impact::{ChangeImpact, ChangedFile, ChangedSymbol};
+pub use clusters::{Cluster, ClusterEdge, ClusterOptions, ClusterReport};
+pub use complexity::{ComplexityIssue, ComplexityMetric, ComplexityReport};
+pub use conformance::{Baseline, BaselineEntry, CheckReport, Violation, BASELINE_FILE};
+pub use cycles::{Cycle, CycleEdge, CycleLevel, CycleOptions, CycleReport};
+pub use deadcode::{Confidence, DeadCodeReport, DeadFile, DeadSymbol};
+pub use diff::{
+    DiffOptions, EdgeChange, GraphDiff, MovedNode, RenamedNode, SignatureChange, VisibilityChange,
+};
 pub use edge::{Edge, EdgeKind, GraphEdge};
+pub use filter::{FilterError, NodeAttribute, NodeFilter, NodeMatcher};
+pub use findings::{Finding, FindingRule, FindingSet, Location, Severity, ToFindings};
 pub use graph::{ArborGraph, NodeId};
 pub use impact::{AffectedNode, ImpactAnalysis, ImpactDirection, ImpactSeverity};
+pub use metrics::{
+    ClassCohesion, MetricsLevel, MetricsOptions, MetricsReport, MetricsTrend, TrendStatus,
+    UnitMetrics, UnitTrend,
+};
+pub use patch::{parse_unified_diff, FilePatch, LineRange};
+pub use ql::{QlError, QlResult, QueryLimits};
 pub use query::{DependentInfo, ImpactResult, NodeInfo, QueryResult};
-pub use ranking::{compute_centrality, CentralityScores};
+pub use quotient::{
+    AffectedUnit, CollapseLevel, CollapseOptions, QuotientEdge, QuotientGraph, QuotientImpact,
+    QuotientNode,
+};
+pub use ranking::{
+    compute_centrality, compute_centrality_with, refresh_centrality, CentralityMeasure,
+    CentralityOptions, CentralityScores,
+};
 pub use slice::{ContextNode, ContextSlice, TruncationReason};
-pub use store::{GraphStore, StoreError};
+pub use store::{GraphStore, RevisionInfo, StoreError};
 pub use symbol_table::SymbolTable;
+pub use task::{TaskField, TaskInterpretation, TaskOptions, TaskSeed, TaskTerm};
+pub use testing::{is_test_file, is_test_node, RunnerCommand, TestRunner, TestSelection};
+pub use tokens::{BpeTokenizer, CharHeuristic, TokenCounter, TokenMeter, TokenizerError};
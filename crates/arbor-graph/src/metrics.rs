@@ -0,0 +1,669 @@
+//! Coupling and cohesion metrics.
+//!
+//! Robert C. Martin's package metrics over files, directories or packages:
+//! afferent and efferent coupling (how many other units depend on a unit,
+//! and how many it depends on), instability `Ce / (Ca + Ce)`, abstractness
+//! (the share of interfaces, traits and abstract classes among its types)
+//! and distance from the main sequence `|A + I - 1|`. Fan-in and fan-out
+//! count the dependencies themselves rather than the units.
+//!
+//! Cohesion is LCOM4 per class: the number of groups of methods that
+//! neither call each other nor touch the same fields. 1 is cohesive;
+//! more suggests the class does several unrelated things.
+
+use crate::edge::EdgeKind;
+use crate::graph::{ArborGraph, NodeId};
+use crate::layout::{directory_of, package_of};
+use crate::resolve::{is_type, TypeIndex};
+use crate::testing::is_test_file;
+use arbor_core::{CodeNode, NodeKind};
+use petgraph::visit::EdgeRef;
+use petgraph::Direction;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// The units metrics are aggregated over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MetricsLevel {
+    /// Source files.
+    #[default]
+    File,
+    /// The directory holding each file.
+    Directory,
+    /// The directory above a `src`, `lib`, `pkg` or `internal` directory,
+    /// like a crate, npm or Go module; otherwise the top-level directory.
+    Package,
+}
+
+impl MetricsLevel {
+    /// The unit a file belongs to.
+    pub fn unit_of(self, file: &str) -> &str {
+        match self {
+            Self::File => file,
+            Self::Directory => directory_of(file),
+            Self::Package => package_of(file),
+        }
+    }
+}
+
+impl std::fmt::Display for MetricsLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::File => "file",
+            Self::Directory => "directory",
+            Self::Package => "package",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl std::str::FromStr for MetricsLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "file" | "module" => Ok(Self::File),
+            "directory" | "dir" => Ok(Self::Directory),
+            "package" | "pkg" => Ok(Self::Package),
+            _ => Err(format!(
+                "unknown metrics level '{}' (expected file, directory or package)",
+                s
+            )),
+        }
+    }
+}
+
+/// What to measure.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MetricsOptions {
+    pub level: MetricsLevel,
+
+    /// Whether test code counts. Tests depend on everything and would make
+    /// every unit look more stable than it is.
+    pub include_tests: bool,
+}
+
+impl MetricsOptions {
+    pub fn new(level: MetricsLevel) -> Self {
+        Self {
+            level,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_tests(mut self, include: bool) -> Self {
+        self.include_tests = include;
+        self
+    }
+}
+
+/// Metrics for one file, directory or package.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UnitMetrics {
+    pub unit: String,
+    pub files: usize,
+    pub symbols: usize,
+    pub types: usize,
+    /// Interfaces, traits and abstract classes.
+    pub abstract_types: usize,
+    /// Ca: other units that depend on this one.
+    pub afferent: usize,
+    /// Ce: other units this one depends on.
+    pub efferent: usize,
+    /// Dependencies from other units.
+    pub fan_in: usize,
+    /// Dependencies on other units.
+    pub fan_out: usize,
+    /// Ce / (Ca + Ce); 0 is maximally stable.
+    pub instability: f64,
+    /// Abstract types / types.
+    pub abstractness: f64,
+    /// |A + I - 1|; 0 is on the main sequence.
+    pub distance: f64,
+    /// Mean LCOM4 of the unit's classes, if it has any with methods.
+    pub lcom: Option<f64>,
+}
+
+/// LCOM4 cohesion of one class.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClassCohesion {
+    pub class: String,
+    pub file: String,
+    pub line: u32,
+    pub methods: usize,
+    /// Declared fields plus those the methods use through `self` or `this`.
+    pub fields: usize,
+    /// Connected groups of methods.
+    pub lcom: usize,
+}
+
+/// Metrics for every unit at one level.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetricsReport {
+    pub level: MetricsLevel,
+    /// Sorted by unit.
+    pub units: Vec<UnitMetrics>,
+    /// Classes with at least one method, least cohesive first.
+    pub classes: Vec<ClassCohesion>,
+}
+
+impl MetricsReport {
+    pub fn get(&self, unit: &str) -> Option<&UnitMetrics> {
+        self.units.iter().find(|u| u.unit == unit)
+    }
+
+    /// Mean distance from the main sequence over units with dependencies.
+    pub fn mean_distance(&self) -> f64 {
+        let coupled: Vec<f64> = self
+            .units
+            .iter()
+            .filter(|u| u.afferent + u.efferent > 0)
+            .map(|u| u.distance)
+            .collect();
+        if coupled.is_empty() {
+            return 0.0;
+        }
+        round(coupled.iter().sum::<f64>() / coupled.len() as f64)
+    }
+
+    /// One row per unit with a header.
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from(
+            "unit,files,symbols,types,abstract_types,afferent,efferent,fan_in,fan_out,instability,abstractness,distance,lcom\n",
+        );
+        for u in &self.units {
+            out.push_str(&format!(
+                "{},{},{},{},{},{},{},{},{},{},{},{},{}\n",
+                csv_field(&u.unit),
+                u.files,
+                u.symbols,
+                u.types,
+                u.abstract_types,
+                u.afferent,
+                u.efferent,
+                u.fan_in,
+                u.fan_out,
+                u.instability,
+                u.abstractness,
+                u.distance,
+                u.lcom.map(|l| l.to_string()).unwrap_or_default()
+            ));
+        }
+        out
+    }
+
+    /// How each unit changed since an earlier report at the same level.
+    pub fn compare(&self, before: &MetricsReport) -> MetricsTrend {
+        let old: HashMap<&str, &UnitMetrics> =
+            before.units.iter().map(|u| (u.unit.as_str(), u)).collect();
+        let new: HashMap<&str, &UnitMetrics> =
+            self.units.iter().map(|u| (u.unit.as_str(), u)).collect();
+        let mut names: Vec<&str> = old.keys().chain(new.keys()).copied().collect();
+        names.sort_unstable();
+        names.dedup();
+
+        let units = names
+            .into_iter()
+            .filter_map(|name| {
+                let (before, after) = (old.get(name).copied(), new.get(name).copied());
+                if before.is_some() && before == after {
+                    return None;
+                }
+                Some(UnitTrend::new(name, before, after))
+            })
+            .collect();
+
+        MetricsTrend {
+            level: self.level,
+            mean_distance_before: before.mean_distance(),
+            mean_distance_after: self.mean_distance(),
+            units,
+        }
+    }
+}
+
+/// Whether a unit appeared, disappeared or changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrendStatus {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// How one unit's metrics moved between two snapshots.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UnitTrend {
+    pub unit: String,
+    pub status: TrendStatus,
+    pub before: Option<UnitMetrics>,
+    pub after: Option<UnitMetrics>,
+    pub afferent_delta: i64,
+    pub efferent_delta: i64,
+    pub instability_delta: f64,
+    pub distance_delta: f64,
+}
+
+impl UnitTrend {
+    fn new(unit: &str, before: Option<&UnitMetrics>, after: Option<&UnitMetrics>) -> Self {
+        let status = match (before, after) {
+            (None, _) => TrendStatus::Added,
+            (_, None) => TrendStatus::Removed,
+            _ => TrendStatus::Changed,
+        };
+        let count = |m: Option<&UnitMetrics>, f: fn(&UnitMetrics) -> usize| m.map_or(0, f) as i64;
+        let ratio = |m: Option<&UnitMetrics>, f: fn(&UnitMetrics) -> f64| m.map_or(0.0, f);
+        Self {
+            unit: unit.to_string(),
+            status,
+            afferent_delta: count(after, |m| m.afferent) - count(before, |m| m.afferent),
+            efferent_delta: count(after, |m| m.efferent) - count(before, |m| m.efferent),
+            instability_delta: round(
+                ratio(after, |m| m.instability) - ratio(before, |m| m.instability),
+            ),
+            distance_delta: round(ratio(after, |m| m.distance) - ratio(before, |m| m.distance)),
+            before: before.cloned(),
+            after: after.cloned(),
+        }
+    }
+}
+
+/// Changes between two metrics reports.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetricsTrend {
+    pub level: MetricsLevel,
+    pub mean_distance_before: f64,
+    pub mean_distance_after: f64,
+    /// Units that changed, sorted by unit.
+    pub units: Vec<UnitTrend>,
+}
+
+impl MetricsTrend {
+    /// One row per changed unit with a header.
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from(
+            "unit,status,afferent_delta,efferent_delta,instability_before,instability_after,distance_before,distance_after\n",
+        );
+        let value = |m: &Option<UnitMetrics>, f: fn(&UnitMetrics) -> f64| {
+            m.as_ref().map(|m| f(m).to_string()).unwrap_or_default()
+        };
+        for t in &self.units {
+            out.push_str(&format!(
+                "{},{},{},{},{},{},{},{}\n",
+                csv_field(&t.unit),
+                serde_json::to_value(t.status)
+                    .ok()
+                    .and_then(|v| v.as_str().map(str::to_string))
+                    .unwrap_or_default(),
+                t.afferent_delta,
+                t.efferent_delta,
+                value(&t.before, |m| m.instability),
+                value(&t.after, |m| m.instability),
+                value(&t.before, |m| m.distance),
+                value(&t.after, |m| m.distance),
+            ));
+        }
+        out
+    }
+}
+
+#[derive(Default)]
+struct Tally<'a> {
+    files: HashSet<&'a str>,
+    symbols: usize,
+    types: usize,
+    abstract_types: usize,
+    afferent: HashSet<&'a str>,
+    efferent: HashSet<&'a str>,
+    fan_in: usize,
+    fan_out: usize,
+    lcom: Vec<usize>,
+}
+
+impl ArborGraph {
+    /// Computes coupling and cohesion metrics.
+    pub fn compute_metrics(&self, options: &MetricsOptions) -> MetricsReport {
+        let level = options.level;
+        let skip = |node: &CodeNode| {
+            !options.include_tests && (is_test_file(&node.file) || self.is_test(node))
+        };
+
+        let mut tallies: BTreeMap<&str, Tally> = BTreeMap::new();
+        for node in self.nodes().filter(|n| !skip(n)) {
+            let tally = tallies.entry(level.unit_of(&node.file)).or_default();
+            tally.files.insert(&node.file);
+            if matches!(
+                node.kind,
+                NodeKind::Import | NodeKind::Export | NodeKind::Module
+            ) {
+                continue;
+            }
+            tally.symbols += 1;
+            if is_type(node.kind) {
+                tally.types += 1;
+                if is_abstract(node) {
+                    tally.abstract_types += 1;
+                }
+            }
+        }
+
+        for dep in self.dependencies(options.include_tests) {
+            let from = level.unit_of(&dep.source.file);
+            let to = level.unit_of(dep.target_file);
+            if from == to || !tallies.contains_key(to) {
+                continue;
+            }
+            if let Some(tally) = tallies.get_mut(from) {
+                tally.efferent.insert(to);
+                tally.fan_out += 1;
+            }
+            if let Some(tally) = tallies.get_mut(to) {
+                tally.afferent.insert(from);
+                tally.fan_in += 1;
+            }
+        }
+
+        let classes = self.class_cohesion(&skip);
+        for class in &classes {
+            if let Some(tally) = tallies.get_mut(level.unit_of(&class.file)) {
+                tally.lcom.push(class.lcom);
+            }
+        }
+
+        let units = tallies
+            .into_iter()
+            .map(|(unit, t)| {
+                let (ca, ce) = (t.afferent.len(), t.efferent.len());
+                let instability = if ca + ce == 0 {
+                    0.0
+                } else {
+                    ce as f64 / (ca + ce) as f64
+                };
+                let abstractness = if t.types == 0 {
+                    0.0
+                } else {
+                    t.abstract_types as f64 / t.types as f64
+                };
+                UnitMetrics {
+                    unit: unit.to_string(),
+                    files: t.files.len(),
+                    symbols: t.symbols,
+                    types: t.types,
+                    abstract_types: t.abstract_types,
+                    afferent: ca,
+                    efferent: ce,
+                    fan_in: t.fan_in,
+                    fan_out: t.fan_out,
+                    instability: round(instability),
+                    abstractness: round(abstractness),
+                    distance: round((abstractness + instability - 1.0).abs()),
+                    lcom: (!t.lcom.is_empty())
+                        .then(|| round(t.lcom.iter().sum::<usize>() as f64 / t.lcom.len() as f64)),
+                }
+            })
+            .collect();
+
+        MetricsReport {
+            level,
+            units,
+            classes,
+        }
+    }
+
+    /// LCOM4 for every class with methods.
+    fn class_cohesion(&self, skip: &dyn Fn(&CodeNode) -> bool) -> Vec<ClassCohesion> {
+        let types = TypeIndex::new(self);
+        let mut members: HashMap<&str, Vec<NodeId>> = HashMap::new();
+        for index in self.node_indexes() {
+            let Some(node) = self.get(index).filter(|n| !skip(n)) else {
+                continue;
+            };
+            if matches!(
+                node.kind,
+                NodeKind::Method | NodeKind::Constructor | NodeKind::Field
+            ) {
+                if let Some(class) = types.enclosing(node).filter(|c| *c != node.qualified_name) {
+                    members.entry(class).or_default().push(index);
+                }
+            }
+        }
+
+        let mut classes = Vec::new();
+        for (class, indexes) in members {
+            let (methods, fields): (Vec<NodeId>, Vec<NodeId>) = indexes
+                .into_iter()
+                .partition(|&i| self.get(i).is_some_and(|n| n.kind != NodeKind::Field));
+            if methods.is_empty() {
+                continue;
+            }
+            let position: HashMap<NodeId, usize> =
+                methods.iter().enumerate().map(|(i, &m)| (m, i)).collect();
+            let method_names: HashSet<&str> = methods
+                .iter()
+                .filter_map(|&m| self.get(m))
+                .map(|m| m.name.as_str())
+                .collect();
+            let mut field_names: HashSet<&str> = fields
+                .iter()
+                .filter_map(|&f| self.get(f))
+                .map(|f| f.name.as_str())
+                .collect();
+
+            // Union-find over methods; calls and a shared field join them.
+            let mut parent: Vec<usize> = (0..methods.len()).collect();
+            fn find(parent: &mut [usize], i: usize) -> usize {
+                let mut root = i;
+                while parent[root] != root {
+                    root = parent[root];
+                }
+                parent[i] = root;
+                root
+            }
+            let mut field_user: HashMap<&str, usize> = HashMap::new();
+            for (i, &method) in methods.iter().enumerate() {
+                for edge in self.graph.edges_directed(method, Direction::Outgoing) {
+                    if matches!(
+                        edge.weight().kind,
+                        EdgeKind::Contains | EdgeKind::FlowsTo | EdgeKind::DataDependency
+                    ) {
+                        continue;
+                    }
+                    let other = match position.get(&edge.target()) {
+                        Some(&j) => j,
+                        None => continue,
+                    };
+                    let (a, b) = (find(&mut parent, i), find(&mut parent, other));
+                    parent[a] = b;
+                }
+                let accessed = self.graph[method]
+                    .accessed_fields
+                    .iter()
+                    .map(String::as_str)
+                    .filter(|f| !method_names.contains(f));
+                for field in accessed {
+                    field_names.insert(field);
+                    let other = *field_user.entry(field).or_insert(i);
+                    let (a, b) = (find(&mut parent, i), find(&mut parent, other));
+                    parent[a] = b;
+                }
+            }
+            let lcom = (0..methods.len())
+                .filter(|&i| find(&mut parent, i) == i)
+                .count();
+
+            let Some(node) = self
+                .nodes_qualified(class)
+                .iter()
+                .filter_map(|&i| self.get(i))
+                .find(|n| is_type(n.kind))
+            else {
+                continue;
+            };
+            classes.push(ClassCohesion {
+                class: class.to_string(),
+                file: node.file.clone(),
+                line: node.line_start,
+                methods: methods.len(),
+                fields: field_names.len(),
+                lcom,
+            });
+        }
+        classes.sort_by(|a, b| {
+            b.lcom
+                .cmp(&a.lcom)
+                .then(b.methods.cmp(&a.methods))
+                .then_with(|| a.class.cmp(&b.class))
+        });
+        classes
+    }
+}
+
+/// Interfaces and traits, plus classes declared abstract.
+fn is_abstract(node: &CodeNode) -> bool {
+    node.kind == NodeKind::Interface
+        || (node.kind == NodeKind::Class
+            && (node
+                .signature
+                .as_deref()
+                .is_some_and(|s| s.split_whitespace().any(|w| w == "abstract"))
+                || node.attributes.iter().any(|a| a.contains("abstract"))
+                || node
+                    .references
+                    .iter()
+                    .any(|r| r == "ABC" || r.ends_with(".ABC"))))
+}
+
+fn round(value: f64) -> f64 {
+    (value * 1000.0).round() / 1000.0
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::GraphBuilder;
+
+    fn node(qualified: &str, kind: NodeKind, file: &str, refs: &[&str]) -> CodeNode {
+        let name = qualified.rsplit('.').next().unwrap();
+        CodeNode::new(name, qualified, kind, file)
+            .with_references(refs.iter().map(|r| r.to_string()).collect())
+    }
+
+    fn graph() -> ArborGraph {
+        let mut builder = GraphBuilder::new();
+        builder.add_nodes(vec![
+            node("Store", NodeKind::Interface, "core/src/store.rs", &[]),
+            node("Mem", NodeKind::Struct, "core/src/store.rs", &[]),
+            node(
+                "Mem.get",
+                NodeKind::Method,
+                "core/src/store.rs",
+                &["Mem.load"],
+            ),
+            node("Mem.load", NodeKind::Method, "core/src/store.rs", &[]),
+            node("Mem.reset", NodeKind::Method, "core/src/store.rs", &[]),
+            node(
+                "main",
+                NodeKind::Function,
+                "app/src/main.rs",
+                &["Mem.get", "helper"],
+            ),
+            node("helper", NodeKind::Function, "app/src/util.rs", &[]),
+        ]);
+        builder.build()
+    }
+
+    #[test]
+    fn test_file_metrics() {
+        let report = graph().compute_metrics(&MetricsOptions::default());
+        let main = report.get("app/src/main.rs").unwrap();
+        assert_eq!((main.afferent, main.efferent), (0, 2));
+        assert_eq!(main.instability, 1.0);
+        assert_eq!(main.distance, 0.0);
+
+        let store = report.get("core/src/store.rs").unwrap();
+        assert_eq!((store.afferent, store.efferent, store.fan_in), (1, 0, 1));
+        assert_eq!((store.types, store.abstract_types), (2, 1));
+        assert_eq!(store.abstractness, 0.5);
+        assert_eq!(store.distance, 0.5);
+        assert_eq!(store.lcom, Some(2.0));
+
+        assert_eq!(report.classes.len(), 1);
+        assert_eq!(report.classes[0].class, "Mem");
+        assert_eq!((report.classes[0].methods, report.classes[0].lcom), (3, 2));
+        assert!(report
+            .to_csv()
+            .lines()
+            .nth(1)
+            .unwrap()
+            .starts_with("app/src/main.rs,1,"));
+    }
+
+    #[test]
+    fn test_shared_fields_join_methods() {
+        let method = |qualified: &str, body: &str| {
+            node(qualified, NodeKind::Method, "shop/cart.py", &[]).with_body(body)
+        };
+        let mut builder = GraphBuilder::new();
+        builder.add_nodes(vec![
+            node("Cart", NodeKind::Class, "shop/cart.py", &[]).with_lines(1, 20),
+            method(
+                "Cart.add",
+                "def add(self, item):\n    self.items.append(item)",
+            ),
+            method(
+                "Cart.clear",
+                "def clear(self):\n    self.items = []\n    self.clear_log()",
+            ),
+            method(
+                "Cart.clear_log",
+                "def clear_log(self):\n    self.log.clear()",
+            ),
+            method(
+                "Cart.price",
+                "def price(self):\n    return self.rates.total()",
+            ),
+        ]);
+        let report = builder.build().compute_metrics(&MetricsOptions::default());
+
+        // add and clear share `items`; `self.clear_log()` is a call, not a field
+        let cart = &report.classes[0];
+        assert_eq!((cart.methods, cart.fields, cart.lcom), (4, 3, 3));
+    }
+
+    #[test]
+    fn test_package_metrics_and_trend() {
+        let graph = graph();
+        let options = MetricsOptions::new(MetricsLevel::Package);
+        let after = graph.compute_metrics(&options);
+        let app = after.get("app").unwrap();
+        assert_eq!((app.efferent, app.fan_out, app.files), (1, 1, 2));
+
+        let mut before = after.clone();
+        before.units.retain(|u| u.unit != "app");
+        before.units[0].afferent = 0;
+        let trend = after.compare(&before);
+        let statuses: Vec<(&str, TrendStatus, i64)> = trend
+            .units
+            .iter()
+            .map(|t| (t.unit.as_str(), t.status, t.afferent_delta))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("app", TrendStatus::Added, 0),
+                ("core", TrendStatus::Changed, 1)
+            ]
+        );
+        assert!(after.compare(&after).units.is_empty());
+    }
+}
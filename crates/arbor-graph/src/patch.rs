@@ -0,0 +1,239 @@
+//! Unified diff parsing.
+//!
+//! Only what impact analysis needs: which files a patch touches, which
+//! lines of the new version it added, and which lines of the old version
+//! it removed. Context lines are ignored, so a hunk's surrounding lines
+//! don't drag neighbouring functions in.
+
+use serde::{Deserialize, Serialize};
+
+/// An inclusive range of lines in one version of a file (1-indexed).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LineRange {
+    pub start: u32,
+    pub end: u32,
+}
+
+impl LineRange {
+    /// Whether `line` falls inside the range.
+    pub fn contains(&self, line: u32) -> bool {
+        self.start <= line && line <= self.end
+    }
+}
+
+/// The changes a patch makes to one file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FilePatch {
+    /// Path of the new version, or of the old one for deleted files.
+    pub path: String,
+    /// Previous path, if the file was renamed.
+    pub old_path: Option<String>,
+    /// The patch deletes the file.
+    pub deleted: bool,
+    /// Added or modified lines, numbered as in the new version.
+    pub lines: Vec<LineRange>,
+    /// Removed lines, numbered as in the old version.
+    #[serde(default)]
+    pub removed: Vec<LineRange>,
+}
+
+/// Parses a unified diff, as printed by `git diff` or `diff -u`.
+///
+/// `a/` and `b/` prefixes are stripped. Anything that isn't part of a
+/// file header or hunk is skipped.
+pub fn parse_unified_diff(text: &str) -> Vec<FilePatch> {
+    let mut patches: Vec<FilePatch> = Vec::new();
+    let mut old_path: Option<String> = None;
+    let mut hunk: Option<Hunk> = None;
+
+    for line in text.lines() {
+        // Inside a hunk the line counts say what's content, so removed
+        // lines starting with "--" aren't mistaken for headers.
+        if let (Some(h), Some(patch)) = (hunk.as_mut(), patches.last_mut()) {
+            let mut in_hunk = true;
+            match line.as_bytes().first() {
+                Some(b'+') => {
+                    push_line(&mut patch.lines, h.new_line);
+                    h.new_line += 1;
+                    h.new_left = h.new_left.saturating_sub(1);
+                }
+                Some(b'-') => {
+                    push_line(&mut patch.removed, h.old_line);
+                    h.old_line += 1;
+                    h.old_left = h.old_left.saturating_sub(1);
+                }
+                Some(b' ') | None => {
+                    h.old_line += 1;
+                    h.new_line += 1;
+                    h.old_left = h.old_left.saturating_sub(1);
+                    h.new_left = h.new_left.saturating_sub(1);
+                }
+                Some(b'\\') => {} // "\ No newline at end of file"
+                _ => in_hunk = false,
+            }
+            if !in_hunk || (h.old_left == 0 && h.new_left == 0) {
+                hunk = None;
+            }
+            if in_hunk {
+                continue;
+            }
+        }
+
+        if let Some(rest) = line.strip_prefix("--- ") {
+            old_path = header_path(rest, "a/");
+        } else if let Some(rest) = line.strip_prefix("+++ ") {
+            let new_path = header_path(rest, "b/");
+            let deleted = new_path.is_none();
+            let Some(path) = new_path.or_else(|| old_path.clone()) else {
+                continue;
+            };
+            let renamed_from = old_path.take().filter(|old| *old != path);
+            patches.push(FilePatch {
+                path,
+                old_path: renamed_from,
+                deleted,
+                lines: Vec::new(),
+                removed: Vec::new(),
+            });
+        } else if let Some(rest) = line.strip_prefix("@@ ") {
+            hunk = Hunk::parse(rest);
+        }
+    }
+
+    patches.retain(|p| !p.lines.is_empty() || !p.removed.is_empty() || p.deleted);
+    patches
+}
+
+/// Position inside a hunk.
+struct Hunk {
+    /// Next line number in the old file.
+    old_line: u32,
+    /// Next line number in the new file.
+    new_line: u32,
+    old_left: u32,
+    new_left: u32,
+}
+
+impl Hunk {
+    /// Parses `-a,b +c,d @@ ...`. Counts default to 1 when omitted.
+    fn parse(header: &str) -> Option<Self> {
+        let mut parts = header.split_whitespace();
+        let (old_line, old_left) = range(parts.next()?.strip_prefix('-')?)?;
+        let (new_line, new_left) = range(parts.next()?.strip_prefix('+')?)?;
+        Some(Self {
+            old_line,
+            new_line,
+            old_left,
+            new_left,
+        })
+    }
+}
+
+fn range(spec: &str) -> Option<(u32, u32)> {
+    match spec.split_once(',') {
+        Some((start, count)) => Some((start.parse().ok()?, count.parse().ok()?)),
+        None => Some((spec.parse().ok()?, 1)),
+    }
+}
+
+/// Extracts the path from a `---`/`+++` header. `None` for `/dev/null`.
+fn header_path(rest: &str, prefix: &str) -> Option<String> {
+    // `diff -u` appends a tab and a timestamp
+    let path = rest.split('\t').next().unwrap_or(rest).trim();
+    let path = path.trim_matches('"');
+    if path == "/dev/null" {
+        return None;
+    }
+    Some(path.strip_prefix(prefix).unwrap_or(path).to_string())
+}
+
+/// Adds a line, extending the last range when it's adjacent.
+fn push_line(ranges: &mut Vec<LineRange>, line: u32) {
+    match ranges.last_mut() {
+        Some(last) if last.contains(line) => {}
+        Some(last) if last.end + 1 == line => last.end = line,
+        _ => ranges.push(LineRange {
+            start: line,
+            end: line,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PATCH: &str = "\
+diff --git a/src/app.py b/src/app.py
+index 1111111..2222222 100644
+--- a/src/app.py
++++ b/src/app.py
+@@ -3,5 +3,6 @@ def a():
+ context
+ context
+-old line
++new line
++another line
+ context
+ context
+@@ -20,3 +21,2 @@ def b():
+ context
+-removed
+ context
+diff --git a/gone.py b/gone.py
+deleted file mode 100644
+--- a/gone.py
++++ /dev/null
+@@ -1,2 +0,0 @@
+--- not a header
+-    pass
+diff --git a/old.py b/new.py
+--- a/old.py
++++ b/new.py
+@@ -1 +1 @@
+-x = 1
++x = 2
+\\ No newline at end of file
+";
+
+    #[test]
+    fn test_parse_unified_diff() {
+        let patches = parse_unified_diff(PATCH);
+        assert_eq!(patches.len(), 3);
+
+        let app = &patches[0];
+        assert_eq!(app.path, "src/app.py");
+        assert!(!app.deleted && app.old_path.is_none());
+        assert_eq!(app.lines, vec![LineRange { start: 5, end: 6 }]);
+        assert_eq!(
+            app.removed,
+            vec![
+                LineRange { start: 5, end: 5 },
+                LineRange { start: 21, end: 21 }
+            ]
+        );
+
+        assert_eq!(patches[1].path, "gone.py");
+        assert!(patches[1].deleted);
+        assert_eq!(patches[1].removed, vec![LineRange { start: 1, end: 2 }]);
+
+        assert_eq!(patches[2].path, "new.py");
+        assert_eq!(patches[2].old_path.as_deref(), Some("old.py"));
+        assert_eq!(patches[2].lines, vec![LineRange { start: 1, end: 1 }]);
+    }
+
+    #[test]
+    fn test_plain_diff_u_headers() {
+        let text = "--- app.py\t2026-01-01 00:00:00\n+++ app.py\t2026-01-02 00:00:00\n@@ -1,0 +2,1 @@\n+y = 3\n";
+        let patches = parse_unified_diff(text);
+        assert_eq!(patches[0].path, "app.py");
+        assert_eq!(patches[0].lines, vec![LineRange { start: 2, end: 2 }]);
+        assert!(parse_unified_diff("not a diff").is_empty());
+
+        // `--unified=0` pure deletion: old lines only
+        let text = "--- a/app.py\n+++ b/app.py\n@@ -6 +5,0 @@\n-    pass\n";
+        let patches = parse_unified_diff(text);
+        assert!(patches[0].lines.is_empty());
+        assert_eq!(patches[0].removed, vec![LineRange { start: 6, end: 6 }]);
+    }
+}
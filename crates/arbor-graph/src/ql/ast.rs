@@ -0,0 +1,368 @@
+//! Syntax tree for ArborQL queries.
+
+use arbor_core::NodeKind;
+use std::fmt;
+
+use crate::edge::EdgeKind;
+
+/// A parsed query.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    pub patterns: Vec<Pattern>,
+    pub filter: Option<Expr>,
+    pub distinct: bool,
+    pub returns: Vec<ReturnItem>,
+    pub order: Vec<OrderItem>,
+    pub limit: Option<usize>,
+}
+
+/// A chain of node patterns joined by edge patterns.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pattern {
+    pub start: NodePattern,
+    pub steps: Vec<(EdgePattern, NodePattern)>,
+}
+
+impl Pattern {
+    /// The node patterns in order.
+    pub fn nodes(&self) -> impl Iterator<Item = &NodePattern> {
+        std::iter::once(&self.start).chain(self.steps.iter().map(|(_, node)| node))
+    }
+}
+
+/// `(var:kind|kind {prop: value})`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodePattern {
+    /// Anonymous nodes get a generated name starting with `#`.
+    pub var: String,
+    /// Any of these kinds; empty allows all.
+    pub kinds: Vec<NodeKind>,
+    /// Properties that must equal the given values.
+    pub props: Vec<(Property, Literal)>,
+}
+
+/// Which way an edge pattern points.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// `-[...]->`
+    Outgoing,
+    /// `<-[...]-`
+    Incoming,
+    /// `-[...]-`
+    Both,
+}
+
+impl Direction {
+    pub fn reversed(self) -> Self {
+        match self {
+            Self::Outgoing => Self::Incoming,
+            Self::Incoming => Self::Outgoing,
+            Self::Both => Self::Both,
+        }
+    }
+}
+
+/// `-[:kind|kind*min..max]->`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EdgePattern {
+    /// Any of these kinds; empty allows all.
+    pub kinds: Vec<EdgeKind>,
+    pub direction: Direction,
+    pub min_hops: usize,
+    /// `None` means up to the query's hop limit.
+    pub max_hops: Option<usize>,
+}
+
+impl EdgePattern {
+    /// The same edge walked from the other end.
+    pub fn reversed(&self) -> Self {
+        Self {
+            direction: self.direction.reversed(),
+            ..self.clone()
+        }
+    }
+}
+
+/// A node property usable in patterns, `WHERE`, `RETURN` and `ORDER BY`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Property {
+    Id,
+    Name,
+    QualifiedName,
+    Kind,
+    File,
+    Line,
+    LineEnd,
+    Visibility,
+    Exported,
+    Async,
+    Static,
+    Generated,
+    Centrality,
+    Signature,
+    Docstring,
+    Language,
+    Test,
+    FanIn,
+    FanOut,
+}
+
+impl Property {
+    /// Parses a property name, accepting a few aliases.
+    pub fn parse(name: &str) -> Option<Self> {
+        Some(match name.to_lowercase().as_str() {
+            "id" => Self::Id,
+            "name" => Self::Name,
+            "qualified_name" | "qualified" => Self::QualifiedName,
+            "kind" => Self::Kind,
+            "file" => Self::File,
+            "line" | "line_start" => Self::Line,
+            "line_end" => Self::LineEnd,
+            "visibility" => Self::Visibility,
+            "exported" | "is_exported" => Self::Exported,
+            "async" | "is_async" => Self::Async,
+            "static" | "is_static" => Self::Static,
+            "generated" => Self::Generated,
+            "centrality" => Self::Centrality,
+            "signature" => Self::Signature,
+            "docstring" | "doc" => Self::Docstring,
+            "language" | "lang" => Self::Language,
+            "test" | "is_test" => Self::Test,
+            "fan_in" => Self::FanIn,
+            "fan_out" => Self::FanOut,
+            _ => return None,
+        })
+    }
+}
+
+impl fmt::Display for Property {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Self::Id => "id",
+            Self::Name => "name",
+            Self::QualifiedName => "qualified_name",
+            Self::Kind => "kind",
+            Self::File => "file",
+            Self::Line => "line",
+            Self::LineEnd => "line_end",
+            Self::Visibility => "visibility",
+            Self::Exported => "exported",
+            Self::Async => "async",
+            Self::Static => "static",
+            Self::Generated => "generated",
+            Self::Centrality => "centrality",
+            Self::Signature => "signature",
+            Self::Docstring => "docstring",
+            Self::Language => "language",
+            Self::Test => "test",
+            Self::FanIn => "fan_in",
+            Self::FanOut => "fan_out",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// A constant in a query.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    List(Vec<Literal>),
+}
+
+impl fmt::Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Null => write!(f, "null"),
+            Self::Bool(b) => write!(f, "{}", b),
+            Self::Int(i) => write!(f, "{}", i),
+            Self::Float(x) => write!(f, "{}", x),
+            Self::Str(s) => write!(f, "{:?}", s),
+            Self::List(items) => {
+                let items: Vec<String> = items.iter().map(|i| i.to_string()).collect();
+                write!(f, "[{}]", items.join(", "))
+            }
+        }
+    }
+}
+
+/// One side of a comparison.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operand {
+    Prop(String, Property),
+    Literal(Literal),
+}
+
+impl Operand {
+    /// The variable this operand reads, if any.
+    pub fn var(&self) -> Option<&str> {
+        match self {
+            Self::Prop(var, _) => Some(var),
+            Self::Literal(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Prop(var, prop) => write!(f, "{}.{}", var, prop),
+            Self::Literal(literal) => write!(f, "{}", literal),
+        }
+    }
+}
+
+/// Comparison operators.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    /// `=~`: glob match.
+    Glob,
+    Contains,
+    StartsWith,
+    EndsWith,
+    In,
+}
+
+impl fmt::Display for CmpOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Self::Eq => "=",
+            Self::Ne => "<>",
+            Self::Lt => "<",
+            Self::Le => "<=",
+            Self::Gt => ">",
+            Self::Ge => ">=",
+            Self::Glob => "=~",
+            Self::Contains => "CONTAINS",
+            Self::StartsWith => "STARTS WITH",
+            Self::EndsWith => "ENDS WITH",
+            Self::In => "IN",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// A boolean expression in `WHERE`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare {
+        left: Operand,
+        op: CmpOp,
+        right: Operand,
+    },
+    IsNull {
+        operand: Operand,
+        negated: bool,
+    },
+    /// A bare operand, e.g. `f.exported`.
+    Truthy(Operand),
+}
+
+impl Expr {
+    /// Splits top-level `AND`s into their parts.
+    pub fn conjuncts(self) -> Vec<Expr> {
+        match self {
+            Self::And(left, right) => {
+                let mut parts = left.conjuncts();
+                parts.extend(right.conjuncts());
+                parts
+            }
+            other => vec![other],
+        }
+    }
+
+    /// Adds every variable the expression reads to `out`.
+    pub fn vars<'a>(&'a self, out: &mut Vec<&'a str>) {
+        let mut push = |operand: &'a Operand| {
+            if let Some(var) = operand.var() {
+                if !out.contains(&var) {
+                    out.push(var);
+                }
+            }
+        };
+        match self {
+            Self::And(left, right) | Self::Or(left, right) => {
+                left.vars(out);
+                right.vars(out);
+            }
+            Self::Not(inner) => inner.vars(out),
+            Self::Compare { left, right, .. } => {
+                push(left);
+                push(right);
+            }
+            Self::IsNull { operand, .. } | Self::Truthy(operand) => push(operand),
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::And(left, right) => write!(f, "({} AND {})", left, right),
+            Self::Or(left, right) => write!(f, "({} OR {})", left, right),
+            Self::Not(inner) => write!(f, "NOT {}", inner),
+            Self::Compare { left, op, right } => write!(f, "{} {} {}", left, op, right),
+            Self::IsNull { operand, negated } => {
+                let not = if *negated { " NOT" } else { "" };
+                write!(f, "{} IS{} NULL", operand, not)
+            }
+            Self::Truthy(operand) => write!(f, "{}", operand),
+        }
+    }
+}
+
+/// What a `RETURN` or `ORDER BY` item computes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Projection {
+    /// The whole node.
+    Node(String),
+    Prop(String, Property),
+    /// `count(*)`: rows per group of the other columns.
+    Count,
+}
+
+impl fmt::Display for Projection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Node(var) => write!(f, "{}", var),
+            Self::Prop(var, prop) => write!(f, "{}.{}", var, prop),
+            Self::Count => write!(f, "count(*)"),
+        }
+    }
+}
+
+/// `RETURN expr [AS alias]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReturnItem {
+    pub projection: Projection,
+    pub alias: Option<String>,
+}
+
+impl ReturnItem {
+    /// The column name in results.
+    pub fn column(&self) -> String {
+        match &self.alias {
+            Some(alias) => alias.clone(),
+            None => self.projection.to_string(),
+        }
+    }
+}
+
+/// `ORDER BY expr [ASC|DESC]`. A bare name may also be a column alias.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderItem {
+    pub projection: Projection,
+    pub descending: bool,
+}
@@ -0,0 +1,565 @@
+//! Plan execution.
+//!
+//! Rows are vectors of variable slots. Each step maps the current rows to
+//! the next ones; limits are checked as rows are produced, so a runaway
+//! pattern fails fast instead of exhausting memory.
+
+use super::ast::{CmpOp, Direction, EdgePattern, Literal, Projection, Property};
+use super::plan::{Cond, Plan, Source, Step, Term};
+use super::QlError;
+use crate::graph::{ArborGraph, NodeId};
+use crate::query::NodeInfo;
+use arbor_core::languages::language_name;
+use petgraph::visit::EdgeRef;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+/// Bounds on the work a query may do.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QueryLimits {
+    /// Intermediate rows any step may produce before the query fails.
+    pub max_rows: usize,
+    /// Result rows returned. A smaller `LIMIT` wins; extra rows set
+    /// `truncated`.
+    pub max_results: usize,
+    /// Upper bound for open-ended hop ranges like `*` or `*2..`.
+    pub max_hops: usize,
+    /// Wall-clock budget.
+    #[serde(with = "millis")]
+    pub timeout: Duration,
+}
+
+impl Default for QueryLimits {
+    fn default() -> Self {
+        Self {
+            max_rows: 100_000,
+            max_results: 1_000,
+            max_hops: 10,
+            timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+mod millis {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(d: &Duration, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_u64(d.as_millis() as u64)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Duration, D::Error> {
+        Ok(Duration::from_millis(u64::deserialize(d)?))
+    }
+}
+
+/// Rows returned by a query.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QlResult {
+    pub columns: Vec<String>,
+    /// One value per column. Nodes are objects like [`NodeInfo`].
+    pub rows: Vec<Vec<serde_json::Value>>,
+    /// More rows matched than `max_results` allowed.
+    pub truncated: bool,
+    pub query_time_ms: u64,
+}
+
+/// A value during evaluation.
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Node(NodeId),
+}
+
+impl Value {
+    fn from_literal(literal: &Literal) -> Self {
+        match literal {
+            Literal::Null | Literal::List(_) => Self::Null,
+            Literal::Bool(b) => Self::Bool(*b),
+            Literal::Int(i) => Self::Int(*i),
+            Literal::Float(x) => Self::Float(*x),
+            Literal::Str(s) => Self::Str(s.clone()),
+        }
+    }
+
+    fn from_opt(value: Option<&str>) -> Self {
+        value.map_or(Self::Null, |s| Self::Str(s.to_string()))
+    }
+
+    fn number(&self) -> Option<f64> {
+        match self {
+            Self::Int(i) => Some(*i as f64),
+            Self::Float(x) => Some(*x),
+            _ => None,
+        }
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            Self::Null => 0,
+            Self::Bool(_) => 1,
+            Self::Int(_) | Self::Float(_) => 2,
+            Self::Str(_) => 3,
+            Self::Node(_) => 4,
+        }
+    }
+
+    /// Ordering within a type; `None` across types.
+    fn partial(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (Self::Bool(a), Self::Bool(b)) => Some(a.cmp(b)),
+            (Self::Str(a), Self::Str(b)) => Some(a.cmp(b)),
+            (Self::Node(a), Self::Node(b)) => Some(a.cmp(b)),
+            _ => self.number()?.partial_cmp(&other.number()?),
+        }
+    }
+
+    /// A total order for sorting: nulls first, then by type.
+    fn total(&self, other: &Self) -> Ordering {
+        self.partial(other)
+            .unwrap_or_else(|| self.rank().cmp(&other.rank()))
+    }
+
+    fn truthy(&self) -> bool {
+        match self {
+            Self::Null => false,
+            Self::Bool(b) => *b,
+            Self::Int(i) => *i != 0,
+            Self::Float(x) => *x != 0.0,
+            Self::Str(s) => !s.is_empty(),
+            Self::Node(_) => true,
+        }
+    }
+
+    fn to_json(&self, graph: &ArborGraph) -> serde_json::Value {
+        match self {
+            Self::Null => serde_json::Value::Null,
+            Self::Bool(b) => (*b).into(),
+            Self::Int(i) => (*i).into(),
+            Self::Float(x) => (*x).into(),
+            Self::Str(s) => s.clone().into(),
+            Self::Node(index) => match graph.get(*index) {
+                Some(node) => {
+                    let mut info = NodeInfo::from(node);
+                    info.centrality = graph.centrality(*index);
+                    serde_json::to_value(info).unwrap_or_default()
+                }
+                None => serde_json::Value::Null,
+            },
+        }
+    }
+}
+
+type Row = Vec<Option<NodeId>>;
+
+struct Executor<'a> {
+    graph: &'a ArborGraph,
+    limits: &'a QueryLimits,
+    start: Instant,
+}
+
+impl Executor<'_> {
+    fn check_time(&self) -> Result<(), QlError> {
+        if self.start.elapsed() > self.limits.timeout {
+            Err(QlError::Timeout(self.limits.timeout))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn push(&self, rows: &mut Vec<Row>, row: Row) -> Result<(), QlError> {
+        if rows.len() >= self.limits.max_rows {
+            return Err(QlError::TooManyRows(self.limits.max_rows));
+        }
+        if rows.len().is_multiple_of(1024) {
+            self.check_time()?;
+        }
+        rows.push(row);
+        Ok(())
+    }
+
+    fn property(&self, index: NodeId, prop: Property) -> Value {
+        let graph = self.graph;
+        let Some(node) = graph.get(index) else {
+            return Value::Null;
+        };
+        match prop {
+            Property::Id => Value::Str(node.id.clone()),
+            Property::Name => Value::Str(node.name.clone()),
+            Property::QualifiedName => Value::Str(node.qualified_name.clone()),
+            Property::Kind => Value::Str(node.kind.to_string()),
+            Property::File => Value::Str(node.file.clone()),
+            Property::Line => Value::Int(node.line_start as i64),
+            Property::LineEnd => Value::Int(node.line_end as i64),
+            Property::Visibility => Value::Str(node.visibility.to_string()),
+            Property::Exported => Value::Bool(node.is_exported),
+            Property::Async => Value::Bool(node.is_async),
+            Property::Static => Value::Bool(node.is_static),
+            Property::Generated => Value::Bool(node.generated),
+            Property::Centrality => Value::Float(graph.centrality(index)),
+            Property::Signature => Value::from_opt(node.signature.as_deref()),
+            Property::Docstring => Value::from_opt(node.docstring.as_deref()),
+            Property::Language => Value::from_opt(
+                node.file
+                    .rsplit_once('.')
+                    .and_then(|(_, ext)| language_name(ext)),
+            ),
+            Property::Test => Value::Bool(graph.is_test(node)),
+            Property::FanIn => Value::Int(
+                graph
+                    .graph
+                    .edges_directed(index, petgraph::Direction::Incoming)
+                    .count() as i64,
+            ),
+            Property::FanOut => Value::Int(
+                graph
+                    .graph
+                    .edges_directed(index, petgraph::Direction::Outgoing)
+                    .count() as i64,
+            ),
+        }
+    }
+
+    fn term(&self, term: &Term, row: &Row) -> Value {
+        match term {
+            Term::Prop(slot, prop) => match row[*slot] {
+                Some(index) => self.property(index, *prop),
+                None => Value::Null,
+            },
+            Term::Literal(literal) => Value::from_literal(literal),
+        }
+    }
+
+    fn eval(&self, cond: &Cond, row: &Row) -> bool {
+        match cond {
+            Cond::True => true,
+            Cond::And(parts) => parts.iter().all(|c| self.eval(c, row)),
+            Cond::Or(left, right) => self.eval(left, row) || self.eval(right, row),
+            Cond::Not(inner) => !self.eval(inner, row),
+            Cond::Glob { term, matcher } => match self.term(term, row) {
+                Value::Str(s) => matcher.is_match(s),
+                _ => false,
+            },
+            Cond::IsNull { term, negated } => (self.term(term, row) == Value::Null) != *negated,
+            Cond::Truthy(term) => self.term(term, row).truthy(),
+            Cond::Compare { left, op, right } => {
+                let left = self.term(left, row);
+                if let (CmpOp::In, Term::Literal(Literal::List(items))) = (op, right) {
+                    return items.iter().any(|item| {
+                        left.partial(&Value::from_literal(item)) == Some(Ordering::Equal)
+                    });
+                }
+                compare(&left, *op, &self.term(right, row))
+            }
+        }
+    }
+
+    fn scan(&self, source: &Source) -> Vec<NodeId> {
+        let graph = self.graph;
+        match source {
+            Source::Name(name) => graph.nodes_named(name).to_vec(),
+            Source::QualifiedName(name) => graph.nodes_qualified(name).to_vec(),
+            Source::File(file) => graph.nodes_in_file(file).to_vec(),
+            Source::Kinds(kinds) => kinds
+                .iter()
+                .flat_map(|k| graph.nodes_of_kind(*k).iter().copied())
+                .collect(),
+            Source::All => graph.node_indexes().collect(),
+        }
+    }
+
+    /// Nodes `edge` reaches from `from`, each at its shortest hop count.
+    /// `from` itself is reached at zero hops, or through a cycle back to it
+    /// when the pattern needs at least one hop.
+    fn reach(&self, from: NodeId, edge: &EdgePattern) -> Vec<NodeId> {
+        let max = edge.max_hops.unwrap_or(self.limits.max_hops);
+        let mut found = Vec::new();
+        if edge.min_hops == 0 {
+            found.push(from);
+        }
+        let mut visited = HashSet::new();
+        let mut frontier = vec![from];
+        for depth in 1..=max {
+            let mut next = Vec::new();
+            for &node in &frontier {
+                for neighbor in self.neighbors(node, edge) {
+                    if (neighbor != from || edge.min_hops > 0) && visited.insert(neighbor) {
+                        next.push(neighbor);
+                        if depth >= edge.min_hops {
+                            found.push(neighbor);
+                        }
+                    }
+                }
+            }
+            if next.is_empty() {
+                break;
+            }
+            frontier = next;
+        }
+        found
+    }
+
+    fn neighbors<'g>(
+        &'g self,
+        node: NodeId,
+        edge: &'g EdgePattern,
+    ) -> impl Iterator<Item = NodeId> + 'g {
+        let graph = &self.graph.graph;
+        let wanted = move |kind| edge.kinds.is_empty() || edge.kinds.contains(kind);
+        let outgoing = matches!(edge.direction, Direction::Outgoing | Direction::Both);
+        let incoming = matches!(edge.direction, Direction::Incoming | Direction::Both);
+        let out = graph
+            .edges_directed(node, petgraph::Direction::Outgoing)
+            .filter(move |e| outgoing && wanted(&e.weight().kind))
+            .map(|e| e.target());
+        let inc = graph
+            .edges_directed(node, petgraph::Direction::Incoming)
+            .filter(move |e| incoming && wanted(&e.weight().kind))
+            .map(|e| e.source());
+        out.chain(inc)
+    }
+
+    fn run_steps(&self, plan: &Plan) -> Result<Vec<Row>, QlError> {
+        let mut rows: Vec<Row> = vec![vec![None; plan.vars.len()]];
+        for (step, cond) in plan.steps.iter().zip(&plan.conds) {
+            let mut next = Vec::new();
+            match step {
+                Step::Scan { var, source, .. } => {
+                    let candidates = self.scan(source);
+                    for row in &rows {
+                        for &candidate in &candidates {
+                            let mut row = row.clone();
+                            row[*var] = Some(candidate);
+                            if self.eval(cond, &row) {
+                                self.push(&mut next, row)?;
+                            }
+                        }
+                    }
+                }
+                Step::Expand {
+                    from,
+                    edge,
+                    to,
+                    bound,
+                    ..
+                } => {
+                    for row in &rows {
+                        let Some(start) = row[*from] else { continue };
+                        let reached = self.reach(start, edge);
+                        if *bound {
+                            let reached: HashSet<NodeId> = reached.into_iter().collect();
+                            if row[*to].is_some_and(|target| reached.contains(&target)) {
+                                self.push(&mut next, row.clone())?;
+                            }
+                            continue;
+                        }
+                        for target in reached {
+                            let mut row = row.clone();
+                            row[*to] = Some(target);
+                            if self.eval(cond, &row) {
+                                self.push(&mut next, row)?;
+                            }
+                        }
+                    }
+                }
+                Step::Filter(_) => {
+                    for row in rows {
+                        if self.eval(cond, &row) {
+                            self.push(&mut next, row)?;
+                        }
+                    }
+                }
+            }
+            rows = next;
+            self.check_time()?;
+        }
+        Ok(rows)
+    }
+
+    fn project(&self, projection: &Projection, slots: &[String], row: &Row) -> Value {
+        let slot = |var: &str| slots.iter().position(|v| v == var);
+        match projection {
+            Projection::Node(var) => match slot(var).and_then(|s| row[s]) {
+                Some(index) => Value::Node(index),
+                None => Value::Null,
+            },
+            Projection::Prop(var, prop) => match slot(var).and_then(|s| row[s]) {
+                Some(index) => self.property(index, *prop),
+                None => Value::Null,
+            },
+            Projection::Count => Value::Int(1),
+        }
+    }
+}
+
+/// How an `ORDER BY` item gets its value.
+enum SortKey<'a> {
+    Column(usize),
+    Row(&'a Projection),
+}
+
+fn compare(left: &Value, op: CmpOp, right: &Value) -> bool {
+    if *left == Value::Null || *right == Value::Null {
+        return false;
+    }
+    let ordering = left.partial(right);
+    match op {
+        CmpOp::Eq => ordering == Some(Ordering::Equal),
+        CmpOp::Ne => ordering.is_some_and(|o| o != Ordering::Equal),
+        CmpOp::Lt => ordering == Some(Ordering::Less),
+        CmpOp::Le => ordering.is_some_and(|o| o != Ordering::Greater),
+        CmpOp::Gt => ordering == Some(Ordering::Greater),
+        CmpOp::Ge => ordering.is_some_and(|o| o != Ordering::Less),
+        CmpOp::Contains | CmpOp::StartsWith | CmpOp::EndsWith => match (left, right) {
+            (Value::Str(l), Value::Str(r)) => match op {
+                CmpOp::Contains => l.contains(r.as_str()),
+                CmpOp::StartsWith => l.starts_with(r.as_str()),
+                _ => l.ends_with(r.as_str()),
+            },
+            _ => false,
+        },
+        // Handled with the literal list in `eval`; globs are compiled
+        CmpOp::In | CmpOp::Glob => false,
+    }
+}
+
+/// Runs a plan.
+pub fn execute(plan: &Plan, graph: &ArborGraph, limits: &QueryLimits) -> Result<QlResult, QlError> {
+    let executor = Executor {
+        graph,
+        limits,
+        start: Instant::now(),
+    };
+    let rows = executor.run_steps(plan)?;
+    let query = &plan.query;
+
+    let aggregate = query
+        .returns
+        .iter()
+        .any(|r| r.projection == Projection::Count);
+    let keys: Vec<(SortKey, bool)> = query
+        .order
+        .iter()
+        .map(|item| {
+            let column = query.returns.iter().position(|r| {
+                r.projection == item.projection
+                    || matches!(&item.projection, Projection::Node(name)
+                        if r.alias.as_deref() == Some(name.as_str()))
+            });
+            match column {
+                Some(column) => Ok((SortKey::Column(column), item.descending)),
+                None if aggregate => Err(QlError::Invalid(format!(
+                    "ORDER BY {} must be a returned column when using count(*)",
+                    item.projection
+                ))),
+                None => Ok((SortKey::Row(&item.projection), item.descending)),
+            }
+        })
+        .collect::<Result<_, _>>()?;
+
+    // (columns, sort values)
+    let mut output: Vec<(Vec<Value>, Vec<Value>)> = Vec::new();
+    if aggregate {
+        let mut groups: HashMap<String, usize> = HashMap::new();
+        for row in &rows {
+            let columns: Vec<Value> = query
+                .returns
+                .iter()
+                .map(|r| executor.project(&r.projection, &plan.vars, row))
+                .collect();
+            let key = format!("{:?}", columns);
+            match groups.get(&key) {
+                Some(&group) => {
+                    for (i, item) in query.returns.iter().enumerate() {
+                        if item.projection == Projection::Count {
+                            if let Value::Int(n) = &mut output[group].0[i] {
+                                *n += 1;
+                            }
+                        }
+                    }
+                }
+                None => {
+                    groups.insert(key, output.len());
+                    output.push((columns, Vec::new()));
+                }
+            }
+        }
+        // `MATCH ... RETURN count(*)` with no matches is still one row
+        if output.is_empty()
+            && query
+                .returns
+                .iter()
+                .all(|r| r.projection == Projection::Count)
+        {
+            output.push((vec![Value::Int(0); query.returns.len()], Vec::new()));
+        }
+    } else {
+        for row in &rows {
+            let columns: Vec<Value> = query
+                .returns
+                .iter()
+                .map(|r| executor.project(&r.projection, &plan.vars, row))
+                .collect();
+            let sort = keys
+                .iter()
+                .filter_map(|(key, _)| match key {
+                    SortKey::Row(projection) => Some(executor.project(projection, &plan.vars, row)),
+                    SortKey::Column(_) => None,
+                })
+                .collect();
+            output.push((columns, sort));
+        }
+    }
+
+    if !keys.is_empty() {
+        output.sort_by(|(a_cols, a_sort), (b_cols, b_sort)| {
+            let mut row_key = 0;
+            for (key, descending) in &keys {
+                let ordering = match key {
+                    SortKey::Column(c) => a_cols[*c].total(&b_cols[*c]),
+                    SortKey::Row(_) => {
+                        row_key += 1;
+                        a_sort[row_key - 1].total(&b_sort[row_key - 1])
+                    }
+                };
+                let ordering = if *descending {
+                    ordering.reverse()
+                } else {
+                    ordering
+                };
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            Ordering::Equal
+        });
+    }
+
+    if query.distinct {
+        let mut seen = HashSet::new();
+        output.retain(|(columns, _)| seen.insert(format!("{:?}", columns)));
+    }
+
+    let cap = query
+        .limit
+        .map_or(limits.max_results, |l| l.min(limits.max_results));
+    let truncated = output.len() > cap && query.limit.is_none_or(|l| l > limits.max_results);
+    output.truncate(cap);
+
+    Ok(QlResult {
+        columns: plan.columns(),
+        rows: output
+            .into_iter()
+            .map(|(columns, _)| columns.iter().map(|v| v.to_json(graph)).collect())
+            .collect(),
+        truncated,
+        query_time_ms: executor.start.elapsed().as_millis() as u64,
+    })
+}
@@ -0,0 +1,208 @@
+//! Tokenizer for ArborQL.
+
+use super::QlError;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Token {
+    Ident(String),
+    Str(String),
+    Int(i64),
+    Float(f64),
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    LBrace,
+    RBrace,
+    Colon,
+    Comma,
+    Dot,
+    DotDot,
+    Pipe,
+    Star,
+    Dash,
+    /// `->`
+    Arrow,
+    /// `<-`
+    LArrow,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    /// `=~`
+    Tilde,
+}
+
+/// A token and the byte offset it starts at.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Spanned {
+    pub token: Token,
+    pub pos: usize,
+}
+
+/// Splits a query into tokens. `//` starts a comment to the end of the line.
+pub(crate) fn tokenize(text: &str) -> Result<Vec<Spanned>, QlError> {
+    let bytes = text.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let c = bytes[i];
+        let start = i;
+        let next = bytes.get(i + 1).copied();
+
+        if c.is_ascii_whitespace() {
+            i += 1;
+            continue;
+        }
+        if c == b'/' && next == Some(b'/') {
+            while i < bytes.len() && bytes[i] != b'\n' {
+                i += 1;
+            }
+            continue;
+        }
+
+        let (token, len) = match (c, next) {
+            (b'(', _) => (Token::LParen, 1),
+            (b')', _) => (Token::RParen, 1),
+            (b'[', _) => (Token::LBracket, 1),
+            (b']', _) => (Token::RBracket, 1),
+            (b'{', _) => (Token::LBrace, 1),
+            (b'}', _) => (Token::RBrace, 1),
+            (b':', _) => (Token::Colon, 1),
+            (b',', _) => (Token::Comma, 1),
+            (b'.', Some(b'.')) => (Token::DotDot, 2),
+            (b'.', _) => (Token::Dot, 1),
+            (b'|', _) => (Token::Pipe, 1),
+            (b'*', _) => (Token::Star, 1),
+            (b'-', Some(b'>')) => (Token::Arrow, 2),
+            (b'-', _) => (Token::Dash, 1),
+            (b'<', Some(b'-')) => (Token::LArrow, 2),
+            (b'<', Some(b'=')) => (Token::Le, 2),
+            (b'<', Some(b'>')) => (Token::Ne, 2),
+            (b'<', _) => (Token::Lt, 1),
+            (b'>', Some(b'=')) => (Token::Ge, 2),
+            (b'>', _) => (Token::Gt, 1),
+            (b'=', Some(b'~')) => (Token::Tilde, 2),
+            (b'=', _) => (Token::Eq, 1),
+            (b'!', Some(b'=')) => (Token::Ne, 2),
+            (b'"' | b'\'', _) => {
+                let (value, end) = string(text, i)?;
+                tokens.push(Spanned {
+                    token: Token::Str(value),
+                    pos: start,
+                });
+                i = end;
+                continue;
+            }
+            (b'0'..=b'9', _) => {
+                while i < bytes.len() && bytes[i].is_ascii_digit() {
+                    i += 1;
+                }
+                // "1..3" is a range, not a float
+                let float =
+                    i + 1 < bytes.len() && bytes[i] == b'.' && bytes[i + 1].is_ascii_digit();
+                if float {
+                    i += 1;
+                    while i < bytes.len() && bytes[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+                let literal = &text[start..i];
+                let token = if float {
+                    literal.parse().map(Token::Float).ok()
+                } else {
+                    literal.parse().map(Token::Int).ok()
+                };
+                let token = token.ok_or_else(|| QlError::syntax(start, "number out of range"))?;
+                tokens.push(Spanned { token, pos: start });
+                continue;
+            }
+            (c, _) if c == b'_' || c.is_ascii_alphabetic() => {
+                while i < bytes.len() && (bytes[i] == b'_' || bytes[i].is_ascii_alphanumeric()) {
+                    i += 1;
+                }
+                tokens.push(Spanned {
+                    token: Token::Ident(text[start..i].to_string()),
+                    pos: start,
+                });
+                continue;
+            }
+            _ => {
+                let ch = text[i..].chars().next().unwrap_or('?');
+                return Err(QlError::syntax(i, format!("unexpected character '{}'", ch)));
+            }
+        };
+        tokens.push(Spanned { token, pos: start });
+        i += len;
+    }
+    Ok(tokens)
+}
+
+/// Reads a quoted string starting at `start`. Returns it and the offset
+/// after the closing quote.
+fn string(text: &str, start: usize) -> Result<(String, usize), QlError> {
+    let quote = text.as_bytes()[start] as char;
+    let mut value = String::new();
+    let mut chars = text[start + 1..].char_indices();
+    while let Some((offset, ch)) = chars.next() {
+        match ch {
+            '\\' => match chars.next() {
+                Some((_, 'n')) => value.push('\n'),
+                Some((_, 't')) => value.push('\t'),
+                Some((_, other)) => value.push(other),
+                None => break,
+            },
+            c if c == quote => return Ok((value, start + 1 + offset + 1)),
+            c => value.push(c),
+        }
+    }
+    Err(QlError::syntax(start, "unterminated string"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize() {
+        let tokens: Vec<Token> = tokenize("(a)<-[:calls*1..3]-(b {line: 2.5}) // hi\n'it\\'s'")
+            .unwrap()
+            .into_iter()
+            .map(|s| s.token)
+            .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::LParen,
+                Token::Ident("a".into()),
+                Token::RParen,
+                Token::LArrow,
+                Token::LBracket,
+                Token::Colon,
+                Token::Ident("calls".into()),
+                Token::Star,
+                Token::Int(1),
+                Token::DotDot,
+                Token::Int(3),
+                Token::RBracket,
+                Token::Dash,
+                Token::LParen,
+                Token::Ident("b".into()),
+                Token::LBrace,
+                Token::Ident("line".into()),
+                Token::Colon,
+                Token::Float(2.5),
+                Token::RBrace,
+                Token::RParen,
+                Token::Str("it's".into()),
+            ]
+        );
+        assert!(matches!(
+            tokenize("a = \"open"),
+            Err(QlError::Syntax { position: 4, .. })
+        ));
+    }
+}
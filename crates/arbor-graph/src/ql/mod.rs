@@ -0,0 +1,279 @@
+//! ArborQL: a small declarative query language over the graph.
+//!
+//! ```text
+//! MATCH (f:function {name: "save"})<-[:calls*1..3]-(caller)
+//! WHERE caller.file =~ "src/api/**" AND NOT caller.test
+//! RETURN caller.qualified_name, caller.file
+//! ORDER BY caller.centrality DESC
+//! LIMIT 10
+//! ```
+//!
+//! Node patterns take kinds (`:function|method`) and exact properties
+//! (`{name: "x"}`). Edge patterns take kinds and a hop range; a
+//! variable-length edge binds each node it reaches once, at its shortest
+//! distance. `WHERE` supports `= <> < <= > >=`, `=~` (glob), `CONTAINS`,
+//! `STARTS WITH`, `ENDS WITH`, `IN [...]`, `IS [NOT] NULL`, `AND`, `OR`
+//! and `NOT`. `RETURN` takes nodes, properties and `count(*)`.
+//!
+//! See [`plan`] for how queries use the graph's indexes.
+
+mod ast;
+mod exec;
+mod lexer;
+mod parser;
+mod plan;
+
+pub use ast::{
+    CmpOp, Direction, EdgePattern, Expr, Literal, NodePattern, Operand, OrderItem, Pattern,
+    Projection, Property, Query, ReturnItem,
+};
+pub use exec::{execute, QlResult, QueryLimits};
+pub use parser::parse_query;
+pub use plan::{plan, Plan, Source, Step};
+
+use crate::graph::ArborGraph;
+use std::time::Duration;
+use thiserror::Error;
+
+/// Things that can go wrong with a query.
+#[derive(Error, Debug)]
+pub enum QlError {
+    /// The query doesn't parse. `position` is a byte offset.
+    #[error("syntax error at {position}: {message}")]
+    Syntax { position: usize, message: String },
+
+    /// A variable used in WHERE, RETURN or ORDER BY isn't in any pattern.
+    #[error("unknown variable '{0}'")]
+    UnknownVariable(String),
+
+    /// A `=~` pattern isn't a valid glob.
+    #[error("invalid glob '{pattern}': {source}")]
+    Glob {
+        pattern: String,
+        #[source]
+        source: globset::Error,
+    },
+
+    /// The query parses but can't be run as written.
+    #[error("{0}")]
+    Invalid(String),
+
+    /// A step produced more intermediate rows than allowed.
+    #[error("query produced more than {0} rows; add constraints or raise the limit")]
+    TooManyRows(usize),
+
+    /// The query ran out of time.
+    #[error("query timed out after {0:?}")]
+    Timeout(Duration),
+}
+
+impl QlError {
+    pub(crate) fn syntax(position: usize, message: impl Into<String>) -> Self {
+        Self::Syntax {
+            position,
+            message: message.into(),
+        }
+    }
+}
+
+impl ArborGraph {
+    /// Parses, plans and runs an ArborQL query.
+    pub fn run_query(&self, text: &str, limits: &QueryLimits) -> Result<QlResult, QlError> {
+        let plan = self.explain_query(text)?;
+        execute(&plan, self, limits)
+    }
+
+    /// Parses and plans a query without running it.
+    pub fn explain_query(&self, text: &str) -> Result<Plan, QlError> {
+        plan(parse_query(text)?, self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::GraphBuilder;
+    use crate::edge::{Edge, EdgeKind};
+    use arbor_core::{CodeNode, NodeKind};
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    fn func(name: &str, file: &str, refs: &[&str]) -> CodeNode {
+        CodeNode::new(name, name, NodeKind::Function, file)
+            .with_references(refs.iter().map(|r| r.to_string()).collect())
+    }
+
+    fn graph() -> ArborGraph {
+        let mut builder = GraphBuilder::new();
+        builder.add_nodes(vec![
+            func("save", "src/db.rs", &[]),
+            func("validate", "src/db.rs", &[]),
+            func("create_user", "src/api/users.rs", &["save", "validate"]),
+            func("update_user", "src/api/users.rs", &["save"]),
+            func(
+                "handle",
+                "src/api/router.rs",
+                &["create_user", "update_user"],
+            ),
+            func("test_create", "tests/users.rs", &["create_user"]),
+        ]);
+        let mut graph = builder.build();
+        let scores: HashMap<_, _> = graph
+            .node_indexes()
+            .map(|i| {
+                let score = match graph.get(i).unwrap().name.as_str() {
+                    "update_user" => 0.9,
+                    "create_user" => 0.5,
+                    _ => 0.1,
+                };
+                (i, score)
+            })
+            .collect();
+        graph.set_centrality(scores);
+        graph
+    }
+
+    fn column(result: &QlResult, index: usize) -> Vec<serde_json::Value> {
+        result.rows.iter().map(|r| r[index].clone()).collect()
+    }
+
+    #[test]
+    fn test_variable_length_callers() {
+        let graph = graph();
+        let result = graph
+            .run_query(
+                "MATCH (s {name: 'save'})<-[:calls*]-(c:function)
+                 WHERE NOT c.test
+                 RETURN c.name, c.centrality ORDER BY c.centrality DESC, c.name",
+                &QueryLimits::default(),
+            )
+            .unwrap();
+        assert_eq!(result.columns, vec!["c.name", "c.centrality"]);
+        assert_eq!(
+            column(&result, 0),
+            vec![json!("update_user"), json!("create_user"), json!("handle")]
+        );
+        assert!(!result.truncated);
+
+        let direct = graph
+            .run_query(
+                "MATCH (a)-[:calls]->(b) WHERE a.file =~ 'src/api/*' AND b.file <> a.file \
+                 RETURN DISTINCT a.name AS caller ORDER BY caller",
+                &QueryLimits::default(),
+            )
+            .unwrap();
+        assert_eq!(
+            column(&direct, 0),
+            vec![json!("create_user"), json!("handle"), json!("update_user")]
+        );
+    }
+
+    #[test]
+    fn test_joins_counts_and_nodes() {
+        let graph = graph();
+        // Functions called by both create_user and update_user
+        let shared = graph
+            .run_query(
+                "MATCH (c {name: 'create_user'})-->(x), (u {name: 'update_user'})-->(x) RETURN x",
+                &QueryLimits::default(),
+            )
+            .unwrap();
+        assert_eq!(shared.rows.len(), 1);
+        assert_eq!(shared.rows[0][0]["name"], json!("save"));
+
+        let per_file = graph
+            .run_query(
+                "MATCH (f:function) RETURN f.file, count(*) AS n ORDER BY n DESC, f.file LIMIT 2",
+                &QueryLimits::default(),
+            )
+            .unwrap();
+        assert_eq!(
+            per_file.rows,
+            vec![
+                vec![json!("src/api/users.rs"), json!(2)],
+                vec![json!("src/db.rs"), json!(2)],
+            ]
+        );
+
+        let none = graph
+            .run_query("MATCH (c:class) RETURN count(*)", &QueryLimits::default())
+            .unwrap();
+        assert_eq!(none.rows, vec![vec![json!(0)]]);
+    }
+
+    #[test]
+    fn test_cycles_reach_their_start() {
+        let mut builder = GraphBuilder::new();
+        builder.add_nodes(vec![
+            func("ping", "src/game.rs", &["pong"]),
+            func("pong", "src/game.rs", &["ping"]),
+            func("fact", "src/math.rs", &[]),
+            func("main", "src/main.rs", &["ping", "fact"]),
+        ]);
+        let mut graph = builder.build();
+        // The builder drops self-references, so add the recursive call here
+        let fact = graph.nodes_named("fact")[0];
+        graph.add_edge(fact, fact, Edge::new(EdgeKind::Calls));
+
+        let cyclic = graph
+            .run_query(
+                "MATCH (a)-[:calls*]->(a) RETURN a.name ORDER BY a.name",
+                &QueryLimits::default(),
+            )
+            .unwrap();
+        assert_eq!(
+            column(&cyclic, 0),
+            vec![json!("fact"), json!("ping"), json!("pong")]
+        );
+
+        let recursive = graph
+            .run_query(
+                "MATCH (a)-[:calls]->(a) RETURN a.name",
+                &QueryLimits::default(),
+            )
+            .unwrap();
+        assert_eq!(column(&recursive, 0), vec![json!("fact")]);
+
+        // Zero hops still reaches the start exactly once
+        let reflexive = graph
+            .run_query(
+                "MATCH (a {name: 'ping'})-[:calls*0..]->(b) RETURN b.name ORDER BY b.name",
+                &QueryLimits::default(),
+            )
+            .unwrap();
+        assert_eq!(column(&reflexive, 0), vec![json!("ping"), json!("pong")]);
+    }
+
+    #[test]
+    fn test_limits() {
+        let graph = graph();
+        let tight = QueryLimits {
+            max_rows: 10,
+            ..QueryLimits::default()
+        };
+        assert!(matches!(
+            graph.run_query("MATCH (a), (b) RETURN a, b", &tight),
+            Err(QlError::TooManyRows(10))
+        ));
+
+        let few = QueryLimits {
+            max_results: 2,
+            ..QueryLimits::default()
+        };
+        let result = graph.run_query("MATCH (a) RETURN a.name", &few).unwrap();
+        assert_eq!(result.rows.len(), 2);
+        assert!(result.truncated);
+        let limited = graph
+            .run_query("MATCH (a) RETURN a.name LIMIT 1", &few)
+            .unwrap();
+        assert!(!limited.truncated);
+
+        assert!(matches!(
+            graph.run_query(
+                "MATCH (a) RETURN a.file, count(*) ORDER BY a.name",
+                &QueryLimits::default()
+            ),
+            Err(QlError::Invalid(_))
+        ));
+    }
+}
@@ -0,0 +1,562 @@
+//! Recursive-descent parser for ArborQL.
+//!
+//! ```text
+//! query    := MATCH pattern ("," pattern)* [WHERE expr]
+//!             RETURN [DISTINCT] item ("," item)*
+//!             [ORDER BY order ("," order)*] [LIMIT int]
+//! pattern  := node (edge node)*
+//! node     := "(" [var] [":" kind ("|" kind)*] ["{" prop ":" literal, ... "}"] ")"
+//! edge     := ("-" | "<-") ["[" [":" kind ("|" kind)*] ["*" [int] [".." [int]]] "]"] ("-" | "->")
+//! expr     := and (OR and)*      and := not (AND not)*      not := NOT not | atom
+//! atom     := "(" expr ")" | operand [op operand | IS [NOT] NULL]
+//! item     := (var | var.prop | count(*)) [AS name]
+//! ```
+//!
+//! Keywords are case-insensitive.
+
+use super::ast::*;
+use super::lexer::{tokenize, Spanned, Token};
+use super::QlError;
+use crate::edge::EdgeKind;
+use arbor_core::NodeKind;
+
+/// Parses a query.
+pub fn parse_query(text: &str) -> Result<Query, QlError> {
+    let mut parser = Parser {
+        tokens: tokenize(text)?,
+        pos: 0,
+        end: text.len(),
+        anonymous: 0,
+    };
+    let query = parser.query()?;
+    match parser.peek() {
+        None => Ok(query),
+        Some(_) => Err(parser.error("expected end of query")),
+    }
+}
+
+struct Parser {
+    tokens: Vec<Spanned>,
+    pos: usize,
+    /// Offset reported for errors at the end of input.
+    end: usize,
+    anonymous: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|s| &s.token)
+    }
+
+    fn peek_at(&self, ahead: usize) -> Option<&Token> {
+        self.tokens.get(self.pos + ahead).map(|s| &s.token)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).map(|s| s.token.clone());
+        self.pos += 1;
+        token
+    }
+
+    fn error(&self, message: impl Into<String>) -> QlError {
+        let position = self.tokens.get(self.pos).map_or(self.end, |s| s.pos);
+        QlError::syntax(position, message)
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &Token, what: &str) -> Result<(), QlError> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err(self.error(format!("expected {}", what)))
+        }
+    }
+
+    fn at_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Ident(word)) if word.eq_ignore_ascii_case(keyword))
+    }
+
+    fn keyword(&mut self, keyword: &str) -> bool {
+        let found = self.at_keyword(keyword);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), QlError> {
+        if self.keyword(keyword) {
+            Ok(())
+        } else {
+            Err(self.error(format!("expected {}", keyword)))
+        }
+    }
+
+    fn ident(&mut self, what: &str) -> Result<String, QlError> {
+        match self.peek() {
+            Some(Token::Ident(name)) if !is_reserved(name) => {
+                let name = name.clone();
+                self.pos += 1;
+                Ok(name)
+            }
+            _ => Err(self.error(format!("expected {}", what))),
+        }
+    }
+
+    fn int(&mut self, what: &str) -> Result<usize, QlError> {
+        match self.peek() {
+            Some(Token::Int(n)) if *n >= 0 => {
+                let n = *n as usize;
+                self.pos += 1;
+                Ok(n)
+            }
+            _ => Err(self.error(format!("expected {}", what))),
+        }
+    }
+
+    // ─── Clauses ────────────────────────────────────────────────────────
+
+    fn query(&mut self) -> Result<Query, QlError> {
+        self.expect_keyword("MATCH")?;
+        let mut patterns = vec![self.pattern()?];
+        while self.eat(&Token::Comma) {
+            patterns.push(self.pattern()?);
+        }
+
+        let filter = if self.keyword("WHERE") {
+            Some(self.expr()?)
+        } else {
+            None
+        };
+
+        self.expect_keyword("RETURN")?;
+        let distinct = self.keyword("DISTINCT");
+        let mut returns = vec![self.return_item()?];
+        while self.eat(&Token::Comma) {
+            returns.push(self.return_item()?);
+        }
+
+        let mut order = Vec::new();
+        if self.keyword("ORDER") {
+            self.expect_keyword("BY")?;
+            loop {
+                let projection = self.projection()?;
+                let descending = if self.keyword("DESC") {
+                    true
+                } else {
+                    self.keyword("ASC");
+                    false
+                };
+                order.push(OrderItem {
+                    projection,
+                    descending,
+                });
+                if !self.eat(&Token::Comma) {
+                    break;
+                }
+            }
+        }
+
+        let limit = if self.keyword("LIMIT") {
+            Some(self.int("a number after LIMIT")?)
+        } else {
+            None
+        };
+
+        Ok(Query {
+            patterns,
+            filter,
+            distinct,
+            returns,
+            order,
+            limit,
+        })
+    }
+
+    fn return_item(&mut self) -> Result<ReturnItem, QlError> {
+        let projection = self.projection()?;
+        let alias = if self.keyword("AS") {
+            Some(self.ident("a column name after AS")?)
+        } else {
+            None
+        };
+        Ok(ReturnItem { projection, alias })
+    }
+
+    fn projection(&mut self) -> Result<Projection, QlError> {
+        if self.at_keyword("count") && self.peek_at(1) == Some(&Token::LParen) {
+            self.pos += 2;
+            if !self.eat(&Token::Star) {
+                self.ident("* or a variable in count()")?;
+            }
+            self.expect(&Token::RParen, "')'")?;
+            return Ok(Projection::Count);
+        }
+        let var = self.ident("a variable")?;
+        if self.eat(&Token::Dot) {
+            Ok(Projection::Prop(var, self.property()?))
+        } else {
+            Ok(Projection::Node(var))
+        }
+    }
+
+    fn property(&mut self) -> Result<Property, QlError> {
+        match self.peek() {
+            Some(Token::Ident(name)) => match Property::parse(name) {
+                Some(prop) => {
+                    self.pos += 1;
+                    Ok(prop)
+                }
+                None => Err(self.error(format!("unknown property '{}'", name))),
+            },
+            _ => Err(self.error("expected a property name")),
+        }
+    }
+
+    // ─── Patterns ───────────────────────────────────────────────────────
+
+    fn pattern(&mut self) -> Result<Pattern, QlError> {
+        let start = self.node()?;
+        let mut steps = Vec::new();
+        while matches!(self.peek(), Some(Token::Dash | Token::LArrow)) {
+            let edge = self.edge()?;
+            steps.push((edge, self.node()?));
+        }
+        Ok(Pattern { start, steps })
+    }
+
+    fn node(&mut self) -> Result<NodePattern, QlError> {
+        self.expect(&Token::LParen, "'(' to start a node pattern")?;
+        let var = match self.peek() {
+            Some(Token::Ident(_)) => self.ident("a variable")?,
+            _ => {
+                self.anonymous += 1;
+                format!("#{}", self.anonymous)
+            }
+        };
+
+        let mut kinds = Vec::new();
+        if self.eat(&Token::Colon) {
+            loop {
+                let name = self.ident("a node kind")?;
+                let kind = name.parse::<NodeKind>().map_err(|e| self.back(e))?;
+                kinds.push(kind);
+                if !self.eat(&Token::Pipe) {
+                    break;
+                }
+            }
+        }
+
+        let mut props = Vec::new();
+        if self.eat(&Token::LBrace) {
+            loop {
+                let prop = self.property()?;
+                self.expect(&Token::Colon, "':' after the property name")?;
+                props.push((prop, self.literal()?));
+                if !self.eat(&Token::Comma) {
+                    break;
+                }
+            }
+            self.expect(&Token::RBrace, "'}'")?;
+        }
+
+        self.expect(&Token::RParen, "')' to close the node pattern")?;
+        Ok(NodePattern { var, kinds, props })
+    }
+
+    fn edge(&mut self) -> Result<EdgePattern, QlError> {
+        let incoming = self.next() == Some(Token::LArrow);
+        let mut kinds = Vec::new();
+        let (mut min_hops, mut max_hops) = (1, Some(1));
+
+        if self.eat(&Token::LBracket) {
+            if matches!(self.peek(), Some(Token::Ident(_))) {
+                return Err(self.error("edge variables aren't supported"));
+            }
+            if self.eat(&Token::Colon) {
+                loop {
+                    let name = self.ident("an edge kind")?;
+                    let kind = name.parse::<EdgeKind>().map_err(|e| self.back(e))?;
+                    kinds.push(kind);
+                    if !self.eat(&Token::Pipe) {
+                        break;
+                    }
+                }
+            }
+            if self.eat(&Token::Star) {
+                min_hops = 1;
+                max_hops = None;
+                if let Some(Token::Int(_)) = self.peek() {
+                    min_hops = self.int("a hop count")?;
+                    max_hops = Some(min_hops);
+                }
+                if self.eat(&Token::DotDot) {
+                    max_hops = match self.peek() {
+                        Some(Token::Int(_)) => Some(self.int("a hop count")?),
+                        _ => None,
+                    };
+                }
+                if max_hops.is_some_and(|max| max < min_hops) {
+                    return Err(self.error("hop range ends before it starts"));
+                }
+            }
+            self.expect(&Token::RBracket, "']'")?;
+        }
+
+        let outgoing = match self.next() {
+            Some(Token::Arrow) => true,
+            Some(Token::Dash) => false,
+            _ => {
+                self.pos -= 1;
+                return Err(self.error("expected '-' or '->' to finish the edge"));
+            }
+        };
+        let direction = match (incoming, outgoing) {
+            (false, true) => Direction::Outgoing,
+            (true, false) => Direction::Incoming,
+            (false, false) => Direction::Both,
+            (true, true) => {
+                self.pos -= 1;
+                return Err(self.error("an edge can't point both ways"));
+            }
+        };
+        Ok(EdgePattern {
+            kinds,
+            direction,
+            min_hops,
+            max_hops,
+        })
+    }
+
+    /// An error about the token just consumed.
+    fn back(&mut self, message: String) -> QlError {
+        self.pos -= 1;
+        self.error(message)
+    }
+
+    // ─── Expressions ────────────────────────────────────────────────────
+
+    fn expr(&mut self) -> Result<Expr, QlError> {
+        let mut left = self.and()?;
+        while self.keyword("OR") {
+            left = Expr::Or(Box::new(left), Box::new(self.and()?));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expr, QlError> {
+        let mut left = self.not()?;
+        while self.keyword("AND") {
+            left = Expr::And(Box::new(left), Box::new(self.not()?));
+        }
+        Ok(left)
+    }
+
+    fn not(&mut self) -> Result<Expr, QlError> {
+        if self.keyword("NOT") {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        if self.eat(&Token::LParen) {
+            let inner = self.expr()?;
+            self.expect(&Token::RParen, "')'")?;
+            return Ok(inner);
+        }
+
+        let left = self.operand()?;
+        if self.keyword("IS") {
+            let negated = self.keyword("NOT");
+            self.expect_keyword("NULL")?;
+            return Ok(Expr::IsNull {
+                operand: left,
+                negated,
+            });
+        }
+
+        let op = match self.peek() {
+            Some(Token::Eq) => CmpOp::Eq,
+            Some(Token::Ne) => CmpOp::Ne,
+            Some(Token::Lt) => CmpOp::Lt,
+            Some(Token::Le) => CmpOp::Le,
+            Some(Token::Gt) => CmpOp::Gt,
+            Some(Token::Ge) => CmpOp::Ge,
+            Some(Token::Tilde) => CmpOp::Glob,
+            // `a < -1` lexes as `a <- 1`
+            Some(Token::LArrow) => {
+                self.pos += 1;
+                let right = match self.operand()? {
+                    Operand::Literal(Literal::Int(n)) => Literal::Int(-n),
+                    Operand::Literal(Literal::Float(x)) => Literal::Float(-x),
+                    _ => return Err(self.error("expected a number after '<-'")),
+                };
+                return Ok(Expr::Compare {
+                    left,
+                    op: CmpOp::Lt,
+                    right: Operand::Literal(right),
+                });
+            }
+            Some(Token::Ident(word)) => match word.to_uppercase().as_str() {
+                "CONTAINS" => CmpOp::Contains,
+                "IN" => CmpOp::In,
+                "STARTS" | "ENDS" => {
+                    let op = if word.eq_ignore_ascii_case("STARTS") {
+                        CmpOp::StartsWith
+                    } else {
+                        CmpOp::EndsWith
+                    };
+                    self.pos += 1;
+                    if !self.at_keyword("WITH") {
+                        return Err(self.error("expected WITH"));
+                    }
+                    op
+                }
+                _ => return Ok(Expr::Truthy(left)),
+            },
+            _ => return Ok(Expr::Truthy(left)),
+        };
+        self.pos += 1;
+        let right = self.operand()?;
+        if op == CmpOp::In && !matches!(right, Operand::Literal(Literal::List(_))) {
+            return Err(self.error("IN needs a list like [\"a\", \"b\"]"));
+        }
+        Ok(Expr::Compare { left, op, right })
+    }
+
+    fn operand(&mut self) -> Result<Operand, QlError> {
+        if let Some(Token::Ident(name)) = self.peek() {
+            if !is_literal_word(name) {
+                let var = self.ident("a variable")?;
+                self.expect(&Token::Dot, "'.' and a property, like n.name")?;
+                return Ok(Operand::Prop(var, self.property()?));
+            }
+        }
+        Ok(Operand::Literal(self.literal()?))
+    }
+
+    fn literal(&mut self) -> Result<Literal, QlError> {
+        let negative = self.eat(&Token::Dash);
+        let literal = match self.peek().cloned() {
+            Some(Token::Int(n)) => Literal::Int(if negative { -n } else { n }),
+            Some(Token::Float(x)) => Literal::Float(if negative { -x } else { x }),
+            _ if negative => return Err(self.error("expected a number after '-'")),
+            Some(Token::Str(s)) => Literal::Str(s),
+            Some(Token::Ident(word)) if word.eq_ignore_ascii_case("true") => Literal::Bool(true),
+            Some(Token::Ident(word)) if word.eq_ignore_ascii_case("false") => Literal::Bool(false),
+            Some(Token::Ident(word)) if word.eq_ignore_ascii_case("null") => Literal::Null,
+            Some(Token::LBracket) => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                if !self.eat(&Token::RBracket) {
+                    loop {
+                        items.push(self.literal()?);
+                        if !self.eat(&Token::Comma) {
+                            break;
+                        }
+                    }
+                    self.expect(&Token::RBracket, "']'")?;
+                }
+                return Ok(Literal::List(items));
+            }
+            _ => return Err(self.error("expected a value")),
+        };
+        self.pos += 1;
+        Ok(literal)
+    }
+}
+
+fn is_literal_word(word: &str) -> bool {
+    ["true", "false", "null"]
+        .iter()
+        .any(|w| word.eq_ignore_ascii_case(w))
+}
+
+/// Words that end a clause, so can't be variable names.
+fn is_reserved(word: &str) -> bool {
+    [
+        "match", "where", "return", "order", "by", "limit", "and", "or", "not", "as", "asc",
+        "desc", "distinct", "is", "in", "contains", "starts", "ends", "with", "true", "false",
+        "null",
+    ]
+    .iter()
+    .any(|w| word.eq_ignore_ascii_case(w))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_full_query() {
+        let query = parse_query(
+            "match (f:function|method {name: 'save'})<-[:calls*1..3]-(c)
+             WHERE c.file =~ 'src/**' AND NOT c.test OR c.line < -1
+             RETURN DISTINCT c.name AS caller, f, count(*)
+             ORDER BY c.centrality DESC, caller LIMIT 5",
+        )
+        .unwrap();
+
+        let pattern = &query.patterns[0];
+        assert_eq!(pattern.start.var, "f");
+        assert_eq!(
+            pattern.start.kinds,
+            vec![NodeKind::Function, NodeKind::Method]
+        );
+        assert_eq!(
+            pattern.start.props,
+            vec![(Property::Name, Literal::Str("save".into()))]
+        );
+        let (edge, caller) = &pattern.steps[0];
+        assert_eq!(edge.direction, Direction::Incoming);
+        assert_eq!(edge.kinds, vec![EdgeKind::Calls]);
+        assert_eq!((edge.min_hops, edge.max_hops), (1, Some(3)));
+        assert_eq!(caller.var, "c");
+
+        assert_eq!(
+            query.filter.unwrap().to_string(),
+            "((c.file =~ \"src/**\" AND NOT c.test) OR c.line < -1)"
+        );
+        assert!(query.distinct);
+        let columns: Vec<String> = query.returns.iter().map(|r| r.column()).collect();
+        assert_eq!(columns, vec!["caller", "f", "count(*)"]);
+        assert!(query.order[0].descending && !query.order[1].descending);
+        assert_eq!(query.limit, Some(5));
+    }
+
+    #[test]
+    fn test_edge_shapes_and_errors() {
+        let edge = |text: &str| {
+            let query = parse_query(&format!("MATCH (a){}(b) RETURN a", text)).unwrap();
+            query.patterns[0].steps[0].0.clone()
+        };
+        assert_eq!(edge("-->").direction, Direction::Outgoing);
+        assert_eq!(edge("<--").direction, Direction::Incoming);
+        assert_eq!(edge("--").direction, Direction::Both);
+        assert_eq!(edge("-[*]->").max_hops, None);
+        assert_eq!(edge("-[*2]->").max_hops, Some(2));
+        assert_eq!(
+            (edge("-[*..4]->").min_hops, edge("-[*..4]->").max_hops),
+            (1, Some(4))
+        );
+
+        let anonymous = parse_query("MATCH ()-->(:class) RETURN count(*)").unwrap();
+        assert_eq!(anonymous.patterns[0].start.var, "#1");
+
+        let error = |text: &str| match parse_query(text) {
+            Err(QlError::Syntax { position, message }) => (position, message),
+            other => panic!("expected a syntax error, got {:?}", other),
+        };
+        assert_eq!(error("MATCH (a:widget) RETURN a").0, 9);
+        assert!(error("MATCH (a)<-->(b) RETURN a").1.contains("both ways"));
+        assert!(error("MATCH (a) RETURN a.colour")
+            .1
+            .contains("unknown property"));
+        assert_eq!(error("MATCH (a) RETURN a LIMIT").0, 24);
+    }
+}
@@ -0,0 +1,538 @@
+//! Query planning.
+//!
+//! Turns a [`Query`] into a list of steps over variable slots:
+//!
+//! 1. Node pattern constraints and single-variable `WHERE` conjuncts are
+//!    pushed down onto the variable they test.
+//! 2. Each pattern starts from its most selective node: an exact `name`,
+//!    `qualified_name` or `file` uses those indexes, a kind list uses the
+//!    kind index, anything else scans all nodes. A pattern sharing a
+//!    variable with an earlier one starts from that variable instead.
+//! 3. The chain is walked outwards from the start, and conjuncts over
+//!    several variables run as soon as all of them are bound.
+
+use super::ast::*;
+use super::QlError;
+use crate::graph::ArborGraph;
+use globset::{Glob, GlobMatcher};
+use std::fmt;
+
+/// Where a scan gets its candidates.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Source {
+    Name(String),
+    QualifiedName(String),
+    File(String),
+    Kinds(Vec<arbor_core::NodeKind>),
+    All,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Name(name) => write!(f, "name index {:?}", name),
+            Self::QualifiedName(name) => write!(f, "qualified name index {:?}", name),
+            Self::File(file) => write!(f, "file index {:?}", file),
+            Self::Kinds(kinds) => {
+                let kinds: Vec<String> = kinds.iter().map(|k| k.to_string()).collect();
+                write!(f, "kind index {}", kinds.join("|"))
+            }
+            Self::All => write!(f, "all nodes"),
+        }
+    }
+}
+
+/// One step of a plan.
+#[derive(Debug, Clone)]
+pub enum Step {
+    /// Binds `var` to each candidate passing `filter`.
+    Scan {
+        var: usize,
+        source: Source,
+        estimate: usize,
+        filter: Vec<Expr>,
+    },
+    /// Follows `edge` from `from` to `to`. When `to` is already bound,
+    /// only checks that it's reachable.
+    Expand {
+        from: usize,
+        edge: EdgePattern,
+        to: usize,
+        bound: bool,
+        filter: Vec<Expr>,
+    },
+    /// Drops rows failing a condition over several variables.
+    Filter(Expr),
+}
+
+/// An executable plan.
+#[derive(Debug, Clone)]
+pub struct Plan {
+    pub vars: Vec<String>,
+    pub steps: Vec<Step>,
+    pub(crate) conds: Vec<Cond>,
+    pub(crate) query: Query,
+}
+
+impl Plan {
+    /// Column names of the results.
+    pub fn columns(&self) -> Vec<String> {
+        self.query.returns.iter().map(|r| r.column()).collect()
+    }
+}
+
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let where_clause = |filter: &[Expr]| {
+            if filter.is_empty() {
+                String::new()
+            } else {
+                let parts: Vec<String> = filter.iter().map(|e| e.to_string()).collect();
+                format!(" where {}", parts.join(" AND "))
+            }
+        };
+        for (i, step) in self.steps.iter().enumerate() {
+            write!(f, "{}. ", i + 1)?;
+            match step {
+                Step::Scan {
+                    var,
+                    source,
+                    estimate,
+                    filter,
+                } => writeln!(
+                    f,
+                    "scan {} from {} (~{} nodes){}",
+                    self.vars[*var],
+                    source,
+                    estimate,
+                    where_clause(filter)
+                )?,
+                Step::Expand {
+                    from,
+                    edge,
+                    to,
+                    bound,
+                    filter,
+                } => {
+                    let kinds: Vec<String> = edge.kinds.iter().map(|k| k.to_string()).collect();
+                    let kinds = if kinds.is_empty() {
+                        "any".to_string()
+                    } else {
+                        kinds.join("|")
+                    };
+                    let hops = match (edge.min_hops, edge.max_hops) {
+                        (1, Some(1)) => String::new(),
+                        (min, Some(max)) if min == max => format!(" ×{}", min),
+                        (min, Some(max)) => format!(" ×{}..{}", min, max),
+                        (min, None) => format!(" ×{}..", min),
+                    };
+                    let (left, right) = match edge.direction {
+                        Direction::Outgoing => ("-", "->"),
+                        Direction::Incoming => ("<-", "-"),
+                        Direction::Both => ("-", "-"),
+                    };
+                    writeln!(
+                        f,
+                        "{} {} {}[{}{}]{} {}{}",
+                        if *bound { "check" } else { "expand" },
+                        self.vars[*from],
+                        left,
+                        kinds,
+                        hops,
+                        right,
+                        self.vars[*to],
+                        where_clause(filter)
+                    )?
+                }
+                Step::Filter(expr) => writeln!(f, "filter {}", expr)?,
+            }
+        }
+        let columns = self.columns().join(", ");
+        write!(f, "{}. return {}", self.steps.len() + 1, columns)?;
+        if !self.query.order.is_empty() {
+            let order: Vec<String> = self
+                .query
+                .order
+                .iter()
+                .map(|o| {
+                    format!(
+                        "{}{}",
+                        o.projection,
+                        if o.descending { " DESC" } else { "" }
+                    )
+                })
+                .collect();
+            write!(f, " order by {}", order.join(", "))?;
+        }
+        if let Some(limit) = self.query.limit {
+            write!(f, " limit {}", limit)?;
+        }
+        writeln!(f)
+    }
+}
+
+/// A compiled condition. Variables are slots; globs are compiled.
+#[derive(Debug, Clone)]
+pub(crate) enum Cond {
+    True,
+    And(Vec<Cond>),
+    Or(Box<Cond>, Box<Cond>),
+    Not(Box<Cond>),
+    Compare { left: Term, op: CmpOp, right: Term },
+    Glob { term: Term, matcher: GlobMatcher },
+    IsNull { term: Term, negated: bool },
+    Truthy(Term),
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum Term {
+    Prop(usize, Property),
+    Literal(Literal),
+}
+
+/// Plans a parsed query against a graph.
+pub fn plan(query: Query, graph: &ArborGraph) -> Result<Plan, QlError> {
+    let mut vars: Vec<String> = Vec::new();
+    for pattern in &query.patterns {
+        for node in pattern.nodes() {
+            if !vars.contains(&node.var) {
+                vars.push(node.var.clone());
+            }
+        }
+    }
+    let slot = |name: &str| {
+        vars.iter()
+            .position(|v| v == name)
+            .ok_or_else(|| QlError::UnknownVariable(name.to_string()))
+    };
+
+    // Pushed-down conditions per variable
+    let mut local: Vec<Vec<Expr>> = vec![Vec::new(); vars.len()];
+    for pattern in &query.patterns {
+        for node in pattern.nodes() {
+            let var = slot(&node.var)?;
+            if !node.kinds.is_empty() {
+                let kinds = node
+                    .kinds
+                    .iter()
+                    .map(|k| Literal::Str(k.to_string()))
+                    .collect();
+                local[var].push(Expr::Compare {
+                    left: Operand::Prop(node.var.clone(), Property::Kind),
+                    op: CmpOp::In,
+                    right: Operand::Literal(Literal::List(kinds)),
+                });
+            }
+            for (prop, value) in &node.props {
+                local[var].push(Expr::Compare {
+                    left: Operand::Prop(node.var.clone(), *prop),
+                    op: CmpOp::Eq,
+                    right: Operand::Literal(value.clone()),
+                });
+            }
+        }
+    }
+    let mut pending: Vec<(Vec<usize>, Expr)> = Vec::new();
+    if let Some(filter) = &query.filter {
+        for conjunct in filter.clone().conjuncts() {
+            let mut names = Vec::new();
+            conjunct.vars(&mut names);
+            let slots = names
+                .iter()
+                .map(|n| slot(n))
+                .collect::<Result<Vec<_>, _>>()?;
+            match slots.as_slice() {
+                [single] => local[*single].push(conjunct),
+                _ => pending.push((slots, conjunct)),
+            }
+        }
+    }
+    for item in &query.returns {
+        check_projection(&item.projection, &slot)?;
+    }
+    for item in &query.order {
+        let is_alias = matches!(&item.projection, Projection::Node(name)
+            if query.returns.iter().any(|r| r.alias.as_deref() == Some(name.as_str())));
+        if !is_alias {
+            check_projection(&item.projection, &slot)?;
+        }
+    }
+
+    let mut steps = Vec::new();
+    let mut bound = vec![false; vars.len()];
+    let flush = |bound: &[bool], pending: &mut Vec<(Vec<usize>, Expr)>, steps: &mut Vec<Step>| {
+        pending.retain(|(slots, expr)| {
+            if slots.iter().all(|s| bound[*s]) {
+                steps.push(Step::Filter(expr.clone()));
+                false
+            } else {
+                true
+            }
+        });
+    };
+    flush(&bound, &mut pending, &mut steps);
+
+    for pattern in &query.patterns {
+        let chain: Vec<usize> = pattern
+            .nodes()
+            .map(|n| slot(&n.var))
+            .collect::<Result<_, _>>()?;
+        let edges: Vec<&EdgePattern> = pattern.steps.iter().map(|(e, _)| e).collect();
+
+        let start = match chain.iter().position(|v| bound[*v]) {
+            Some(position) => position,
+            None => {
+                let (position, source, estimate) = chain
+                    .iter()
+                    .enumerate()
+                    .map(|(i, v)| {
+                        let (source, estimate) = choose_source(&vars[*v], &local[*v], graph);
+                        (i, source, estimate)
+                    })
+                    .min_by_key(|(_, _, estimate)| *estimate)
+                    .expect("a pattern has at least one node");
+                let var = chain[position];
+                steps.push(Step::Scan {
+                    var,
+                    source,
+                    estimate,
+                    filter: local[var].clone(),
+                });
+                bound[var] = true;
+                flush(&bound, &mut pending, &mut steps);
+                position
+            }
+        };
+
+        let mut walk = |from: usize, edge: EdgePattern, to: usize, steps: &mut Vec<Step>| {
+            let already = bound[to];
+            steps.push(Step::Expand {
+                from,
+                edge,
+                to,
+                bound: already,
+                filter: if already {
+                    Vec::new()
+                } else {
+                    local[to].clone()
+                },
+            });
+            bound[to] = true;
+            flush(&bound, &mut pending, steps);
+        };
+        for i in start + 1..chain.len() {
+            walk(chain[i - 1], edges[i - 1].clone(), chain[i], &mut steps);
+        }
+        for i in (0..start).rev() {
+            walk(chain[i + 1], edges[i].reversed(), chain[i], &mut steps);
+        }
+    }
+
+    let conds = steps
+        .iter()
+        .map(|step| match step {
+            Step::Scan { filter, .. } | Step::Expand { filter, .. } => compile_all(filter, &slot),
+            Step::Filter(expr) => compile(expr, &slot),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Plan {
+        vars,
+        steps,
+        conds,
+        query,
+    })
+}
+
+fn check_projection(
+    projection: &Projection,
+    slot: &impl Fn(&str) -> Result<usize, QlError>,
+) -> Result<(), QlError> {
+    match projection {
+        Projection::Node(var) | Projection::Prop(var, _) => slot(var).map(|_| ()),
+        Projection::Count => Ok(()),
+    }
+}
+
+/// Picks the cheapest index for a variable from its conditions.
+fn choose_source(var: &str, conditions: &[Expr], graph: &ArborGraph) -> (Source, usize) {
+    let mut best = (Source::All, graph.node_count());
+    for condition in conditions {
+        let Expr::Compare {
+            left: Operand::Prop(v, prop),
+            op,
+            right: Operand::Literal(value),
+        } = condition
+        else {
+            continue;
+        };
+        if v != var {
+            continue;
+        }
+        let candidate = match (prop, op, value) {
+            (Property::Name, CmpOp::Eq, Literal::Str(name)) => {
+                (Source::Name(name.clone()), graph.nodes_named(name).len())
+            }
+            (Property::QualifiedName, CmpOp::Eq, Literal::Str(name)) => (
+                Source::QualifiedName(name.clone()),
+                graph.nodes_qualified(name).len(),
+            ),
+            (Property::File, CmpOp::Eq, Literal::Str(file)) => {
+                (Source::File(file.clone()), graph.nodes_in_file(file).len())
+            }
+            (Property::Kind, CmpOp::Eq | CmpOp::In, value) => {
+                let names = match value {
+                    Literal::List(items) => items.clone(),
+                    other => vec![other.clone()],
+                };
+                let kinds: Vec<_> = names
+                    .iter()
+                    .filter_map(|n| match n {
+                        Literal::Str(s) => s.parse().ok(),
+                        _ => None,
+                    })
+                    .collect();
+                let estimate = kinds.iter().map(|k| graph.nodes_of_kind(*k).len()).sum();
+                (Source::Kinds(kinds), estimate)
+            }
+            _ => continue,
+        };
+        if candidate.1 < best.1 {
+            best = candidate;
+        }
+    }
+    best
+}
+
+fn compile_all(
+    exprs: &[Expr],
+    slot: &impl Fn(&str) -> Result<usize, QlError>,
+) -> Result<Cond, QlError> {
+    if exprs.is_empty() {
+        return Ok(Cond::True);
+    }
+    Ok(Cond::And(
+        exprs
+            .iter()
+            .map(|e| compile(e, slot))
+            .collect::<Result<_, _>>()?,
+    ))
+}
+
+fn compile(expr: &Expr, slot: &impl Fn(&str) -> Result<usize, QlError>) -> Result<Cond, QlError> {
+    let term = |operand: &Operand| -> Result<Term, QlError> {
+        Ok(match operand {
+            Operand::Prop(var, prop) => Term::Prop(slot(var)?, *prop),
+            Operand::Literal(literal) => Term::Literal(literal.clone()),
+        })
+    };
+    Ok(match expr {
+        Expr::And(left, right) => Cond::And(vec![compile(left, slot)?, compile(right, slot)?]),
+        Expr::Or(left, right) => Cond::Or(
+            Box::new(compile(left, slot)?),
+            Box::new(compile(right, slot)?),
+        ),
+        Expr::Not(inner) => Cond::Not(Box::new(compile(inner, slot)?)),
+        Expr::Compare {
+            left,
+            op: CmpOp::Glob,
+            right,
+        } => {
+            let Operand::Literal(Literal::Str(pattern)) = right else {
+                return Err(QlError::Invalid(format!(
+                    "the right side of =~ must be a glob string, not {}",
+                    right
+                )));
+            };
+            let matcher = Glob::new(pattern)
+                .map_err(|source| QlError::Glob {
+                    pattern: pattern.clone(),
+                    source,
+                })?
+                .compile_matcher();
+            Cond::Glob {
+                term: term(left)?,
+                matcher,
+            }
+        }
+        Expr::Compare { left, op, right } => Cond::Compare {
+            left: term(left)?,
+            op: *op,
+            right: term(right)?,
+        },
+        Expr::IsNull { operand, negated } => Cond::IsNull {
+            term: term(operand)?,
+            negated: *negated,
+        },
+        Expr::Truthy(operand) => Cond::Truthy(term(operand)?),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::GraphBuilder;
+    use crate::ql::parse_query;
+    use arbor_core::{CodeNode, NodeKind};
+
+    fn graph() -> ArborGraph {
+        let mut builder = GraphBuilder::new();
+        let mut nodes: Vec<CodeNode> = (0..20)
+            .map(|i| {
+                let name = format!("f{}", i);
+                CodeNode::new(&name, &name, NodeKind::Function, "src/lib.rs")
+            })
+            .collect();
+        nodes.push(CodeNode::new(
+            "Config",
+            "Config",
+            NodeKind::Struct,
+            "src/config.rs",
+        ));
+        builder.add_nodes(nodes);
+        builder.build()
+    }
+
+    fn explain(text: &str) -> String {
+        plan(parse_query(text).unwrap(), &graph())
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn test_plan_starts_from_most_selective_node() {
+        assert_eq!(
+            explain("MATCH (f:function)-[:uses_type]->(s:struct) WHERE f.line > s.line RETURN f"),
+            "1. scan s from kind index struct (~1 nodes) where s.kind IN [\"struct\"]\n\
+             2. expand s <-[uses_type]- f where f.kind IN [\"function\"]\n\
+             3. filter f.line > s.line\n\
+             4. return f\n"
+        );
+        assert_eq!(
+            explain(
+                "MATCH (a)-[*2..]->(b {name: 'f3'}), (b)-->(a) RETURN a.name ORDER BY a.centrality DESC LIMIT 3"
+            ),
+            "1. scan b from name index \"f3\" (~1 nodes) where b.name = \"f3\"\n\
+             2. expand b <-[any ×2..]- a\n\
+             3. check b -[any]-> a\n\
+             4. return a.name order by a.centrality DESC limit 3\n"
+        );
+    }
+
+    #[test]
+    fn test_plan_errors() {
+        let error = |text: &str| plan(parse_query(text).unwrap(), &graph()).unwrap_err();
+        assert!(matches!(
+            error("MATCH (a) RETURN b"),
+            QlError::UnknownVariable(v) if v == "b"
+        ));
+        assert!(matches!(
+            error("MATCH (a) WHERE a.file =~ a.name RETURN a"),
+            QlError::Invalid(_)
+        ));
+        assert!(matches!(
+            error("MATCH (a) WHERE a.file =~ 'src/[' RETURN a"),
+            QlError::Glob { .. }
+        ));
+    }
+}
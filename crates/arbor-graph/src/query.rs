@@ -45,8 +45,13 @@ pub struct NodeInfo {
     pub file: String,
     pub line_start: u32,
     pub line_end: u32,
+    #[serde(default)]
+    pub column: u32,
     pub signature: Option<String>,
     pub centrality: f64,
+    /// Search relevance, when the node came from a search.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<f64>,
 }
 
 impl From<&CodeNode> for NodeInfo {
@@ -59,8 +64,10 @@ impl From<&CodeNode> for NodeInfo {
             file: node.file.clone(),
             line_start: node.line_start,
             line_end: node.line_end,
+            column: node.column,
             signature: node.signature.clone(),
             centrality: 0.0, // Will be filled in by the graph
+            score: None,
         }
     }
 }
@@ -0,0 +1,579 @@
+//! Collapsed dependency graphs.
+//!
+//! The symbol graph is too fine for architecture questions like "which
+//! packages depend on `billing`?". Collapsing it merges every node in a
+//! file, directory, package or cluster into one unit; the quotient graph
+//! has an edge between two units when any of their symbols depend on each
+//! other, weighted by how many symbol-level dependencies of each kind it
+//! stands for. Resolved imports count as `imports`.
+
+use crate::clusters::ClusterOptions;
+use crate::edge::EdgeKind;
+use crate::graph::ArborGraph;
+use crate::impact::{ImpactDirection, ImpactSeverity};
+use crate::metrics::MetricsLevel;
+use crate::testing::is_test_file;
+use arbor_core::{CodeNode, NodeKind};
+use petgraph::graph::{DiGraph, NodeIndex};
+use petgraph::visit::EdgeRef;
+use petgraph::Direction;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+/// What nodes are collapsed into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CollapseLevel {
+    File,
+    Directory,
+    /// The directory above `src`, `lib`, `pkg` or `internal`, as in
+    /// `arbor metrics`.
+    #[default]
+    Package,
+    /// Architecture clusters; computed with default options if the graph
+    /// has none assigned.
+    Cluster,
+}
+
+impl std::fmt::Display for CollapseLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::File => "file",
+            Self::Directory => "directory",
+            Self::Package => "package",
+            Self::Cluster => "cluster",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl std::str::FromStr for CollapseLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "file" | "module" => Ok(Self::File),
+            "directory" | "dir" => Ok(Self::Directory),
+            "package" | "pkg" => Ok(Self::Package),
+            "cluster" => Ok(Self::Cluster),
+            _ => Err(format!(
+                "unknown collapse level '{}' (expected file, directory, package or cluster)",
+                s
+            )),
+        }
+    }
+}
+
+/// How to collapse the graph.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CollapseOptions {
+    pub level: CollapseLevel,
+
+    /// Edge kinds that count. Empty means every kind except `contains`,
+    /// `flows_to` and `data_dependency`.
+    pub edge_kinds: Vec<EdgeKind>,
+
+    /// Whether test code takes part.
+    pub include_tests: bool,
+}
+
+impl CollapseOptions {
+    pub fn new(level: CollapseLevel) -> Self {
+        Self {
+            level,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_edge_kind(mut self, kind: EdgeKind) -> Self {
+        self.edge_kinds.push(kind);
+        self
+    }
+
+    pub fn with_tests(mut self, include: bool) -> Self {
+        self.include_tests = include;
+        self
+    }
+}
+
+/// A file, directory, package or cluster.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuotientNode {
+    pub id: String,
+    pub files: usize,
+    /// Symbols other than imports, exports and modules.
+    pub symbols: usize,
+}
+
+/// All dependencies from one unit to another.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuotientEdge {
+    pub from: String,
+    pub to: String,
+    /// Symbol-level dependencies this edge stands for.
+    pub weight: usize,
+    pub kinds: BTreeMap<EdgeKind, usize>,
+}
+
+/// A unit reached from the target of an impact query.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AffectedUnit {
+    pub unit: String,
+    pub severity: ImpactSeverity,
+    pub hop_distance: usize,
+    /// The unit it was reached from.
+    pub via: String,
+    /// Weight of the edge between `via` and this unit.
+    pub weight: usize,
+    pub direction: ImpactDirection,
+}
+
+/// Units that depend on, or are depended on by, a unit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuotientImpact {
+    pub target: String,
+    pub level: CollapseLevel,
+    /// Units depending on the target, nearest first.
+    pub upstream: Vec<AffectedUnit>,
+    /// Units the target depends on, nearest first.
+    pub downstream: Vec<AffectedUnit>,
+    pub total_affected: usize,
+    pub max_depth: usize,
+}
+
+/// The graph with nodes collapsed into units.
+#[derive(Debug, Clone, Serialize)]
+pub struct QuotientGraph {
+    pub level: CollapseLevel,
+    /// Sorted by ID.
+    nodes: Vec<QuotientNode>,
+    /// Heaviest first.
+    edges: Vec<QuotientEdge>,
+    /// Positions in `nodes` and `edges`.
+    #[serde(skip)]
+    graph: DiGraph<usize, usize>,
+    #[serde(skip)]
+    index: HashMap<String, NodeIndex>,
+}
+
+impl ArborGraph {
+    /// Collapses the graph into a weighted graph of files, directories,
+    /// packages or clusters.
+    pub fn collapse(&self, options: &CollapseOptions) -> QuotientGraph {
+        let level = options.level;
+        let computed: HashMap<String, String>;
+        let file_cluster: Option<&HashMap<String, String>> =
+            if level == CollapseLevel::Cluster && self.clusters().is_empty() {
+                computed = self
+                    .compute_clusters(&ClusterOptions::default())
+                    .clusters
+                    .into_iter()
+                    .flat_map(|c| c.files.into_iter().map(move |f| (f, c.id.clone())))
+                    .collect();
+                Some(&computed)
+            } else {
+                None
+            };
+        let unit_of = |node: &CodeNode| -> String {
+            match level {
+                CollapseLevel::File => node.file.clone(),
+                CollapseLevel::Directory => MetricsLevel::Directory.unit_of(&node.file).to_string(),
+                CollapseLevel::Package => MetricsLevel::Package.unit_of(&node.file).to_string(),
+                CollapseLevel::Cluster => file_cluster
+                    .and_then(|m| m.get(&node.file).cloned())
+                    .or_else(|| node.cluster.clone())
+                    .unwrap_or_else(|| "unclustered".to_string()),
+            }
+        };
+        let skip = |node: &CodeNode| {
+            !options.include_tests && (is_test_file(&node.file) || self.is_test(node))
+        };
+
+        let mut units: BTreeMap<String, (HashSet<&str>, usize)> = BTreeMap::new();
+        let mut unit_by_file: HashMap<&str, String> = HashMap::new();
+        for node in self.nodes().filter(|n| !skip(n)) {
+            let unit = unit_of(node);
+            unit_by_file.insert(&node.file, unit.clone());
+            let entry = units.entry(unit).or_default();
+            entry.0.insert(&node.file);
+            if !matches!(
+                node.kind,
+                NodeKind::Import | NodeKind::Export | NodeKind::Module
+            ) {
+                entry.1 += 1;
+            }
+        }
+
+        let mut weights: BTreeMap<(String, String), BTreeMap<EdgeKind, usize>> = BTreeMap::new();
+        for dep in self.dependencies(options.include_tests) {
+            if !options.edge_kinds.is_empty() && !options.edge_kinds.contains(&dep.kind) {
+                continue;
+            }
+            let (Some(from), Some(to)) = (
+                unit_by_file.get(dep.source.file.as_str()),
+                unit_by_file.get(dep.target_file),
+            ) else {
+                continue;
+            };
+            if from == to {
+                continue;
+            }
+            *weights
+                .entry((from.clone(), to.clone()))
+                .or_default()
+                .entry(dep.kind)
+                .or_default() += 1;
+        }
+
+        let nodes = units
+            .into_iter()
+            .map(|(id, (files, symbols))| QuotientNode {
+                id,
+                files: files.len(),
+                symbols,
+            })
+            .collect();
+        let mut edges: Vec<QuotientEdge> = weights
+            .into_iter()
+            .map(|((from, to), kinds)| QuotientEdge {
+                from,
+                to,
+                weight: kinds.values().sum(),
+                kinds,
+            })
+            .collect();
+        edges.sort_by_key(|e| std::cmp::Reverse(e.weight));
+        QuotientGraph::new(level, nodes, edges)
+    }
+}
+
+impl QuotientGraph {
+    fn new(level: CollapseLevel, nodes: Vec<QuotientNode>, edges: Vec<QuotientEdge>) -> Self {
+        let mut graph = DiGraph::new();
+        let index: HashMap<String, NodeIndex> = nodes
+            .iter()
+            .enumerate()
+            .map(|(i, n)| (n.id.clone(), graph.add_node(i)))
+            .collect();
+        for (i, edge) in edges.iter().enumerate() {
+            graph.add_edge(index[&edge.from], index[&edge.to], i);
+        }
+        Self {
+            level,
+            nodes,
+            edges,
+            graph,
+            index,
+        }
+    }
+
+    pub fn nodes(&self) -> &[QuotientNode] {
+        &self.nodes
+    }
+
+    pub fn edges(&self) -> &[QuotientEdge] {
+        &self.edges
+    }
+
+    pub fn node(&self, unit: &str) -> Option<&QuotientNode> {
+        self.index.get(unit).map(|&i| &self.nodes[self.graph[i]])
+    }
+
+    /// Finds a unit by ID, or by its last path segment if that is unique,
+    /// so `billing` finds `services/billing`.
+    pub fn find_unit(&self, name: &str) -> Option<&str> {
+        if let Some(node) = self.node(name) {
+            return Some(&node.id);
+        }
+        let name = name.trim_end_matches('/');
+        let mut matches = self.nodes.iter().filter(|n| {
+            n.id.rsplit('/').next() == Some(name) || n.id.ends_with(&format!("/{}", name))
+        });
+        match (matches.next(), matches.next()) {
+            (Some(only), None) => Some(&only.id),
+            _ => None,
+        }
+    }
+
+    /// Edges from a unit, heaviest first.
+    pub fn dependencies_of(&self, unit: &str) -> Vec<&QuotientEdge> {
+        self.neighbors(unit, Direction::Outgoing)
+    }
+
+    /// Edges into a unit, heaviest first.
+    pub fn dependents_of(&self, unit: &str) -> Vec<&QuotientEdge> {
+        self.neighbors(unit, Direction::Incoming)
+    }
+
+    fn neighbors(&self, unit: &str, direction: Direction) -> Vec<&QuotientEdge> {
+        let Some(&index) = self.index.get(unit) else {
+            return Vec::new();
+        };
+        let mut edges: Vec<&QuotientEdge> = self
+            .graph
+            .edges_directed(index, direction)
+            .map(|e| &self.edges[*e.weight()])
+            .collect();
+        edges.sort_by(|a, b| b.weight.cmp(&a.weight).then_with(|| a.from.cmp(&b.from)));
+        edges
+    }
+
+    /// Shortest chain of dependencies from one unit to another.
+    pub fn find_path(&self, from: &str, to: &str) -> Option<Vec<&str>> {
+        let (&start, &goal) = (self.index.get(from)?, self.index.get(to)?);
+        let (_, path) = petgraph::algo::astar(&self.graph, start, |n| n == goal, |_| 1, |_| 0)?;
+        Some(
+            path.into_iter()
+                .map(|i| self.nodes[self.graph[i]].id.as_str())
+                .collect(),
+        )
+    }
+
+    /// Units that depend on a unit, transitively, with their hop distance.
+    /// `max_depth` 0 is unlimited.
+    pub fn get_dependents(&self, unit: &str, max_depth: usize) -> Vec<(&str, usize)> {
+        self.walk(unit, Direction::Incoming, max_depth)
+            .into_iter()
+            .map(|a| (self.nodes[self.graph[a.0]].id.as_str(), a.1))
+            .collect()
+    }
+
+    /// Units affected by changing a unit, in both directions. `max_depth`
+    /// 0 is unlimited.
+    pub fn analyze_impact(&self, unit: &str, max_depth: usize) -> Option<QuotientImpact> {
+        self.index.get(unit)?;
+        let affected = |direction: Direction| -> Vec<AffectedUnit> {
+            self.walk(unit, direction, max_depth)
+                .into_iter()
+                .map(|(index, hops, via, edge)| AffectedUnit {
+                    unit: self.nodes[self.graph[index]].id.clone(),
+                    severity: ImpactSeverity::from_hops(hops),
+                    hop_distance: hops,
+                    via: self.nodes[self.graph[via]].id.clone(),
+                    weight: self.edges[edge].weight,
+                    direction: match direction {
+                        Direction::Incoming => ImpactDirection::Upstream,
+                        Direction::Outgoing => ImpactDirection::Downstream,
+                    },
+                })
+                .collect()
+        };
+        let upstream = affected(Direction::Incoming);
+        let downstream = affected(Direction::Outgoing);
+        Some(QuotientImpact {
+            target: unit.to_string(),
+            level: self.level,
+            total_affected: upstream.len() + downstream.len(),
+            upstream,
+            downstream,
+            max_depth,
+        })
+    }
+
+    /// Breadth-first search returning (unit, hops, previous unit, edge).
+    fn walk(
+        &self,
+        unit: &str,
+        direction: Direction,
+        max_depth: usize,
+    ) -> Vec<(NodeIndex, usize, NodeIndex, usize)> {
+        let Some(&start) = self.index.get(unit) else {
+            return Vec::new();
+        };
+        let mut seen = HashMap::from([(start, 0)]);
+        let mut queue = VecDeque::from([(start, 0)]);
+        let mut found = Vec::new();
+        while let Some((current, hops)) = queue.pop_front() {
+            if max_depth > 0 && hops >= max_depth {
+                continue;
+            }
+            let mut next: Vec<_> = self.graph.edges_directed(current, direction).collect();
+            next.sort_by_key(|e| std::cmp::Reverse(self.edges[*e.weight()].weight));
+            for edge in next {
+                let other = match direction {
+                    Direction::Incoming => edge.source(),
+                    Direction::Outgoing => edge.target(),
+                };
+                if seen.contains_key(&other) {
+                    continue;
+                }
+                seen.insert(other, hops + 1);
+                found.push((other, hops + 1, current, *edge.weight()));
+                queue.push_back((other, hops + 1));
+            }
+        }
+        found
+    }
+
+    /// The units within `depth` hops of a unit in either direction, and the
+    /// edges between them.
+    pub fn neighborhood(&self, unit: &str, depth: usize) -> QuotientGraph {
+        let mut keep: Vec<&str> = vec![unit];
+        for direction in [Direction::Incoming, Direction::Outgoing] {
+            keep.extend(
+                self.walk(unit, direction, depth)
+                    .into_iter()
+                    .map(|(i, ..)| self.nodes[self.graph[i]].id.as_str()),
+            );
+        }
+        let nodes = self
+            .nodes
+            .iter()
+            .filter(|n| keep.contains(&n.id.as_str()))
+            .cloned()
+            .collect();
+        let edges = self
+            .edges
+            .iter()
+            .filter(|e| keep.contains(&e.from.as_str()) && keep.contains(&e.to.as_str()))
+            .cloned()
+            .collect();
+        QuotientGraph::new(self.level, nodes, edges)
+    }
+
+    /// Graphviz DOT, with edge labels giving weights.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph arbor {\n  rankdir=LR;\n  node [shape=box];\n");
+        for node in &self.nodes {
+            out.push_str(&format!(
+                "  \"{}\" [label=\"{}\\n{} symbols\"];\n",
+                dot_escape(&node.id),
+                dot_escape(&node.id),
+                node.symbols
+            ));
+        }
+        for edge in &self.edges {
+            out.push_str(&format!(
+                "  \"{}\" -> \"{}\" [label=\"{}\", penwidth={:.1}];\n",
+                dot_escape(&edge.from),
+                dot_escape(&edge.to),
+                edge.weight,
+                1.0 + (edge.weight as f64).ln()
+            ));
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// A Mermaid flowchart for Markdown docs.
+    pub fn to_mermaid(&self) -> String {
+        let ids: HashMap<&str, usize> = self
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(i, n)| (n.id.as_str(), i))
+            .collect();
+        let mut out = String::from("flowchart LR\n");
+        for (i, node) in self.nodes.iter().enumerate() {
+            out.push_str(&format!(
+                "  u{}[\"{}\"]\n",
+                i,
+                node.id.replace('"', "#quot;")
+            ));
+        }
+        for edge in &self.edges {
+            out.push_str(&format!(
+                "  u{} -->|{}| u{}\n",
+                ids[edge.from.as_str()],
+                edge.weight,
+                ids[edge.to.as_str()]
+            ));
+        }
+        out
+    }
+}
+
+fn dot_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::GraphBuilder;
+
+    fn func(name: &str, file: &str, refs: &[&str]) -> CodeNode {
+        CodeNode::new(name, name, NodeKind::Function, file)
+            .with_references(refs.iter().map(|r| r.to_string()).collect())
+    }
+
+    fn graph() -> ArborGraph {
+        let mut builder = GraphBuilder::new();
+        builder.add_nodes(vec![
+            func("charge", "services/billing/src/charge.rs", &["query"]),
+            func(
+                "refund",
+                "services/billing/src/refund.rs",
+                &["charge", "query"],
+            ),
+            func(
+                "checkout",
+                "services/shop/src/cart.rs",
+                &["charge", "refund"],
+            ),
+            func("report", "tools/admin/main.rs", &["refund"]),
+            func("query", "services/db/src/pool.rs", &[]),
+        ]);
+        builder.build()
+    }
+
+    #[test]
+    fn test_collapse_packages() {
+        let quotient = graph().collapse(&CollapseOptions::default());
+        let ids: Vec<&str> = quotient.nodes().iter().map(|n| n.id.as_str()).collect();
+        assert_eq!(
+            ids,
+            vec!["services/billing", "services/db", "services/shop", "tools"]
+        );
+        let billing = quotient.node("services/billing").unwrap();
+        assert_eq!((billing.files, billing.symbols), (2, 2));
+
+        let dependents: Vec<(&str, usize)> = quotient
+            .dependents_of("services/billing")
+            .iter()
+            .map(|e| (e.from.as_str(), e.weight))
+            .collect();
+        assert_eq!(dependents, vec![("services/shop", 2), ("tools", 1)]);
+        assert_eq!(
+            quotient.dependencies_of("services/billing")[0].kinds[&EdgeKind::Calls],
+            2
+        );
+
+        assert_eq!(quotient.find_unit("billing"), Some("services/billing"));
+        assert_eq!(
+            quotient.find_path("tools", "services/db"),
+            Some(vec!["tools", "services/billing", "services/db"])
+        );
+    }
+
+    #[test]
+    fn test_quotient_impact_and_export() {
+        let quotient = graph().collapse(&CollapseOptions::new(CollapseLevel::Package));
+        let impact = quotient.analyze_impact("services/db", 0).unwrap();
+        let upstream: Vec<(&str, usize)> = impact
+            .upstream
+            .iter()
+            .map(|a| (a.unit.as_str(), a.hop_distance))
+            .collect();
+        assert_eq!(
+            upstream,
+            vec![("services/billing", 1), ("services/shop", 2), ("tools", 2)]
+        );
+        assert!(impact.downstream.is_empty());
+        assert_eq!(quotient.get_dependents("services/db", 1).len(), 1);
+        assert!(quotient.analyze_impact("missing", 0).is_none());
+
+        let near = quotient.neighborhood("services/shop", 1);
+        assert_eq!(near.nodes().len(), 2);
+        assert_eq!(near.edges().len(), 1);
+        assert!(near
+            .to_dot()
+            .contains("\"services/shop\" -> \"services/billing\" [label=\"2\""));
+        assert!(near.to_mermaid().contains("u1 -->|2| u0"));
+
+        let json = serde_json::to_value(&near).unwrap();
+        assert_eq!(json["level"], "package");
+        assert_eq!(json["edges"][0]["kinds"]["calls"], 2);
+    }
+}
@@ -1,16 +1,28 @@
 //! Centrality ranking for code nodes.
 //!
-//! We use a simplified PageRank variant to score nodes by their
-//! architectural significance. Nodes that are called by many
-//! others rank higher.
+//! PageRank over call edges scores nodes by their architectural
+//! significance: nodes that are called by many others rank higher.
+//! `compute_centrality_with` adds personalized PageRank, betweenness,
+//! degree and HITS measures over a weighted choice of edge kinds. Every
+//! measure runs on a compressed sparse row snapshot of the graph rather
+//! than walking petgraph neighbours on each iteration.
 
+use crate::edge::EdgeKind;
 use crate::graph::{ArborGraph, NodeId};
-use std::collections::HashMap;
+use petgraph::visit::{EdgeRef, IntoEdgeReferences};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+/// Above this many nodes betweenness samples its BFS sources.
+const BETWEENNESS_SOURCES: usize = 256;
 
 /// Stores centrality scores after computation.
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct CentralityScores {
+    /// Normalized to [0, 1].
     scores: HashMap<NodeId, f64>,
+    /// Unnormalized PageRank values, kept for incremental refreshes.
+    raw: HashMap<NodeId, f64>,
 }
 
 impl CentralityScores {
@@ -19,18 +31,239 @@ impl CentralityScores {
         self.scores.get(&id).copied().unwrap_or(0.0)
     }
 
+    /// Returns true if no scores have been computed.
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+
+    /// Returns the normalized scores.
+    pub fn scores(&self) -> &HashMap<NodeId, f64> {
+        &self.scores
+    }
+
     /// Converts to a HashMap for storage in the graph.
     pub fn into_map(self) -> HashMap<NodeId, f64> {
         self.scores
     }
 }
 
+/// Which centrality measure to compute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CentralityMeasure {
+    /// Global PageRank.
+    #[default]
+    PageRank,
+    /// PageRank that teleports back to the seed nodes.
+    PersonalizedPageRank,
+    /// How many shortest paths pass through a node.
+    Betweenness,
+    /// Weighted count of incoming edges.
+    InDegree,
+    /// Weighted count of outgoing edges.
+    OutDegree,
+    /// HITS hub score: points at good authorities.
+    Hub,
+    /// HITS authority score: pointed at by good hubs.
+    Authority,
+}
+
+impl std::fmt::Display for CentralityMeasure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::PageRank => "page_rank",
+            Self::PersonalizedPageRank => "personalized_page_rank",
+            Self::Betweenness => "betweenness",
+            Self::InDegree => "in_degree",
+            Self::OutDegree => "out_degree",
+            Self::Hub => "hub",
+            Self::Authority => "authority",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl std::str::FromStr for CentralityMeasure {
+    type Err = String;
+
+    /// Parses the names `Display` prints, plus `pagerank` and `ppr`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().replace('-', "_").as_str() {
+            "page_rank" | "pagerank" => Ok(Self::PageRank),
+            "personalized_page_rank" | "personalized" | "ppr" => Ok(Self::PersonalizedPageRank),
+            "betweenness" => Ok(Self::Betweenness),
+            "in_degree" => Ok(Self::InDegree),
+            "out_degree" => Ok(Self::OutDegree),
+            "hub" => Ok(Self::Hub),
+            "authority" => Ok(Self::Authority),
+            _ => Err(format!(
+                "unknown centrality measure '{}' (expected page_rank, personalized_page_rank, \
+                 betweenness, in_degree, out_degree, hub or authority)",
+                s
+            )),
+        }
+    }
+}
+
+/// What to rank and which edges count.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CentralityOptions {
+    pub measure: CentralityMeasure,
+    /// Weight of each edge kind. Kinds that are missing or weighted zero
+    /// are ignored. Defaults to calls only.
+    pub weights: BTreeMap<EdgeKind, f64>,
+    /// Iterations for PageRank and HITS.
+    pub iterations: usize,
+    /// PageRank damping factor.
+    pub damping: f64,
+}
+
+impl Default for CentralityOptions {
+    fn default() -> Self {
+        Self {
+            measure: CentralityMeasure::PageRank,
+            weights: BTreeMap::from([(EdgeKind::Calls, 1.0)]),
+            iterations: 20,
+            damping: 0.85,
+        }
+    }
+}
+
+impl CentralityOptions {
+    /// Default options for one measure.
+    pub fn new(measure: CentralityMeasure) -> Self {
+        Self {
+            measure,
+            ..Self::default()
+        }
+    }
+
+    /// Sets the weight of an edge kind; zero drops it.
+    pub fn with_weight(mut self, kind: EdgeKind, weight: f64) -> Self {
+        self.weights.insert(kind, weight);
+        self
+    }
+
+    /// Sets the iteration count.
+    pub fn with_iterations(mut self, iterations: usize) -> Self {
+        self.iterations = iterations;
+        self
+    }
+
+    /// Sets the damping factor.
+    pub fn with_damping(mut self, damping: f64) -> Self {
+        self.damping = damping;
+        self
+    }
+}
+
+/// Compressed sparse row snapshot of the weighted edges.
+///
+/// Nodes are renumbered densely; `outgoing(i)` and `incoming(i)` are
+/// contiguous slices of `(neighbour, weight)` pairs.
+#[derive(Debug, Default)]
+pub(crate) struct CsrGraph {
+    nodes: Vec<NodeId>,
+    positions: HashMap<NodeId, usize>,
+    out_offsets: Vec<usize>,
+    out_edges: Vec<(u32, f64)>,
+    in_offsets: Vec<usize>,
+    in_edges: Vec<(u32, f64)>,
+    out_weight: Vec<f64>,
+}
+
+impl CsrGraph {
+    /// Snapshots every edge whose kind has a positive weight.
+    pub(crate) fn new(graph: &ArborGraph, weights: &BTreeMap<EdgeKind, f64>) -> Self {
+        let nodes: Vec<NodeId> = graph.node_indexes().collect();
+        let positions: HashMap<NodeId, usize> =
+            nodes.iter().enumerate().map(|(i, &id)| (id, i)).collect();
+
+        let mut edges: Vec<(usize, usize, f64)> = Vec::new();
+        for edge in graph.graph.edge_references() {
+            let weight = weights.get(&edge.weight().kind).copied().unwrap_or(0.0);
+            if weight <= 0.0 {
+                continue;
+            }
+            if let (Some(&from), Some(&to)) =
+                (positions.get(&edge.source()), positions.get(&edge.target()))
+            {
+                edges.push((from, to, weight));
+            }
+        }
+
+        let n = nodes.len();
+        let (out_offsets, out_edges) = compress(n, edges.iter().map(|&(f, t, w)| (f, t, w)));
+        let (in_offsets, in_edges) = compress(n, edges.iter().map(|&(f, t, w)| (t, f, w)));
+        let out_weight = (0..n)
+            .map(|i| {
+                out_edges[out_offsets[i]..out_offsets[i + 1]]
+                    .iter()
+                    .map(|e| e.1)
+                    .sum()
+            })
+            .collect();
+
+        Self {
+            nodes,
+            positions,
+            out_offsets,
+            out_edges,
+            in_offsets,
+            in_edges,
+            out_weight,
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub(crate) fn position(&self, id: NodeId) -> Option<usize> {
+        self.positions.get(&id).copied()
+    }
+
+    pub(crate) fn outgoing(&self, i: usize) -> &[(u32, f64)] {
+        &self.out_edges[self.out_offsets[i]..self.out_offsets[i + 1]]
+    }
+
+    pub(crate) fn incoming(&self, i: usize) -> &[(u32, f64)] {
+        &self.in_edges[self.in_offsets[i]..self.in_offsets[i + 1]]
+    }
+
+    /// Maps dense scores back to node IDs.
+    fn scores(&self, values: Vec<f64>) -> CentralityScores {
+        CentralityScores::from_raw(self.nodes.iter().copied().zip(values).collect())
+    }
+}
+
+/// Groups `(row, column, weight)` triples by row.
+fn compress(
+    rows: usize,
+    triples: impl Iterator<Item = (usize, usize, f64)> + Clone,
+) -> (Vec<usize>, Vec<(u32, f64)>) {
+    let mut offsets = vec![0usize; rows + 1];
+    for (row, _, _) in triples.clone() {
+        offsets[row + 1] += 1;
+    }
+    for i in 0..rows {
+        offsets[i + 1] += offsets[i];
+    }
+    let mut next = offsets.clone();
+    let mut entries = vec![(0u32, 0.0); offsets[rows]];
+    for (row, column, weight) in triples {
+        entries[next[row]] = (column as u32, weight);
+        next[row] += 1;
+    }
+    (offsets, entries)
+}
+
 /// Computes centrality scores for all nodes in the graph.
 ///
-/// This is a simplified PageRank that:
-/// 1. Initializes all nodes with equal score
-/// 2. Iteratively distributes scores along edges
-/// 3. Applies damping to prevent score concentration
+/// PageRank over call edges: every node starts with an equal score,
+/// which is then distributed along edges for `iterations` rounds with
+/// `damping` applied to prevent score concentration.
 ///
 /// # Arguments
 ///
@@ -38,60 +271,298 @@ impl CentralityScores {
 /// * `iterations` - Number of iterations (10-20 is usually enough)
 /// * `damping` - Damping factor (0.85 is standard)
 pub fn compute_centrality(graph: &ArborGraph, iterations: usize, damping: f64) -> CentralityScores {
-    let node_count = graph.node_count();
-    if node_count == 0 {
+    let options = CentralityOptions::default()
+        .with_iterations(iterations)
+        .with_damping(damping);
+    compute_centrality_with(graph, &options, &[])
+}
+
+/// Computes the measure chosen in `options`.
+///
+/// `seeds` only matter for personalized PageRank, which falls back to
+/// global PageRank when none of them are in the graph.
+pub fn compute_centrality_with(
+    graph: &ArborGraph,
+    options: &CentralityOptions,
+    seeds: &[NodeId],
+) -> CentralityScores {
+    let csr = CsrGraph::new(graph, &options.weights);
+    let n = csr.len();
+    if n == 0 {
         return CentralityScores::default();
     }
 
-    // Initialize scores
-    let initial_score = 1.0 / node_count as f64;
-    let mut scores: HashMap<NodeId, f64> = graph
-        .node_indexes()
-        .map(|idx| (idx, initial_score))
+    let values = match options.measure {
+        CentralityMeasure::PageRank => page_rank(&csr, &vec![1.0 / n as f64; n], options, false),
+        CentralityMeasure::PersonalizedPageRank => {
+            let seeds: HashSet<usize> = seeds.iter().filter_map(|&s| csr.position(s)).collect();
+            if seeds.is_empty() {
+                page_rank(&csr, &vec![1.0 / n as f64; n], options, false)
+            } else {
+                let mut teleport = vec![0.0; n];
+                for &seed in &seeds {
+                    teleport[seed] = 1.0 / seeds.len() as f64;
+                }
+                page_rank(&csr, &teleport, options, true)
+            }
+        }
+        CentralityMeasure::Betweenness => betweenness(&csr),
+        CentralityMeasure::InDegree => (0..n)
+            .map(|i| csr.incoming(i).iter().map(|e| e.1).sum())
+            .collect(),
+        CentralityMeasure::OutDegree => csr.out_weight.clone(),
+        CentralityMeasure::Hub => hits(&csr, options.iterations).0,
+        CentralityMeasure::Authority => hits(&csr, options.iterations).1,
+    };
+
+    csr.scores(values)
+}
+
+/// Power iteration starting from and teleporting to `teleport`.
+///
+/// Global PageRank walks call direction only and loses the mass of
+/// nodes without outgoing edges, as it always has. The personalized walk
+/// follows edges both ways, so callers and callees of a seed both score,
+/// and sends stranded mass back to the seeds.
+fn page_rank(
+    csr: &CsrGraph,
+    teleport: &[f64],
+    options: &CentralityOptions,
+    personalized: bool,
+) -> Vec<f64> {
+    let n = csr.len();
+    let damping = options.damping;
+    let degree: Vec<f64> = (0..n)
+        .map(|i| {
+            let inward: f64 = if personalized {
+                csr.incoming(i).iter().map(|e| e.1).sum()
+            } else {
+                0.0
+            };
+            csr.out_weight[i] + inward
+        })
         .collect();
 
-    // Count outgoing edges for each node
-    let mut out_degree: HashMap<NodeId, usize> = HashMap::new();
-    for idx in graph.node_indexes() {
-        let callees = graph.get_callees(idx);
-        out_degree.insert(idx, callees.len().max(1)); // Avoid division by zero
+    let mut scores = teleport.to_vec();
+    for _ in 0..options.iterations {
+        let stranded: f64 = if personalized {
+            (0..n)
+                .filter(|&i| degree[i] == 0.0)
+                .map(|i| scores[i])
+                .sum()
+        } else {
+            0.0
+        };
+        scores = (0..n)
+            .map(|i| {
+                let mut flow: f64 = csr
+                    .incoming(i)
+                    .iter()
+                    .map(|&(j, w)| scores[j as usize] * w / degree[j as usize])
+                    .sum();
+                if personalized {
+                    flow += csr
+                        .outgoing(i)
+                        .iter()
+                        .map(|&(j, w)| scores[j as usize] * w / degree[j as usize])
+                        .sum::<f64>();
+                }
+                (1.0 - damping) * teleport[i] + damping * (flow + stranded * teleport[i])
+            })
+            .collect();
     }
+    scores
+}
 
-    // Iterate
-    for _ in 0..iterations {
-        let mut new_scores: HashMap<NodeId, f64> = HashMap::new();
+/// Brandes' algorithm over unweighted directed shortest paths.
+///
+/// Large graphs use an evenly spaced subset of sources, scaled up to
+/// estimate the full sum.
+fn betweenness(csr: &CsrGraph) -> Vec<f64> {
+    let n = csr.len();
+    let step = n.div_ceil(BETWEENNESS_SOURCES).max(1);
+    let scale = step as f64;
 
-        for idx in graph.node_indexes() {
-            // Base score (random jump)
-            let base = (1.0 - damping) / node_count as f64;
+    let mut centrality = vec![0.0; n];
+    let mut sigma = vec![0.0f64; n];
+    let mut distance = vec![usize::MAX; n];
+    let mut delta = vec![0.0f64; n];
+    let mut predecessors: Vec<Vec<usize>> = vec![Vec::new(); n];
+    let mut order = Vec::with_capacity(n);
+    let mut queue = VecDeque::new();
 
-            // Score from callers
-            let callers = graph.get_callers(idx);
-            let incoming: f64 = callers
-                .iter()
-                .filter_map(|caller| {
-                    let caller_idx = graph.get_index(&caller.id)?;
-                    let caller_score = scores.get(&caller_idx)?;
-                    let caller_out = *out_degree.get(&caller_idx)? as f64;
-                    Some(caller_score / caller_out)
-                })
-                .sum();
+    for source in (0..n).step_by(step) {
+        for i in 0..n {
+            sigma[i] = 0.0;
+            distance[i] = usize::MAX;
+            delta[i] = 0.0;
+            predecessors[i].clear();
+        }
+        order.clear();
+        sigma[source] = 1.0;
+        distance[source] = 0;
+        queue.push_back(source);
 
-            new_scores.insert(idx, base + damping * incoming);
+        while let Some(v) = queue.pop_front() {
+            order.push(v);
+            for &(w, _) in csr.outgoing(v) {
+                let w = w as usize;
+                if distance[w] == usize::MAX {
+                    distance[w] = distance[v] + 1;
+                    queue.push_back(w);
+                }
+                if distance[w] == distance[v] + 1 {
+                    sigma[w] += sigma[v];
+                    predecessors[w].push(v);
+                }
+            }
         }
 
-        scores = new_scores;
+        while let Some(w) = order.pop() {
+            for &v in &predecessors[w] {
+                delta[v] += sigma[v] / sigma[w] * (1.0 + delta[w]);
+            }
+            if w != source {
+                centrality[w] += delta[w] * scale;
+            }
+        }
     }
+    centrality
+}
 
-    // Normalize to [0, 1] range
-    let max_score = scores.values().cloned().fold(0.0f64, f64::max);
-    if max_score > 0.0 {
-        for score in scores.values_mut() {
-            *score /= max_score;
+/// HITS hub and authority scores, L2-normalized each round.
+fn hits(csr: &CsrGraph, iterations: usize) -> (Vec<f64>, Vec<f64>) {
+    let n = csr.len();
+    let mut hubs = vec![1.0; n];
+    let mut authorities = vec![1.0; n];
+    let normalize = |values: &mut Vec<f64>| {
+        let norm = values.iter().map(|v| v * v).sum::<f64>().sqrt();
+        if norm > 0.0 {
+            values.iter_mut().for_each(|v| *v /= norm);
         }
+    };
+
+    for _ in 0..iterations.max(1) {
+        authorities = (0..n)
+            .map(|i| {
+                csr.incoming(i)
+                    .iter()
+                    .map(|&(j, w)| w * hubs[j as usize])
+                    .sum()
+            })
+            .collect();
+        normalize(&mut authorities);
+        hubs = (0..n)
+            .map(|i| {
+                csr.outgoing(i)
+                    .iter()
+                    .map(|&(j, w)| w * authorities[j as usize])
+                    .sum()
+            })
+            .collect();
+        normalize(&mut hubs);
     }
+    (hubs, authorities)
+}
 
-    CentralityScores { scores }
+/// Refreshes centrality after a local change to the graph.
+///
+/// Scores flow from callers to callees, so only nodes downstream of
+/// `changed` can move. Those are recomputed starting from their previous
+/// values; every other node keeps its score. When the affected region is
+/// a large part of the graph this falls back to `compute_centrality`.
+///
+/// `changed` should hold new nodes, nodes whose call edges changed, and
+/// the former callees of removed nodes.
+pub fn refresh_centrality(
+    graph: &ArborGraph,
+    previous: &CentralityScores,
+    changed: &[NodeId],
+    iterations: usize,
+    damping: f64,
+) -> CentralityScores {
+    let node_count = graph.node_count();
+    if node_count == 0 {
+        return CentralityScores::default();
+    }
+    if previous.raw.is_empty() {
+        return compute_centrality(graph, iterations, damping);
+    }
+
+    let csr = CsrGraph::new(graph, &CentralityOptions::default().weights);
+    let changed: HashSet<usize> = changed.iter().filter_map(|&id| csr.position(id)).collect();
+
+    // Everything a changed node can reach within `iterations` hops
+    let mut region: HashSet<usize> = changed.clone();
+    let mut queue: VecDeque<(usize, usize)> = changed.iter().map(|&i| (i, 0)).collect();
+    while let Some((i, depth)) = queue.pop_front() {
+        if depth >= iterations {
+            continue;
+        }
+        for &(callee, _) in csr.outgoing(i) {
+            if region.insert(callee as usize) {
+                queue.push_back((callee as usize, depth + 1));
+            }
+        }
+    }
+
+    if region.len() * 4 > node_count {
+        return compute_centrality(graph, iterations, damping);
+    }
+
+    let base = (1.0 - damping) / node_count as f64;
+    let mut scores: Vec<f64> = csr
+        .nodes
+        .iter()
+        .enumerate()
+        .map(|(i, id)| {
+            // Indexes of removed nodes get reused, so changed nodes start fresh
+            if changed.contains(&i) {
+                base
+            } else {
+                previous.raw.get(id).copied().unwrap_or(base)
+            }
+        })
+        .collect();
+
+    let region: Vec<usize> = region.into_iter().collect();
+    for _ in 0..iterations {
+        let updates: Vec<f64> = region
+            .iter()
+            .map(|&i| {
+                let incoming: f64 = csr
+                    .incoming(i)
+                    .iter()
+                    .map(|&(j, w)| scores[j as usize] * w / csr.out_weight[j as usize])
+                    .sum();
+                base + damping * incoming
+            })
+            .collect();
+        for (&i, score) in region.iter().zip(updates) {
+            scores[i] = score;
+        }
+    }
+
+    csr.scores(scores)
+}
+
+impl CentralityScores {
+    /// Normalizes raw scores to the [0, 1] range.
+    fn from_raw(raw: HashMap<NodeId, f64>) -> Self {
+        let max_score = raw.values().cloned().fold(0.0f64, f64::max);
+        let scores = raw
+            .iter()
+            .map(|(&idx, &score)| {
+                let normalized = if max_score > 0.0 {
+                    score / max_score
+                } else {
+                    score
+                };
+                (idx, normalized)
+            })
+            .collect();
+        Self { scores, raw }
+    }
 }
 
 #[cfg(test)]
@@ -143,4 +614,105 @@ mod tests {
         let popular_score = scores.get(popular_idx);
         assert!(popular_score > 0.5, "Popular node should rank high");
     }
+
+    #[test]
+    fn test_refresh_matches_full_computation() {
+        let mut graph = ArborGraph::new();
+        let hub = graph.add_node(CodeNode::new("hub", "hub", NodeKind::Function, "a.rs"));
+        let leaf = graph.add_node(CodeNode::new("leaf", "leaf", NodeKind::Function, "a.rs"));
+        graph.add_edge(hub, leaf, Edge::new(EdgeKind::Calls));
+        for i in 0..8 {
+            let caller = graph.add_node(CodeNode::new(
+                format!("c{}", i),
+                format!("c{}", i),
+                NodeKind::Function,
+                "b.rs",
+            ));
+            graph.add_edge(caller, hub, Edge::new(EdgeKind::Calls));
+        }
+        let previous = compute_centrality(&graph, 20, 0.85);
+
+        // A new caller of the leaf only affects the leaf
+        let extra = graph.add_node(CodeNode::new("extra", "extra", NodeKind::Function, "c.rs"));
+        graph.add_edge(extra, leaf, Edge::new(EdgeKind::Calls));
+
+        let refreshed = refresh_centrality(&graph, &previous, &[extra], 20, 0.85);
+        let full = compute_centrality(&graph, 20, 0.85);
+
+        assert!(refreshed.get(leaf) > refreshed.get(extra));
+        assert!((refreshed.get(hub) - full.get(hub)).abs() < 0.05);
+        assert!((refreshed.get(leaf) - full.get(leaf)).abs() < 0.05);
+    }
+
+    fn function(graph: &mut ArborGraph, name: &str) -> NodeId {
+        graph.add_node(CodeNode::new(name, name, NodeKind::Function, "test.rs"))
+    }
+
+    #[test]
+    fn test_personalized_page_rank_favours_seed_neighbourhood() {
+        // Two separate call chains: a -> b -> c and x -> y -> z
+        let mut graph = ArborGraph::new();
+        let ids: Vec<NodeId> = ["a", "b", "c", "x", "y", "z"]
+            .iter()
+            .map(|name| function(&mut graph, name))
+            .collect();
+        for pair in [(0, 1), (1, 2), (3, 4), (4, 5)] {
+            graph.add_edge(ids[pair.0], ids[pair.1], Edge::new(EdgeKind::Calls));
+        }
+
+        let options = CentralityOptions::new(CentralityMeasure::PersonalizedPageRank);
+        let scores = compute_centrality_with(&graph, &options, &[ids[1]]);
+        assert_eq!(scores.get(ids[1]), 1.0);
+        assert!(scores.get(ids[0]) > 0.0 && scores.get(ids[2]) > 0.0);
+        assert_eq!(scores.get(ids[4]), 0.0);
+
+        // Without seeds it is global PageRank
+        let global = compute_centrality_with(&graph, &options, &[]);
+        let plain = compute_centrality(&graph, 20, 0.85);
+        assert_eq!(global.get(ids[5]), plain.get(ids[5]));
+    }
+
+    #[test]
+    fn test_betweenness_hits_and_weights() {
+        // a -> bridge -> {c, d}, with an import from e to a
+        let mut graph = ArborGraph::new();
+        let a = function(&mut graph, "a");
+        let bridge = function(&mut graph, "bridge");
+        let c = function(&mut graph, "c");
+        let d = function(&mut graph, "d");
+        let e = function(&mut graph, "e");
+        graph.add_edge(a, bridge, Edge::new(EdgeKind::Calls));
+        graph.add_edge(bridge, c, Edge::new(EdgeKind::Calls));
+        graph.add_edge(bridge, d, Edge::new(EdgeKind::Calls));
+        graph.add_edge(e, a, Edge::new(EdgeKind::Imports));
+
+        let rank = |measure| compute_centrality_with(&graph, &CentralityOptions::new(measure), &[]);
+        let between = rank(CentralityMeasure::Betweenness);
+        assert_eq!(between.get(bridge), 1.0);
+        assert_eq!(between.get(c), 0.0);
+
+        assert_eq!(rank(CentralityMeasure::Hub).get(bridge), 1.0);
+        let authority = rank(CentralityMeasure::Authority);
+        assert!(authority.get(c) > authority.get(a));
+        assert_eq!(rank(CentralityMeasure::OutDegree).get(bridge), 1.0);
+
+        // Imports only count once weighted
+        assert_eq!(rank(CentralityMeasure::InDegree).get(a), 0.0);
+        let weighted = CentralityOptions::new(CentralityMeasure::OutDegree)
+            .with_weight(EdgeKind::Imports, 2.0);
+        assert_eq!(compute_centrality_with(&graph, &weighted, &[]).get(e), 1.0);
+    }
+
+    #[test]
+    fn test_measure_names_round_trip() {
+        for name in ["page_rank", "personalized_page_rank", "betweenness", "hub"] {
+            let measure: CentralityMeasure = name.parse().unwrap();
+            assert_eq!(measure.to_string(), name);
+        }
+        assert_eq!(
+            "ppr".parse::<CentralityMeasure>(),
+            Ok(CentralityMeasure::PersonalizedPageRank)
+        );
+        assert!("closeness".parse::<CentralityMeasure>().is_err());
+    }
 }
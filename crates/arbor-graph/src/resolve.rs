@@ -0,0 +1,183 @@
+//! What nodes belong to and what imports point at.
+//!
+//! The graph links calls but not types and imports: these indexes find
+//! the type a member belongs to and the indexed file an import loads,
+//! for the reports that group or follow them.
+
+use crate::graph::ArborGraph;
+use crate::layout::directory_of;
+use arbor_core::{CodeNode, NodeKind};
+use std::collections::{HashMap, HashSet};
+
+/// Classes, structs, interfaces, enums and type aliases.
+pub(crate) fn is_type(kind: NodeKind) -> bool {
+    matches!(
+        kind,
+        NodeKind::Class
+            | NodeKind::Struct
+            | NodeKind::Interface
+            | NodeKind::Enum
+            | NodeKind::TypeAlias
+    )
+}
+
+/// Finds the type a member belongs to.
+pub(crate) struct TypeIndex<'a> {
+    by_qualified: HashMap<&'a str, &'a CodeNode>,
+    by_file: HashMap<&'a str, Vec<&'a CodeNode>>,
+}
+
+impl<'a> TypeIndex<'a> {
+    pub(crate) fn new(graph: &'a ArborGraph) -> Self {
+        let mut by_qualified = HashMap::new();
+        let mut by_file: HashMap<&str, Vec<&CodeNode>> = HashMap::new();
+        for node in graph.nodes().filter(|n| is_type(n.kind)) {
+            by_qualified.insert(node.qualified_name.as_str(), node);
+            by_file.entry(node.file.as_str()).or_default().push(node);
+        }
+        Self {
+            by_qualified,
+            by_file,
+        }
+    }
+
+    /// The type itself, the type named by the qualified-name prefix
+    /// (`Class.method`, `Type::method`), or the narrowest type whose lines
+    /// enclose the node.
+    pub(crate) fn enclosing(&self, node: &CodeNode) -> Option<&'a str> {
+        if is_type(node.kind) {
+            return self
+                .by_qualified
+                .get(node.qualified_name.as_str())
+                .map(|t| t.qualified_name.as_str());
+        }
+        let parent = node
+            .qualified_name
+            .rsplit_once("::")
+            .or_else(|| node.qualified_name.rsplit_once('.'))
+            .map(|(parent, _)| parent);
+        if let Some(found) = parent.and_then(|p| self.by_qualified.get(p)) {
+            return Some(found.qualified_name.as_str());
+        }
+        self.by_file
+            .get(node.file.as_str())?
+            .iter()
+            .filter(|t| t.line_start <= node.line_start && node.line_end <= t.line_end)
+            .min_by_key(|t| t.line_end - t.line_start)
+            .map(|t| t.qualified_name.as_str())
+    }
+}
+
+/// Maps import statements to the indexed files they load.
+pub(crate) struct ImportResolver<'a> {
+    files: HashSet<&'a str>,
+    /// Dotted module names (and every dotted suffix) to Python files.
+    python_modules: HashMap<String, Vec<&'a str>>,
+}
+
+impl<'a> ImportResolver<'a> {
+    const SCRIPT_EXTENSIONS: [&'static str; 6] = ["ts", "tsx", "js", "jsx", "mjs", "cjs"];
+
+    pub(crate) fn new(graph: &'a ArborGraph) -> Self {
+        let files: HashSet<&str> = graph.nodes().map(|n| n.file.as_str()).collect();
+        let mut python_modules: HashMap<String, Vec<&str>> = HashMap::new();
+        for &file in &files {
+            let Some(stem) = file.strip_suffix(".py") else {
+                continue;
+            };
+            let stem = stem.strip_suffix("/__init__").unwrap_or(stem);
+            let parts: Vec<&str> = stem.split('/').collect();
+            for skip in 0..parts.len() {
+                python_modules
+                    .entry(parts[skip..].join("."))
+                    .or_default()
+                    .push(file);
+            }
+        }
+        Self {
+            files,
+            python_modules,
+        }
+    }
+
+    pub(crate) fn resolve(&self, import: &CodeNode) -> Vec<&'a str> {
+        if import.file.ends_with(".py") {
+            return import
+                .name
+                .split(',')
+                .filter_map(|module| {
+                    let module = module.split(" as ").next().unwrap_or(module).trim();
+                    self.resolve_python(&import.file, module)
+                })
+                .collect();
+        }
+        let spec = import.name.as_str();
+        if spec.starts_with("./") || spec.starts_with("../") {
+            return self
+                .resolve_relative(&import.file, spec)
+                .into_iter()
+                .collect();
+        }
+        Vec::new()
+    }
+
+    fn resolve_python(&self, from: &str, module: &str) -> Option<&'a str> {
+        let dots = module.chars().take_while(|&c| c == '.').count();
+        if dots == 0 {
+            // Prefer the full path from the project root, then a unique suffix
+            let candidates = self.python_modules.get(module)?;
+            let exact = candidates.iter().find(|f| {
+                let stem = f.trim_end_matches(".py").trim_end_matches("/__init__");
+                stem.replace('/', ".") == module
+            });
+            return match (exact, candidates.as_slice()) {
+                (Some(file), _) => Some(*file),
+                (None, [only]) => Some(*only),
+                _ => None,
+            };
+        }
+
+        // Relative: one dot is the importing file's package, each extra
+        // dot goes up one directory.
+        let mut dir = directory_of(from).to_string();
+        for _ in 1..dots {
+            dir = directory_of(&dir).to_string();
+        }
+        let rest = module[dots..].replace('.', "/");
+        let base = match (dir.as_str(), rest.is_empty()) {
+            (".", _) => rest,
+            (_, true) => dir,
+            _ => format!("{}/{}", dir, rest),
+        };
+        [format!("{}.py", base), format!("{}/__init__.py", base)]
+            .iter()
+            .find_map(|candidate| self.files.get(candidate.as_str()).copied())
+    }
+
+    fn resolve_relative(&self, from: &str, spec: &str) -> Option<&'a str> {
+        let mut parts: Vec<&str> = match directory_of(from) {
+            "." => Vec::new(),
+            dir => dir.split('/').collect(),
+        };
+        for segment in spec.split('/') {
+            match segment {
+                "." | "" => {}
+                ".." => {
+                    parts.pop();
+                }
+                other => parts.push(other),
+            }
+        }
+        let base = parts.join("/");
+        let mut candidates = vec![base.clone()];
+        for ext in Self::SCRIPT_EXTENSIONS {
+            candidates.push(format!("{}.{}", base, ext));
+        }
+        for ext in Self::SCRIPT_EXTENSIONS {
+            candidates.push(format!("{}/index.{}", base, ext));
+        }
+        candidates
+            .iter()
+            .find_map(|candidate| self.files.get(candidate.as_str()).copied())
+    }
+}
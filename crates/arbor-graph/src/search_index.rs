@@ -1,10 +1,16 @@
-//! Search index for fast substring matching.
+//! Search index for code symbols.
 //!
-//! This module provides an inverted index that enables O(k) substring
-//! search where k is the number of matches, instead of O(n) linear scan.
+//! Two structures live side by side. An n-gram inverted index answers
+//! plain substring queries in O(k) for k matches. A token index splits
+//! identifiers at case and underscore boundaries, so `getUserById`,
+//! `get_user_by_id` and `GetUserByID` share the tokens `get user by id`,
+//! and scores matches with BM25F over the name, qualified name,
+//! signature and docstring. Query tokens also match by prefix, by edit
+//! distance and, for names, by acronym (`gubi`).
 
 use crate::graph::NodeId;
-use std::collections::{HashMap, HashSet};
+use arbor_core::CodeNode;
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 /// Minimum n-gram length for indexing.
 const MIN_NGRAM_LEN: usize = 2;
@@ -12,7 +18,47 @@ const MIN_NGRAM_LEN: usize = 2;
 /// Maximum n-gram length for indexing.
 const MAX_NGRAM_LEN: usize = 4;
 
-/// An inverted index for fast substring search.
+/// Indexed fields, in order: name, qualified name, signature, docstring.
+const FIELD_COUNT: usize = 4;
+
+/// How much a token counts in each field.
+const FIELD_WEIGHTS: [f64; FIELD_COUNT] = [3.0, 1.5, 1.0, 0.5];
+
+/// BM25 term frequency saturation.
+const BM25_K1: f64 = 1.2;
+
+/// BM25 length normalization.
+const BM25_B: f64 = 0.75;
+
+/// Bonus when the whole name equals the query, ignoring case and separators.
+const EXACT_BONUS: f64 = 10.0;
+
+/// Bonus when the name contains the query as a substring.
+const SUBSTRING_BONUS: f64 = 2.0;
+
+/// Bonus when the query starts the name's acronym, scaled by coverage.
+const ACRONYM_BONUS: f64 = 4.0;
+
+/// Most vocabulary terms one query token may expand to by prefix.
+const MAX_PREFIX_EXPANSIONS: usize = 100;
+
+/// A scored search result.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SearchHit {
+    pub id: NodeId,
+    pub score: f64,
+}
+
+/// What the token index keeps per node, so it can be removed again.
+#[derive(Debug, Default, Clone)]
+struct Document {
+    terms: Vec<String>,
+    lengths: [u32; FIELD_COUNT],
+    normalized: String,
+    acronym: String,
+}
+
+/// An inverted index for substring and scored search.
 ///
 /// Uses n-gram indexing to support substring matching. When a name is added,
 /// we break it into overlapping n-grams and index each one. During search,
@@ -23,6 +69,17 @@ pub struct SearchIndex {
     exact_index: HashMap<String, Vec<NodeId>>,
     /// Maps lowercased n-grams to NodeIds for substring search.
     ngram_index: HashMap<String, HashSet<NodeId>>,
+    /// Lowercased name of each node, for verifying n-gram candidates.
+    names: HashMap<NodeId, String>,
+    /// Token -> node -> occurrences per field.
+    postings: BTreeMap<String, HashMap<NodeId, [u32; FIELD_COUNT]>>,
+    documents: HashMap<NodeId, Document>,
+    /// Summed field lengths, for the averages BM25 normalizes against.
+    total_lengths: [u64; FIELD_COUNT],
+    /// Names with case and separators stripped.
+    normalized_index: HashMap<String, Vec<NodeId>>,
+    /// First letter of every name token.
+    acronym_index: BTreeMap<String, Vec<NodeId>>,
 }
 
 impl SearchIndex {
@@ -33,7 +90,24 @@ impl SearchIndex {
 
     /// Inserts a name into the index.
     pub fn insert(&mut self, name: &str, id: NodeId) {
-        let lower = name.to_lowercase();
+        self.insert_fields(id, [name, "", "", ""]);
+    }
+
+    /// Inserts a node's name, qualified name, signature and docstring.
+    pub fn insert_node(&mut self, node: &CodeNode, id: NodeId) {
+        self.insert_fields(
+            id,
+            [
+                &node.name,
+                &node.qualified_name,
+                node.signature.as_deref().unwrap_or(""),
+                node.docstring.as_deref().unwrap_or(""),
+            ],
+        );
+    }
+
+    fn insert_fields(&mut self, id: NodeId, fields: [&str; FIELD_COUNT]) {
+        let lower = fields[0].to_lowercase();
 
         // Add to exact index
         self.exact_index.entry(lower.clone()).or_default().push(id);
@@ -42,6 +116,46 @@ impl SearchIndex {
         for ngram in self.generate_ngrams(&lower) {
             self.ngram_index.entry(ngram).or_default().insert(id);
         }
+        self.names.insert(id, lower);
+
+        // Add to token index
+        self.remove_document(id);
+        let mut document = Document::default();
+        let mut counts: HashMap<String, [u32; FIELD_COUNT]> = HashMap::new();
+        for (field, text) in fields.iter().enumerate() {
+            let tokens = identifier_tokens(text);
+            document.lengths[field] = tokens.len() as u32;
+            self.total_lengths[field] += tokens.len() as u64;
+            for token in tokens {
+                counts.entry(token).or_default()[field] += 1;
+            }
+        }
+        let name_tokens = identifier_tokens(fields[0]);
+        document.acronym = name_tokens
+            .iter()
+            .filter_map(|t| t.chars().next())
+            .collect();
+        document.normalized = name_tokens.concat();
+        for (term, tf) in counts {
+            self.postings
+                .entry(term.clone())
+                .or_default()
+                .insert(id, tf);
+            document.terms.push(term);
+        }
+        if !document.normalized.is_empty() {
+            self.normalized_index
+                .entry(document.normalized.clone())
+                .or_default()
+                .push(id);
+        }
+        if document.acronym.len() >= 2 {
+            self.acronym_index
+                .entry(document.acronym.clone())
+                .or_default()
+                .push(id);
+        }
+        self.documents.insert(id, document);
     }
 
     /// Removes a name from the index.
@@ -65,6 +179,39 @@ impl SearchIndex {
                 }
             }
         }
+        self.names.remove(&id);
+
+        self.remove_document(id);
+    }
+
+    /// Drops a node from the token index.
+    fn remove_document(&mut self, id: NodeId) {
+        let Some(document) = self.documents.remove(&id) else {
+            return;
+        };
+        for term in &document.terms {
+            if let Some(ids) = self.postings.get_mut(term) {
+                ids.remove(&id);
+                if ids.is_empty() {
+                    self.postings.remove(term);
+                }
+            }
+        }
+        for (total, &length) in self.total_lengths.iter_mut().zip(&document.lengths) {
+            *total -= length as u64;
+        }
+        if let Some(ids) = self.normalized_index.get_mut(&document.normalized) {
+            ids.retain(|&x| x != id);
+            if ids.is_empty() {
+                self.normalized_index.remove(&document.normalized);
+            }
+        }
+        if let Some(ids) = self.acronym_index.get_mut(&document.acronym) {
+            ids.retain(|&x| x != id);
+            if ids.is_empty() {
+                self.acronym_index.remove(&document.acronym);
+            }
+        }
     }
 
     /// Searches for nodes whose names contain the query substring.
@@ -116,9 +263,9 @@ impl SearchIndex {
             .unwrap_or_default()
             .into_iter()
             .filter(|id| {
-                self.exact_index
-                    .iter()
-                    .any(|(name, ids)| ids.contains(id) && name.contains(&query_lower))
+                self.names
+                    .get(id)
+                    .is_some_and(|name| name.contains(&query_lower))
             })
             .collect();
 
@@ -126,6 +273,130 @@ impl SearchIndex {
         results
     }
 
+    /// Scores every node that matches the query, best first.
+    ///
+    /// Each query token contributes its best BM25F score among the terms
+    /// it matches: exactly, as a prefix of a longer term, or within one
+    /// edit (two for tokens of eight or more characters). Names equal to
+    /// the query, containing it, or whose acronym starts with it get a
+    /// bonus on top.
+    pub fn rank(&self, query: &str) -> Vec<SearchHit> {
+        let mut scores: HashMap<NodeId, f64> = HashMap::new();
+
+        let tokens: Vec<String> = {
+            let mut seen = HashSet::new();
+            identifier_tokens(query)
+                .into_iter()
+                .filter(|t| seen.insert(t.clone()))
+                .collect()
+        };
+        for token in &tokens {
+            let mut best: HashMap<NodeId, f64> = HashMap::new();
+            for (term, quality) in self.expand(token) {
+                for (&id, &tf) in &self.postings[term] {
+                    let score = quality * self.bm25(term, id, &tf);
+                    let entry = best.entry(id).or_default();
+                    *entry = entry.max(score);
+                }
+            }
+            for (id, score) in best {
+                *scores.entry(id).or_default() += score;
+            }
+        }
+
+        let normalized = tokens.concat();
+        if let Some(ids) = self.normalized_index.get(&normalized) {
+            for &id in ids {
+                *scores.entry(id).or_default() += EXACT_BONUS;
+            }
+        }
+        if !query.trim().is_empty() {
+            for id in self.search(query.trim()) {
+                *scores.entry(id).or_default() += SUBSTRING_BONUS;
+            }
+        }
+        if tokens.len() == 1 && normalized.len() >= 2 {
+            for (acronym, ids) in self
+                .acronym_index
+                .range(normalized.clone()..)
+                .take_while(|(acronym, _)| acronym.starts_with(&normalized))
+            {
+                let coverage = normalized.len() as f64 / acronym.len() as f64;
+                for &id in ids {
+                    *scores.entry(id).or_default() += ACRONYM_BONUS * coverage;
+                }
+            }
+        }
+
+        let mut hits: Vec<SearchHit> = scores
+            .into_iter()
+            .map(|(id, score)| SearchHit { id, score })
+            .collect();
+        hits.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.id.cmp(&b.id)));
+        hits
+    }
+
+    /// Vocabulary terms a query token matches, with a match quality.
+    fn expand<'a>(&'a self, token: &str) -> Vec<(&'a String, f64)> {
+        let mut terms = Vec::new();
+        if let Some((term, _)) = self.postings.get_key_value(token) {
+            terms.push((term, 1.0));
+        }
+        if token.len() >= 2 {
+            let prefixed = self
+                .postings
+                .range::<str, _>((std::ops::Bound::Excluded(token), std::ops::Bound::Unbounded))
+                .map(|(term, _)| term)
+                .take_while(|term| term.starts_with(token))
+                .take(MAX_PREFIX_EXPANSIONS);
+            for term in prefixed {
+                // Longer completions are weaker evidence
+                let coverage = token.len() as f64 / term.len() as f64;
+                terms.push((term, 0.4 + 0.4 * coverage));
+            }
+        }
+        let max_edits = match token.chars().count() {
+            0..=3 => 0,
+            4..=7 => 1,
+            _ => 2,
+        };
+        if max_edits > 0 {
+            for term in self.postings.keys() {
+                if term.starts_with(token) || term.len().abs_diff(token.len()) > max_edits {
+                    continue;
+                }
+                match edit_distance(token, term, max_edits) {
+                    Some(1) => terms.push((term, 0.5)),
+                    Some(2) => terms.push((term, 0.3)),
+                    _ => {}
+                }
+            }
+        }
+        terms
+    }
+
+    /// BM25F: field occurrences are weighted and length-normalized before
+    /// saturating.
+    fn bm25(&self, term: &str, id: NodeId, tf: &[u32; FIELD_COUNT]) -> f64 {
+        let Some(document) = self.documents.get(&id) else {
+            return 0.0;
+        };
+        let docs = self.documents.len() as f64;
+        let df = self.postings.get(term).map_or(0, HashMap::len) as f64;
+        let idf = (1.0 + (docs - df + 0.5) / (df + 0.5)).ln();
+
+        let weighted: f64 = (0..FIELD_COUNT)
+            .filter(|&field| tf[field] > 0)
+            .map(|field| {
+                let average = self.total_lengths[field] as f64 / docs;
+                let length = document.lengths[field] as f64;
+                let norm = 1.0 - BM25_B + BM25_B * length / average.max(1.0);
+                FIELD_WEIGHTS[field] * tf[field] as f64 / norm
+            })
+            .sum();
+        idf * weighted * (BM25_K1 + 1.0) / (weighted + BM25_K1)
+    }
+
     /// Generates n-grams for a lowercased string.
     fn generate_ngrams(&self, s: &str) -> Vec<String> {
         let chars: Vec<char> = s.chars().collect();
@@ -153,9 +424,58 @@ impl SearchIndex {
     }
 }
 
+/// Splits text into lowercased identifier tokens.
+///
+/// Breaks at anything that isn't a letter or digit, before an uppercase
+/// letter that follows a lowercase one (`getUser`), and before the last
+/// capital of a run followed by lowercase (`IDParser` → `id parser`).
+/// Digits stay attached, so `sha256` is one token.
+pub fn identifier_tokens(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    for word in text.split(|c: char| !c.is_alphanumeric()) {
+        let chars: Vec<char> = word.chars().collect();
+        let mut start = 0;
+        for i in 1..chars.len() {
+            let (prev, c) = (chars[i - 1], chars[i]);
+            let next_lower = chars.get(i + 1).is_some_and(|n| n.is_lowercase());
+            let boundary = c.is_uppercase()
+                && ((prev.is_lowercase() || prev.is_ascii_digit())
+                    || (prev.is_uppercase() && next_lower));
+            if boundary {
+                tokens.push(chars[start..i].iter().collect::<String>().to_lowercase());
+                start = i;
+            }
+        }
+        if start < chars.len() {
+            tokens.push(chars[start..].iter().collect::<String>().to_lowercase());
+        }
+    }
+    tokens
+}
+
+/// Levenshtein distance, or `None` once it exceeds `max`.
+pub(crate) fn edit_distance(a: &str, b: &str, max: usize) -> Option<usize> {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, &ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        if current.iter().min().is_some_and(|&m| m > max) {
+            return None;
+        }
+        previous = current;
+    }
+    Some(previous[b.len()]).filter(|&d| d <= max)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use arbor_core::NodeKind;
     use petgraph::graph::NodeIndex;
 
     fn node_id(n: u32) -> NodeId {
@@ -245,4 +565,74 @@ mod tests {
         assert!(results.contains(&node_id(1)));
         assert!(!results.contains(&node_id(2)));
     }
+
+    #[test]
+    fn test_identifier_tokens() {
+        for name in [
+            "getUserById",
+            "get_user_by_id",
+            "GetUserByID",
+            "get-user by.id",
+        ] {
+            assert_eq!(identifier_tokens(name), vec!["get", "user", "by", "id"]);
+        }
+        assert_eq!(identifier_tokens("IDParser"), vec!["id", "parser"]);
+        assert_eq!(identifier_tokens("sha256Sum"), vec!["sha256", "sum"]);
+    }
+
+    #[test]
+    fn test_rank_fuzzy_prefix_and_acronym() {
+        let mut index = SearchIndex::new();
+        let get_user = CodeNode::new(
+            "getUserById",
+            "UserRepo.getUserById",
+            NodeKind::Method,
+            "a.ts",
+        )
+        .with_signature("getUserById(id: string): User");
+        index.insert_node(&get_user, node_id(0));
+        index.insert("get_users", node_id(1));
+        index.insert("validate_email", node_id(2));
+        index.insert("GetUserByID", node_id(3));
+
+        let top = |query: &str| index.rank(query).first().map(|hit| hit.id);
+
+        // Separators and case don't matter; both spellings are exact
+        let mut exact: Vec<NodeId> = index.rank("get_user_by_id")[..2]
+            .iter()
+            .map(|hit| hit.id)
+            .collect();
+        exact.sort();
+        assert_eq!(exact, vec![node_id(0), node_id(3)]);
+
+        assert_eq!(top("gubi"), Some(node_id(0)));
+        assert_eq!(top("valdate emial"), Some(node_id(2)));
+        assert_eq!(top("valid"), Some(node_id(2)));
+        assert!(index.rank("nothing").is_empty());
+
+        index.remove("validate_email", node_id(2));
+        assert!(index.rank("validate").is_empty());
+    }
+
+    #[test]
+    fn test_graph_search_boosts_central_nodes() {
+        let mut graph = crate::graph::ArborGraph::new();
+        let fetch =
+            |file: &str| CodeNode::new("fetch_user", "fetch_user", NodeKind::Function, file);
+        let first = graph.add_node(fetch("a.rs"));
+        let second = graph.add_node(fetch("b.rs"));
+        graph.add_node(CodeNode::new("user", "user", NodeKind::Variable, "c.rs"));
+
+        let ids = |graph: &crate::graph::ArborGraph| -> Vec<NodeId> {
+            graph
+                .search_ranked("fetchUser")
+                .iter()
+                .map(|h| h.id)
+                .collect()
+        };
+        assert_eq!(ids(&graph)[..2], [first, second]);
+
+        graph.set_centrality(HashMap::from([(second, 1.0)]));
+        assert_eq!(ids(&graph)[..2], [second, first]);
+    }
 }
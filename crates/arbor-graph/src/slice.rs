@@ -6,10 +6,11 @@
 
 use crate::graph::{ArborGraph, NodeId};
 use crate::query::NodeInfo;
+use crate::ranking::{compute_centrality_with, CentralityOptions};
 use petgraph::visit::EdgeRef;
 use petgraph::Direction;
 use serde::{Deserialize, Serialize};
-use std::collections::{HashSet, VecDeque};
+use std::collections::HashSet;
 use std::time::Instant;
 
 /// Reason for stopping context collection.
@@ -80,18 +81,6 @@ impl ContextSlice {
     }
 }
 
-/// Estimates tokens for a code node.
-///
-/// Simple heuristic: 1 token ≈ 4 characters.
-/// This matches GPT-4's average for code.
-fn estimate_tokens(node: &NodeInfo) -> usize {
-    let base = node.name.len() + node.qualified_name.len() + node.file.len();
-    let signature_len = node.signature.as_ref().map(|s| s.len()).unwrap_or(0);
-    let lines = (node.line_end.saturating_sub(node.line_start) + 1) as usize;
-    let estimated_chars = base + signature_len + (lines * 40);
-    (estimated_chars + 3) / 4
-}
-
 impl ArborGraph {
     /// Extracts a token-bounded context slice around a target node.
     ///
@@ -114,13 +103,103 @@ impl ArborGraph {
         max_tokens: usize,
         max_depth: usize,
         pinned: &[NodeId],
+    ) -> ContextSlice {
+        self.slice_scored(target, max_tokens, max_depth, pinned, |id| {
+            self.centrality(id)
+        })
+    }
+
+    /// Like `slice_context`, but orders nodes within each depth by the
+    /// given centrality measure instead of the stored PageRank.
+    /// Personalized PageRank is seeded with the target and pinned nodes.
+    pub fn slice_context_with(
+        &self,
+        target: NodeId,
+        max_tokens: usize,
+        max_depth: usize,
+        pinned: &[NodeId],
+        ranking: &CentralityOptions,
+    ) -> ContextSlice {
+        let seeds: Vec<NodeId> = std::iter::once(target)
+            .chain(pinned.iter().copied())
+            .collect();
+        let scores = compute_centrality_with(self, ranking, &seeds);
+        self.slice_scored(target, max_tokens, max_depth, pinned, |id| scores.get(id))
+    }
+
+    /// Nodes within `max_depth` hops of any seed in either direction,
+    /// with their hop distance, ordered like `slice_context` but without
+    /// counting tokens. Seeds come first, at depth 0. `max_depth` 0
+    /// means unlimited.
+    pub fn neighborhood(&self, seeds: &[NodeId], max_depth: usize) -> Vec<(NodeId, usize)> {
+        self.walk(seeds, max_depth, |id| self.centrality(id)).0
+    }
+
+    /// Breadth-first walk out from `seeds`, ordering each depth by `score`.
+    /// Also returns whether `max_depth` cut the walk short.
+    fn walk(
+        &self,
+        seeds: &[NodeId],
+        max_depth: usize,
+        score: impl Fn(NodeId) -> f64,
+    ) -> (Vec<(NodeId, usize)>, bool) {
+        let max_depth = if max_depth == 0 {
+            usize::MAX
+        } else {
+            max_depth
+        };
+        let mut visited: HashSet<NodeId> = HashSet::new();
+        let mut frontier: Vec<NodeId> = seeds
+            .iter()
+            .copied()
+            .filter(|&seed| self.get(seed).is_some() && visited.insert(seed))
+            .collect();
+        let mut found: Vec<(NodeId, usize)> = frontier.iter().map(|&n| (n, 0)).collect();
+        let mut depth = 0;
+        while !frontier.is_empty() {
+            let mut next = Vec::new();
+            for &current in &frontier {
+                let neighbors = self
+                    .graph
+                    .edges_directed(current, Direction::Incoming)
+                    .map(|e| e.source())
+                    .chain(
+                        self.graph
+                            .edges_directed(current, Direction::Outgoing)
+                            .map(|e| e.target()),
+                    );
+                for neighbor in neighbors {
+                    if !visited.contains(&neighbor) {
+                        if depth == max_depth {
+                            return (found, true);
+                        }
+                        visited.insert(neighbor);
+                        next.push(neighbor);
+                    }
+                }
+            }
+            depth += 1;
+            next.sort_by(|&a, &b| score(b).total_cmp(&score(a)));
+            found.extend(next.iter().map(|&n| (n, depth)));
+            frontier = next;
+        }
+        (found, false)
+    }
+
+    fn slice_scored(
+        &self,
+        target: NodeId,
+        max_tokens: usize,
+        max_depth: usize,
+        pinned: &[NodeId],
+        score: impl Fn(NodeId) -> f64,
     ) -> ContextSlice {
         let start = Instant::now();
 
         let target_node = match self.get(target) {
             Some(node) => {
                 let mut info = NodeInfo::from(node);
-                info.centrality = self.centrality(target);
+                info.centrality = score(target);
                 info
             }
             None => {
@@ -133,8 +212,10 @@ impl ArborGraph {
                         file: String::new(),
                         line_start: 0,
                         line_end: 0,
+                        column: 0,
                         signature: None,
                         centrality: 0.0,
+                        score: None,
                     },
                     nodes: Vec::new(),
                     total_tokens: 0,
@@ -145,11 +226,6 @@ impl ArborGraph {
             }
         };
 
-        let effective_max = if max_depth == 0 {
-            usize::MAX
-        } else {
-            max_depth
-        };
         let effective_tokens = if max_tokens == 0 {
             usize::MAX
         } else {
@@ -157,73 +233,40 @@ impl ArborGraph {
         };
 
         let pinned_set: HashSet<NodeId> = pinned.iter().copied().collect();
-        let mut visited: HashSet<NodeId> = HashSet::new();
         let mut result: Vec<ContextNode> = Vec::new();
         let mut total_tokens = 0usize;
-        let mut truncation_reason = TruncationReason::Complete;
-
-        // BFS queue: (node_id, depth)
-        let mut queue: VecDeque<(NodeId, usize)> = VecDeque::new();
-
-        // Start with target
-        queue.push_back((target, 0));
-
-        while let Some((current, depth)) = queue.pop_front() {
-            if visited.contains(&current) {
-                continue;
-            }
+        let mut meter = self.token_meter();
 
-            if depth > effective_max {
-                truncation_reason = TruncationReason::MaxDepth;
+        // Walk the whole neighbourhood even past the budget, so pinned
+        // nodes further out are still found
+        let (reached, cut) = self.walk(&[target], max_depth, &score);
+        let mut truncation_reason = if cut {
+            TruncationReason::MaxDepth
+        } else {
+            TruncationReason::Complete
+        };
+        for (current, depth) in reached {
+            let Some(node) = self.get(current) else {
                 continue;
-            }
-
-            visited.insert(current);
-
+            };
             let is_pinned = pinned_set.contains(&current);
+            let token_est = meter.node(current);
 
-            if let Some(node) = self.get(current) {
-                let mut node_info = NodeInfo::from(node);
-                node_info.centrality = self.centrality(current);
-
-                let token_est = estimate_tokens(&node_info);
-
-                // Check budget (pinned nodes bypass budget)
-                let within_budget = is_pinned || total_tokens + token_est <= effective_tokens;
-
-                if within_budget {
-                    total_tokens += token_est;
-
-                    result.push(ContextNode {
-                        node_info,
-                        token_estimate: token_est,
-                        depth,
-                        pinned: is_pinned,
-                    });
-                } else {
-                    truncation_reason = TruncationReason::TokenBudget;
-                    // Don't add to result, but STILL explore neighbors to find pinned nodes
-                }
-            }
-
-            // Always add neighbors to queue (to find pinned nodes even when budget exceeded)
-            if depth < effective_max {
-                // Upstream (incoming)
-                for edge_ref in self.graph.edges_directed(current, Direction::Incoming) {
-                    let neighbor = edge_ref.source();
-                    if !visited.contains(&neighbor) {
-                        queue.push_back((neighbor, depth + 1));
-                    }
-                }
-
-                // Downstream (outgoing)
-                for edge_ref in self.graph.edges_directed(current, Direction::Outgoing) {
-                    let neighbor = edge_ref.target();
-                    if !visited.contains(&neighbor) {
-                        queue.push_back((neighbor, depth + 1));
-                    }
-                }
+            // Check budget (pinned nodes bypass budget)
+            if !is_pinned && total_tokens + token_est > effective_tokens {
+                truncation_reason = TruncationReason::TokenBudget;
+                continue;
             }
+            total_tokens += token_est;
+
+            let mut node_info = NodeInfo::from(node);
+            node_info.centrality = score(current);
+            result.push(ContextNode {
+                node_info,
+                token_estimate: token_est,
+                depth,
+                pinned: is_pinned,
+            });
         }
 
         // Sort by: pinned first, then by depth, then by centrality (desc)
@@ -308,6 +351,7 @@ mod tests {
         assert!(names.contains(&"a"));
         assert!(names.contains(&"c"));
         assert!(!names.contains(&"d"));
+        assert_eq!(result.truncation_reason, TruncationReason::MaxDepth);
     }
 
     #[test]
@@ -362,4 +406,63 @@ mod tests {
         assert_eq!(result.truncation_reason, TruncationReason::Complete);
         assert_eq!(result.nodes.len(), 2);
     }
+
+    #[test]
+    fn test_neighborhood_matches_unbudgeted_slice() {
+        // a -> b -> c -> d, e -> b
+        let mut graph = ArborGraph::new();
+        let ids: Vec<NodeId> = ["a", "b", "c", "d", "e"]
+            .iter()
+            .map(|name| graph.add_node(make_node(name)))
+            .collect();
+        for (from, to) in [(0, 1), (1, 2), (2, 3), (4, 1)] {
+            graph.add_edge(ids[from], ids[to], Edge::new(EdgeKind::Calls));
+        }
+
+        let around = graph.neighborhood(&[ids[1]], 1);
+        let slice = graph.slice_context(ids[1], 0, 1, &[]);
+        let mut found: Vec<(NodeId, usize)> = slice
+            .nodes
+            .iter()
+            .map(|n| (graph.get_index(&n.node_info.id).unwrap(), n.depth))
+            .collect();
+        let mut expected = around.clone();
+        found.sort();
+        expected.sort();
+        assert_eq!(found, expected);
+        assert_eq!(around[0], (ids[1], 0));
+        assert!(graph.neighborhood(&[ids[1]], 0).contains(&(ids[3], 2)));
+
+        // Several seeds walk out together; each node keeps its nearest depth
+        let around = graph.neighborhood(&[ids[0], ids[3]], 1);
+        assert_eq!(&around[..2], &[(ids[0], 0), (ids[3], 0)]);
+        assert!(around.contains(&(ids[1], 1)));
+        assert!(around.contains(&(ids[2], 1)));
+        assert_eq!(around.len(), 4);
+    }
+
+    #[test]
+    fn test_slice_with_measure() {
+        // a -> b -> {c, d}, e -> c: by in-degree c outranks d at depth 2
+        let mut graph = ArborGraph::new();
+        let a = graph.add_node(make_node("a"));
+        let b = graph.add_node(make_node("b"));
+        let c = graph.add_node(make_node("c"));
+        let d = graph.add_node(make_node("d"));
+        let e = graph.add_node(make_node("e"));
+        graph.add_edge(a, b, Edge::new(EdgeKind::Calls));
+        graph.add_edge(b, d, Edge::new(EdgeKind::Calls));
+        graph.add_edge(b, c, Edge::new(EdgeKind::Calls));
+        graph.add_edge(e, c, Edge::new(EdgeKind::Calls));
+
+        let ranking = CentralityOptions::new(crate::CentralityMeasure::InDegree);
+        let result = graph.slice_context_with(a, 0, 2, &[], &ranking);
+        let names: Vec<&str> = result
+            .nodes
+            .iter()
+            .map(|n| n.node_info.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["a", "b", "c", "d"]);
+        assert_eq!(result.nodes[2].node_info.centrality, 1.0);
+    }
 }
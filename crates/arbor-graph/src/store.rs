@@ -1,10 +1,18 @@
 use crate::builder::GraphBuilder;
 use crate::graph::ArborGraph;
 use arbor_core::CodeNode;
-use sled::{Batch, Db};
-use std::path::Path;
+use serde::{Deserialize, Serialize};
+use sled::{Batch, Db, Tree};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 use thiserror::Error;
 
+/// Directory of the on-disk store inside a project's `.arbor/`.
+pub const STORE_DIR: &str = "store";
+
+/// Sled tree holding [`RevisionInfo`] records, keyed by commit.
+const REVISIONS_TREE: &str = "revisions";
+
 #[derive(Error, Debug)]
 pub enum StoreError {
     #[error("Database error: {0}")]
@@ -15,6 +23,19 @@ pub enum StoreError {
     Corrupted(String),
 }
 
+/// A revision indexed from git and saved with [`GraphStore::save_revision`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RevisionInfo {
+    /// Full commit hash the revision resolved to.
+    pub commit: String,
+    /// Names it was requested under (`main`, `v1.2`, ...).
+    pub refs: Vec<String>,
+    pub files: usize,
+    pub nodes: usize,
+    /// Seconds since the Unix epoch.
+    pub indexed_at: u64,
+}
+
 pub struct GraphStore {
     db: Db,
 }
@@ -26,78 +47,219 @@ impl GraphStore {
         Ok(Self { db })
     }
 
+    /// Returns the default store location for a project root.
+    pub fn path_for(root: &Path) -> PathBuf {
+        root.join(arbor_core::config::CONFIG_DIR).join(STORE_DIR)
+    }
+
     /// Updates the nodes for a specific file.
     ///
     /// This operation is atomic: it removes old nodes associated with the file
     /// and inserts the new ones.
     pub fn update_file(&self, file_path: &str, nodes: &[CodeNode]) -> Result<(), StoreError> {
-        let file_key = format!("f:{}", file_path);
+        self.apply_changes(&[(file_path, nodes)], &[])
+    }
+
+    /// Removes every node stored for a file.
+    pub fn remove_file(&self, file_path: &str) -> Result<(), StoreError> {
+        self.apply_changes(&[], &[file_path])
+    }
+
+    /// Applies updates to several files in one atomic batch.
+    ///
+    /// `updated` files get their nodes replaced, `removed` files are
+    /// dropped. Either every change lands or none does.
+    pub fn apply_changes(
+        &self,
+        updated: &[(&str, &[CodeNode])],
+        removed: &[&str],
+    ) -> Result<(), StoreError> {
         let mut batch = Batch::default();
 
-        // 1. Get old nodes for this file
-        if let Some(old_bytes) = self.db.get(&file_key)? {
-            let old_ids: Vec<String> = bincode::deserialize(&old_bytes)?;
-            for id in old_ids {
-                batch.remove(format!("n:{}", id).as_bytes());
+        // 1. Drop old nodes for every touched file
+        let touched = updated
+            .iter()
+            .map(|(file, _)| *file)
+            .chain(removed.iter().copied());
+        for file_path in touched {
+            let file_key = format!("f:{}", file_path);
+            if let Some(old_bytes) = self.db.get(&file_key)? {
+                let old_ids: Vec<String> = bincode::deserialize(&old_bytes)?;
+                for id in old_ids {
+                    batch.remove(format!("n:{}", id).as_bytes());
+                }
             }
+            batch.remove(file_key.as_bytes());
         }
 
-        // 2. Insert new nodes
-        let mut new_ids = Vec::with_capacity(nodes.len());
-        for node in nodes {
-            let node_key = format!("n:{}", node.id);
-            let bytes = bincode::serialize(node)?;
-            batch.insert(node_key.as_bytes(), bytes);
-            new_ids.push(node.id.clone());
-        }
+        // 2. Insert new nodes and file indexes
+        for (file_path, nodes) in updated {
+            let mut new_ids = Vec::with_capacity(nodes.len());
+            for node in *nodes {
+                let node_key = format!("n:{}", node.id);
+                let bytes = bincode::serialize(node)?;
+                batch.insert(node_key.as_bytes(), bytes);
+                new_ids.push(node.id.clone());
+            }
 
-        // 3. Update file index
-        let index_bytes = bincode::serialize(&new_ids)?;
-        batch.insert(file_key.as_bytes(), index_bytes);
+            let file_key = format!("f:{}", file_path);
+            let index_bytes = bincode::serialize(&new_ids)?;
+            batch.insert(file_key.as_bytes(), index_bytes);
+        }
 
-        // 4. Commit batch
+        // 3. Commit batch
         self.db.apply_batch(batch)?;
         self.db.flush()?; // flushing optional for perf, but good for safety
         Ok(())
     }
 
+    /// Returns the files that have nodes in the store.
+    pub fn files(&self) -> Result<Vec<String>, StoreError> {
+        self.db
+            .scan_prefix(b"f:")
+            .keys()
+            .map(|key| {
+                let key = key?;
+                String::from_utf8(key[2..].to_vec())
+                    .map_err(|e| StoreError::Corrupted(e.to_string()))
+            })
+            .collect()
+    }
+
     /// Loads the entire graph from the store.
     ///
     /// This iterates over all stored nodes and reconstructs the ArborGraph
     /// using the GraphBuilder (which re-links edges).
     pub fn load_graph(&self) -> Result<ArborGraph, StoreError> {
-        let mut builder = GraphBuilder::new();
-        let mut nodes = Vec::new();
-
-        // Iterate over all keys starting with "n:"
-        let prefix = b"n:";
-        for item in self.db.scan_prefix(prefix) {
-            let (_key, value) = item?;
-            let node: CodeNode = bincode::deserialize(&value)?;
-            nodes.push(node);
+        load_tree(&self.db)
+    }
+
+    /// Clears the stored graph.
+    ///
+    /// Saved revisions live in their own trees and are kept.
+    pub fn clear(&self) -> Result<(), StoreError> {
+        self.db.clear()?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    // ─── Revisions ───────────────────────────────────────────────────
+
+    /// Saves the graph of a git revision, replacing any earlier copy.
+    ///
+    /// Each revision gets its own sled tree with the same layout as the
+    /// working-tree graph, so several branches can live side by side.
+    /// `info.refs` is merged with the refs already recorded.
+    pub fn save_revision(&self, info: &RevisionInfo, graph: &ArborGraph) -> Result<(), StoreError> {
+        let tree = self.revision_tree(&info.commit)?;
+        tree.clear()?;
+
+        let mut by_file: BTreeMap<&str, Vec<&CodeNode>> = BTreeMap::new();
+        for node in graph.nodes() {
+            by_file.entry(node.file.as_str()).or_default().push(node);
+        }
+        let mut batch = Batch::default();
+        for (file, nodes) in &by_file {
+            let mut ids = Vec::with_capacity(nodes.len());
+            for node in nodes {
+                batch.insert(
+                    format!("n:{}", node.id).as_bytes(),
+                    bincode::serialize(node)?,
+                );
+                ids.push(node.id.clone());
+            }
+            batch.insert(format!("f:{}", file).as_bytes(), bincode::serialize(&ids)?);
         }
+        tree.apply_batch(batch)?;
 
-        if nodes.is_empty() {
-            // Return empty graph
-            return Ok(ArborGraph::new());
+        let mut info = info.clone();
+        if let Some(old) = self.revision(&info.commit)? {
+            for name in old.refs {
+                if !info.refs.contains(&name) {
+                    info.refs.push(name);
+                }
+            }
         }
+        self.db
+            .open_tree(REVISIONS_TREE)?
+            .insert(info.commit.as_bytes(), bincode::serialize(&info)?)?;
+        self.db.flush()?;
+        Ok(())
+    }
 
-        // Reconstruct graph
-        builder.add_nodes(nodes);
-        // resolve_edges() is called by build()
-        let graph = builder.build();
+    /// Returns what's recorded about a saved revision.
+    pub fn revision(&self, commit: &str) -> Result<Option<RevisionInfo>, StoreError> {
+        match self.db.open_tree(REVISIONS_TREE)?.get(commit.as_bytes())? {
+            Some(bytes) => Ok(Some(bincode::deserialize(&bytes)?)),
+            None => Ok(None),
+        }
+    }
 
-        Ok(graph)
+    /// Returns every saved revision, ordered by commit hash.
+    pub fn revisions(&self) -> Result<Vec<RevisionInfo>, StoreError> {
+        self.db
+            .open_tree(REVISIONS_TREE)?
+            .iter()
+            .values()
+            .map(|value| Ok(bincode::deserialize(&value?)?))
+            .collect()
     }
 
-    /// Clears the stored graph.
-    pub fn clear(&self) -> Result<(), StoreError> {
-        self.db.clear()?;
+    /// Loads the graph of a saved revision.
+    pub fn load_revision(&self, commit: &str) -> Result<Option<ArborGraph>, StoreError> {
+        if self.revision(commit)?.is_none() {
+            return Ok(None);
+        }
+        load_tree(&self.revision_tree(commit)?).map(Some)
+    }
+
+    /// Drops a saved revision. Returns whether it existed.
+    pub fn remove_revision(&self, commit: &str) -> Result<bool, StoreError> {
+        let existed = self
+            .db
+            .open_tree(REVISIONS_TREE)?
+            .remove(commit.as_bytes())?
+            .is_some();
+        self.db.drop_tree(revision_tree_name(commit))?;
         self.db.flush()?;
-        Ok(())
+        Ok(existed)
+    }
+
+    fn revision_tree(&self, commit: &str) -> Result<Tree, StoreError> {
+        Ok(self.db.open_tree(revision_tree_name(commit))?)
     }
 }
 
+fn revision_tree_name(commit: &str) -> String {
+    format!("rev:{}", commit)
+}
+
+/// Rebuilds a graph from the `n:` entries of a tree.
+fn load_tree(tree: &Tree) -> Result<ArborGraph, StoreError> {
+    let mut builder = GraphBuilder::new();
+    let mut nodes = Vec::new();
+
+    // Iterate over all keys starting with "n:"
+    let prefix = b"n:";
+    for item in tree.scan_prefix(prefix) {
+        let (_key, value) = item?;
+        let node: CodeNode = bincode::deserialize(&value)?;
+        nodes.push(node);
+    }
+
+    if nodes.is_empty() {
+        // Return empty graph
+        return Ok(ArborGraph::new());
+    }
+
+    // Reconstruct graph
+    builder.add_nodes(nodes);
+    // resolve_edges() is called by build()
+    let graph = builder.build();
+
+    Ok(graph)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -128,4 +290,79 @@ mod tests {
         assert!(graph2.find_by_name("foo").len() > 0);
         assert!(graph2.find_by_name("bar").is_empty());
     }
+
+    #[test]
+    fn test_apply_changes_across_files() {
+        let dir = tempdir().unwrap();
+        let store = GraphStore::open(dir.path()).unwrap();
+
+        let a = CodeNode::new("a", "a", NodeKind::Function, "a.rs");
+        let b = CodeNode::new("b", "b", NodeKind::Function, "b.rs");
+        store.update_file("a.rs", std::slice::from_ref(&a)).unwrap();
+        store.update_file("b.rs", std::slice::from_ref(&b)).unwrap();
+
+        // Rename a.rs to c.rs and drop b.rs in one go
+        let c = CodeNode::new("a", "a", NodeKind::Function, "c.rs");
+        store
+            .apply_changes(&[("c.rs", std::slice::from_ref(&c))], &["a.rs", "b.rs"])
+            .unwrap();
+
+        assert_eq!(store.files().unwrap(), vec!["c.rs".to_string()]);
+        let graph = store.load_graph().unwrap();
+        assert_eq!(graph.node_count(), 1);
+        assert_eq!(graph.find_by_name("a")[0].file, "c.rs");
+    }
+
+    #[test]
+    fn test_revisions_are_kept_apart() {
+        let dir = tempdir().unwrap();
+        let store = GraphStore::open(dir.path()).unwrap();
+        store
+            .update_file(
+                "a.rs",
+                &[CodeNode::new("work", "work", NodeKind::Function, "a.rs")],
+            )
+            .unwrap();
+
+        let mut builder = GraphBuilder::new();
+        builder.add_nodes(vec![CodeNode::new(
+            "old",
+            "old",
+            NodeKind::Function,
+            "a.rs",
+        )]);
+        let graph = builder.build();
+        let info = RevisionInfo {
+            commit: "abc123".to_string(),
+            refs: vec!["main".to_string()],
+            files: 1,
+            nodes: 1,
+            indexed_at: 0,
+        };
+        store.save_revision(&info, &graph).unwrap();
+        store
+            .save_revision(
+                &RevisionInfo {
+                    refs: vec!["v1".to_string()],
+                    ..info.clone()
+                },
+                &graph,
+            )
+            .unwrap();
+
+        let saved = store.load_revision("abc123").unwrap().unwrap();
+        assert_eq!(saved.node_count(), 1);
+        assert!(!saved.find_by_name("old").is_empty());
+        assert!(store.load_revision("def456").unwrap().is_none());
+        assert_eq!(store.revisions().unwrap()[0].refs, vec!["v1", "main"]);
+
+        // The working-tree graph is untouched, and clearing it keeps revisions
+        assert!(!store.load_graph().unwrap().find_by_name("work").is_empty());
+        store.clear().unwrap();
+        assert!(store.load_revision("abc123").unwrap().is_some());
+
+        assert!(store.remove_revision("abc123").unwrap());
+        assert!(store.revisions().unwrap().is_empty());
+        assert!(store.load_revision("abc123").unwrap().is_none());
+    }
 }
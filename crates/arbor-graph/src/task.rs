@@ -0,0 +1,592 @@
+//! Turning a task description into seed nodes.
+//!
+//! A task like "fix the login bug in token refresh" rarely appears in
+//! any name, so it is broken into terms first: identifier-aware tokens,
+//! minus stopwords and task filler, reduced to a crude stem so `refreshing`
+//! meets `refresh_token`. Adjacent terms also form identifier variants
+//! (`token_refresh`, `tokenRefresh`). Nodes are scored by the terms their
+//! name, qualified name, path, attributes and docstring contain, weighted
+//! by field and by how rare each term is, and the best become seeds for
+//! context slicing.
+
+use crate::graph::{ArborGraph, NodeId, CENTRALITY_BOOST};
+use crate::query::NodeInfo;
+use crate::search_index::identifier_tokens;
+use crate::testing::{is_test_file, is_test_node};
+use arbor_core::{CodeNode, NodeKind};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Seeds scoring below this share of the best seed are dropped.
+const SEED_CUTOFF: f64 = 0.25;
+
+/// Weight of a variant found in a name, per unit of its terms' rarity.
+const VARIANT_WEIGHT: f64 = 2.0;
+
+/// Score multiplier for tests, unless the task is about tests.
+const TEST_PENALTY: f64 = 0.5;
+
+/// Shortest term matched inside a joined name (`pagerank` in `page_rank`).
+const MIN_COMPOUND_LEN: usize = 6;
+
+/// Common English words and task filler that never name code.
+const STOPWORDS: &[&str] = &[
+    "a",
+    "about",
+    "after",
+    "all",
+    "also",
+    "an",
+    "and",
+    "any",
+    "are",
+    "as",
+    "at",
+    "be",
+    "been",
+    "before",
+    "being",
+    "broken",
+    "bug",
+    "bugs",
+    "but",
+    "by",
+    "can",
+    "could",
+    "did",
+    "do",
+    "does",
+    "done",
+    "fix",
+    "fixed",
+    "fixes",
+    "fixing",
+    "for",
+    "from",
+    "had",
+    "has",
+    "have",
+    "how",
+    "i",
+    "if",
+    "implement",
+    "in",
+    "into",
+    "is",
+    "issue",
+    "issues",
+    "it",
+    "its",
+    "just",
+    "make",
+    "may",
+    "me",
+    "might",
+    "must",
+    "my",
+    "need",
+    "needs",
+    "no",
+    "not",
+    "of",
+    "on",
+    "onto",
+    "or",
+    "our",
+    "please",
+    "problem",
+    "should",
+    "so",
+    "some",
+    "than",
+    "that",
+    "the",
+    "their",
+    "them",
+    "then",
+    "there",
+    "these",
+    "they",
+    "this",
+    "those",
+    "to",
+    "too",
+    "up",
+    "very",
+    "was",
+    "we",
+    "were",
+    "what",
+    "when",
+    "where",
+    "which",
+    "while",
+    "who",
+    "why",
+    "will",
+    "with",
+    "would",
+    "wrong",
+    "you",
+    "your",
+];
+
+/// Where a task term matched a node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskField {
+    Name,
+    QualifiedName,
+    Path,
+    Attribute,
+    Docstring,
+}
+
+impl TaskField {
+    /// Fields from the most to the least telling.
+    const ALL: [TaskField; 5] = [
+        TaskField::Name,
+        TaskField::QualifiedName,
+        TaskField::Path,
+        TaskField::Attribute,
+        TaskField::Docstring,
+    ];
+
+    fn weight(self) -> f64 {
+        match self {
+            TaskField::Name => 3.0,
+            TaskField::QualifiedName => 2.0,
+            TaskField::Path => 1.5,
+            TaskField::Attribute | TaskField::Docstring => 1.0,
+        }
+    }
+}
+
+/// A word kept from the task.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskTerm {
+    /// The word as written, lowercased.
+    pub word: String,
+    /// What it is matched by.
+    pub stem: String,
+}
+
+/// A node picked to start context from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskSeed {
+    #[serde(flatten)]
+    pub node: NodeInfo,
+    /// Each matched term and the best field it matched in.
+    pub matched: BTreeMap<String, TaskField>,
+    /// Identifier variants found in the node's name.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub variants: Vec<String>,
+}
+
+/// How a task was read, and the nodes it points at.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskInterpretation {
+    pub task: String,
+    pub terms: Vec<TaskTerm>,
+    /// Stopwords and filler left out.
+    pub ignored: Vec<String>,
+    /// Identifier spellings of adjacent terms.
+    pub variants: Vec<String>,
+    /// Best first.
+    pub seeds: Vec<TaskSeed>,
+}
+
+impl TaskInterpretation {
+    /// Indexes of the seed nodes, best first.
+    pub fn seed_ids<'a>(&'a self, graph: &'a ArborGraph) -> impl Iterator<Item = NodeId> + 'a {
+        self.seeds
+            .iter()
+            .filter_map(|seed| graph.get_index(&seed.node.id))
+    }
+}
+
+/// Limits for `interpret_task`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskOptions {
+    /// Most seeds to return.
+    pub max_seeds: usize,
+    /// Only seed from this workspace root.
+    pub root: Option<String>,
+}
+
+impl Default for TaskOptions {
+    fn default() -> Self {
+        Self {
+            max_seeds: 5,
+            root: None,
+        }
+    }
+}
+
+impl TaskOptions {
+    pub fn with_max_seeds(mut self, max_seeds: usize) -> Self {
+        self.max_seeds = max_seeds;
+        self
+    }
+
+    pub fn with_root(mut self, root: impl Into<String>) -> Self {
+        self.root = Some(root.into());
+        self
+    }
+}
+
+/// An adjacent pair of terms, spelled as one identifier.
+struct Variant {
+    spellings: [String; 2],
+    /// Lowercase letters and digits only, as compared with names.
+    normalized: String,
+    stems: [String; 2],
+}
+
+/// Matches found in one node.
+struct Candidate {
+    id: NodeId,
+    matched: BTreeMap<String, TaskField>,
+    variants: Vec<usize>,
+}
+
+impl ArborGraph {
+    /// Reads a task description and picks the nodes it is most likely
+    /// about. Returns no seeds when every word is a stopword or nothing
+    /// matches.
+    pub fn interpret_task(&self, task: &str, options: &TaskOptions) -> TaskInterpretation {
+        let (terms, ignored) = task_terms(task);
+        let variants = task_variants(&terms);
+        let stems: HashSet<&str> = terms.iter().map(|t| t.stem.as_str()).collect();
+
+        let mut candidates = Vec::new();
+        let mut document_frequency: HashMap<&str, usize> = HashMap::new();
+        let mut path_stems: HashMap<&str, HashSet<String>> = HashMap::new();
+        let mut nodes = 0usize;
+        for id in self.node_indexes() {
+            let Some(node) = self.get(id) else {
+                continue;
+            };
+            // Imports restate a name defined elsewhere
+            if !node.in_root(options.root.as_deref())
+                || matches!(node.kind, NodeKind::Import | NodeKind::Export)
+            {
+                continue;
+            }
+            nodes += 1;
+            if stems.is_empty() {
+                continue;
+            }
+
+            let path = path_stems
+                .entry(node.file.as_str())
+                .or_insert_with(|| stem_all(&identifier_tokens(strip_extension(&node.file))));
+            let mut matched = BTreeMap::new();
+            for field in TaskField::ALL {
+                let own;
+                let found = match field {
+                    TaskField::Path => &*path,
+                    _ => {
+                        own = field_stems(node, field);
+                        &own
+                    }
+                };
+                for term in &terms {
+                    if found.contains(&term.stem) {
+                        matched.entry(term.word.clone()).or_insert(field);
+                    }
+                }
+            }
+            let name = normalize(&node.qualified_name);
+            for term in &terms {
+                if term.word.len() >= MIN_COMPOUND_LEN && name.contains(&term.word) {
+                    matched
+                        .entry(term.word.clone())
+                        .or_insert(TaskField::QualifiedName);
+                }
+            }
+            let found_variants: Vec<usize> = variants
+                .iter()
+                .enumerate()
+                .filter(|(_, v)| name.contains(&v.normalized))
+                .map(|(i, _)| i)
+                .collect();
+            if matched.is_empty() && found_variants.is_empty() {
+                continue;
+            }
+            for term in &terms {
+                if matched.contains_key(&term.word) {
+                    *document_frequency.entry(term.stem.as_str()).or_default() += 1;
+                }
+            }
+            candidates.push(Candidate {
+                id,
+                matched,
+                variants: found_variants,
+            });
+        }
+
+        let about_tests = stems.contains("test");
+        let rarity = |stem: &str| {
+            let df = document_frequency.get(stem).copied().unwrap_or(0).max(1);
+            (1.0 + nodes as f64 / df as f64).ln()
+        };
+        let stem_of: HashMap<&str, &str> = terms
+            .iter()
+            .map(|t| (t.word.as_str(), t.stem.as_str()))
+            .collect();
+
+        let mut scored: Vec<(f64, Candidate)> = candidates
+            .into_iter()
+            .map(|candidate| {
+                let mut score: f64 = candidate
+                    .matched
+                    .iter()
+                    .map(|(word, field)| field.weight() * rarity(stem_of[word.as_str()]))
+                    .sum();
+                for &v in &candidate.variants {
+                    score +=
+                        VARIANT_WEIGHT * variants[v].stems.iter().map(|s| rarity(s)).sum::<f64>();
+                }
+                // Covering more of the task counts, but one rare term can still seed
+                let coverage = candidate.matched.len().max(1) as f64 / terms.len().max(1) as f64;
+                score *= 0.5 + 0.5 * coverage;
+                score *= 1.0 + CENTRALITY_BOOST * self.centrality(candidate.id);
+                let node = &self.graph[candidate.id];
+                if !about_tests && (is_test_node(node) || is_test_file(&node.file)) {
+                    score *= TEST_PENALTY;
+                }
+                (score, candidate)
+            })
+            .collect();
+        scored.sort_by(|(a_score, a), (b_score, b)| {
+            let generated = |c: &Candidate| self.graph[c.id].generated;
+            generated(a)
+                .cmp(&generated(b))
+                .then(b_score.total_cmp(a_score))
+                .then(a.id.cmp(&b.id))
+        });
+
+        let best = scored.first().map_or(0.0, |(score, _)| *score);
+        let seeds = scored
+            .into_iter()
+            .take_while(|(score, _)| *score >= best * SEED_CUTOFF)
+            .take(options.max_seeds)
+            .filter_map(|(score, candidate)| {
+                let mut node = NodeInfo::from(self.get(candidate.id)?);
+                node.centrality = self.centrality(candidate.id);
+                node.score = Some(score);
+                Some(TaskSeed {
+                    node,
+                    matched: candidate.matched,
+                    variants: candidate
+                        .variants
+                        .iter()
+                        .map(|&v| variants[v].spellings[0].clone())
+                        .collect(),
+                })
+            })
+            .collect();
+
+        TaskInterpretation {
+            task: task.to_string(),
+            terms,
+            ignored,
+            variants: variants.into_iter().flat_map(|v| v.spellings).collect(),
+            seeds,
+        }
+    }
+}
+
+/// Splits a task into kept terms, deduplicated by stem, and ignored words.
+fn task_terms(task: &str) -> (Vec<TaskTerm>, Vec<String>) {
+    let mut terms: Vec<TaskTerm> = Vec::new();
+    let mut ignored: Vec<String> = Vec::new();
+    for word in identifier_tokens(task) {
+        if word.chars().count() < 2 || STOPWORDS.contains(&word.as_str()) {
+            if !ignored.contains(&word) {
+                ignored.push(word);
+            }
+            continue;
+        }
+        let stem = stem(&word);
+        if !terms.iter().any(|t| t.stem == stem) {
+            terms.push(TaskTerm { word, stem });
+        }
+    }
+    (terms, ignored)
+}
+
+/// snake_case and camelCase spellings of each adjacent pair of terms.
+fn task_variants(terms: &[TaskTerm]) -> Vec<Variant> {
+    terms
+        .windows(2)
+        .map(|pair| {
+            let (a, b) = (&pair[0].word, &pair[1].word);
+            let mut camel = a.clone();
+            let mut rest = b.chars();
+            camel.extend(rest.next().map(|c| c.to_ascii_uppercase()));
+            camel.extend(rest);
+            Variant {
+                spellings: [format!("{}_{}", a, b), camel],
+                normalized: format!("{}{}", a, b),
+                stems: [pair[0].stem.clone(), pair[1].stem.clone()],
+            }
+        })
+        .collect()
+}
+
+/// Stems of the words in one field of a node.
+fn field_stems(node: &CodeNode, field: TaskField) -> HashSet<String> {
+    match field {
+        TaskField::Name => stem_all(&identifier_tokens(&node.name)),
+        TaskField::QualifiedName => stem_all(&identifier_tokens(&node.qualified_name)),
+        TaskField::Attribute => node
+            .attributes
+            .iter()
+            .flat_map(|a| stem_all(&identifier_tokens(a)))
+            .collect(),
+        TaskField::Docstring => node
+            .docstring
+            .as_deref()
+            .map(|d| stem_all(&identifier_tokens(d)))
+            .unwrap_or_default(),
+        TaskField::Path => HashSet::new(),
+    }
+}
+
+fn stem_all(words: &[String]) -> HashSet<String> {
+    words.iter().map(|w| stem(w)).collect()
+}
+
+/// `src/auth/session.rs` → `src/auth/session`.
+fn strip_extension(file: &str) -> &str {
+    match file.rsplit_once('.') {
+        Some((stem, ext)) if !ext.contains('/') && !stem.ends_with('/') => stem,
+        _ => file,
+    }
+}
+
+/// Lowercase letters and digits of an identifier.
+fn normalize(text: &str) -> String {
+    text.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Reduces a lowercase word to a crude stem: plurals, `-ing`, `-ed` and
+/// `-er` are dropped, a doubled final consonant is undoubled and a final
+/// `e` removed, so `caches`, `caching` and `cache` all become `cach`. Only
+/// ever compared with other stems.
+pub(crate) fn stem(word: &str) -> String {
+    let mut w = word.to_string();
+    if w.len() <= 3 || !w.is_ascii() {
+        return w;
+    }
+    if let Some(base) = w.strip_suffix("ies") {
+        return format!("{}y", base);
+    }
+    if w.ends_with('s') && !(w.ends_with("ss") || w.ends_with("us") || w.ends_with("is")) {
+        w.pop();
+    }
+    for (suffix, min) in [("ing", 3), ("ed", 3), ("er", 4)] {
+        if w.len() >= suffix.len() + min && w.ends_with(suffix) {
+            w.truncate(w.len() - suffix.len());
+            break;
+        }
+    }
+    let bytes = w.as_bytes();
+    if let [.., a, b] = bytes {
+        if a == b && !b"aeioulsz".contains(b) {
+            w.pop();
+        }
+    }
+    if w.len() > 3 && w.ends_with('e') {
+        w.pop();
+    }
+    w
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_terms_and_stems() {
+        let (terms, ignored) = task_terms("Fix the login bug in tokenRefresh handling");
+        let words: Vec<&str> = terms.iter().map(|t| t.word.as_str()).collect();
+        assert_eq!(words, vec!["login", "token", "refresh", "handling"]);
+        assert_eq!(ignored, vec!["fix", "the", "bug", "in"]);
+
+        for (word, expected) in [
+            ("caches", "cach"),
+            ("caching", "cach"),
+            ("cache", "cach"),
+            ("logging", "log"),
+            ("called", "call"),
+            ("entries", "entry"),
+            ("handler", "handl"),
+            ("status", "status"),
+            ("user", "user"),
+        ] {
+            assert_eq!(stem(word), expected, "{}", word);
+        }
+
+        let variants = task_variants(&terms);
+        assert_eq!(variants[1].spellings, ["token_refresh", "tokenRefresh"]);
+        assert_eq!(variants[1].normalized, "tokenrefresh");
+    }
+
+    #[test]
+    fn test_interpret_task_picks_seeds() {
+        let mut graph = ArborGraph::new();
+        graph.add_node(
+            CodeNode::new(
+                "refresh_token",
+                "auth.refresh_token",
+                NodeKind::Function,
+                "src/auth.py",
+            )
+            .with_lines(10, 20),
+        );
+        graph.add_node(
+            CodeNode::new("login", "auth.login", NodeKind::Function, "src/auth.py")
+                .with_lines(30, 40),
+        );
+        let mut render =
+            CodeNode::new("render", "views.render", NodeKind::Function, "src/views.py");
+        render.docstring = Some("Draws the login page.".to_string());
+        graph.add_node(render);
+        graph.add_node(CodeNode::new(
+            "parse",
+            "config.parse",
+            NodeKind::Function,
+            "src/config.py",
+        ));
+
+        let interpretation = graph.interpret_task(
+            "fix the login bug in token refresh",
+            &TaskOptions::default(),
+        );
+        let names: Vec<&str> = interpretation
+            .seeds
+            .iter()
+            .map(|s| s.node.name.as_str())
+            .collect();
+        assert_eq!(names[0], "refresh_token");
+        assert!(names.contains(&"login"));
+        assert!(!names.contains(&"parse"));
+        assert_eq!(
+            interpretation.seeds[0].matched.get("refresh"),
+            Some(&TaskField::Name)
+        );
+        assert!(interpretation
+            .variants
+            .contains(&"tokenRefresh".to_string()));
+
+        let empty = graph.interpret_task("fix the bug", &TaskOptions::default());
+        assert!(empty.terms.is_empty());
+        assert!(empty.seeds.is_empty());
+    }
+}
@@ -0,0 +1,500 @@
+//! Recognizing test code.
+//!
+//! Heuristics over file paths and names, covering the usual conventions
+//! of the languages we parse: `tests/` directories, `test_*.py`,
+//! `*_test.go`, `*.test.ts`, `*.spec.js`, `FooTest.java`, `test_*` and
+//! `TestXxx` functions.
+//!
+//! [`ArborGraph::select_tests`] walks the reverse call graph from changed
+//! code to the tests that reach it, and renders them as runner commands.
+
+use crate::graph::{ArborGraph, NodeId};
+use crate::query::NodeInfo;
+use arbor_core::{CodeNode, NodeKind};
+use petgraph::visit::EdgeRef;
+use petgraph::Direction;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashSet, VecDeque};
+
+/// Whether a file looks like it holds tests.
+pub fn is_test_file(path: &str) -> bool {
+    let path = path.replace('\\', "/");
+    let mut parts = path.split('/').rev();
+    let file = parts.next().unwrap_or("");
+    if parts.any(|dir| matches!(dir, "test" | "tests" | "__tests__" | "spec" | "specs")) {
+        return true;
+    }
+
+    let (stem, ext) = file.rsplit_once('.').unwrap_or((file, ""));
+    match ext {
+        "py" => stem.starts_with("test_") || stem.ends_with("_test") || stem == "conftest",
+        "go" => stem.ends_with("_test"),
+        "rs" => stem == "tests" || stem.ends_with("_test") || stem.ends_with("_tests"),
+        "java" | "kt" | "cs" => {
+            stem.ends_with("Test") || stem.ends_with("Tests") || stem.ends_with("IT")
+        }
+        "js" | "jsx" | "ts" | "tsx" | "mjs" | "cjs" => {
+            stem.ends_with(".test") || stem.ends_with(".spec")
+        }
+        "rb" => stem.ends_with("_spec") || stem.ends_with("_test"),
+        _ => false,
+    }
+}
+
+/// Whether a node is a test, judged by its name and file.
+///
+/// Python and Go runners only collect `test_*` / `TestXxx` functions, so
+/// other functions in their test files are helpers. Elsewhere every
+/// function in a test file counts, since frameworks mark tests with
+/// attributes or annotations we don't see.
+pub fn is_test_node(node: &CodeNode) -> bool {
+    match node.kind {
+        NodeKind::Function | NodeKind::Method => {
+            let by_convention = node.file.ends_with(".py") || node.file.ends_with(".go");
+            is_test_name(&node.name) || (!by_convention && is_test_file(&node.file))
+        }
+        NodeKind::Class | NodeKind::Struct => {
+            node.name.starts_with("Test")
+                || node.name.ends_with("Test")
+                || node.name.ends_with("Tests")
+        }
+        _ => false,
+    }
+}
+
+impl ArborGraph {
+    /// Whether a node is a test. On top of [`is_test_node`], functions
+    /// nested in a `tests` module (Rust's `#[cfg(test)] mod tests`) count.
+    pub fn is_test(&self, node: &CodeNode) -> bool {
+        if is_test_node(node) {
+            return true;
+        }
+        matches!(node.kind, NodeKind::Function | NodeKind::Method)
+            && self.find_by_file(&node.file).iter().any(|m| {
+                m.kind == NodeKind::Module
+                    && matches!(m.name.as_str(), "tests" | "test")
+                    && m.line_start <= node.line_start
+                    && node.line_end <= m.line_end
+            })
+    }
+}
+
+/// A test runner we can build commands for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TestRunner {
+    Cargo,
+    Pytest,
+    GoTest,
+    Jest,
+}
+
+impl TestRunner {
+    /// Picks the runner for a test file by extension.
+    pub fn for_file(path: &str) -> Option<Self> {
+        let ext = path.rsplit_once('.').map(|(_, ext)| ext)?;
+        match ext {
+            "rs" => Some(Self::Cargo),
+            "py" => Some(Self::Pytest),
+            "go" => Some(Self::GoTest),
+            "js" | "jsx" | "ts" | "tsx" | "mjs" | "cjs" => Some(Self::Jest),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for TestRunner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Cargo => "cargo",
+            Self::Pytest => "pytest",
+            Self::GoTest => "go_test",
+            Self::Jest => "jest",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// One command that runs part of a [`TestSelection`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunnerCommand {
+    pub runner: TestRunner,
+    /// Program and arguments, unquoted.
+    pub args: Vec<String>,
+}
+
+impl RunnerCommand {
+    fn new(runner: TestRunner, args: &[&str], rest: impl IntoIterator<Item = String>) -> Self {
+        let mut all: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+        all.extend(rest);
+        Self { runner, args: all }
+    }
+
+    /// The command as a line for a POSIX shell.
+    pub fn to_shell(&self) -> String {
+        self.args
+            .iter()
+            .map(|arg| shell_quote(arg))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Tests that reach a set of changed nodes.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TestSelection {
+    /// Test functions, methods and classes, by file and line.
+    pub tests: Vec<NodeInfo>,
+    /// Files holding those tests.
+    pub files: Vec<String>,
+    /// Commands running exactly those tests, grouped by runner.
+    pub commands: Vec<RunnerCommand>,
+}
+
+impl ArborGraph {
+    /// Finds the tests that transitively reach any of `targets`.
+    ///
+    /// Walks incoming edges up to `max_depth` hops (0 = unlimited). Targets
+    /// that are tests themselves are included. The walk doesn't continue
+    /// past a test, so helpers shared by tests don't pull in every test
+    /// that uses them unless they reach the target too.
+    pub fn tests_reaching(&self, targets: &[NodeId], max_depth: usize) -> Vec<NodeId> {
+        let max_depth = if max_depth == 0 {
+            usize::MAX
+        } else {
+            max_depth
+        };
+        let mut visited: HashSet<NodeId> = targets.iter().copied().collect();
+        let mut queue: VecDeque<(NodeId, usize)> = targets.iter().map(|&t| (t, 0)).collect();
+        let mut tests = BTreeSet::new();
+
+        while let Some((current, depth)) = queue.pop_front() {
+            let Some(node) = self.get(current) else {
+                continue;
+            };
+            if self.is_test(node) {
+                tests.insert(current);
+                continue;
+            }
+            if depth >= max_depth {
+                continue;
+            }
+            for edge in self.graph.edges_directed(current, Direction::Incoming) {
+                if visited.insert(edge.source()) {
+                    queue.push_back((edge.source(), depth + 1));
+                }
+            }
+        }
+        tests.into_iter().collect()
+    }
+
+    /// Selects the tests for a change and builds runner commands for them.
+    pub fn select_tests(&self, targets: &[NodeId], max_depth: usize) -> TestSelection {
+        let mut tests: Vec<&CodeNode> = self
+            .tests_reaching(targets, max_depth)
+            .into_iter()
+            .filter_map(|i| self.get(i))
+            .collect();
+        tests.sort_by(|a, b| (&a.file, a.line_start).cmp(&(&b.file, b.line_start)));
+
+        // A test class and its methods: keep the class only
+        let classes: HashSet<(&str, &str)> = tests
+            .iter()
+            .filter(|t| matches!(t.kind, NodeKind::Class | NodeKind::Struct))
+            .map(|t| (t.file.as_str(), t.qualified_name.as_str()))
+            .collect();
+        tests.retain(|t| {
+            let parent = t.qualified_name.rsplit_once('.').map(|(p, _)| p);
+            !parent.is_some_and(|p| classes.contains(&(t.file.as_str(), p)))
+        });
+
+        let files: Vec<String> = tests
+            .iter()
+            .map(|t| t.file.clone())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        TestSelection {
+            commands: runner_commands(self, &tests),
+            tests: tests.into_iter().map(NodeInfo::from).collect(),
+            files,
+        }
+    }
+}
+
+/// Groups tests by runner and renders each group as a command.
+fn runner_commands(graph: &ArborGraph, tests: &[&CodeNode]) -> Vec<RunnerCommand> {
+    let mut by_runner: BTreeMap<TestRunner, Vec<&CodeNode>> = BTreeMap::new();
+    for test in tests {
+        if let Some(runner) = TestRunner::for_file(&test.file) {
+            by_runner.entry(runner).or_default().push(test);
+        }
+    }
+
+    let mut commands = Vec::new();
+    for (runner, tests) in by_runner {
+        match runner {
+            TestRunner::Cargo => {
+                // Integration tests need `--test <name>`. Filters are full
+                // test paths with `--exact`, since cargo otherwise matches
+                // any test whose path contains them.
+                let mut targets: BTreeMap<Option<String>, BTreeSet<String>> = BTreeMap::new();
+                for test in tests {
+                    targets
+                        .entry(integration_target(&test.file))
+                        .or_default()
+                        .insert(graph.rust_test_path(test));
+                }
+                for (target, paths) in targets {
+                    let mut args = vec!["cargo".to_string(), "test".to_string()];
+                    if let Some(target) = target {
+                        args.extend(["--test".to_string(), target]);
+                    }
+                    args.extend(["--".to_string(), "--exact".to_string()]);
+                    args.extend(paths);
+                    commands.push(RunnerCommand { runner, args });
+                }
+            }
+            TestRunner::Pytest => {
+                let ids = tests
+                    .iter()
+                    .map(|t| format!("{}::{}", t.file, t.qualified_name.replace('.', "::")));
+                commands.push(RunnerCommand::new(runner, &["pytest"], ids));
+            }
+            TestRunner::GoTest => {
+                let mut packages: BTreeMap<String, BTreeSet<&str>> = BTreeMap::new();
+                for test in tests {
+                    let dir = match test.file.rsplit_once('/') {
+                        Some((dir, _)) => format!("./{}", dir),
+                        None => ".".to_string(),
+                    };
+                    packages.entry(dir).or_default().insert(&test.name);
+                }
+                for (package, names) in packages {
+                    let names: Vec<&str> = names.into_iter().collect();
+                    let pattern = format!("^({})$", names.join("|"));
+                    commands.push(RunnerCommand::new(
+                        runner,
+                        &["go", "test", &package, "-run"],
+                        [pattern],
+                    ));
+                }
+            }
+            TestRunner::Jest => {
+                let paths: BTreeSet<String> = tests.iter().map(|t| t.file.clone()).collect();
+                commands.push(RunnerCommand::new(runner, &["npx", "jest"], paths));
+            }
+        }
+    }
+    commands
+}
+
+impl ArborGraph {
+    /// The path libtest knows a Rust test by: modules from the file's
+    /// place in its crate, then any inline `mod` blocks around it, e.g.
+    /// `parser::tests::test_parse`.
+    fn rust_test_path(&self, test: &CodeNode) -> String {
+        let mut path = rust_module_path(&test.file);
+        let mut inline: Vec<&CodeNode> = self
+            .find_by_file(&test.file)
+            .into_iter()
+            .filter(|m| {
+                m.kind == NodeKind::Module
+                    && m.line_start <= test.line_start
+                    && test.line_end <= m.line_end
+                    && m.id != test.id
+            })
+            .collect();
+        inline.sort_by_key(|m| (m.line_start, std::cmp::Reverse(m.line_end)));
+        path.extend(inline.into_iter().map(|m| m.name.clone()));
+        path.push(test.name.clone());
+        path.join("::")
+    }
+}
+
+/// Module names from a crate root to a Rust file: `src/a/b.rs` and
+/// `src/a/b/mod.rs` are `a::b`, and crate roots (`lib.rs`, `main.rs`,
+/// `src/bin/x.rs`, `tests/x.rs`) are empty.
+fn rust_module_path(file: &str) -> Vec<String> {
+    let parts: Vec<&str> = file.split('/').collect();
+    let rest = match parts.iter().rposition(|p| *p == "src" || *p == "tests") {
+        Some(root) if parts[root] == "tests" => {
+            // Skip the integration test's own root file or directory
+            match &parts[root + 1..] {
+                [_] | [_, "main.rs"] => &[][..],
+                [_, rest @ ..] => rest,
+                [] => &[][..],
+            }
+        }
+        Some(root) => match &parts[root + 1..] {
+            ["bin", _] | ["bin", _, "main.rs"] => &[][..],
+            ["bin", _, rest @ ..] => rest,
+            rest => rest,
+        },
+        None => &parts[parts.len().saturating_sub(1)..],
+    };
+    let mut modules: Vec<String> = rest.iter().map(|p| p.to_string()).collect();
+    if let Some(last) = modules.pop() {
+        match last.strip_suffix(".rs") {
+            Some("lib" | "main" | "mod") if modules.is_empty() || last == "mod.rs" => {}
+            Some(stem) => modules.push(stem.to_string()),
+            None => modules.push(last),
+        }
+    }
+    modules
+}
+
+/// `tests/foo.rs` and `tests/foo/main.rs` are the `foo` integration test.
+fn integration_target(file: &str) -> Option<String> {
+    let parts: Vec<&str> = file.split('/').collect();
+    let tests_dir = parts.iter().rposition(|p| *p == "tests")?;
+    match &parts[tests_dir + 1..] {
+        [file] => file.strip_suffix(".rs").map(str::to_string),
+        [dir, "main.rs"] => Some(dir.to_string()),
+        _ => None,
+    }
+}
+
+/// Quotes an argument for a POSIX shell if it needs it.
+fn shell_quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=@,+".contains(c));
+    if plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+/// `test_foo`, `testFoo`, `TestFoo`, or `test` itself.
+fn is_test_name(name: &str) -> bool {
+    if name == "test" {
+        return true;
+    }
+    if let Some(rest) = name.strip_prefix("test_") {
+        return !rest.is_empty();
+    }
+    if let Some(rest) = name
+        .strip_prefix("test")
+        .or_else(|| name.strip_prefix("Test"))
+    {
+        return rest.starts_with(|c: char| c.is_ascii_uppercase() || c == '_');
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_test_files() {
+        for path in [
+            "tests/api.rs",
+            "src/__tests__/app.tsx",
+            "test_users.py",
+            "pkg/users_test.go",
+            "web/app.test.ts",
+            "web/app.spec.js",
+            "src/test/java/UserServiceTest.java",
+            "spec/models/user_spec.rb",
+        ] {
+            assert!(is_test_file(path), "{}", path);
+        }
+        for path in ["src/lib.rs", "users.py", "latest.go", "contest.ts"] {
+            assert!(!is_test_file(path), "{}", path);
+        }
+    }
+
+    #[test]
+    fn test_test_nodes() {
+        let f = |name: &str, file: &str| CodeNode::new(name, name, NodeKind::Function, file);
+        assert!(is_test_node(&f("test_login", "app.py")));
+        assert!(is_test_node(&f("TestLogin", "login_test.go")));
+        assert!(is_test_node(&f("logsIn", "LoginTest.java")));
+        assert!(is_test_node(&f("parses", "tests/parser.rs")));
+        assert!(!is_test_node(&f("make_user", "tests/test_users.py")));
+        assert!(!is_test_node(&f("testament", "app.py")));
+        assert!(!is_test_node(&f("login", "app.py")));
+        assert!(is_test_node(&CodeNode::new(
+            "TestUsers",
+            "TestUsers",
+            NodeKind::Class,
+            "app.py"
+        )));
+    }
+
+    #[test]
+    fn test_rust_tests_module() {
+        let mut builder = crate::GraphBuilder::new();
+        builder.add_nodes(vec![
+            CodeNode::new("add", "add", NodeKind::Function, "src/lib.rs").with_lines(1, 1),
+            CodeNode::new("tests", "tests", NodeKind::Module, "src/lib.rs").with_lines(3, 9),
+            CodeNode::new("adds", "adds", NodeKind::Function, "src/lib.rs").with_lines(5, 7),
+        ]);
+        let graph = builder.build();
+        let adds = graph.find_by_name("adds")[0];
+        assert!(graph.is_test(adds));
+        assert!(!graph.is_test(graph.find_by_name("add")[0]));
+        assert_eq!(graph.rust_test_path(adds), "tests::adds");
+
+        assert!(rust_module_path("crates/core/src/lib.rs").is_empty());
+        assert_eq!(rust_module_path("src/parse/mod.rs"), vec!["parse"]);
+        assert_eq!(rust_module_path("src/parse/expr.rs"), vec!["parse", "expr"]);
+        assert!(rust_module_path("src/bin/tool.rs").is_empty());
+        assert!(rust_module_path("tests/cli.rs").is_empty());
+        assert_eq!(rust_module_path("tests/cli/util.rs"), vec!["util"]);
+    }
+
+    #[test]
+    fn test_select_tests_builds_runner_commands() {
+        let func = |name: &str, file: &str, refs: &[&str]| {
+            CodeNode::new(name, name, NodeKind::Function, file)
+                .with_references(refs.iter().map(|r| r.to_string()).collect())
+        };
+        let mut builder = crate::GraphBuilder::new();
+        builder.add_nodes(vec![
+            func("core", "src/core.rs", &[]),
+            func("wrapper", "src/api.rs", &["core"]),
+            func("unrelated", "src/other.rs", &[]),
+            func("test_core", "tests/core.rs", &["core"]),
+            func("test_wrapper_works", "src/api_tests.rs", &["wrapper"]),
+            func("test_other", "tests/other.rs", &["unrelated"]),
+            CodeNode::new(
+                "test_core",
+                "TestCore.test_core",
+                NodeKind::Method,
+                "py/test_core.py",
+            )
+            .with_references(vec!["core".to_string()]),
+            func("TestCore", "pkg/core_test.go", &["core"]),
+            func("helper", "web/core.test.ts", &["core"]),
+        ]);
+        let graph = builder.build();
+        let core = graph.get_index(&graph.find_by_name("core")[0].id).unwrap();
+
+        let selection = graph.select_tests(&[core], 0);
+        let names: Vec<&str> = selection.tests.iter().map(|t| t.name.as_str()).collect();
+        assert!(!names.contains(&"test_other"));
+        assert_eq!(selection.tests.len(), 5);
+        assert_eq!(selection.files.len(), 5);
+
+        let shell: Vec<String> = selection.commands.iter().map(|c| c.to_shell()).collect();
+        assert_eq!(
+            shell,
+            vec![
+                "cargo test -- --exact api_tests::test_wrapper_works",
+                "cargo test --test core -- --exact test_core",
+                "pytest py/test_core.py::TestCore::test_core",
+                "go test ./pkg -run '^(TestCore)$'",
+                "npx jest web/core.test.ts",
+            ]
+        );
+
+        // Depth 1 stops before the test behind `wrapper`
+        let shallow = graph.select_tests(&[core], 1);
+        assert!(shallow.tests.iter().all(|t| t.name != "test_wrapper_works"));
+    }
+}
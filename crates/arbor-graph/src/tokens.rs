@@ -0,0 +1,505 @@
+//! Token counting for context budgets.
+//!
+//! Budgets are spent on real source: each node's bytes are taken from the
+//! body index or read from disk and counted with a `TokenCounter`. The
+//! default counter is a byte-level BPE in the tiktoken format, splitting
+//! text with cl100k_base's pre-tokenizer and merging with a compact
+//! vocabulary learned from source code (`data/code_bpe.tiktoken`, built
+//! by `data/train_vocab.py`). It is compiled in, so nothing is downloaded.
+//! Load OpenAI's `cl100k_base.tiktoken` with `BpeTokenizer::from_file` for
+//! exact counts. Nodes without readable source fall back to a character
+//! heuristic. Counts are cached by a hash of the counted text, keeping at
+//! most `MAX_CACHED_COUNTS` of the most recently used.
+
+use crate::graph::{ArborGraph, NodeId};
+use arbor_core::decode_source;
+use regex::Regex;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, Mutex, OnceLock};
+use thiserror::Error;
+
+/// The bundled vocabulary.
+const BUNDLED_VOCAB: &str = include_str!("../data/code_bpe.tiktoken");
+
+/// cl100k_base's pre-tokenizer, minus `\s+(?!\S)`, which `pieces` emulates.
+const PIECE_PATTERN: &str = r"(?i:'s|'t|'re|'ve|'m|'ll|'d)|[^\r\n\p{L}\p{N}]?\p{L}+|\p{N}{1,3}| ?[^\s\p{L}\p{N}]+[\r\n]*|\s*[\r\n]+|\s+";
+
+/// Pieces longer than this are merged in chunks, bounding the quadratic
+/// merge loop on minified or generated text.
+const MAX_PIECE_LEN: usize = 512;
+
+/// Counts kept per graph. Each generation holds half; when the newer one
+/// fills, the older is dropped.
+const MAX_CACHED_COUNTS: usize = 1 << 16;
+
+/// Characters per line assumed for nodes without readable source.
+const FALLBACK_LINE_LEN: usize = 40;
+
+/// Counts the tokens a piece of text costs in a prompt.
+pub trait TokenCounter: Send + Sync {
+    /// Short name for logs and output, e.g. `bpe` or `chars`.
+    fn name(&self) -> &str;
+
+    fn count(&self, text: &str) -> usize;
+}
+
+/// Estimates one token per `chars_per_token` characters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CharHeuristic {
+    pub chars_per_token: f64,
+}
+
+impl Default for CharHeuristic {
+    fn default() -> Self {
+        Self {
+            chars_per_token: 4.0,
+        }
+    }
+}
+
+impl CharHeuristic {
+    /// Tokens for `chars` characters of text.
+    pub fn count_chars(&self, chars: usize) -> usize {
+        (chars as f64 / self.chars_per_token).ceil() as usize
+    }
+}
+
+impl TokenCounter for CharHeuristic {
+    fn name(&self) -> &str {
+        "chars"
+    }
+
+    fn count(&self, text: &str) -> usize {
+        self.count_chars(text.chars().count())
+    }
+}
+
+/// Errors loading a tiktoken vocabulary.
+#[derive(Debug, Error)]
+pub enum TokenizerError {
+    #[error("failed to read vocabulary: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("vocabulary line {line}: {message}")]
+    Parse { line: usize, message: String },
+
+    #[error("vocabulary has no token for byte {0:#04x}")]
+    MissingByte(u8),
+}
+
+/// Byte-level BPE with cl100k_base's pre-tokenizer.
+pub struct BpeTokenizer {
+    ranks: HashMap<Vec<u8>, u32>,
+    pattern: Regex,
+}
+
+impl fmt::Debug for BpeTokenizer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BpeTokenizer")
+            .field("vocab_size", &self.ranks.len())
+            .finish()
+    }
+}
+
+impl BpeTokenizer {
+    /// The vocabulary compiled into Arbor, loaded once.
+    pub fn bundled() -> Arc<BpeTokenizer> {
+        static BUNDLED: OnceLock<Arc<BpeTokenizer>> = OnceLock::new();
+        BUNDLED
+            .get_or_init(|| {
+                Arc::new(Self::from_tiktoken(BUNDLED_VOCAB).expect("bundled vocabulary is valid"))
+            })
+            .clone()
+    }
+
+    /// Parses a vocabulary in the tiktoken format: one base64 token and
+    /// its rank per line. Every single byte must have a rank.
+    pub fn from_tiktoken(text: &str) -> Result<Self, TokenizerError> {
+        let mut ranks = HashMap::new();
+        for (i, line) in text.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let parse_error = |message: &str| TokenizerError::Parse {
+                line: i + 1,
+                message: message.to_string(),
+            };
+            let (token, rank) = line
+                .split_once(' ')
+                .ok_or_else(|| parse_error("expected `<base64> <rank>`"))?;
+            let token = decode_base64(token).ok_or_else(|| parse_error("invalid base64"))?;
+            let rank = rank
+                .trim()
+                .parse()
+                .map_err(|_| parse_error("invalid rank"))?;
+            ranks.insert(token, rank);
+        }
+        if let Some(byte) = (0..=255u8).find(|b| !ranks.contains_key([*b].as_slice())) {
+            return Err(TokenizerError::MissingByte(byte));
+        }
+        Ok(Self {
+            ranks,
+            pattern: Regex::new(PIECE_PATTERN).expect("piece pattern is valid"),
+        })
+    }
+
+    /// Reads a `.tiktoken` vocabulary file, e.g. `cl100k_base.tiktoken`.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, TokenizerError> {
+        Self::from_tiktoken(&fs::read_to_string(path)?)
+    }
+
+    pub fn vocab_size(&self) -> usize {
+        self.ranks.len()
+    }
+
+    /// Token ranks for `text`.
+    pub fn encode(&self, text: &str) -> Vec<u32> {
+        let mut tokens = Vec::new();
+        for piece in self.pieces(text) {
+            for chunk in piece.as_bytes().chunks(MAX_PIECE_LEN) {
+                let bounds = self.merge(chunk);
+                tokens.extend(bounds.windows(2).map(|w| self.ranks[&chunk[w[0]..w[1]]]));
+            }
+        }
+        tokens
+    }
+
+    /// Splits text the way cl100k_base does before merging.
+    fn pieces<'t>(&self, text: &'t str) -> Vec<&'t str> {
+        let mut pieces = Vec::new();
+        let mut pos = 0;
+        while let Some(m) = self.pattern.find_at(text, pos) {
+            let mut end = m.end();
+            // `\s+(?!\S)`: a run of spaces before a word leaves its last
+            // space to the word
+            let run = m.as_str();
+            if end < text.len()
+                && run.chars().all(char::is_whitespace)
+                && !run.ends_with(['\r', '\n'])
+            {
+                if let Some((last, _)) = run.char_indices().last().filter(|(i, _)| *i > 0) {
+                    end = m.start() + last;
+                }
+            }
+            pieces.push(&text[m.start()..end]);
+            pos = end;
+        }
+        pieces
+    }
+
+    /// Merges the lowest-ranked adjacent pair until none is in the
+    /// vocabulary, and returns the token boundaries.
+    fn merge(&self, piece: &[u8]) -> Vec<usize> {
+        if self.ranks.contains_key(piece) {
+            return vec![0, piece.len()];
+        }
+        let mut bounds: Vec<usize> = (0..=piece.len()).collect();
+        loop {
+            let best = (0..bounds.len().saturating_sub(2))
+                .filter_map(|i| {
+                    let rank = self.ranks.get(&piece[bounds[i]..bounds[i + 2]])?;
+                    Some((*rank, i))
+                })
+                .min();
+            match best {
+                Some((_, i)) => {
+                    bounds.remove(i + 1);
+                }
+                None => return bounds,
+            }
+        }
+    }
+}
+
+impl TokenCounter for BpeTokenizer {
+    fn name(&self) -> &str {
+        "bpe"
+    }
+
+    fn count(&self, text: &str) -> usize {
+        self.pieces(text)
+            .iter()
+            .flat_map(|piece| piece.as_bytes().chunks(MAX_PIECE_LEN))
+            .map(|chunk| self.merge(chunk).len() - 1)
+            .sum()
+    }
+}
+
+/// The graph's token counter and its cache of counts by content hash.
+pub(crate) struct TokenCounts {
+    counter: Arc<dyn TokenCounter>,
+    cache: Mutex<CountCache>,
+}
+
+impl TokenCounts {
+    fn new(counter: Arc<dyn TokenCounter>) -> Self {
+        Self {
+            counter,
+            cache: Mutex::new(CountCache::new(MAX_CACHED_COUNTS)),
+        }
+    }
+}
+
+impl Default for TokenCounts {
+    fn default() -> Self {
+        Self::new(BpeTokenizer::bundled())
+    }
+}
+
+impl fmt::Debug for TokenCounts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let cached = self.cache.lock().map_or(0, |cache| cache.len());
+        f.debug_struct("TokenCounts")
+            .field("counter", &self.counter.name())
+            .field("cached", &cached)
+            .finish()
+    }
+}
+
+/// Counts by content hash in two generations: lookups promote entries to
+/// the newer one, and filling it retires the older, so recently used
+/// counts survive and at most `capacity` are kept.
+struct CountCache {
+    capacity: usize,
+    current: HashMap<u64, usize>,
+    previous: HashMap<u64, usize>,
+}
+
+impl CountCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            current: HashMap::new(),
+            previous: HashMap::new(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.current.len() + self.previous.len()
+    }
+
+    fn get(&mut self, key: u64) -> Option<usize> {
+        if let Some(&count) = self.current.get(&key) {
+            return Some(count);
+        }
+        let count = self.previous.remove(&key)?;
+        self.insert(key, count);
+        Some(count)
+    }
+
+    fn insert(&mut self, key: u64, count: usize) {
+        if self.current.len() >= self.capacity / 2 {
+            self.previous = std::mem::take(&mut self.current);
+        }
+        self.current.insert(key, count);
+    }
+}
+
+/// Counts node tokens for one request, reading each file at most once.
+pub struct TokenMeter<'g> {
+    graph: &'g ArborGraph,
+    files: HashMap<String, Option<Arc<str>>>,
+}
+
+impl TokenMeter<'_> {
+    /// Tokens in a node's source, or a character estimate from its line
+    /// span when the source can't be read.
+    pub fn node(&mut self, id: NodeId) -> usize {
+        let Some(node) = self.graph.get(id) else {
+            return 0;
+        };
+        let graph = self.graph;
+        let text = self
+            .files
+            .entry(node.file.clone())
+            .or_insert_with(|| graph.file_text(&node.file))
+            .clone();
+        let source = text.as_deref().and_then(|text| {
+            (node.byte_end > node.byte_start)
+                .then(|| text.get(node.byte_start as usize..node.byte_end as usize))
+                .flatten()
+        });
+        match source {
+            Some(source) => graph.count_tokens(source),
+            None => {
+                let lines = (node.line_end.saturating_sub(node.line_start) + 1) as usize;
+                CharHeuristic::default().count_chars(lines * FALLBACK_LINE_LEN)
+            }
+        }
+    }
+}
+
+impl ArborGraph {
+    /// Counts tokens with `counter` from now on.
+    pub fn set_token_counter(&mut self, counter: Arc<dyn TokenCounter>) {
+        self.tokens = TokenCounts::new(counter);
+    }
+
+    pub fn token_counter(&self) -> &dyn TokenCounter {
+        self.tokens.counter.as_ref()
+    }
+
+    /// Counts the tokens in `text`, reusing the count for text seen before.
+    pub fn count_tokens(&self, text: &str) -> usize {
+        let mut hasher = DefaultHasher::new();
+        text.hash(&mut hasher);
+        let key = hasher.finish();
+        if let Some(count) = self.tokens.cache.lock().unwrap().get(key) {
+            return count;
+        }
+        let count = self.tokens.counter.count(text);
+        self.tokens.cache.lock().unwrap().insert(key, count);
+        count
+    }
+
+    /// A meter for counting many nodes' tokens.
+    pub fn token_meter(&self) -> TokenMeter<'_> {
+        TokenMeter {
+            graph: self,
+            files: HashMap::new(),
+        }
+    }
+
+    /// Tokens in one node's source.
+    pub fn node_tokens(&self, id: NodeId) -> usize {
+        self.token_meter().node(id)
+    }
+
+    /// A file's text, from the body index or from disk, decoded like the
+    /// indexer decoded it.
+    fn file_text(&self, file: &str) -> Option<Arc<str>> {
+        if let Some(text) = self.bodies.text(file) {
+            return Some(Arc::from(text));
+        }
+        let bytes = fs::read(self.absolute_path(file)).ok()?;
+        let decoded = decode_source(&bytes, self.encoding_for(file))?;
+        Some(Arc::from(decoded.text))
+    }
+}
+
+/// Standard base64 with padding, as tiktoken files use.
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    fn value(c: u8) -> Option<u32> {
+        match c {
+            b'A'..=b'Z' => Some((c - b'A') as u32),
+            b'a'..=b'z' => Some((c - b'a') as u32 + 26),
+            b'0'..=b'9' => Some((c - b'0') as u32 + 52),
+            b'+' => Some(62),
+            b'/' => Some(63),
+            _ => None,
+        }
+    }
+    let bytes = text.trim_end_matches('=').as_bytes();
+    if bytes.len() % 4 == 1 {
+        return None;
+    }
+    let mut out = Vec::with_capacity(bytes.len() * 3 / 4);
+    for chunk in bytes.chunks(4) {
+        let mut acc = 0u32;
+        for (i, &c) in chunk.iter().enumerate() {
+            acc |= value(c)? << (18 - 6 * i);
+        }
+        out.extend(&acc.to_be_bytes()[1..chunk.len()]);
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arbor_core::{CodeNode, NodeKind};
+
+    #[test]
+    fn test_pieces_follow_cl100k() {
+        let bpe = BpeTokenizer::bundled();
+        assert_eq!(
+            bpe.pieces("fn main() {\n    let x = 1234;  y\n}"),
+            vec![
+                "fn", " main", "()", " {\n", "   ", " let", " x", " =", " ", "123", "4", ";", " ",
+                " y", "\n", "}"
+            ]
+        );
+        assert_eq!(bpe.pieces("it's  \n"), vec!["it", "'s", "  \n"]);
+        assert_eq!(decode_base64("aGk="), Some(b"hi".to_vec()));
+    }
+
+    #[test]
+    fn test_bpe_counts_and_errors() {
+        let bpe = BpeTokenizer::bundled();
+        assert!(bpe.vocab_size() > 10_000);
+        let code = "pub fn parse_config(path: &Path) -> Result<Config, Error> {\n    let text = fs::read_to_string(path)?;\n    serde_json::from_str(&text)\n}\n";
+        let count = bpe.count(code);
+        assert_eq!(count, bpe.encode(code).len());
+        // Code vocabularies land well under one token per 3 characters
+        assert!(count < code.len() / 3, "{} tokens", count);
+        assert!(count > code.len() / 8, "{} tokens", count);
+        assert_eq!(bpe.count(""), 0);
+
+        assert!(matches!(
+            BpeTokenizer::from_tiktoken("QQ== 0\n"),
+            Err(TokenizerError::MissingByte(0))
+        ));
+        assert!(matches!(
+            BpeTokenizer::from_tiktoken("not-base64 x\n"),
+            Err(TokenizerError::Parse { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_node_tokens_use_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = "def helper():\n    return compute_the_answer(42)\n\ndef other():\n    pass\n";
+        fs::write(dir.path().join("lib.py"), source).unwrap();
+
+        let mut graph = ArborGraph::new();
+        graph.set_root(dir.path().to_path_buf());
+        let helper = graph.add_node(
+            CodeNode::new("helper", "helper", NodeKind::Function, "lib.py")
+                .with_lines(1, 2)
+                .with_bytes(0, 48),
+        );
+        let missing = graph.add_node(
+            CodeNode::new("gone", "gone", NodeKind::Function, "gone.py").with_lines(1, 10),
+        );
+        assert_eq!(graph.node_tokens(helper), graph.count_tokens(&source[..48]));
+        assert_eq!(graph.node_tokens(missing), 100);
+
+        // UTF-16 is decoded as the indexer would, so offsets line up
+        let wide = "def wide():\n    return 1\n";
+        let mut bytes = vec![0xFF, 0xFE];
+        bytes.extend(wide.encode_utf16().flat_map(u16::to_le_bytes));
+        fs::write(dir.path().join("wide.py"), bytes).unwrap();
+        let node = graph.add_node(
+            CodeNode::new("wide", "wide", NodeKind::Function, "wide.py")
+                .with_lines(1, 2)
+                .with_bytes(0, wide.len() as u32),
+        );
+        assert_eq!(graph.node_tokens(node), graph.count_tokens(wide));
+
+        graph.set_token_counter(Arc::new(CharHeuristic::default()));
+        assert_eq!(graph.token_counter().name(), "chars");
+        assert_eq!(graph.node_tokens(helper), 12);
+    }
+
+    #[test]
+    fn test_count_cache_is_bounded() {
+        let mut cache = CountCache::new(4);
+        for key in 0..3 {
+            cache.insert(key, key as usize);
+        }
+        // 0 and 1 were retired to the older generation; reading 0 keeps it
+        assert_eq!(cache.get(0), Some(0));
+        for key in 3..5 {
+            cache.insert(key, key as usize);
+        }
+        assert!(cache.len() <= 4);
+        assert_eq!(cache.get(1), None);
+        assert_eq!(cache.get(4), Some(4));
+    }
+}
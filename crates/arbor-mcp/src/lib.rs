@@ -3,6 +3,7 @@ use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::io::{self, BufRead, Write};
 
+use arbor_core::QueryDefaults;
 use arbor_server::{SharedGraph, SyncServerHandle};
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -33,6 +34,7 @@ struct JsonRpcError {
 pub struct McpServer {
     graph: SharedGraph,
     spotlight_handle: Option<SyncServerHandle>,
+    defaults: QueryDefaults,
 }
 
 impl McpServer {
@@ -40,6 +42,7 @@ impl McpServer {
         Self {
             graph,
             spotlight_handle: None,
+            defaults: QueryDefaults::default(),
         }
     }
 
@@ -48,24 +51,28 @@ impl McpServer {
         Self {
             graph,
             spotlight_handle: Some(handle),
+            defaults: QueryDefaults::default(),
         }
     }
 
+    /// Uses the project's query defaults when a tool call omits them.
+    pub fn with_defaults(mut self, defaults: QueryDefaults) -> Self {
+        self.defaults = defaults;
+        self
+    }
+
     /// Triggers a spotlight on the visualizer for the given node.
-    async fn trigger_spotlight(&self, node_name: &str) {
+    async fn trigger_spotlight(&self, node_name: &str, root: Option<&str>) {
         if let Some(handle) = &self.spotlight_handle {
             let graph = self.graph.read().await;
 
             // Find the node by name or ID
-            let node = if let Some(idx) = graph.get_index(node_name) {
-                graph.get(idx)
-            } else {
-                let candidates = graph.find_by_name(node_name);
-                candidates.into_iter().next()
-            };
+            let node = graph
+                .find_node(node_name, root)
+                .and_then(|idx| graph.get(idx));
 
             if let Some(node) = node {
-                handle.spotlight_node(&node.id, &node.file, node.line_start as u32);
+                handle.spotlight_node(&node.id, &node.file, node.line_start);
                 eprintln!("🔦 Spotlight: {} in {}", node.name, node.file);
             }
         }
@@ -132,9 +139,7 @@ impl McpServer {
             }),
         };
 
-        if id.is_none() {
-            return None;
-        }
+        id.as_ref()?;
 
         Some(match result {
             Ok(val) => JsonRpcResponse {
@@ -161,7 +166,8 @@ impl McpServer {
                     "inputSchema": {
                         "type": "object",
                         "properties": {
-                            "start_node": { "type": "string", "description": "Name of the function or class to trace" }
+                            "start_node": { "type": "string", "description": "Name of the function or class to trace" },
+                            "root": { "type": "string", "description": "Workspace root (namespace) to look names up in" }
                         },
                         "required": ["start_node"]
                     }
@@ -173,11 +179,100 @@ impl McpServer {
                         "type": "object",
                         "properties": {
                             "node_id": { "type": "string", "description": "ID or name of the node to analyze" },
-                            "max_depth": { "type": "integer", "description": "Maximum hop distance (default: 5, 0 = unlimited)", "default": 5 }
+                            "max_depth": {
+                                "type": "integer",
+                                "description": format!("Maximum hop distance (default: {}, 0 = unlimited)", self.defaults.impact_depth),
+                                "default": self.defaults.impact_depth
+                            },
+                            "root": { "type": "string", "description": "Workspace root (namespace) to look names up in" }
                         },
                         "required": ["node_id"]
                     }
                 },
+                {
+                    "name": "analyze_diff_impact",
+                    "description": "Analyzes what a patch touches. Maps the changed lines of a unified diff onto symbols and returns, per symbol, its upstream callers and the tests that reach it.",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {
+                            "diff": { "type": "string", "description": "Unified diff, as printed by `git diff`" },
+                            "max_depth": {
+                                "type": "integer",
+                                "description": format!("Maximum hop distance (default: {}, 0 = unlimited)", self.defaults.impact_depth),
+                                "default": self.defaults.impact_depth
+                            },
+                            "format": {
+                                "type": "string",
+                                "enum": ["json", "markdown"],
+                                "description": "Report format (default: json)",
+                                "default": "json"
+                            }
+                        },
+                        "required": ["diff"]
+                    }
+                },
+                {
+                    "name": "search_nodes",
+                    "description": "Searches symbols by name, qualified name, signature and docstring, most relevant first, and filters them by kind, visibility, export, async, file glob, language, attributes or centrality.",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {
+                            "query": { "type": "string", "description": "Words, identifiers in any case style, prefixes or acronyms such as gubi; typos are tolerated. Empty lists every match of the filter by centrality" },
+                            "kinds": { "type": "array", "items": { "type": "string" }, "description": "Node kinds, e.g. function, method, class, struct" },
+                            "visibility": { "type": "array", "items": { "type": "string" }, "description": "public, private, protected or internal" },
+                            "exported": { "type": "boolean" },
+                            "async": { "type": "boolean" },
+                            "files": { "type": "array", "items": { "type": "string" }, "description": "File globs, e.g. src/api/** or *.rs" },
+                            "languages": { "type": "array", "items": { "type": "string" } },
+                            "attributes": { "type": "array", "items": { "type": "string" }, "description": "static, generated, documented or test" },
+                            "min_centrality": { "type": "number" },
+                            "limit": {
+                                "type": "integer",
+                                "description": format!("Maximum results (default: {})", self.defaults.search_limit),
+                                "default": self.defaults.search_limit
+                            },
+                            "root": { "type": "string", "description": "Workspace root (namespace) to search in" }
+                        }
+                    }
+                },
+                {
+                    "name": "query_graph",
+                    "description": "Runs an ArborQL query, e.g. MATCH (f:function {name: 'save'})<-[:calls*1..3]-(c) WHERE NOT c.test RETURN c.name, c.file ORDER BY c.centrality DESC LIMIT 10. Patterns take node kinds, exact properties, edge kinds and hop ranges; WHERE supports comparisons, =~ globs, CONTAINS, IN, AND/OR/NOT.",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {
+                            "query": { "type": "string", "description": "ArborQL query text" },
+                            "explain": { "type": "boolean", "description": "Return the query plan instead of running it", "default": false },
+                            "limit": { "type": "integer", "description": "Maximum rows to return (default: 1000)" }
+                        },
+                        "required": ["query"]
+                    }
+                },
+                {
+                    "name": "find_cycles",
+                    "description": "Finds circular dependencies between functions, types, files or packages (directories). Each cycle lists its members, the shortest loop through them and the file:line of every hop, so you can see which import or call closes it.",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {
+                            "level": { "type": "string", "enum": ["function", "type", "file", "directory", "package"], "default": "file" },
+                            "edge_kinds": { "type": "array", "items": { "type": "string" }, "description": "Edge kinds to follow, e.g. imports or calls (default: all dependency kinds)" },
+                            "include_tests": { "type": "boolean", "default": false },
+                            "limit": { "type": "integer", "description": "Maximum cycles to return, largest first" }
+                        }
+                    }
+                },
+                {
+                    "name": "get_architecture",
+                    "description": "Summarizes the codebase as clusters of files that mostly talk to each other: each cluster's directory, keywords, key symbols and which clusters it depends on. Use it to get oriented in an unfamiliar codebase, then pass a cluster ID for just that part.",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {
+                            "cluster": { "type": "string", "description": "Cluster ID to summarize, e.g. src-auth (default: all)" },
+                            "weights": { "type": "object", "description": "Weight per edge kind, e.g. {\"imports\": 2}", "additionalProperties": { "type": "number" } },
+                            "resolution": { "type": "number", "description": "Higher values give more, smaller clusters", "default": 1.0 }
+                        }
+                    }
+                },
                 {
                     "name": "find_path",
                     "description": "Finds the shortest path between two nodes.",
@@ -185,7 +280,8 @@ impl McpServer {
                         "type": "object",
                         "properties": {
                             "start_node": { "type": "string", "description": "Name or ID of the start node" },
-                            "end_node": { "type": "string", "description": "Name or ID of the end node" }
+                            "end_node": { "type": "string", "description": "Name or ID of the end node" },
+                            "root": { "type": "string", "description": "Workspace root (namespace) to look names up in" }
                         },
                         "required": ["start_node", "end_node"]
                     }
@@ -205,6 +301,7 @@ impl McpServer {
             })?;
 
         let arguments = params.get("arguments").unwrap_or(&Value::Null);
+        let root = arguments.get("root").and_then(|v| v.as_str());
 
         match name {
             "get_logic_path" => {
@@ -214,9 +311,9 @@ impl McpServer {
                     .unwrap_or("");
 
                 // Trigger Spotlight so the Visualizer shows what the AI is looking at
-                self.trigger_spotlight(start_node).await;
+                self.trigger_spotlight(start_node, root).await;
 
-                let context = self.generate_context(start_node).await;
+                let context = self.generate_context(start_node, root).await;
                 Ok(json!({
                     "content": [
                         {
@@ -235,20 +332,16 @@ impl McpServer {
                 let max_depth = arguments
                     .get("max_depth")
                     .and_then(|v| v.as_u64())
-                    .unwrap_or(5) as usize;
+                    .map(|d| d as usize)
+                    .unwrap_or(self.defaults.impact_depth);
 
                 // Trigger Spotlight
-                self.trigger_spotlight(node_id).await;
+                self.trigger_spotlight(node_id, root).await;
 
                 let graph = self.graph.read().await;
 
                 // Resolve node by name or ID
-                let node_index = graph.get_index(node_id).or_else(|| {
-                    graph
-                        .find_by_name(node_id)
-                        .first()
-                        .and_then(|n| graph.get_index(&n.id))
-                });
+                let node_index = graph.find_node(node_id, root);
 
                 match node_index {
                     Some(idx) => {
@@ -314,6 +407,170 @@ impl McpServer {
                     })),
                 }
             }
+            "analyze_diff_impact" => {
+                let diff = arguments.get("diff").and_then(|v| v.as_str()).unwrap_or("");
+                let max_depth = arguments
+                    .get("max_depth")
+                    .and_then(|v| v.as_u64())
+                    .map(|d| d as usize)
+                    .unwrap_or(self.defaults.impact_depth);
+                let markdown = arguments.get("format").and_then(|v| v.as_str()) == Some("markdown");
+
+                let patches = arbor_graph::parse_unified_diff(diff);
+                if patches.is_empty() {
+                    return Err(JsonRpcError {
+                        code: -32602,
+                        message: "No file changes found in 'diff'".to_string(),
+                        data: None,
+                    });
+                }
+
+                let graph = self.graph.read().await;
+                let report = graph.analyze_change_impact(&patches, max_depth);
+                let text = if markdown {
+                    report.to_markdown()
+                } else {
+                    serde_json::to_string_pretty(&report).unwrap_or_default()
+                };
+                Ok(json!({
+                    "content": [{ "type": "text", "text": text }]
+                }))
+            }
+            "search_nodes" => {
+                let query = arguments
+                    .get("query")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("");
+                let limit = arguments
+                    .get("limit")
+                    .and_then(|v| v.as_u64())
+                    .map(|l| l as usize)
+                    .unwrap_or(self.defaults.search_limit);
+                let invalid = |message: String| JsonRpcError {
+                    code: -32602,
+                    message,
+                    data: None,
+                };
+                let filter: arbor_graph::NodeFilter = if arguments.is_null() {
+                    Default::default()
+                } else {
+                    serde_json::from_value(arguments.clone()).map_err(|e| invalid(e.to_string()))?
+                };
+                let matcher = filter.compile().map_err(|e| invalid(e.to_string()))?;
+
+                let graph = self.graph.read().await;
+                // Searches come back most relevant first; plain filters by centrality
+                let hits: Vec<(_, Option<f64>)> = if query.is_empty() {
+                    let mut indexes = graph
+                        .filter_nodes(&filter)
+                        .map_err(|e| invalid(e.to_string()))?;
+                    indexes.sort_by(|a, b| graph.centrality(*b).total_cmp(&graph.centrality(*a)));
+                    indexes.into_iter().map(|i| (i, None)).collect()
+                } else {
+                    graph
+                        .search_ranked_filtered(query, &matcher)
+                        .into_iter()
+                        .map(|hit| (hit.id, Some(hit.score)))
+                        .collect()
+                };
+                let total = hits.len();
+                let nodes: Vec<arbor_graph::NodeInfo> = hits
+                    .into_iter()
+                    .take(limit)
+                    .filter_map(|(i, score)| {
+                        let mut info = arbor_graph::NodeInfo::from(graph.get(i)?);
+                        info.centrality = graph.centrality(i);
+                        info.score = score;
+                        Some(info)
+                    })
+                    .collect();
+                Ok(json!({
+                    "content": [{
+                        "type": "text",
+                        "text": serde_json::to_string_pretty(&json!({
+                            "nodes": nodes,
+                            "total": total
+                        })).unwrap_or_default()
+                    }]
+                }))
+            }
+            "query_graph" => {
+                let query = arguments
+                    .get("query")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("");
+                let explain = arguments
+                    .get("explain")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false);
+                let mut limits = arbor_graph::QueryLimits::default();
+                if let Some(limit) = arguments.get("limit").and_then(|v| v.as_u64()) {
+                    limits.max_results = limits.max_results.min(limit as usize);
+                }
+
+                let graph = self.graph.read().await;
+                let text = if explain {
+                    graph.explain_query(query).map(|plan| plan.to_string())
+                } else {
+                    graph
+                        .run_query(query, &limits)
+                        .map(|result| serde_json::to_string_pretty(&result).unwrap_or_default())
+                };
+                let text = text.map_err(|e| JsonRpcError {
+                    code: -32602,
+                    message: e.to_string(),
+                    data: None,
+                })?;
+                Ok(json!({
+                    "content": [{ "type": "text", "text": text }]
+                }))
+            }
+            "find_cycles" => {
+                let options: arbor_graph::CycleOptions = if arguments.is_null() {
+                    Default::default()
+                } else {
+                    serde_json::from_value(arguments.clone()).map_err(|e| JsonRpcError {
+                        code: -32602,
+                        message: e.to_string(),
+                        data: None,
+                    })?
+                };
+                let graph = self.graph.read().await;
+                let mut report = graph.find_cycles(&options);
+                if let Some(limit) = arguments.get("limit").and_then(|v| v.as_u64()) {
+                    report.cycles.truncate(limit as usize);
+                }
+                Ok(json!({
+                    "content": [{
+                        "type": "text",
+                        "text": serde_json::to_string_pretty(&report).unwrap_or_default()
+                    }]
+                }))
+            }
+            "get_architecture" => {
+                let options: arbor_graph::ClusterOptions = if arguments.is_null() {
+                    Default::default()
+                } else {
+                    serde_json::from_value(arguments.clone()).map_err(|e| JsonRpcError {
+                        code: -32602,
+                        message: e.to_string(),
+                        data: None,
+                    })?
+                };
+                let graph = self.graph.read().await;
+                let report = graph.compute_clusters(&options);
+                let text = match arguments.get("cluster").and_then(|v| v.as_str()) {
+                    Some(cluster) => report.summary(cluster).ok_or_else(|| JsonRpcError {
+                        code: -32602,
+                        message: format!("unknown cluster '{}'", cluster),
+                        data: None,
+                    })?,
+                    None => report.to_markdown(),
+                };
+                Ok(json!({
+                    "content": [{ "type": "text", "text": text }]
+                }))
+            }
             "find_path" => {
                 let start_node = arguments
                     .get("start_node")
@@ -326,18 +583,8 @@ impl McpServer {
 
                 let graph = self.graph.read().await;
 
-                let start_idx = graph.get_index(start_node).or_else(|| {
-                    graph
-                        .find_by_name(start_node)
-                        .first()
-                        .and_then(|n| graph.get_index(&n.id))
-                });
-                let end_idx = graph.get_index(end_node).or_else(|| {
-                    graph
-                        .find_by_name(end_node)
-                        .first()
-                        .and_then(|n| graph.get_index(&n.id))
-                });
+                let start_idx = graph.find_node(start_node, root);
+                let end_idx = graph.find_node(end_node, root);
 
                 match (start_idx, end_idx) {
                     (Some(u), Some(v)) => {
@@ -371,23 +618,11 @@ impl McpServer {
         }
     }
 
-    async fn generate_context(&self, node_start: &str) -> String {
+    async fn generate_context(&self, node_start: &str, root: Option<&str>) -> String {
         let graph = self.graph.read().await;
 
-        // 1. Resolve Node
-        let node_idx = if let Some(idx) = graph.get_index(node_start) {
-            Some(idx)
-        } else {
-            // Try by name
-            let candidates = graph.find_by_name(node_start);
-            if let Some(first) = candidates.first() {
-                graph.get_index(&first.id)
-            } else {
-                None
-            }
-        };
-
-        let node_idx = match node_idx {
+        // 1. Resolve Node, by ID or by name
+        let node_idx = match graph.find_node(node_start, root) {
             Some(idx) => idx,
             None => {
                 return format!(
@@ -407,8 +642,8 @@ impl McpServer {
         let mut brief = String::new();
 
         brief.push_str(&format!("# Architectural Brief: `{}`\n\n", node.name));
-        brief.push_str(&format!("| Property | Value |\n"));
-        brief.push_str(&format!("|----------|-------|\n"));
+        brief.push_str("| Property | Value |\n");
+        brief.push_str("|----------|-------|\n");
         brief.push_str(&format!("| **Type** | {} |\n", node.kind));
         brief.push_str(&format!("| **File** | `{}` |\n", node.file));
         brief.push_str(&format!("| **Impact Level** | {:.2} |\n", centrality));
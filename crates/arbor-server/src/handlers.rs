@@ -3,11 +3,17 @@
 //! Each handler implements one method from the Arbor Protocol.
 
 use crate::protocol::{
-    ContextParams, DiscoverParams, ImpactParams, NodeGetParams, Response, SearchParams,
+    ContextParams, DiscoverParams, GraphClustersParams, GraphCollapseParams, GraphCyclesParams,
+    GraphGrepParams, GraphMetricsParams, GraphQueryParams, ImpactParams, NodeGetParams, Response,
+    SearchParams,
+};
+use arbor_graph::{
+    compute_centrality_with, ArborGraph, BodyQueryError, CentralityMeasure, CentralityOptions,
+    NodeId, NodeInfo, QlError, QueryLimits, TaskOptions,
 };
-use arbor_graph::{ArborGraph, NodeInfo};
 use serde::Serialize;
 use serde_json::Value;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use std::time::Instant;
 use tokio::sync::RwLock;
@@ -28,6 +34,10 @@ pub async fn handle_info(graph: SharedGraph, id: Option<Value>) -> Response {
         edge_count: usize,
         languages: Vec<&'static str>,
         version: &'static str,
+        /// Absolute project root; node file paths are relative to it.
+        root: Option<String>,
+        /// Workspace roots; node paths start with their namespace.
+        roots: Vec<RootInfo>,
     }
 
     Response::success(
@@ -37,10 +47,32 @@ pub async fn handle_info(graph: SharedGraph, id: Option<Value>) -> Response {
             edge_count: g.edge_count(),
             languages: vec!["typescript", "rust", "python"],
             version: env!("CARGO_PKG_VERSION"),
+            root: g.root().map(|root| root.to_string_lossy().into_owned()),
+            roots: root_infos(&g),
         },
     )
 }
 
+/// A workspace root as reported to clients.
+#[derive(Debug, Clone, Serialize)]
+pub struct RootInfo {
+    pub namespace: String,
+    /// Absolute path of the root.
+    pub path: String,
+}
+
+/// Lists the graph's workspace roots.
+pub fn root_infos(graph: &ArborGraph) -> Vec<RootInfo> {
+    graph
+        .roots()
+        .iter()
+        .map(|root| RootInfo {
+            namespace: root.namespace.clone(),
+            path: root.path.to_string_lossy().into_owned(),
+        })
+        .collect()
+}
+
 /// Handles the discover method.
 pub async fn handle_discover(
     graph: SharedGraph,
@@ -52,24 +84,33 @@ pub async fn handle_discover(
 
     debug!("Discover query: {}", params.query);
 
-    // Search for nodes matching the query
-    let mut matches: Vec<_> = g
-        .search(&params.query)
-        .into_iter()
-        .map(|node| {
-            let centrality = g.centrality(g.get_index(&node.id).unwrap_or_default());
-            let mut info = NodeInfo::from(node);
-            info.centrality = centrality;
-            info
+    let mut filter = params.filter;
+    if params.root.is_some() {
+        filter.root = params.root;
+    }
+    let matcher = match filter.compile() {
+        Ok(matcher) => matcher,
+        Err(e) => return Response::invalid_params(id, e.to_string()),
+    };
+
+    // Search for nodes matching the query, most relevant first
+    let hits = g.search_ranked_filtered(&params.query, &matcher);
+    let found: Vec<NodeId> = hits.iter().map(|hit| hit.id).collect();
+    let centrality = ranking(&g, params.centrality.as_ref(), &found);
+    let mut matches: Vec<_> = hits
+        .iter()
+        .filter_map(|hit| {
+            let mut info = NodeInfo::from(g.get(hit.id)?);
+            info.centrality = centrality(hit.id);
+            info.score = Some(hit.score);
+            Some(info)
         })
         .collect();
 
-    // Sort by centrality (most important first)
-    matches.sort_by(|a, b| {
-        b.centrality
-            .partial_cmp(&a.centrality)
-            .unwrap_or(std::cmp::Ordering::Equal)
-    });
+    // A requested measure overrides relevance
+    if params.centrality.is_some() {
+        matches.sort_by(|a, b| b.centrality.total_cmp(&a.centrality));
+    }
 
     // Limit results
     matches.truncate(params.limit);
@@ -90,6 +131,22 @@ pub async fn handle_discover(
     )
 }
 
+/// Scores nodes by the requested measure, seeded with `seeds`, or by
+/// the stored PageRank when no measure was requested.
+fn ranking<'a>(
+    graph: &'a ArborGraph,
+    options: Option<&CentralityOptions>,
+    seeds: &[NodeId],
+) -> Box<dyn Fn(NodeId) -> f64 + 'a> {
+    match options {
+        Some(options) => {
+            let scores = compute_centrality_with(graph, options, seeds);
+            Box::new(move |idx| scores.get(idx))
+        }
+        None => Box::new(|idx| graph.centrality(idx)),
+    }
+}
+
 /// Handles the impact method.
 pub async fn handle_impact(
     graph: SharedGraph,
@@ -102,7 +159,7 @@ pub async fn handle_impact(
     debug!("Impact analysis for: {}", params.node);
 
     // Find the target node
-    let target_idx = match g.get_index(&params.node) {
+    let target_idx = match g.find_node(&params.node, params.root.as_deref()) {
         Some(idx) => idx,
         None => {
             return Response::error(id, -32001, format!("Node not found: {}", params.node));
@@ -149,36 +206,73 @@ pub async fn handle_context(
 
     debug!("Context request for task: {}", params.task);
 
-    // Search for relevant nodes
-    let mut matches: Vec<_> = g
-        .search(&params.task)
-        .into_iter()
-        .map(|node| {
-            let centrality = g.centrality(g.get_index(&node.id).unwrap_or_default());
-            let mut info = NodeInfo::from(node);
-            info.centrality = centrality;
-            info
+    // Pick seeds from the task's words, then walk out from all of them at once
+    let in_root = |idx: NodeId| {
+        g.get(idx)
+            .is_some_and(|node| node.in_root(params.root.as_deref()))
+    };
+    let options = TaskOptions {
+        max_seeds: params.max_seeds,
+        root: params.root.clone(),
+    };
+    let interpretation = g.interpret_task(&params.task, &options);
+    let relevance: HashMap<NodeId, f64> = interpretation
+        .seeds
+        .iter()
+        .filter_map(|seed| Some((g.get_index(&seed.node.id)?, seed.node.score?)))
+        .collect();
+    let seeds: Vec<NodeId> = interpretation.seed_ids(&g).collect();
+    let mut candidates = seeds.clone();
+    if params.depth > 0 {
+        // Tokens are counted once, against the budget, below
+        candidates.extend(
+            g.neighborhood(&seeds, params.depth)
+                .into_iter()
+                .filter(|&(idx, depth)| depth > 0 && in_root(idx))
+                .map(|(idx, _)| idx),
+        );
+    }
+    let centrality = ranking(&g, params.centrality.as_ref(), &seeds);
+
+    // Personalized PageRank also pulls in the neighbourhood of the matches
+    let personalized = params
+        .centrality
+        .as_ref()
+        .is_some_and(|c| c.measure == CentralityMeasure::PersonalizedPageRank);
+    if personalized && !candidates.is_empty() {
+        let seen: HashSet<NodeId> = candidates.iter().copied().collect();
+        candidates.extend(
+            g.node_indexes()
+                .filter(|idx| !seen.contains(idx) && centrality(*idx) > 0.0 && in_root(*idx)),
+        );
+    }
+
+    let mut matches: Vec<_> = candidates
+        .iter()
+        .filter_map(|&idx| {
+            let mut info = NodeInfo::from(g.get(idx)?);
+            info.centrality = centrality(idx);
+            info.score = relevance.get(&idx).copied();
+            Some((idx, info))
         })
         .collect();
 
-    // Sort by centrality
-    matches.sort_by(|a, b| {
-        b.centrality
-            .partial_cmp(&a.centrality)
-            .unwrap_or(std::cmp::Ordering::Equal)
-    });
+    // Most relevant first, unless a measure was requested
+    if params.centrality.is_some() {
+        matches.sort_by(|(_, a), (_, b)| b.centrality.total_cmp(&a.centrality));
+    }
 
-    // Estimate tokens and truncate
-    // (Rough estimate: 4 characters per token)
+    // Count each node's source and stop at the budget
+    let mut meter = g.token_meter();
     let mut total_tokens = 0usize;
     let mut selected = Vec::new();
 
-    for node in matches {
-        let estimated_tokens = (node.line_end - node.line_start) as usize * 40 / 4;
-        if total_tokens + estimated_tokens > params.max_tokens {
+    for (idx, node) in matches {
+        let tokens = meter.node(idx);
+        if total_tokens + tokens > params.max_tokens {
             break;
         }
-        total_tokens += estimated_tokens;
+        total_tokens += tokens;
         selected.push(node);
     }
 
@@ -186,7 +280,9 @@ pub async fn handle_context(
         id,
         serde_json::json!({
             "nodes": selected,
+            "interpretation": interpretation,
             "totalTokens": total_tokens,
+            "tokenizer": g.token_counter().name(),
             "queryTime": start.elapsed().as_millis()
         }),
     )
@@ -203,18 +299,25 @@ pub async fn handle_search(
 
     debug!("Search: {}", params.query);
 
+    let mut filter = params.filter;
+    filter.kinds.extend(params.kind);
+    if params.root.is_some() {
+        filter.root = params.root;
+    }
+    let matcher = match filter.compile() {
+        Ok(matcher) => matcher,
+        Err(e) => return Response::invalid_params(id, e.to_string()),
+    };
+
     let mut matches: Vec<_> = g
-        .search(&params.query)
+        .search_ranked_filtered(&params.query, &matcher)
         .into_iter()
-        .filter(|node| {
-            // Filter by kind if specified
-            if let Some(ref kind) = params.kind {
-                node.kind.to_string() == *kind
-            } else {
-                true
-            }
+        .filter_map(|hit| {
+            let mut info = NodeInfo::from(g.get(hit.id)?);
+            info.centrality = g.centrality(hit.id);
+            info.score = Some(hit.score);
+            Some(info)
         })
-        .map(NodeInfo::from)
         .collect();
 
     let total = matches.len();
@@ -230,6 +333,194 @@ pub async fn handle_search(
     )
 }
 
+/// Handles the graph.query method.
+pub async fn handle_graph_query(
+    graph: SharedGraph,
+    id: Option<Value>,
+    params: GraphQueryParams,
+) -> Response {
+    let g = graph.read().await;
+
+    debug!("ArborQL: {}", params.query);
+
+    if params.explain {
+        return match g.explain_query(&params.query) {
+            Ok(plan) => Response::success(id, serde_json::json!({ "plan": plan.to_string() })),
+            Err(e) => Response::invalid_params(id, e.to_string()),
+        };
+    }
+
+    let mut limits = QueryLimits::default();
+    if let Some(limit) = params.limit {
+        limits.max_results = limits.max_results.min(limit);
+    }
+    match g.run_query(&params.query, &limits) {
+        Ok(result) => Response::success(
+            id,
+            serde_json::json!({
+                "columns": result.columns,
+                "rows": result.rows,
+                "truncated": result.truncated,
+                "queryTime": result.query_time_ms
+            }),
+        ),
+        Err(e @ (QlError::TooManyRows(_) | QlError::Timeout(_))) => {
+            Response::error(id, -32002, e.to_string())
+        }
+        Err(e) => Response::invalid_params(id, e.to_string()),
+    }
+}
+
+/// Handles the graph.grep method.
+pub async fn handle_graph_grep(
+    graph: SharedGraph,
+    id: Option<Value>,
+    params: GraphGrepParams,
+) -> Response {
+    let start = Instant::now();
+    let g = graph.read().await;
+
+    match g.search_bodies(&params.query) {
+        Ok(result) => Response::success(
+            id,
+            serde_json::json!({
+                "pattern": result.pattern,
+                "matches": result.matches,
+                "total_hits": result.total_hits,
+                "files_searched": result.files_searched,
+                "truncated": result.truncated,
+                "queryTime": start.elapsed().as_millis() as u64
+            }),
+        ),
+        Err(e @ BodyQueryError::NotIndexed) => Response::error(id, -32003, e.to_string()),
+        Err(e) => Response::invalid_params(id, e.to_string()),
+    }
+}
+
+/// Handles the graph.cycles method.
+pub async fn handle_graph_cycles(
+    graph: SharedGraph,
+    id: Option<Value>,
+    params: GraphCyclesParams,
+) -> Response {
+    let start = Instant::now();
+    let g = graph.read().await;
+
+    let mut report = g.find_cycles(&params.options);
+    let total = report.cycles.len();
+    if let Some(limit) = params.limit {
+        report.cycles.truncate(limit);
+    }
+
+    Response::success(
+        id,
+        serde_json::json!({
+            "level": report.level,
+            "edge_kinds": report.edge_kinds,
+            "units": report.units,
+            "dependencies": report.dependencies,
+            "total": total,
+            "cycles": report.cycles,
+            "queryTime": start.elapsed().as_millis() as u64
+        }),
+    )
+}
+
+/// Handles the graph.clusters method.
+pub async fn handle_graph_clusters(
+    graph: SharedGraph,
+    id: Option<Value>,
+    params: GraphClustersParams,
+) -> Response {
+    let start = Instant::now();
+    let g = graph.read().await;
+
+    let mut report = g.compute_clusters(&params.options);
+    let mut summary = params.summary.then(|| report.to_markdown());
+    if let Some(cluster) = &params.cluster {
+        let Some(markdown) = report.summary(cluster) else {
+            return Response::invalid_params(id, format!("unknown cluster '{}'", cluster));
+        };
+        summary = Some(markdown);
+        report.clusters.retain(|c| c.id == *cluster);
+        report
+            .edges
+            .retain(|e| e.from == *cluster || e.to == *cluster);
+    }
+
+    Response::success(
+        id,
+        serde_json::json!({
+            "clusters": report.clusters,
+            "edges": report.edges,
+            "modularity": report.modularity,
+            "summary": summary,
+            "queryTime": start.elapsed().as_millis() as u64
+        }),
+    )
+}
+
+/// Handles the graph.metrics method.
+pub async fn handle_graph_metrics(
+    graph: SharedGraph,
+    id: Option<Value>,
+    params: GraphMetricsParams,
+) -> Response {
+    let start = Instant::now();
+    let g = graph.read().await;
+
+    let report = g.compute_metrics(&params.options);
+
+    Response::success(
+        id,
+        serde_json::json!({
+            "level": report.level,
+            "units": report.units,
+            "meanDistance": report.mean_distance(),
+            "classes": params.classes.then_some(&report.classes),
+            "queryTime": start.elapsed().as_millis() as u64
+        }),
+    )
+}
+
+/// Handles the graph.collapse method.
+pub async fn handle_graph_collapse(
+    graph: SharedGraph,
+    id: Option<Value>,
+    params: GraphCollapseParams,
+) -> Response {
+    let start = Instant::now();
+    let g = graph.read().await;
+
+    let quotient = g.collapse(&params.options);
+    let Some(name) = &params.unit else {
+        return Response::success(
+            id,
+            serde_json::json!({
+                "level": quotient.level,
+                "nodes": quotient.nodes(),
+                "edges": quotient.edges(),
+                "queryTime": start.elapsed().as_millis() as u64
+            }),
+        );
+    };
+    let Some(unit) = quotient.find_unit(name) else {
+        return Response::invalid_params(id, format!("unknown {} '{}'", quotient.level, name));
+    };
+
+    Response::success(
+        id,
+        serde_json::json!({
+            "level": quotient.level,
+            "unit": quotient.node(unit),
+            "dependencies": quotient.dependencies_of(unit),
+            "dependents": quotient.dependents_of(unit),
+            "impact": quotient.analyze_impact(unit, params.depth),
+            "queryTime": start.elapsed().as_millis() as u64
+        }),
+    )
+}
+
 /// Handles the node.get method.
 pub async fn handle_node_get(
     graph: SharedGraph,
@@ -30,6 +30,7 @@ mod protocol;
 mod server;
 pub mod sync_server;
 
+pub use handlers::RootInfo;
 pub use protocol::{Request, Response, RpcError};
 pub use server::{ArborServer, ServerConfig};
 pub use sync_server::{
@@ -3,6 +3,11 @@
 //! Implements the message format for the Arbor Protocol.
 //! Based on JSON-RPC 2.0 with some custom extensions.
 
+use arbor_core::NodeKind;
+use arbor_graph::{
+    BodyQuery, CentralityOptions, ClusterOptions, CollapseOptions, CycleOptions, MetricsOptions,
+    NodeFilter,
+};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
@@ -113,6 +118,15 @@ pub struct DiscoverParams {
     pub query: String,
     #[serde(default = "default_limit")]
     pub limit: usize,
+    /// Only return nodes from this workspace root.
+    #[serde(default)]
+    pub root: Option<String>,
+    /// Only return nodes matching this filter.
+    #[serde(default)]
+    pub filter: NodeFilter,
+    /// Rank matches by this measure instead of the stored PageRank.
+    #[serde(default)]
+    pub centrality: Option<CentralityOptions>,
 }
 
 /// Params for the impact method.
@@ -121,6 +135,9 @@ pub struct ImpactParams {
     pub node: String,
     #[serde(default = "default_depth")]
     pub depth: usize,
+    /// Workspace root to look `node` up in when it's a name rather than an ID.
+    #[serde(default)]
+    pub root: Option<String>,
 }
 
 /// Params for the context method.
@@ -131,15 +148,102 @@ pub struct ContextParams {
     pub max_tokens: usize,
     #[serde(default, rename = "includeSource")]
     pub _include_source: bool,
+    /// Only return nodes from this workspace root.
+    #[serde(default)]
+    pub root: Option<String>,
+    /// Rank candidates by this measure instead of the stored PageRank.
+    #[serde(default)]
+    pub centrality: Option<CentralityOptions>,
+    /// Most nodes to pick from the task before slicing around them.
+    #[serde(default = "default_max_seeds", rename = "maxSeeds")]
+    pub max_seeds: usize,
+    /// Hops to follow from each seed.
+    #[serde(default = "default_context_depth")]
+    pub depth: usize,
 }
 
 /// Params for the search method.
 #[derive(Debug, Deserialize)]
 pub struct SearchParams {
     pub query: String,
-    pub kind: Option<String>,
+    /// Shorthand for a single kind in `filter.kinds`.
+    pub kind: Option<NodeKind>,
     #[serde(default = "default_limit")]
     pub limit: usize,
+    /// Only return nodes from this workspace root.
+    #[serde(default)]
+    pub root: Option<String>,
+    /// Only return nodes matching this filter.
+    #[serde(default)]
+    pub filter: NodeFilter,
+}
+
+/// Params for the graph.query method.
+#[derive(Debug, Deserialize)]
+pub struct GraphQueryParams {
+    /// ArborQL text.
+    pub query: String,
+    /// Return the plan instead of running the query.
+    #[serde(default)]
+    pub explain: bool,
+    /// Maximum rows to return; capped by the server's limit.
+    pub limit: Option<usize>,
+}
+
+/// Params for the graph.grep method.
+#[derive(Debug, Deserialize)]
+pub struct GraphGrepParams {
+    /// Pattern, regex flag, case sensitivity, scope and node limit.
+    #[serde(flatten)]
+    pub query: BodyQuery,
+}
+
+/// Params for the graph.cycles method. Every field is optional.
+#[derive(Debug, Default, Deserialize)]
+pub struct GraphCyclesParams {
+    /// Level, edge kinds and whether to include tests.
+    #[serde(flatten)]
+    pub options: CycleOptions,
+    /// Maximum cycles to return, largest first.
+    pub limit: Option<usize>,
+}
+
+/// Params for the graph.metrics method. Every field is optional.
+#[derive(Debug, Default, Deserialize)]
+pub struct GraphMetricsParams {
+    /// Level and whether to include tests.
+    #[serde(flatten)]
+    pub options: MetricsOptions,
+    /// Include LCOM4 cohesion for each class.
+    #[serde(default)]
+    pub classes: bool,
+}
+
+/// Params for the graph.collapse method. Every field is optional.
+#[derive(Debug, Default, Deserialize)]
+pub struct GraphCollapseParams {
+    /// Level, edge kinds and whether to include tests.
+    #[serde(flatten)]
+    pub options: CollapseOptions,
+    /// A unit to return the dependencies, dependents and impact of instead
+    /// of the whole graph.
+    pub unit: Option<String>,
+    /// Maximum hops for impact (0 = unlimited).
+    #[serde(default)]
+    pub depth: usize,
+}
+
+/// Params for the graph.clusters method. Every field is optional.
+#[derive(Debug, Default, Deserialize)]
+pub struct GraphClustersParams {
+    /// Edge-kind weights, directory weight and resolution.
+    #[serde(flatten)]
+    pub options: ClusterOptions,
+    /// Only this cluster, with its Markdown summary.
+    pub cluster: Option<String>,
+    /// Include a Markdown summary for LLM context.
+    #[serde(default)]
+    pub summary: bool,
 }
 
 /// Params for node.get method.
@@ -159,3 +263,11 @@ fn default_depth() -> usize {
 fn default_max_tokens() -> usize {
     8000
 }
+
+fn default_max_seeds() -> usize {
+    5
+}
+
+fn default_context_depth() -> usize {
+    1
+}
@@ -3,11 +3,14 @@
 //! Handles client connections and routes messages to handlers.
 
 use crate::handlers::{
-    handle_context, handle_discover, handle_impact, handle_info, handle_node_get, handle_search,
-    SharedGraph,
+    handle_context, handle_discover, handle_graph_clusters, handle_graph_collapse,
+    handle_graph_cycles, handle_graph_grep, handle_graph_metrics, handle_graph_query,
+    handle_impact, handle_info, handle_node_get, handle_search, SharedGraph,
 };
 use crate::protocol::{
-    ContextParams, DiscoverParams, ImpactParams, NodeGetParams, Request, Response, SearchParams,
+    ContextParams, DiscoverParams, GraphClustersParams, GraphCollapseParams, GraphCyclesParams,
+    GraphGrepParams, GraphMetricsParams, GraphQueryParams, ImpactParams, NodeGetParams, Request,
+    Response, SearchParams,
 };
 use arbor_graph::ArborGraph;
 use futures_util::{SinkExt, StreamExt};
@@ -160,6 +163,57 @@ async fn process_message(text: &str, graph: SharedGraph) -> Response {
             Err(e) => Response::invalid_params(id, e.to_string()),
         },
 
+        "graph.query" => match serde_json::from_value::<GraphQueryParams>(request.params) {
+            Ok(params) => handle_graph_query(graph, id, params).await,
+            Err(e) => Response::invalid_params(id, e.to_string()),
+        },
+
+        "graph.grep" => match serde_json::from_value::<GraphGrepParams>(request.params) {
+            Ok(params) => handle_graph_grep(graph, id, params).await,
+            Err(e) => Response::invalid_params(id, e.to_string()),
+        },
+
+        // Every param is optional, so a missing params object is fine
+        "graph.cycles" => match request.params {
+            serde_json::Value::Null => {
+                handle_graph_cycles(graph, id, GraphCyclesParams::default()).await
+            }
+            params => match serde_json::from_value::<GraphCyclesParams>(params) {
+                Ok(params) => handle_graph_cycles(graph, id, params).await,
+                Err(e) => Response::invalid_params(id, e.to_string()),
+            },
+        },
+
+        "graph.clusters" => match request.params {
+            serde_json::Value::Null => {
+                handle_graph_clusters(graph, id, GraphClustersParams::default()).await
+            }
+            params => match serde_json::from_value::<GraphClustersParams>(params) {
+                Ok(params) => handle_graph_clusters(graph, id, params).await,
+                Err(e) => Response::invalid_params(id, e.to_string()),
+            },
+        },
+
+        "graph.metrics" => match request.params {
+            serde_json::Value::Null => {
+                handle_graph_metrics(graph, id, GraphMetricsParams::default()).await
+            }
+            params => match serde_json::from_value::<GraphMetricsParams>(params) {
+                Ok(params) => handle_graph_metrics(graph, id, params).await,
+                Err(e) => Response::invalid_params(id, e.to_string()),
+            },
+        },
+
+        "graph.collapse" => match request.params {
+            serde_json::Value::Null => {
+                handle_graph_collapse(graph, id, GraphCollapseParams::default()).await
+            }
+            params => match serde_json::from_value::<GraphCollapseParams>(params) {
+                Ok(params) => handle_graph_collapse(graph, id, params).await,
+                Err(e) => Response::invalid_params(id, e.to_string()),
+            },
+        },
+
         "node.get" => match serde_json::from_value::<NodeGetParams>(request.params) {
             Ok(params) => handle_node_get(graph, id, params).await,
             Err(e) => Response::invalid_params(id, e.to_string()),
@@ -7,18 +7,20 @@
 //! "Give Arbor a voice so the visualizer can hear the code breathe."
 
 use crate::SharedGraph;
-use arbor_core::ArborParser;
-use arbor_graph::{ArborGraph, Edge, EdgeKind};
+use arbor_core::{ArborConfig, Workspace};
+use arbor_graph::{ArborGraph, GraphStore};
+use arbor_watcher::{
+    ChangeBatch, FileWatcher, IncrementalIndexer, IndexPhase, IndexProgress, ProgressSink,
+};
 use futures_util::{SinkExt, StreamExt};
-use notify::{Config, Event, RecommendedWatcher, RecursiveMode, Watcher};
-use std::collections::HashMap;
 use std::net::SocketAddr;
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 use tokio::net::{TcpListener, TcpStream};
 use tokio::sync::{broadcast, mpsc, RwLock};
-use tokio_tungstenite::{accept_async, tungstenite::Message};
+use tokio_tungstenite::tungstenite::Message;
 use tracing::{debug, error, info, warn};
 
 // ─────────────────────────────────────────────────────────────────────────────
@@ -34,8 +36,11 @@ pub struct SyncServerConfig {
     pub watch_path: PathBuf,
     /// Debounce duration for file events.
     pub debounce_ms: u64,
-    /// File extensions to watch.
-    pub extensions: Vec<String>,
+    /// Project config deciding which files are watched and how they're parsed.
+    pub project: ArborConfig,
+    /// Watch every root of this workspace instead of `watch_path`. Each
+    /// root uses its own config.
+    pub workspace: Option<Workspace>,
 }
 
 impl Default for SyncServerConfig {
@@ -44,14 +49,8 @@ impl Default for SyncServerConfig {
             addr: "127.0.0.1:8080".parse().unwrap(),
             watch_path: PathBuf::from("."),
             debounce_ms: 150,
-            extensions: vec![
-                "ts".into(),
-                "tsx".into(),
-                "js".into(),
-                "jsx".into(),
-                "rs".into(),
-                "py".into(),
-            ],
+            project: ArborConfig::default(),
+            workspace: None,
         }
     }
 }
@@ -83,12 +82,18 @@ pub struct HelloPayload {
     pub version: String,
     pub node_count: usize,
     pub edge_count: usize,
+    /// Absolute project root. Node `file` paths are relative to it.
+    pub root: Option<String>,
+    /// Workspace roots. Node `file` paths start with their namespace.
+    pub roots: Vec<crate::RootInfo>,
 }
 
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct GraphBeginPayload {
     pub total_nodes: usize,
     pub total_edges: usize,
+    /// Architecture clusters; nodes name theirs in `cluster`.
+    pub clusters: Vec<arbor_graph::Cluster>,
 }
 
 #[derive(Debug, Clone, serde::Serialize)]
@@ -117,6 +122,9 @@ pub struct GraphUpdatePayload {
     pub timestamp: u64,
     pub nodes: Option<Vec<arbor_core::CodeNode>>,
     pub edges: Option<Vec<arbor_graph::GraphEdge>>,
+    /// Architecture clusters, with full snapshots and with deltas that
+    /// change them.
+    pub clusters: Option<Vec<arbor_graph::Cluster>>,
 }
 
 #[derive(Debug, Clone, serde::Serialize)]
@@ -141,15 +149,6 @@ pub struct IndexerStatusPayload {
     pub current_file: Option<String>,
 }
 
-/// Internal event for the file watcher.
-#[derive(Debug, Clone)]
-#[allow(dead_code)]
-enum WatcherEvent {
-    Changed(PathBuf),
-    Created(PathBuf),
-    Deleted(PathBuf),
-}
-
 // ─────────────────────────────────────────────────────────────────────────────
 // SyncServer
 // ─────────────────────────────────────────────────────────────────────────────
@@ -190,6 +189,72 @@ impl SyncServerHandle {
     pub fn graph(&self) -> SharedGraph {
         self.graph.clone()
     }
+
+    /// Broadcasts an indexer status update.
+    pub fn update_status(
+        &self,
+        phase: &str,
+        processed: usize,
+        total: usize,
+        current: Option<&str>,
+    ) {
+        let msg = BroadcastMessage::IndexerStatus(IndexerStatusPayload {
+            phase: phase.to_string(),
+            files_processed: processed,
+            files_total: total,
+            current_file: current.map(|s| s.to_string()),
+        });
+        let _ = self.broadcast_tx.send(msg);
+    }
+
+    /// Broadcasts the whole graph, e.g. after the initial index replaced it.
+    pub async fn publish_graph(&self) {
+        let g = self.graph.read().await;
+        let update = BroadcastMessage::GraphUpdate(GraphUpdatePayload {
+            is_delta: false,
+            node_count: g.node_count(),
+            edge_count: g.edge_count(),
+            file_count: g.stats().files,
+            changed_files: Vec::new(),
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+            nodes: Some(g.nodes().cloned().collect()),
+            edges: Some(g.export_edges()),
+            clusters: Some(g.clusters().to_vec()),
+        });
+        let _ = self.broadcast_tx.send(update);
+    }
+}
+
+/// Lets an index run report straight to connected clients.
+///
+/// Parsing reports are thinned out so a large index doesn't flood
+/// the broadcast channel.
+impl ProgressSink for SyncServerHandle {
+    fn report(&self, progress: &IndexProgress) {
+        const PARSING_REPORT_EVERY: usize = 25;
+        if progress.phase == IndexPhase::Parsing
+            && !progress
+                .files_processed
+                .is_multiple_of(PARSING_REPORT_EVERY)
+            && progress.files_processed != progress.files_total
+        {
+            return;
+        }
+
+        let current = progress
+            .current_file
+            .as_ref()
+            .map(|p| p.to_string_lossy().to_string());
+        self.update_status(
+            progress.phase.as_str(),
+            progress.files_processed,
+            progress.files_total,
+            current.as_deref(),
+        );
+    }
 }
 
 impl SyncServer {
@@ -250,32 +315,84 @@ impl SyncServer {
         info!("║          ARBOR SYNC SERVER - THE PULSE OF CODE            ║");
         info!("╚═══════════════════════════════════════════════════════════╝");
 
-        // Channel for watcher events
-        let (watcher_tx, watcher_rx) = mpsc::channel::<WatcherEvent>(256);
+        // The guard stops the watchers and the indexer when this returns
+        // or is cancelled.
+        let _guard = self.start_indexing()?;
 
-        // Start the file watcher
-        let watch_path = self.config.watch_path.clone();
-        let extensions = self.config.extensions.clone();
-        let debounce_ms = self.config.debounce_ms;
+        // Start accepting WebSocket connections.
+        self.run_websocket_server().await
+    }
 
-        tokio::spawn(async move {
-            if let Err(e) = run_file_watcher(watch_path, extensions, debounce_ms, watcher_tx).await
-            {
-                error!("File watcher error: {}", e);
+    /// Creates an indexer per watched root, each writing through to the
+    /// root's store, alongside the path and config its watcher uses.
+    fn open_indexers(&self) -> Result<Vec<WatchedRoot>, Box<dyn std::error::Error + Send + Sync>> {
+        // Fail early on a bad project config
+        let mut roots = Vec::new();
+        match &self.config.workspace {
+            Some(workspace) => {
+                for root in &workspace.roots {
+                    let project = ArborConfig::load(&root.path)?;
+                    let indexer = IncrementalIndexer::for_workspace_root(root)?;
+                    roots.push((root.path.clone(), project, indexer));
+                }
             }
-        });
+            None => {
+                let indexer =
+                    IncrementalIndexer::new(&self.config.watch_path, &self.config.project)?;
+                roots.push((
+                    self.config.watch_path.clone(),
+                    self.config.project.clone(),
+                    indexer,
+                ));
+            }
+        }
+
+        Ok(roots
+            .into_iter()
+            .map(|(path, project, indexer)| {
+                let indexer = match GraphStore::open(GraphStore::path_for(&path)) {
+                    Ok(store) => indexer.with_store(store),
+                    Err(e) => {
+                        warn!("⚠️  Store unavailable for {}: {}", path.display(), e);
+                        indexer
+                    }
+                };
+                (path, project, indexer)
+            })
+            .collect())
+    }
+
+    /// Starts a watcher per root and the background indexer they feed.
+    fn start_indexing(&self) -> Result<WatchGuard, Box<dyn std::error::Error + Send + Sync>> {
+        let roots = self.open_indexers()?;
+
+        let debounce = Duration::from_millis(self.config.debounce_ms);
+        let (watcher_tx, watcher_rx) = mpsc::channel::<(usize, ChangeBatch)>(64);
+        let stop = Arc::new(AtomicBool::new(false));
+        let mut indexers = Vec::new();
+        for (path, project, indexer) in roots {
+            spawn_file_watcher(
+                indexers.len(),
+                path,
+                project,
+                debounce,
+                watcher_tx.clone(),
+                stop.clone(),
+            );
+            indexers.push(indexer);
+        }
 
         // Start the indexer background task
         let graph = self.graph.clone();
         let broadcast_tx = self.broadcast_tx.clone();
-        let watch_path = self.config.watch_path.clone();
-
-        tokio::spawn(async move {
-            run_background_indexer(watcher_rx, graph, broadcast_tx, watch_path).await;
+        let indexer = tokio::spawn(async move {
+            run_background_indexer(watcher_rx, graph, broadcast_tx, indexers).await;
         });
 
-        // Start accepting WebSocket connections
-        self.run_websocket_server().await
+        Ok(WatchGuard {
+            stop,
+            indexer: Some(indexer),
+        })
     }
 
     /// Runs just the WebSocket server (no file watching).
@@ -324,14 +441,8 @@ impl SyncServer {
         total: usize,
         current: Option<&str>,
     ) {
-        let msg = BroadcastMessage::IndexerStatus(IndexerStatusPayload {
-            phase: phase.to_string(),
-            files_processed: processed,
-            files_total: total,
-            current_file: current.map(|s| s.to_string()),
-        });
-
-        let _ = self.broadcast_tx.send(msg);
+        self.handle()
+            .update_status(phase, processed, total, current);
     }
 }
 
@@ -349,7 +460,6 @@ async fn handle_client(
     use tokio_tungstenite::tungstenite::protocol::WebSocketConfig;
 
     let config = WebSocketConfig {
-        max_send_queue: None,
         max_message_size: Some(64 * 1024 * 1024), // 64 MB
         max_frame_size: Some(64 * 1024 * 1024),   // 64 MB
         accept_unmasked_frames: false,
@@ -362,13 +472,16 @@ async fn handle_client(
     info!("✅ WebSocket handshake complete with {}", addr);
 
     // 1. Send Hello (Metadata)
-    let (node_count, edge_count, nodes, edges) = {
+    let (node_count, edge_count, root, roots, nodes, edges, clusters) = {
         let g = graph.read().await;
         (
             g.node_count(),
             g.edge_count(),
+            g.root().map(|root| root.to_string_lossy().into_owned()),
+            crate::handlers::root_infos(&g),
             g.nodes().cloned().collect::<Vec<_>>(),
             g.export_edges(),
+            g.clusters().to_vec(),
         )
     };
 
@@ -376,6 +489,8 @@ async fn handle_client(
         version: "1.1.1".to_string(),
         node_count,
         edge_count,
+        root,
+        roots,
     });
 
     let json = serde_json::to_string(&hello)?;
@@ -417,6 +532,7 @@ async fn handle_client(
     let begin = BroadcastMessage::GraphBegin(GraphBeginPayload {
         total_nodes: node_count,
         total_edges: edge_count,
+        clusters,
     });
     write
         .send(Message::Text(serde_json::to_string(&begin)?))
@@ -504,202 +620,133 @@ async fn handle_client(
 }
 
 // ─────────────────────────────────────────────────────────────────────────────
-// File Watcher with Debouncing
+// File Watcher
 // ─────────────────────────────────────────────────────────────────────────────
 
-/// Runs the file watcher with debouncing.
-async fn run_file_watcher(
-    watch_path: PathBuf,
-    extensions: Vec<String>,
-    debounce_ms: u64,
-    tx: mpsc::Sender<WatcherEvent>,
-) -> notify::Result<()> {
-    let (notify_tx, mut notify_rx) = mpsc::channel::<notify::Result<Event>>(256);
-
-    // Create watcher in sync context
-    let mut watcher = RecommendedWatcher::new(
-        move |res| {
-            let _ = notify_tx.blocking_send(res);
-        },
-        Config::default(),
-    )?;
-
-    watcher.watch(&watch_path, RecursiveMode::Recursive)?;
-    info!("👁️  File watcher started for {}", watch_path.display());
-
-    // Debounce state
-    let mut pending: HashMap<PathBuf, Instant> = HashMap::new();
-    let debounce_dur = Duration::from_millis(debounce_ms);
+/// How often a watcher thread checks whether the server has stopped.
+const WATCHER_POLL: Duration = Duration::from_millis(250);
 
-    loop {
-        // Process pending debounced events
-        let now = Instant::now();
-        let mut ready: Vec<PathBuf> = Vec::new();
+/// A root to watch: its path, its project config and its indexer.
+type WatchedRoot = (PathBuf, ArborConfig, IncrementalIndexer);
 
-        for (path, time) in pending.iter() {
-            if now.duration_since(*time) >= debounce_dur {
-                ready.push(path.clone());
-            }
-        }
+/// Stops the watcher threads and the indexer task when dropped.
+struct WatchGuard {
+    stop: Arc<AtomicBool>,
+    indexer: Option<tokio::task::JoinHandle<()>>,
+}
 
-        for path in ready {
-            pending.remove(&path);
-            if should_process_file(&path, &extensions) {
-                let event = if path.exists() {
-                    WatcherEvent::Changed(path)
-                } else {
-                    WatcherEvent::Deleted(path)
-                };
-                let _ = tx.send(event).await;
-            }
+impl Drop for WatchGuard {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(indexer) = self.indexer.take() {
+            indexer.abort();
         }
+    }
+}
 
-        // Wait for new events with timeout
-        match tokio::time::timeout(Duration::from_millis(50), notify_rx.recv()).await {
-            Ok(Some(Ok(event))) => {
-                for path in event.paths {
-                    if should_process_file(&path, &extensions) {
-                        pending.insert(path, Instant::now());
-                    }
-                }
+/// Runs the shared debounced watcher on its own thread, forwarding batches
+/// tagged with the index of the root they came from. The thread exits
+/// once `stop` is set.
+fn spawn_file_watcher(
+    root: usize,
+    watch_path: PathBuf,
+    project: ArborConfig,
+    debounce: Duration,
+    tx: mpsc::Sender<(usize, ChangeBatch)>,
+    stop: Arc<AtomicBool>,
+) {
+    std::thread::spawn(move || {
+        let watcher = match FileWatcher::with_config(&watch_path, &project, debounce) {
+            Ok(watcher) => watcher,
+            Err(e) => {
+                error!("File watcher error: {}", e);
+                return;
             }
-            Ok(Some(Err(e))) => {
-                warn!("Watch error: {}", e);
+        };
+        info!("👁️  File watcher started for {}", watch_path.display());
+
+        while !stop.load(Ordering::Relaxed) {
+            let Some(batch) = watcher.recv_timeout(WATCHER_POLL) else {
+                continue;
+            };
+            if tx.blocking_send((root, batch)).is_err() {
+                break; // Indexer shut down
             }
-            Ok(None) => break, // Channel closed
-            Err(_) => {}       // Timeout, continue
         }
-    }
-
-    Ok(())
-}
-
-/// Checks if a file should be processed based on extension.
-fn should_process_file(path: &Path, extensions: &[String]) -> bool {
-    path.extension()
-        .and_then(|ext| ext.to_str())
-        .map(|ext| extensions.iter().any(|e| e == ext))
-        .unwrap_or(false)
+        debug!("File watcher stopped for {}", watch_path.display());
+    });
 }
 
 // ─────────────────────────────────────────────────────────────────────────────
 // Background Indexer
 // ─────────────────────────────────────────────────────────────────────────────
 
-/// Runs the background indexer that processes file changes.
+/// Runs the background indexer that applies each batch of file changes,
+/// using the indexer for the root the batch came from.
 async fn run_background_indexer(
-    mut rx: mpsc::Receiver<WatcherEvent>,
+    mut rx: mpsc::Receiver<(usize, ChangeBatch)>,
     graph: SharedGraph,
     broadcast_tx: broadcast::Sender<BroadcastMessage>,
-    _root_path: PathBuf,
+    mut indexers: Vec<IncrementalIndexer>,
 ) {
-    let mut parser = ArborParser::new().expect("Failed to initialize parser");
-
     info!("🔧 Background indexer started");
 
-    while let Some(event) = rx.recv().await {
-        let start = Instant::now();
-
-        match event {
-            WatcherEvent::Changed(path) | WatcherEvent::Created(path) => {
-                let file_name = path
-                    .file_name()
-                    .and_then(|n| n.to_str())
-                    .unwrap_or("unknown");
-
-                info!("📝 Re-indexing: {}", file_name);
-
-                match parser.parse_file(&path) {
-                    Ok(result) => {
-                        let mut g = graph.write().await;
+    // The initial index may still be running when the server starts, so
+    // each root's store is seeded from the graph when that root first changes.
+    let mut seeded = vec![false; indexers.len()];
+    while let Some((root, batch)) = rx.recv().await {
+        let Some(indexer) = indexers.get_mut(root) else {
+            continue;
+        };
+        for change in &batch.changes {
+            info!("📝 Re-indexing: {}", change.path().display());
+        }
 
-                        // Remove old nodes from this file
-                        g.remove_file(&result.file_path);
+        // Parse outside the lock so readers aren't blocked
+        let prepared = indexer.prepare(&batch);
+        if prepared.is_empty() {
+            continue;
+        }
 
-                        // Add new nodes
-                        let mut node_ids = HashMap::new();
-                        for symbol in &result.symbols {
-                            let id = g.add_node(symbol.clone());
-                            node_ids.insert(symbol.id.clone(), id);
-                        }
+        let mut g = graph.write().await;
+        if !seeded[root] {
+            if let Err(e) = indexer.seed_store(&g) {
+                warn!("⚠️  Failed to seed store: {}", e);
+            }
+            seeded[root] = true;
+        }
+        let summary = match indexer.commit(&mut g, prepared) {
+            Ok(summary) => summary,
+            Err(e) => {
+                warn!("⚠️  Failed to apply changes: {}", e);
+                continue;
+            }
+        };
 
-                        // Add edges for relations
-                        for relation in &result.relations {
-                            if let Some(&from_id) = node_ids.get(&relation.from_id) {
-                                // Try to find the target by name
-                                let targets = g.find_by_name(&relation.to_name);
-                                if let Some(target) = targets.first() {
-                                    if let Some(to_id) = g.get_index(&target.id) {
-                                        let edge_kind = match relation.kind {
-                                            arbor_core::RelationType::Calls => EdgeKind::Calls,
-                                            arbor_core::RelationType::Imports => EdgeKind::Imports,
-                                            arbor_core::RelationType::Extends => EdgeKind::Extends,
-                                            arbor_core::RelationType::Implements => {
-                                                EdgeKind::Implements
-                                            }
-                                        };
-                                        g.add_edge(from_id, to_id, Edge::new(edge_kind));
-                                    }
-                                }
-                            }
-                        }
+        info!(
+            "✅ Applied {} change(s) in {}ms ({} files parsed, {} nodes relinked)",
+            batch.len(),
+            summary.duration_ms,
+            summary.files_indexed,
+            summary.nodes_relinked
+        );
 
-                        let elapsed = start.elapsed();
-                        info!(
-                            "✅ Indexed {} in {:?} ({} symbols, {} relations)",
-                            file_name,
-                            elapsed,
-                            result.symbols.len(),
-                            result.relations.len()
-                        );
-
-                        // Broadcast update
-                        let update = BroadcastMessage::GraphUpdate(GraphUpdatePayload {
-                            is_delta: true,
-                            node_count: g.node_count(),
-                            edge_count: g.edge_count(),
-                            file_count: g.stats().files,
-                            changed_files: vec![result.file_path],
-                            timestamp: std::time::SystemTime::now()
-                                .duration_since(std::time::UNIX_EPOCH)
-                                .unwrap()
-                                .as_secs(),
-                            nodes: Some(g.nodes().cloned().collect()),
-                            edges: Some(g.export_edges()),
-                        });
-
-                        let _ = broadcast_tx.send(update);
-                    }
-                    Err(e) => {
-                        warn!("⚠️  Parse error for {}: {}", file_name, e);
-                    }
-                }
-            }
+        let update = BroadcastMessage::GraphUpdate(GraphUpdatePayload {
+            is_delta: true,
+            node_count: g.node_count(),
+            edge_count: g.edge_count(),
+            file_count: g.stats().files,
+            changed_files: summary.changed_files,
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+            nodes: Some(g.nodes().cloned().collect()),
+            edges: Some(g.export_edges()),
+            clusters: summary.clusters_changed.then(|| g.clusters().to_vec()),
+        });
 
-            WatcherEvent::Deleted(path) => {
-                let file_str = path.to_string_lossy().to_string();
-                info!("🗑️  File deleted: {}", path.display());
-
-                let mut g = graph.write().await;
-                g.remove_file(&file_str);
-
-                let update = BroadcastMessage::GraphUpdate(GraphUpdatePayload {
-                    is_delta: true,
-                    node_count: g.node_count(),
-                    edge_count: g.edge_count(),
-                    file_count: g.stats().files,
-                    changed_files: vec![file_str],
-                    timestamp: std::time::SystemTime::now()
-                        .duration_since(std::time::UNIX_EPOCH)
-                        .unwrap()
-                        .as_secs(),
-                    nodes: Some(g.nodes().cloned().collect()),
-                    edges: Some(g.export_edges()),
-                });
-
-                let _ = broadcast_tx.send(update);
-            }
-        }
+        let _ = broadcast_tx.send(update);
     }
 }
 
@@ -710,16 +757,7 @@ async fn run_background_indexer(
 #[cfg(test)]
 mod tests {
     use super::*;
-
-    #[test]
-    fn test_should_process_file() {
-        let extensions = vec!["ts".to_string(), "rs".to_string()];
-
-        assert!(should_process_file(Path::new("foo.ts"), &extensions));
-        assert!(should_process_file(Path::new("bar.rs"), &extensions));
-        assert!(!should_process_file(Path::new("baz.py"), &extensions));
-        assert!(!should_process_file(Path::new("README.md"), &extensions));
-    }
+    use arbor_watcher::FileChange;
 
     #[test]
     fn test_broadcast_message_serialization() {
@@ -732,10 +770,97 @@ mod tests {
             timestamp: 1234567890,
             nodes: None,
             edges: None,
+            clusters: None,
         });
 
         let json = serde_json::to_string(&msg).unwrap();
         assert!(json.contains("GraphUpdate"));
         assert!(json.contains("42"));
     }
+
+    #[tokio::test]
+    async fn test_watcher_ignore_rules_decide_what_is_indexed() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join(".gitignore"), "generated/\n").unwrap();
+        std::fs::create_dir(dir.path().join("generated")).unwrap();
+        let server = SyncServer::new(SyncServerConfig {
+            watch_path: dir.path().to_path_buf(),
+            debounce_ms: 50,
+            ..Default::default()
+        });
+        let _guard = server.start_indexing().unwrap();
+        // Give the watcher thread time to register its watch
+        tokio::time::sleep(Duration::from_millis(300)).await;
+
+        std::fs::write(
+            dir.path().join("generated/skip.ts"),
+            "export function skipped() {}",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("kept.ts"), "export function kept() {}").unwrap();
+
+        let graph = server.graph();
+        let mut indexed = false;
+        for _ in 0..40 {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            if !graph.read().await.find_by_name("kept").is_empty() {
+                indexed = true;
+                break;
+            }
+        }
+
+        // Skip on systems where file watching doesn't work (e.g. some
+        // containers without inotify)
+        if !indexed {
+            eprintln!("Warning: File change not detected - may be unsupported environment");
+            return;
+        }
+        let g = graph.read().await;
+        assert!(g.find_by_name("skipped").is_empty());
+        assert!(g.nodes().all(|n| !n.file.starts_with("generated")));
+    }
+
+    #[tokio::test]
+    async fn test_change_batches_reach_the_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("lib.ts");
+        std::fs::write(&file, "export function helper() {}").unwrap();
+        let server = SyncServer::new(SyncServerConfig {
+            watch_path: dir.path().to_path_buf(),
+            ..Default::default()
+        });
+
+        let indexers = server
+            .open_indexers()
+            .unwrap()
+            .into_iter()
+            .map(|(_, _, indexer)| indexer)
+            .collect();
+        let (tx, rx) = mpsc::channel(1);
+        tx.send((
+            0,
+            ChangeBatch {
+                changes: vec![FileChange::Created(file)],
+            },
+        ))
+        .await
+        .unwrap();
+        drop(tx);
+        run_background_indexer(rx, server.graph(), server.broadcast_tx.clone(), indexers).await;
+
+        // sled's flusher thread releases the lock shortly after the
+        // indexer drops its handle
+        let mut store = GraphStore::open(GraphStore::path_for(dir.path()));
+        for _ in 0..20 {
+            if store.is_ok() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            store = GraphStore::open(GraphStore::path_for(dir.path()));
+        }
+        let store = store.unwrap();
+        assert_eq!(store.files().unwrap(), vec!["lib.ts".to_string()]);
+        let stored = store.load_graph().unwrap();
+        assert_eq!(stored.find_by_name("helper").len(), 1);
+    }
 }
@@ -0,0 +1,557 @@
+//! Indexing git revisions without a checkout.
+//!
+//! A commit's tree is read straight from the object database through the
+//! `git` CLI (`ls-tree` for the file list, one `cat-file --batch` for the
+//! contents), so branches and tags can be indexed while the working tree
+//! stays on whatever is checked out. The revision's own
+//! `.arbor/config.json` is used if it has one.
+
+use crate::indexer::{
+    apply_overrides, assemble, parse_bytes, parse_parallel, FileNotes, FileOutcome, IndexResult,
+};
+use crate::options::{IndexOptions, IndexPhase, IndexProgress};
+use arbor_core::config::{CONFIG_DIR, CONFIG_FILE};
+use arbor_core::{ArborConfig, ConfigError};
+use arbor_graph::{ArborGraph, GraphStore, RevisionInfo, StoreError};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+use tracing::{debug, info};
+
+/// Things that can go wrong while indexing a revision.
+#[derive(Error, Debug)]
+pub enum GitError {
+    /// Couldn't run git or talk to it.
+    #[error("git I/O error: {0}")]
+    Io(#[from] io::Error),
+
+    /// The path isn't inside a git repository.
+    #[error("not a git repository: {}", .0.display())]
+    NotARepository(PathBuf),
+
+    /// The revision doesn't name a commit.
+    #[error("unknown revision '{0}'")]
+    UnknownRevision(String),
+
+    /// A git command exited with an error.
+    #[error("git {command} failed: {stderr}")]
+    Command { command: String, stderr: String },
+
+    /// Git printed something we couldn't make sense of.
+    #[error("unexpected output from git {0}")]
+    Malformed(String),
+
+    /// The revision's `.arbor/config.json` is invalid.
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+
+    #[error(transparent)]
+    Store(#[from] StoreError),
+}
+
+/// A file in a commit's tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeFile {
+    /// Path relative to the repository root, with forward slashes.
+    pub path: String,
+    /// Blob object ID.
+    pub blob: String,
+    pub size: u64,
+}
+
+/// A local git repository, accessed through the `git` CLI.
+#[derive(Debug, Clone)]
+pub struct GitRepo {
+    root: PathBuf,
+}
+
+impl GitRepo {
+    /// Opens the repository containing `path`.
+    pub fn open(path: &Path) -> Result<Self, GitError> {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(path)
+            .args(["rev-parse", "--show-toplevel"])
+            .output()?;
+        if !output.status.success() {
+            return Err(GitError::NotARepository(path.to_path_buf()));
+        }
+        let root = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        Ok(Self {
+            root: PathBuf::from(root),
+        })
+    }
+
+    /// Returns the top of the working tree.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Resolves a branch, tag or other revision to a full commit hash.
+    pub fn resolve(&self, revision: &str) -> Result<String, GitError> {
+        if revision.is_empty() || revision.starts_with('-') {
+            return Err(GitError::UnknownRevision(revision.to_string()));
+        }
+        let spec = format!("{}^{{commit}}", revision);
+        let output = self
+            .command(&["rev-parse", "--verify", "--quiet", &spec])
+            .output()?;
+        if !output.status.success() {
+            return Err(GitError::UnknownRevision(revision.to_string()));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    /// Lists the regular files in a commit's tree.
+    ///
+    /// Symlinks and submodules are left out.
+    pub fn files(&self, commit: &str) -> Result<Vec<TreeFile>, GitError> {
+        let stdout = self.run(&["ls-tree", "-r", "-l", "-z", "--full-tree", commit])?;
+        let mut files = Vec::new();
+        for entry in stdout.split(|&b| b == 0).filter(|e| !e.is_empty()) {
+            let entry = String::from_utf8_lossy(entry);
+            let (meta, path) = entry
+                .split_once('\t')
+                .ok_or_else(|| GitError::Malformed("ls-tree".to_string()))?;
+            let fields: Vec<&str> = meta.split_whitespace().collect();
+            let [mode, kind, blob, size] = fields.as_slice() else {
+                return Err(GitError::Malformed("ls-tree".to_string()));
+            };
+            if *kind != "blob" || !matches!(*mode, "100644" | "100755") {
+                continue;
+            }
+            files.push(TreeFile {
+                path: path.to_string(),
+                blob: blob.to_string(),
+                size: size.parse().unwrap_or(0),
+            });
+        }
+        Ok(files)
+    }
+
+    /// Returns the unified diff from a revision to the working tree,
+    /// with paths relative to the repository root.
+    pub fn diff_since(&self, revision: &str) -> Result<String, GitError> {
+        let commit = self.resolve(revision)?;
+        let stdout = self.run(&[
+            "diff",
+            "--no-color",
+            "--no-ext-diff",
+            "--find-renames",
+            "--unified=0",
+            &commit,
+            "--",
+        ])?;
+        Ok(String::from_utf8_lossy(&stdout).into_owned())
+    }
+
+    /// Reads several blobs with a single `git cat-file --batch`.
+    pub fn read_blobs(&self, blobs: &[&str]) -> Result<Vec<Vec<u8>>, GitError> {
+        if blobs.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut child = self
+            .command(&["cat-file", "--batch"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        // Feed the requests from another thread so a full stdout pipe
+        // can't deadlock us.
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        let request: String = blobs.iter().map(|id| format!("{}\n", id)).collect();
+        let writer = std::thread::spawn(move || stdin.write_all(request.as_bytes()));
+
+        let mut reader = BufReader::new(child.stdout.take().expect("stdout is piped"));
+        let read = read_batch(&mut reader, blobs);
+        if read.is_err() {
+            // Stop git so the writer's pipe breaks instead of blocking
+            let _ = child.kill();
+        }
+        drop(reader);
+        let written = writer.join();
+        let status = child.wait();
+
+        let contents = read?;
+        written.map_err(|_| GitError::Malformed("cat-file".to_string()))??;
+        status?;
+        Ok(contents)
+    }
+
+    fn command(&self, args: &[&str]) -> Command {
+        let mut command = Command::new("git");
+        command.arg("-C").arg(&self.root).args(args);
+        command
+    }
+
+    fn run(&self, args: &[&str]) -> Result<Vec<u8>, GitError> {
+        let output = self.command(args).output()?;
+        if !output.status.success() {
+            return Err(GitError::Command {
+                command: args.first().copied().unwrap_or_default().to_string(),
+                stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            });
+        }
+        Ok(output.stdout)
+    }
+}
+
+/// Reads one `cat-file --batch` response per requested blob.
+fn read_batch(reader: &mut impl BufRead, blobs: &[&str]) -> Result<Vec<Vec<u8>>, GitError> {
+    let mut contents = Vec::with_capacity(blobs.len());
+    let mut header = String::new();
+    for id in blobs {
+        header.clear();
+        reader.read_line(&mut header)?;
+        let fields: Vec<&str> = header.split_whitespace().collect();
+        let size = match fields.as_slice() {
+            [_, "blob", size] => size
+                .parse::<usize>()
+                .map_err(|_| GitError::Malformed("cat-file".to_string()))?,
+            _ => {
+                return Err(GitError::Command {
+                    command: "cat-file".to_string(),
+                    stderr: format!("cannot read blob {}", id),
+                })
+            }
+        };
+        let mut blob = vec![0; size + 1];
+        reader.read_exact(&mut blob)?;
+        blob.pop(); // trailing newline
+        contents.push(blob);
+    }
+    Ok(contents)
+}
+
+/// The result of indexing one revision.
+pub struct RevisionIndex {
+    /// The revision as requested.
+    pub revision: String,
+    /// The commit it resolved to.
+    pub commit: String,
+    pub result: IndexResult,
+}
+
+impl RevisionIndex {
+    /// Describes this revision for [`GraphStore::save_revision`].
+    pub fn info(&self) -> RevisionInfo {
+        let mut refs = Vec::new();
+        if self.revision != self.commit {
+            refs.push(self.revision.clone());
+        }
+        RevisionInfo {
+            commit: self.commit.clone(),
+            refs,
+            files: self.result.files_indexed,
+            nodes: self.result.nodes_extracted,
+            indexed_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+        }
+    }
+}
+
+/// Indexes a commit, branch or tag from the object database.
+///
+/// Nothing in the working tree is read or written. Paths are stored
+/// relative to the repository root, as they are for the working tree.
+/// File contents are held in memory for the duration of the parse.
+pub fn index_revision(
+    repo: &GitRepo,
+    revision: &str,
+    options: &IndexOptions,
+) -> Result<RevisionIndex, GitError> {
+    let start = Instant::now();
+    let commit = repo.resolve(revision)?;
+    info!("Starting index of {} ({})", revision, commit);
+    options.report(IndexProgress {
+        phase: IndexPhase::Scanning,
+        files_processed: 0,
+        files_total: 0,
+        current_file: None,
+    });
+
+    let tree = repo.files(&commit)?;
+    let config = revision_config(repo, &commit, &tree)?;
+    let config = apply_overrides(&config, options);
+    let filter = config.file_filter(repo.root())?;
+
+    // The directory walker skips hidden files; do the same here.
+    let files: Vec<&TreeFile> = tree
+        .iter()
+        .filter(|f| !f.path.split('/').any(|part| part.starts_with('.')))
+        .filter(|f| filter.should_index(Path::new(&f.path)))
+        .collect();
+    let wanted: Vec<&str> = files
+        .iter()
+        .filter(|f| !filter.exceeds_size_limit(f.size))
+        .map(|f| f.blob.as_str())
+        .collect();
+    debug!("Reading {} blobs from {}", wanted.len(), commit);
+    let mut blobs = repo.read_blobs(&wanted)?.into_iter();
+    let items: Vec<(&TreeFile, Option<Vec<u8>>)> = files
+        .iter()
+        .map(|&f| {
+            (
+                f,
+                (!filter.exceeds_size_limit(f.size))
+                    .then(|| blobs.next())
+                    .flatten(),
+            )
+        })
+        .collect();
+
+    let outcomes = parse_parallel(
+        &items,
+        config.effective_threads(),
+        options,
+        |(file, _)| PathBuf::from(&file.path),
+        |(file, bytes)| match bytes {
+            Some(bytes) => parse_bytes(
+                Path::new(&file.path),
+                &file.path,
+                bytes,
+                &filter,
+                options.parse_timeout,
+            ),
+            None => (
+                FileOutcome::Skipped(format!("file too large ({} bytes)", file.size)),
+                FileNotes::default(),
+            ),
+        },
+    )?;
+
+    let parsed = items
+        .iter()
+        .zip(outcomes)
+        .map(|((file, _), (outcome, notes))| (file.path.clone(), outcome, notes));
+    let mut result = assemble(
+        parsed,
+        Vec::new(),
+        Some(repo.root().to_path_buf()),
+        start,
+        options,
+    );
+    result.graph.set_encoding(filter.encoding());
+    Ok(RevisionIndex {
+        revision: revision.to_string(),
+        commit,
+        result,
+    })
+}
+
+/// A revision's graph, from the store or freshly indexed.
+pub struct StoredRevision {
+    pub info: RevisionInfo,
+    pub graph: ArborGraph,
+    /// Whether the graph came from the store rather than a new index.
+    pub cached: bool,
+}
+
+/// Loads a revision from the store, indexing and saving it first if it
+/// isn't there yet. Commits never change, so a stored copy stays valid.
+pub fn load_or_index_revision(
+    repo: &GitRepo,
+    revision: &str,
+    store: &GraphStore,
+    options: &IndexOptions,
+) -> Result<StoredRevision, GitError> {
+    let commit = repo.resolve(revision)?;
+    if let (Some(info), Some(mut graph)) = (store.revision(&commit)?, store.load_revision(&commit)?)
+    {
+        graph.set_root(repo.root().to_path_buf());
+        return Ok(StoredRevision {
+            info,
+            graph,
+            cached: true,
+        });
+    }
+
+    let indexed = index_revision(repo, revision, options)?;
+    let info = indexed.info();
+    store.save_revision(&info, &indexed.result.graph)?;
+    Ok(StoredRevision {
+        info,
+        graph: indexed.result.graph,
+        cached: false,
+    })
+}
+
+/// Reads `.arbor/config.json` from the commit, or falls back to defaults.
+fn revision_config(
+    repo: &GitRepo,
+    commit: &str,
+    tree: &[TreeFile],
+) -> Result<ArborConfig, GitError> {
+    let config_path = format!("{}/{}", CONFIG_DIR, CONFIG_FILE);
+    let Some(file) = tree.iter().find(|f| f.path == config_path) else {
+        return Ok(ArborConfig::default());
+    };
+    let bytes = repo.read_blobs(&[&file.blob])?.remove(0);
+    let origin = PathBuf::from(format!("{}:{}", commit, config_path));
+    Ok(ArborConfig::from_json(
+        &String::from_utf8_lossy(&bytes),
+        &origin,
+    )?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .unwrap();
+        assert!(status.success(), "git {:?} failed", args);
+    }
+
+    /// A repo with `main` and a checked-out `feature` branch that adds `b`.
+    fn two_branch_repo(dir: &Path) {
+        git(dir, &["init", "-q", "-b", "main"]);
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join("src/app.py"), "def a():\n    pass\n").unwrap();
+        git(dir, &["add", "."]);
+        git(dir, &["commit", "-q", "-m", "initial"]);
+        git(dir, &["tag", "v1"]);
+        git(dir, &["checkout", "-q", "-b", "feature"]);
+        fs::write(
+            dir.join("src/app.py"),
+            "def a():\n    pass\n\ndef b():\n    a()\n",
+        )
+        .unwrap();
+        git(dir, &["commit", "-q", "-am", "add b"]);
+    }
+
+    #[test]
+    fn test_index_branches_without_checkout() {
+        let dir = tempdir().unwrap();
+        two_branch_repo(dir.path());
+        // Uncommitted edits must not leak into indexed revisions
+        fs::write(dir.path().join("src/app.py"), "def dirty():\n    pass\n").unwrap();
+
+        let repo = GitRepo::open(dir.path()).unwrap();
+        let options = IndexOptions::default();
+
+        let main = index_revision(&repo, "main", &options).unwrap();
+        let graph = &main.result.graph;
+        assert_eq!(main.result.files_indexed, 1);
+        assert!(!graph.find_by_name("a").is_empty());
+        assert!(graph.find_by_name("b").is_empty());
+        assert!(graph.find_by_name("dirty").is_empty());
+        assert_eq!(graph.find_by_name("a")[0].file, "src/app.py");
+
+        let feature = index_revision(&repo, "feature", &options).unwrap();
+        assert!(!feature.result.graph.find_by_name("b").is_empty());
+        assert_ne!(main.commit, feature.commit);
+        assert_eq!(repo.resolve("v1").unwrap(), main.commit);
+
+        // The working tree is exactly as we left it
+        let content = fs::read_to_string(dir.path().join("src/app.py")).unwrap();
+        assert_eq!(content, "def dirty():\n    pass\n");
+        let head = Command::new("git")
+            .arg("-C")
+            .arg(dir.path())
+            .args(["rev-parse", "--abbrev-ref", "HEAD"])
+            .output()
+            .unwrap();
+        assert_eq!(String::from_utf8_lossy(&head.stdout).trim(), "feature");
+    }
+
+    #[test]
+    fn test_revision_config_and_errors() {
+        let dir = tempdir().unwrap();
+        two_branch_repo(dir.path());
+        fs::create_dir_all(dir.path().join(CONFIG_DIR)).unwrap();
+        fs::write(
+            dir.path().join(CONFIG_DIR).join(CONFIG_FILE),
+            r#"{"ignore": ["src/**"]}"#,
+        )
+        .unwrap();
+        git(dir.path(), &["add", "-f", "."]);
+        git(dir.path(), &["commit", "-q", "-m", "ignore src"]);
+
+        let repo = GitRepo::open(dir.path()).unwrap();
+        let options = IndexOptions::default();
+        let head = index_revision(&repo, "HEAD", &options).unwrap();
+        assert_eq!(head.result.files_indexed, 0);
+        let main = index_revision(&repo, "main", &options).unwrap();
+        assert_eq!(main.result.files_indexed, 1);
+
+        assert!(matches!(
+            index_revision(&repo, "no-such-branch", &options),
+            Err(GitError::UnknownRevision(_))
+        ));
+        assert!(matches!(
+            repo.resolve("--all"),
+            Err(GitError::UnknownRevision(_))
+        ));
+
+        // Bigger than a pipe buffer, so git blocks until it's killed
+        let missing = vec!["0000000000000000000000000000000000000000"; 20_000];
+        assert!(matches!(
+            repo.read_blobs(&missing),
+            Err(GitError::Command { .. })
+        ));
+
+        let elsewhere = tempdir().unwrap();
+        assert!(matches!(
+            GitRepo::open(elsewhere.path()),
+            Err(GitError::NotARepository(_))
+        ));
+    }
+
+    #[test]
+    fn test_diff_since() {
+        let dir = tempdir().unwrap();
+        two_branch_repo(dir.path());
+        fs::write(
+            dir.path().join("src/app.py"),
+            "def a():\n    return 1\n\ndef b():\n    a()\n",
+        )
+        .unwrap();
+
+        let repo = GitRepo::open(dir.path()).unwrap();
+        let diff = repo.diff_since("main").unwrap();
+        let patches = arbor_graph::parse_unified_diff(&diff);
+        assert_eq!(patches.len(), 1);
+        assert_eq!(patches[0].path, "src/app.py");
+        assert_eq!(patches[0].lines[0].start, 2);
+    }
+
+    #[test]
+    fn test_revisions_are_stored() {
+        let dir = tempdir().unwrap();
+        two_branch_repo(dir.path());
+        let repo = GitRepo::open(dir.path()).unwrap();
+        let store_dir = tempdir().unwrap();
+        let store = GraphStore::open(store_dir.path()).unwrap();
+        let options = IndexOptions::default();
+
+        let first = load_or_index_revision(&repo, "main", &store, &options).unwrap();
+        assert!(!first.cached);
+        assert_eq!(first.info.refs, vec!["main"]);
+
+        let again = load_or_index_revision(&repo, "main", &store, &options).unwrap();
+        assert!(again.cached);
+        assert_eq!(again.graph.node_count(), first.graph.node_count());
+        assert!(!again.graph.find_by_name("a").is_empty());
+
+        let feature = load_or_index_revision(&repo, "feature", &store, &options).unwrap();
+        assert!(!feature.cached);
+        assert!(!feature.graph.find_by_name("b").is_empty());
+        assert_eq!(store.revisions().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,169 @@
+//! Ignore rules shared by the indexer and the watcher.
+//!
+//! `index_directory` gets gitignore handling for free from the `ignore`
+//! walker. A watcher sees one path at a time, so it needs the same rules
+//! in a form it can ask about individual paths: hidden files, every
+//! `.gitignore` in the tree, `.git/info/exclude`, the global gitignore,
+//! and the project's `.arbor/config.json`.
+
+use arbor_core::{ArborConfig, ConfigError, FileFilter};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::{Match, WalkBuilder};
+use std::path::{Component, Path, PathBuf};
+use tracing::{debug, warn};
+
+/// Decides whether a single path belongs to the project.
+#[derive(Debug, Clone)]
+pub struct IgnoreRules {
+    root: PathBuf,
+    filter: FileFilter,
+    /// Per-directory gitignores, deepest directory last.
+    gitignores: Vec<Gitignore>,
+    /// `.git/info/exclude` plus the user's global gitignore.
+    global: Vec<Gitignore>,
+}
+
+impl IgnoreRules {
+    /// Builds the rules for a project root.
+    pub fn new(root: &Path, config: &ArborConfig) -> Result<Self, ConfigError> {
+        let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+        let filter = config.file_filter(&root)?;
+
+        let mut rules = Self {
+            root,
+            filter,
+            gitignores: Vec::new(),
+            global: Vec::new(),
+        };
+        rules.reload_gitignores();
+        Ok(rules)
+    }
+
+    /// Returns the compiled config filter.
+    pub fn filter(&self) -> &FileFilter {
+        &self.filter
+    }
+
+    /// Returns the (canonicalized) project root.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Re-reads every gitignore file. Call this when one of them changes.
+    pub fn reload_gitignores(&mut self) {
+        self.global.clear();
+
+        let (global, err) = Gitignore::global();
+        if let Some(e) = err {
+            debug!("Global gitignore: {}", e);
+        }
+        self.global.push(global);
+
+        let exclude = self.root.join(".git").join("info").join("exclude");
+        if exclude.exists() {
+            self.global.push(build_gitignore(&self.root, &exclude));
+        }
+
+        // Find .gitignore files the same way the indexer walks: skipping
+        // hidden and already-ignored directories.
+        let prune = self.filter.clone();
+        let mut dirs: Vec<PathBuf> = WalkBuilder::new(&self.root)
+            .hidden(true)
+            .git_ignore(true)
+            .git_global(true)
+            .git_exclude(true)
+            .require_git(false)
+            .filter_entry(move |entry| !prune.is_ignored(entry.path()))
+            .build()
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_type().is_some_and(|t| t.is_dir()))
+            .map(|entry| entry.into_path())
+            .filter(|dir| dir.join(".gitignore").is_file())
+            .collect();
+        dirs.sort_by_key(|d| d.components().count());
+
+        self.gitignores = dirs
+            .iter()
+            .map(|dir| build_gitignore(dir, &dir.join(".gitignore")))
+            .collect();
+    }
+
+    /// Whether the path is hidden, gitignored or excluded by the config.
+    ///
+    /// Paths outside the root are always ignored.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let Ok(rel) = path.strip_prefix(&self.root) else {
+            return true;
+        };
+
+        let hidden = rel.components().any(|c| match c {
+            Component::Normal(name) => name.to_string_lossy().starts_with('.'),
+            _ => false,
+        });
+        if hidden {
+            return true;
+        }
+
+        if self.filter.is_ignored(path) {
+            return true;
+        }
+
+        // Deeper gitignores win, and a whitelist (`!pattern`) stops the search.
+        for gitignore in self.gitignores.iter().rev() {
+            if !path.starts_with(gitignore.path()) {
+                continue;
+            }
+            match gitignore.matched_path_or_any_parents(path, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => {}
+            }
+        }
+
+        self.global.iter().any(|g| {
+            path.starts_with(g.path()) && g.matched_path_or_any_parents(path, is_dir).is_ignore()
+        })
+    }
+
+    /// Whether a file should be indexed: not ignored, and wanted by the config.
+    pub fn should_index(&self, path: &Path) -> bool {
+        !self.is_ignored(path, false) && self.filter.should_index(path)
+    }
+}
+
+fn build_gitignore(dir: &Path, file: &Path) -> Gitignore {
+    let mut builder = GitignoreBuilder::new(dir);
+    if let Some(e) = builder.add(file) {
+        warn!("Failed to read {}: {}", file.display(), e);
+    }
+    builder.build().unwrap_or_else(|e| {
+        warn!("Invalid gitignore {}: {}", file.display(), e);
+        Gitignore::empty()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_rules_match_indexer() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        fs::create_dir_all(root.join("web/build")).unwrap();
+        fs::write(root.join(".gitignore"), "*.gen.rs\n").unwrap();
+        fs::write(root.join("web/.gitignore"), "build/\n").unwrap();
+
+        let rules = IgnoreRules::new(&root, &ArborConfig::default()).unwrap();
+
+        assert!(rules.should_index(&root.join("src/lib.rs")));
+        assert!(!rules.should_index(&root.join("src/schema.gen.rs")));
+        assert!(!rules.should_index(&root.join("web/build/app.js")));
+        assert!(rules.should_index(&root.join("web/src/app.js")));
+        assert!(!rules.should_index(&root.join("target/debug/main.rs")));
+        assert!(!rules.should_index(&root.join(".hidden/tool.py")));
+        assert!(!rules.should_index(Path::new("/elsewhere/lib.rs")));
+    }
+}
@@ -0,0 +1,689 @@
+//! Incremental indexing.
+//!
+//! Takes the change batches produced by [`FileWatcher`](crate::FileWatcher)
+//! and applies them to an `ArborGraph` and, optionally, a `GraphStore`.
+//! Only changed files are reparsed. Edges pointing into them from other
+//! files are re-resolved, and centrality is refreshed around the change
+//! instead of from scratch.
+//!
+//! Work is split in two so callers holding the graph behind a lock can
+//! parse without it: [`IncrementalIndexer::prepare`] reads and parses,
+//! [`IncrementalIndexer::commit`] mutates.
+
+use crate::indexer::{parse_with_filter, record_encoding, FileOutcome};
+use crate::options::DEFAULT_PARSE_TIMEOUT;
+use crate::watcher::{ChangeBatch, FileChange};
+use arbor_core::{ArborConfig, CodeNode, ConfigError, FileFilter, WorkspaceRoot};
+use arbor_graph::{
+    compute_centrality, refresh_centrality, ArborGraph, CentralityScores, Edge, GraphStore, NodeId,
+    StoreError,
+};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Mutex;
+use std::time::Instant;
+use thiserror::Error;
+use tracing::{debug, info, warn};
+
+const CENTRALITY_ITERATIONS: usize = 20;
+const CENTRALITY_DAMPING: f64 = 0.85;
+
+/// Errors from applying a batch.
+#[derive(Error, Debug)]
+pub enum IndexerError {
+    #[error("Invalid project config: {0}")]
+    Config(#[from] ConfigError),
+    #[error("Store error: {0}")]
+    Store(#[from] StoreError),
+}
+
+/// What happened to the graph, published to subscribers.
+#[derive(Debug, Clone, PartialEq)]
+pub enum IndexEvent {
+    /// A file was parsed and its nodes replaced. Holds node IDs.
+    FileIndexed {
+        file: String,
+        added: Vec<String>,
+        changed: Vec<String>,
+        removed: Vec<String>,
+    },
+    /// A file left the index (deleted, ignored or skipped).
+    FileRemoved { file: String, removed: Vec<String> },
+    /// A file moved. Its nodes show up as removed and indexed events too,
+    /// since node IDs include the path.
+    FileRenamed { from: String, to: String },
+    /// A file could not be parsed. Its previous nodes are kept.
+    FileFailed { file: String, error: String },
+    /// Nodes in untouched files whose references were re-resolved.
+    EdgesRelinked { nodes: Vec<String> },
+    /// The batch was applied.
+    BatchApplied(BatchSummary),
+}
+
+/// Totals for one applied batch.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BatchSummary {
+    pub files_indexed: usize,
+    pub files_removed: usize,
+    pub files_failed: usize,
+    pub nodes_added: usize,
+    pub nodes_changed: usize,
+    pub nodes_removed: usize,
+    /// Nodes outside the changed files whose edges were re-resolved.
+    pub nodes_relinked: usize,
+    /// Every file the batch touched, removed files included.
+    pub changed_files: Vec<String>,
+    /// Whether files joined or left an architecture cluster.
+    pub clusters_changed: bool,
+    pub duration_ms: u64,
+}
+
+/// A parsed batch, ready to be committed.
+#[derive(Debug, Default)]
+pub struct PreparedBatch {
+    parsed: Vec<(String, Vec<CodeNode>)>,
+    /// File text for the body index, by stored path.
+    bodies: HashMap<String, String>,
+    removed: Vec<String>,
+    /// Deleted paths that may have been directories.
+    removed_dirs: Vec<String>,
+    renamed: Vec<(String, String)>,
+    failed: Vec<(String, String)>,
+    started: Option<Instant>,
+}
+
+impl PreparedBatch {
+    /// Returns true if committing would change nothing.
+    pub fn is_empty(&self) -> bool {
+        self.parsed.is_empty()
+            && self.removed.is_empty()
+            && self.removed_dirs.is_empty()
+            && self.failed.is_empty()
+    }
+}
+
+/// Keeps a graph (and optionally a store) in sync with file changes.
+pub struct IncrementalIndexer {
+    filter: FileFilter,
+    store: Option<GraphStore>,
+    centrality: CentralityScores,
+    subscribers: Mutex<Vec<Sender<IndexEvent>>>,
+}
+
+impl IncrementalIndexer {
+    /// Creates an indexer for a project root.
+    pub fn new(root: &Path, config: &ArborConfig) -> Result<Self, IndexerError> {
+        Ok(Self {
+            filter: config.file_filter(root)?,
+            store: None,
+            centrality: CentralityScores::default(),
+            subscribers: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Creates an indexer for one root of a workspace.
+    pub fn for_workspace_root(root: &WorkspaceRoot) -> Result<Self, IndexerError> {
+        let config = ArborConfig::load(&root.path)?;
+        let mut indexer = Self::new(&root.path, &config)?;
+        indexer.filter = indexer.filter.with_namespace(&root.namespace);
+        Ok(indexer)
+    }
+
+    /// Also writes every change to a store.
+    pub fn with_store(mut self, store: GraphStore) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Returns the store, if one is attached.
+    pub fn store(&self) -> Option<&GraphStore> {
+        self.store.as_ref()
+    }
+
+    /// Subscribes to change events. Dropped receivers are cleaned up.
+    pub fn subscribe(&self) -> Receiver<IndexEvent> {
+        let (tx, rx) = channel();
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.push(tx);
+        }
+        rx
+    }
+
+    /// Writes this indexer's part of a graph to the attached store,
+    /// replacing what's there. For a workspace root that's the nodes
+    /// under its namespace.
+    ///
+    /// Use this once after the initial `index_directory`.
+    pub fn seed_store(&self, graph: &ArborGraph) -> Result<(), IndexerError> {
+        let Some(store) = &self.store else {
+            return Ok(());
+        };
+
+        let namespace = self.filter.namespace();
+        let mut by_file: HashMap<&str, Vec<CodeNode>> = HashMap::new();
+        for node in graph.nodes().filter(|n| n.in_root(namespace)) {
+            by_file.entry(&node.file).or_default().push(node.clone());
+        }
+        let updated: Vec<(&str, &[CodeNode])> = by_file
+            .iter()
+            .map(|(file, nodes)| (*file, nodes.as_slice()))
+            .collect();
+
+        store.clear()?;
+        store.apply_changes(&updated, &[])?;
+        Ok(())
+    }
+
+    /// Reads and parses every file in the batch. Doesn't touch the graph.
+    pub fn prepare(&self, batch: &ChangeBatch) -> PreparedBatch {
+        let mut prepared = PreparedBatch {
+            started: Some(Instant::now()),
+            ..Default::default()
+        };
+
+        let path_key = |path: &Path| self.filter.stored_path(path);
+        for change in &batch.changes {
+            let (path, from) = match change {
+                FileChange::Deleted(path) => {
+                    if self.filter.language_for(path).is_none() {
+                        prepared.removed_dirs.push(path_key(path));
+                    }
+                    prepared.removed.push(path_key(path));
+                    continue;
+                }
+                FileChange::Renamed { from, to } => (to, Some(from)),
+                FileChange::Created(path) | FileChange::Modified(path) => (path, None),
+            };
+
+            // A renamed file's old nodes go once the new path has been read.
+            // If it can't be parsed they stay where they were.
+            let moved = |prepared: &mut PreparedBatch| {
+                if let Some(from) = from {
+                    prepared.removed.push(path_key(from));
+                    prepared.renamed.push((path_key(from), path_key(path)));
+                }
+            };
+
+            if !self.filter.should_index(path) {
+                moved(&mut prepared);
+                prepared.removed.push(path_key(path));
+                continue;
+            }
+
+            let (outcome, notes) =
+                parse_with_filter(path, &self.filter, Some(DEFAULT_PARSE_TIMEOUT));
+            match outcome {
+                FileOutcome::Parsed(nodes) => {
+                    moved(&mut prepared);
+                    if let Some(source) = notes.source {
+                        prepared.bodies.insert(path_key(path), source);
+                    }
+                    prepared.parsed.push((path_key(path), nodes));
+                }
+                FileOutcome::Skipped(reason) => {
+                    debug!("Skipping {}: {}", path.display(), reason);
+                    moved(&mut prepared);
+                    prepared.removed.push(path_key(path));
+                }
+                FileOutcome::Failed(e) => {
+                    warn!("Failed to parse {}: {}", path.display(), e);
+                    prepared.failed.push((path_key(path), e));
+                }
+            }
+        }
+
+        prepared
+    }
+
+    /// Applies a prepared batch.
+    ///
+    /// The store is written first, in one transaction; if that fails the
+    /// graph is left untouched.
+    pub fn commit(
+        &mut self,
+        graph: &mut ArborGraph,
+        mut prepared: PreparedBatch,
+    ) -> Result<BatchSummary, IndexerError> {
+        let started = prepared.started.unwrap_or_else(Instant::now);
+        let mut summary = BatchSummary::default();
+        let mut events = Vec::new();
+        if graph.root().is_none() && graph.roots().is_empty() {
+            graph.set_root(self.filter.project_root().path().to_path_buf());
+        }
+        record_encoding(graph, &self.filter);
+        for dir in std::mem::take(&mut prepared.removed_dirs) {
+            prepared.removed.extend(graph.files_under(&dir));
+        }
+
+        if let Some(store) = &self.store {
+            let updated: Vec<(&str, &[CodeNode])> = prepared
+                .parsed
+                .iter()
+                .map(|(file, nodes)| (file.as_str(), nodes.as_slice()))
+                .collect();
+            let removed: Vec<&str> = prepared.removed.iter().map(String::as_str).collect();
+            store.apply_changes(&updated, &removed)?;
+        }
+
+        // Names defined by the old and new versions of changed files.
+        // References to any of them may now resolve differently.
+        let mut touched_names: HashSet<String> = HashSet::new();
+        let mut dirty: Vec<NodeId> = Vec::new();
+        let mut saved_edges = Vec::new();
+
+        for file in &prepared.removed {
+            let old = take_file(
+                graph,
+                file,
+                &mut touched_names,
+                &mut dirty,
+                &mut saved_edges,
+            );
+            if old.is_empty() {
+                continue; // Never indexed
+            }
+            summary.files_removed += 1;
+            summary.changed_files.push(file.clone());
+            summary.nodes_removed += old.len();
+            events.push(IndexEvent::FileRemoved {
+                file: file.clone(),
+                removed: old.into_keys().collect(),
+            });
+        }
+
+        for (from, to) in &prepared.renamed {
+            events.push(IndexEvent::FileRenamed {
+                from: from.clone(),
+                to: to.clone(),
+            });
+        }
+
+        let mut new_nodes: Vec<NodeId> = Vec::new();
+        for (file, nodes) in prepared.parsed {
+            let old = take_file(
+                graph,
+                &file,
+                &mut touched_names,
+                &mut dirty,
+                &mut saved_edges,
+            );
+
+            let (mut added, mut changed) = (Vec::new(), Vec::new());
+            let mut kept: HashSet<String> = HashSet::new();
+            for node in nodes {
+                match old.get(&node.id) {
+                    Some(previous) if same_content(previous, &node) => {}
+                    Some(_) => changed.push(node.id.clone()),
+                    None => added.push(node.id.clone()),
+                }
+                kept.insert(node.id.clone());
+                touched_names.insert(node.name.clone());
+                touched_names.insert(node.qualified_name.clone());
+                new_nodes.push(graph.add_node(node));
+            }
+            let removed: Vec<String> = old.into_keys().filter(|id| !kept.contains(id)).collect();
+            if let Some(source) = prepared.bodies.remove(&file) {
+                graph.index_body(&file, source);
+            }
+
+            summary.files_indexed += 1;
+            summary.nodes_added += added.len();
+            summary.nodes_changed += changed.len();
+            summary.nodes_removed += removed.len();
+            summary.changed_files.push(file.clone());
+            events.push(IndexEvent::FileIndexed {
+                file,
+                added,
+                changed,
+                removed,
+            });
+        }
+        for (file, error) in prepared.failed {
+            summary.files_failed += 1;
+            events.push(IndexEvent::FileFailed { file, error });
+        }
+
+        // Put back edges that references don't produce, then re-resolve
+        // edges: new nodes, plus anything elsewhere that referred to a
+        // name these files define (or used to).
+        graph.restore_edges(saved_edges);
+        let new_set: HashSet<NodeId> = new_nodes.iter().copied().collect();
+        let relinked: Vec<NodeId> = graph
+            .find_referencing(&touched_names)
+            .into_iter()
+            .filter(|idx| !new_set.contains(idx))
+            .collect();
+        for &idx in new_nodes.iter().chain(&relinked) {
+            dirty.extend(graph.relink(idx));
+            dirty.push(idx);
+        }
+
+        if !relinked.is_empty() {
+            summary.nodes_relinked = relinked.len();
+            events.push(IndexEvent::EdgesRelinked {
+                nodes: relinked
+                    .iter()
+                    .filter_map(|idx| graph.get(*idx))
+                    .map(|node| node.id.clone())
+                    .collect(),
+            });
+        }
+
+        self.refresh_centrality(graph, &dirty);
+        summary.clusters_changed = graph.refresh_clusters();
+
+        summary.duration_ms = started.elapsed().as_millis() as u64;
+        info!(
+            "Applied batch: {} indexed, {} removed, {} relinked in {}ms",
+            summary.files_indexed,
+            summary.files_removed,
+            summary.nodes_relinked,
+            summary.duration_ms
+        );
+
+        events.push(IndexEvent::BatchApplied(summary.clone()));
+        self.publish(events);
+
+        Ok(summary)
+    }
+
+    /// Parses and applies a batch in one step.
+    pub fn apply(
+        &mut self,
+        graph: &mut ArborGraph,
+        batch: &ChangeBatch,
+    ) -> Result<BatchSummary, IndexerError> {
+        let prepared = self.prepare(batch);
+        self.commit(graph, prepared)
+    }
+
+    fn refresh_centrality(&mut self, graph: &mut ArborGraph, dirty: &[NodeId]) {
+        self.centrality = if self.centrality.is_empty() {
+            compute_centrality(graph, CENTRALITY_ITERATIONS, CENTRALITY_DAMPING)
+        } else {
+            refresh_centrality(
+                graph,
+                &self.centrality,
+                dirty,
+                CENTRALITY_ITERATIONS,
+                CENTRALITY_DAMPING,
+            )
+        };
+        graph.set_centrality(self.centrality.scores().clone());
+    }
+
+    fn publish(&self, events: Vec<IndexEvent>) {
+        let Ok(mut subscribers) = self.subscribers.lock() else {
+            return;
+        };
+        subscribers.retain(|tx| events.iter().all(|event| tx.send(event.clone()).is_ok()));
+    }
+}
+
+/// Removes a file's nodes from the graph and returns them by ID.
+///
+/// Records their names and former callees so edges and centrality
+/// around them can be refreshed, and saves their non-call edges.
+fn take_file(
+    graph: &mut ArborGraph,
+    file: &str,
+    names: &mut HashSet<String>,
+    dirty: &mut Vec<NodeId>,
+    edges: &mut Vec<(String, String, Edge)>,
+) -> HashMap<String, CodeNode> {
+    let old: HashMap<String, CodeNode> = graph
+        .find_by_file(file)
+        .into_iter()
+        .map(|node| (node.id.clone(), node.clone()))
+        .collect();
+
+    for node in old.values() {
+        names.insert(node.name.clone());
+        names.insert(node.qualified_name.clone());
+        if let Some(idx) = graph.get_index(&node.id) {
+            dirty.extend(
+                graph
+                    .get_callees(idx)
+                    .iter()
+                    .filter(|callee| callee.file != file)
+                    .filter_map(|callee| graph.get_index(&callee.id)),
+            );
+        }
+    }
+
+    edges.extend(graph.file_edges(file));
+    graph.remove_file(file);
+    old
+}
+
+/// `CodeNode` equality only compares IDs; this compares everything.
+fn same_content(a: &CodeNode, b: &CodeNode) -> bool {
+    a.name == b.name
+        && a.qualified_name == b.qualified_name
+        && a.kind == b.kind
+        && a.line_start == b.line_start
+        && a.line_end == b.line_end
+        && a.column == b.column
+        && a.signature == b.signature
+        && a.visibility == b.visibility
+        && a.is_async == b.is_async
+        && a.is_static == b.is_static
+        && a.is_exported == b.is_exported
+        && a.docstring == b.docstring
+        && a.byte_start == b.byte_start
+        && a.byte_end == b.byte_end
+        && a.body_hash == b.body_hash
+        && a.references == b.references
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index_directory_with_config;
+    use arbor_core::NodeKind;
+    use arbor_graph::{BodyQuery, EdgeKind};
+    use std::fs;
+    use std::path::PathBuf;
+    use tempfile::tempdir;
+
+    fn setup() -> (tempfile::TempDir, PathBuf, ArborGraph, IncrementalIndexer) {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        fs::write(root.join("lib.py"), "def helper():\n    return 1\n").unwrap();
+        fs::write(root.join("main.py"), "def main():\n    helper()\n").unwrap();
+
+        let config = ArborConfig::default();
+        let graph = index_directory_with_config(&root, &config).unwrap().graph;
+        let indexer = IncrementalIndexer::new(&root, &config).unwrap();
+        (dir, root, graph, indexer)
+    }
+
+    #[test]
+    fn test_modify_relinks_callers_in_other_files() {
+        let (_dir, root, mut graph, mut indexer) = setup();
+        assert_eq!(graph.edge_count(), 1);
+
+        // Renaming the function breaks the call from main.py
+        fs::write(root.join("lib.py"), "def renamed():\n    return 1\n").unwrap();
+        let batch = ChangeBatch {
+            changes: vec![FileChange::Modified(root.join("lib.py"))],
+        };
+        let summary = indexer.apply(&mut graph, &batch).unwrap();
+        assert_eq!(summary.nodes_added, 1);
+        assert_eq!(summary.nodes_removed, 1);
+        assert_eq!(summary.nodes_relinked, 1);
+        assert_eq!(graph.edge_count(), 0);
+
+        // Restoring it brings the edge back without touching main.py
+        fs::write(root.join("lib.py"), "def helper():\n    return 2\n").unwrap();
+        indexer.apply(&mut graph, &batch).unwrap();
+        assert_eq!(graph.edge_count(), 1);
+        let main = graph.get_index(&graph.find_by_name("main")[0].id).unwrap();
+        assert_eq!(graph.get_callees(main)[0].name, "helper");
+    }
+
+    #[test]
+    fn test_reindex_keeps_edge_kinds_and_empties_files() {
+        let (_dir, root, _, mut indexer) = setup();
+        let mut graph = ArborGraph::new();
+        let main = graph.add_node(CodeNode {
+            references: vec!["helper".to_string()],
+            ..CodeNode::new("main", "main", NodeKind::Function, "main.py")
+        });
+        let helper = graph.add_node(CodeNode::new(
+            "helper",
+            "helper",
+            NodeKind::Function,
+            "lib.py",
+        ));
+        graph.add_edge(main, helper, Edge::new(EdgeKind::UsesType));
+
+        let batch = ChangeBatch {
+            changes: vec![FileChange::Modified(root.join("lib.py"))],
+        };
+        indexer.apply(&mut graph, &batch).unwrap();
+        let kinds: Vec<EdgeKind> = graph.export_edges().iter().map(|e| e.kind).collect();
+        assert_eq!(kinds, vec![EdgeKind::UsesType]);
+
+        // Emptying a file removes its nodes rather than failing
+        fs::write(root.join("lib.py"), "").unwrap();
+        let summary = indexer.apply(&mut graph, &batch).unwrap();
+        assert_eq!(summary.files_failed, 0);
+        assert!(graph.find_by_name("helper").is_empty());
+        assert_eq!(graph.edge_count(), 0);
+    }
+
+    #[test]
+    fn test_deleted_directory_removes_its_files() {
+        let (_dir, root, mut graph, mut indexer) = setup();
+        fs::create_dir(root.join("pkg")).unwrap();
+        fs::write(root.join("pkg/util.py"), "def util():\n    pass\n").unwrap();
+        let created = ChangeBatch {
+            changes: vec![FileChange::Created(root.join("pkg/util.py"))],
+        };
+        indexer.apply(&mut graph, &created).unwrap();
+        assert_eq!(graph.find_by_name("util").len(), 1);
+
+        fs::remove_dir_all(root.join("pkg")).unwrap();
+        let deleted = ChangeBatch {
+            changes: vec![FileChange::Deleted(root.join("pkg"))],
+        };
+        let summary = indexer.apply(&mut graph, &deleted).unwrap();
+        assert_eq!(summary.files_removed, 1);
+        assert!(graph.find_by_name("util").is_empty());
+        assert_eq!(graph.find_by_name("helper").len(), 1);
+    }
+
+    #[test]
+    fn test_rename_and_delete_update_store_and_events() {
+        let (dir, root, mut graph, indexer) = setup();
+        let store = GraphStore::open(dir.path().join("store")).unwrap();
+        let mut indexer = indexer.with_store(store);
+        indexer.seed_store(&graph).unwrap();
+        let events = indexer.subscribe();
+
+        fs::rename(root.join("lib.py"), root.join("util.py")).unwrap();
+        fs::remove_file(root.join("main.py")).unwrap();
+        let batch = ChangeBatch {
+            changes: vec![
+                FileChange::Renamed {
+                    from: root.join("lib.py"),
+                    to: root.join("util.py"),
+                },
+                FileChange::Deleted(root.join("main.py")),
+            ],
+        };
+        let summary = indexer.apply(&mut graph, &batch).unwrap();
+        assert_eq!(summary.files_indexed, 1);
+        assert_eq!(summary.files_removed, 2);
+
+        let mut files = indexer.store().unwrap().files().unwrap();
+        files.sort();
+        assert_eq!(files, vec!["util.py".to_string()]);
+        assert_eq!(graph.node_count(), 1);
+        assert_eq!(graph.find_by_name("helper")[0].file, "util.py");
+
+        let received: Vec<IndexEvent> = events.try_iter().collect();
+        assert!(received
+            .iter()
+            .any(|e| matches!(e, IndexEvent::FileRenamed { .. })));
+        assert!(matches!(
+            received.last(),
+            Some(IndexEvent::BatchApplied(s)) if s.files_indexed == 1
+        ));
+    }
+
+    #[test]
+    fn test_parse_failure_keeps_previous_nodes() {
+        let (_dir, root, mut graph, mut indexer) = setup();
+
+        // A directory where the file was can't be read
+        fs::remove_file(root.join("lib.py")).unwrap();
+        fs::create_dir(root.join("lib.py")).unwrap();
+        let batch = ChangeBatch {
+            changes: vec![FileChange::Modified(root.join("lib.py"))],
+        };
+        let summary = indexer.apply(&mut graph, &batch).unwrap();
+
+        assert_eq!(summary.files_failed, 1);
+        assert!(!graph.find_by_name("helper").is_empty());
+        assert_eq!(graph.edge_count(), 1);
+    }
+
+    #[test]
+    fn test_rename_into_unparseable_file_keeps_old_nodes() {
+        let (_dir, root, mut graph, mut indexer) = setup();
+
+        fs::remove_file(root.join("lib.py")).unwrap();
+        fs::create_dir(root.join("util.py")).unwrap();
+        let batch = ChangeBatch {
+            changes: vec![FileChange::Renamed {
+                from: root.join("lib.py"),
+                to: root.join("util.py"),
+            }],
+        };
+        let summary = indexer.apply(&mut graph, &batch).unwrap();
+
+        assert_eq!(summary.files_failed, 1);
+        assert_eq!(summary.files_removed, 0);
+        assert_eq!(graph.find_by_name("helper")[0].file, "lib.py");
+        assert_eq!(graph.edge_count(), 1);
+    }
+
+    #[test]
+    fn test_body_index_follows_edits() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        fs::write(root.join("lib.py"), "def helper():\n    return 'old'\n").unwrap();
+        let config = ArborConfig {
+            body_index: true,
+            ..ArborConfig::default()
+        };
+        let mut graph = index_directory_with_config(&root, &config).unwrap().graph;
+        let mut indexer = IncrementalIndexer::new(&root, &config).unwrap();
+        let search = |graph: &ArborGraph, text: &str| {
+            graph
+                .search_bodies(&BodyQuery::literal(text))
+                .unwrap()
+                .matches
+                .len()
+        };
+        assert_eq!(search(&graph, "old"), 1);
+
+        fs::write(root.join("lib.py"), "def helper():\n    return 'new'\n").unwrap();
+        let batch = ChangeBatch {
+            changes: vec![FileChange::Modified(root.join("lib.py"))],
+        };
+        indexer.apply(&mut graph, &batch).unwrap();
+        assert_eq!(search(&graph, "old"), 0);
+        assert_eq!(search(&graph, "new"), 1);
+
+        fs::remove_file(root.join("lib.py")).unwrap();
+        let batch = ChangeBatch {
+            changes: vec![FileChange::Deleted(root.join("lib.py"))],
+        };
+        indexer.apply(&mut graph, &batch).unwrap();
+        assert_eq!(search(&graph, "new"), 0);
+    }
+}
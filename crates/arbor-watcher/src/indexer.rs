@@ -3,11 +3,18 @@
 //! Walks directories to find and parse source files, building
 //! the initial code graph.
 
-use arbor_core::{parse_file, CodeNode};
+use crate::options::{IndexOptions, IndexPhase, IndexProgress};
+use arbor_core::{
+    parse_file, parse_source_with_timeout, ArborConfig, CodeNode, Encoding, FileFilter,
+    GeneratedPolicy, GeneratedReason, ParseError, Workspace, WorkspaceRoot,
+};
 use arbor_graph::{ArborGraph, GraphBuilder};
 use ignore::WalkBuilder;
-use std::path::Path;
-use std::time::Instant;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
 use tracing::{debug, info, warn};
 
 /// Result of indexing a directory.
@@ -26,12 +33,47 @@ pub struct IndexResult {
 
     /// Files that failed to parse.
     pub errors: Vec<(String, String)>,
+
+    /// Files deliberately left out (too large, generated), with the reason.
+    pub skipped: Vec<(String, String)>,
+
+    /// Files decoded from something other than plain UTF-8.
+    pub transcoded: Vec<TranscodedFile>,
+
+    /// Generated or minified files, whether they were skipped or tagged.
+    pub generated: Vec<(String, GeneratedReason)>,
+}
+
+/// A file that wasn't plain UTF-8 and was decoded anyway.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TranscodedFile {
+    pub path: String,
+    pub encoding: Encoding,
+    /// Some bytes couldn't be decoded and were replaced.
+    pub lossy: bool,
+}
+
+/// Side observations about a file, reported whatever the outcome.
+#[derive(Debug, Default)]
+pub(crate) struct FileNotes {
+    pub transcoded: Option<(Encoding, bool)>,
+    pub generated: Option<GeneratedReason>,
+    /// Decoded text of a parsed file, kept when the body index is on.
+    pub source: Option<String>,
+}
+
+/// What happened to a single file during indexing.
+pub(crate) enum FileOutcome {
+    Parsed(Vec<CodeNode>),
+    Skipped(String),
+    Failed(String),
 }
 
 /// Indexes a directory and returns the code graph.
 ///
 /// This walks all source files, parses them, and builds the
-/// relationship graph. It respects .gitignore patterns.
+/// relationship graph. It respects .gitignore patterns and the
+/// project's `.arbor/config.json`, if there is one.
 ///
 /// # Example
 ///
@@ -42,71 +84,399 @@ pub struct IndexResult {
 /// let result = index_directory(Path::new("./src")).unwrap();
 /// println!("Indexed {} files, {} nodes", result.files_indexed, result.nodes_extracted);
 /// ```
-pub fn index_directory(root: &Path) -> Result<IndexResult, std::io::Error> {
-    let start = Instant::now();
-    let mut builder = GraphBuilder::new();
-    let mut files_indexed = 0;
-    let mut nodes_extracted = 0;
-    let mut errors = Vec::new();
+pub fn index_directory(root: &Path) -> Result<IndexResult, io::Error> {
+    let config = ArborConfig::load(root).map_err(invalid_config)?;
+    index_directory_with_config(root, &config)
+}
 
+/// Indexes a directory using an already-loaded config.
+pub fn index_directory_with_config(
+    root: &Path,
+    config: &ArborConfig,
+) -> Result<IndexResult, io::Error> {
+    index_directory_with_options(root, config, &IndexOptions::default())
+}
+
+/// Indexes a directory with progress reporting, cancellation and limits.
+///
+/// Returns an `Interrupted` error if the options' token is cancelled.
+pub fn index_directory_with_options(
+    root: &Path,
+    config: &ArborConfig,
+    options: &IndexOptions,
+) -> Result<IndexResult, io::Error> {
+    let config = apply_overrides(config, options);
+    let filter = config.file_filter(root).map_err(invalid_config)?;
     info!("Starting index of {}", root.display());
+    index_filters(
+        vec![filter],
+        config.effective_threads(),
+        Vec::new(),
+        options,
+    )
+}
 
-    // Walk the directory, respecting .gitignore
-    let walker = WalkBuilder::new(root)
-        .hidden(true) // Skip hidden files
-        .git_ignore(true) // Respect .gitignore
-        .git_global(true)
-        .git_exclude(true)
-        .build();
+/// Indexes every root of a workspace into one graph.
+///
+/// Each root is walked with its own `.arbor/config.json`. File paths are
+/// prefixed with the root's namespace and nodes tagged with it. References
+/// resolve within a root first, then into the roots its `depends_on` lists.
+pub fn index_workspace(
+    workspace: &Workspace,
+    options: &IndexOptions,
+) -> Result<IndexResult, io::Error> {
+    let mut filters = Vec::with_capacity(workspace.roots.len());
+    let mut threads = 1;
+    for root in &workspace.roots {
+        let config = ArborConfig::load(&root.path).map_err(invalid_config)?;
+        let config = apply_overrides(&config, options);
+        threads = threads.max(config.effective_threads());
+        let filter = config
+            .file_filter(&root.path)
+            .map_err(invalid_config)?
+            .with_namespace(&root.namespace);
+        filters.push(filter);
+    }
+    info!("Starting index of {} workspace roots", filters.len());
+    index_filters(filters, threads, workspace.roots.clone(), options)
+}
 
-    for entry in walker.filter_map(Result::ok) {
-        let path = entry.path();
+pub(crate) fn apply_overrides(config: &ArborConfig, options: &IndexOptions) -> ArborConfig {
+    let mut config = config.clone();
+    if let Some(max_file_size) = options.max_file_size {
+        config.max_file_size = max_file_size;
+    }
+    config.body_index |= options.body_index;
+    config
+}
 
-        // Skip directories
-        if path.is_dir() {
-            continue;
-        }
+/// Parses the files of one or more roots into a single graph.
+fn index_filters(
+    filters: Vec<FileFilter>,
+    threads: usize,
+    roots: Vec<WorkspaceRoot>,
+    options: &IndexOptions,
+) -> Result<IndexResult, io::Error> {
+    let start = Instant::now();
+    options.report(IndexProgress {
+        phase: IndexPhase::Scanning,
+        files_processed: 0,
+        files_total: 0,
+        current_file: None,
+    });
 
-        // Check if it's a supported file type
-        let extension = match path.extension().and_then(|e| e.to_str()) {
-            Some(ext) => ext,
-            None => continue,
-        };
+    let files: Vec<(&FileFilter, PathBuf)> = filters
+        .iter()
+        .flat_map(|filter| {
+            collect_files(filter.project_root().path(), filter)
+                .into_iter()
+                .map(move |path| (filter, path))
+        })
+        .collect();
 
-        if !arbor_core::languages::is_supported(extension) {
-            continue;
-        }
+    let outcomes = parse_parallel(
+        &files,
+        threads,
+        options,
+        |(_, path)| path.clone(),
+        |(filter, path)| parse_with_filter(path, filter, options.parse_timeout),
+    )?;
+
+    let single_root = match filters.as_slice() {
+        [filter] if roots.is_empty() => Some(filter.project_root().path().to_path_buf()),
+        _ => None,
+    };
+    let parsed = files
+        .iter()
+        .zip(outcomes)
+        .map(|((filter, path), (outcome, notes))| (filter.stored_path(path), outcome, notes));
+    let mut result = assemble(parsed, roots, single_root, start, options);
+    for filter in &filters {
+        record_encoding(&mut result.graph, filter);
+    }
+    Ok(result)
+}
 
-        debug!("Parsing {}", path.display());
+/// Records how the filter's files were decoded, for reading them back.
+pub(crate) fn record_encoding(graph: &mut ArborGraph, filter: &FileFilter) {
+    match filter.namespace() {
+        Some(namespace) => graph.set_root_encoding(namespace, filter.encoding()),
+        None => graph.set_encoding(filter.encoding()),
+    }
+}
+
+/// Parses `items` on up to `threads` threads, reporting progress.
+///
+/// Results come back in input order so node indexes are stable from one
+/// run to the next. Returns an `Interrupted` error if cancelled.
+pub(crate) fn parse_parallel<T: Sync>(
+    items: &[T],
+    threads: usize,
+    options: &IndexOptions,
+    path_of: impl Fn(&T) -> PathBuf + Sync,
+    parse: impl Fn(&T) -> (FileOutcome, FileNotes) + Sync,
+) -> Result<Vec<(FileOutcome, FileNotes)>, io::Error> {
+    let files_total = items.len();
+    let threads = threads.clamp(1, items.len().max(1));
+    debug!("Parsing {} files on {} threads", items.len(), threads);
+
+    let processed = AtomicUsize::new(0);
+    let chunk_size = items.len().div_ceil(threads).max(1);
+    let outcomes: Vec<(FileOutcome, FileNotes)> = std::thread::scope(|scope| {
+        let handles: Vec<_> = items
+            .chunks(chunk_size)
+            .map(|chunk| {
+                let processed = &processed;
+                let path_of = &path_of;
+                let parse = &parse;
+                scope.spawn(move || {
+                    let mut outcomes = Vec::with_capacity(chunk.len());
+                    for item in chunk {
+                        if options.cancel.is_cancelled() {
+                            break;
+                        }
+                        outcomes.push(parse(item));
+                        options.report(IndexProgress {
+                            phase: IndexPhase::Parsing,
+                            files_processed: processed.fetch_add(1, Ordering::Relaxed) + 1,
+                            files_total,
+                            current_file: Some(path_of(item)),
+                        });
+                    }
+                    outcomes
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().expect("parser thread panicked"))
+            .collect()
+    });
 
-        match parse_file(path) {
-            Ok(nodes) => {
+    if options.cancel.is_cancelled() {
+        options.report(IndexProgress {
+            phase: IndexPhase::Cancelled,
+            files_processed: processed.load(Ordering::Relaxed),
+            files_total,
+            current_file: None,
+        });
+        return Err(io::Error::new(
+            io::ErrorKind::Interrupted,
+            "indexing cancelled",
+        ));
+    }
+    Ok(outcomes)
+}
+
+/// Builds the graph and tallies from per-file outcomes, keyed by stored path.
+pub(crate) fn assemble(
+    parsed: impl Iterator<Item = (String, FileOutcome, FileNotes)>,
+    roots: Vec<WorkspaceRoot>,
+    root: Option<PathBuf>,
+    start: Instant,
+    options: &IndexOptions,
+) -> IndexResult {
+    let mut builder = GraphBuilder::new();
+    let mut files_total = 0;
+    let mut files_indexed = 0;
+    let mut nodes_extracted = 0;
+    let mut errors = Vec::new();
+    let mut skipped = Vec::new();
+    let mut transcoded = Vec::new();
+    let mut generated = Vec::new();
+    let mut bodies = Vec::new();
+
+    for (stored, outcome, notes) in parsed {
+        files_total += 1;
+        if let Some((encoding, lossy)) = notes.transcoded {
+            transcoded.push(TranscodedFile {
+                path: stored.clone(),
+                encoding,
+                lossy,
+            });
+        }
+        if let Some(reason) = notes.generated {
+            generated.push((stored.clone(), reason));
+        }
+
+        match outcome {
+            FileOutcome::Parsed(nodes) => {
                 nodes_extracted += nodes.len();
                 files_indexed += 1;
                 builder.add_nodes(nodes);
+                if let Some(source) = notes.source {
+                    bodies.push((stored, source));
+                }
+            }
+            FileOutcome::Skipped(reason) => {
+                debug!("Skipping {}: {}", stored, reason);
+                skipped.push((stored, reason));
             }
-            Err(e) => {
-                warn!("Failed to parse {}: {}", path.display(), e);
-                errors.push((path.display().to_string(), e.to_string()));
+            FileOutcome::Failed(e) => {
+                warn!("Failed to parse {}: {}", stored, e);
+                errors.push((stored, e));
             }
         }
     }
 
-    let graph = builder.build();
+    options.report(IndexProgress {
+        phase: IndexPhase::Linking,
+        files_processed: files_total,
+        files_total,
+        current_file: None,
+    });
+    builder.set_roots(roots);
+    let mut graph = builder.build();
+    if let Some(root) = root {
+        graph.set_root(root);
+    }
+    for (file, source) in bodies {
+        graph.index_body(&file, source);
+    }
     let duration = start.elapsed();
 
     info!(
         "Indexed {} files ({} nodes) in {:?}",
         files_indexed, nodes_extracted, duration
     );
+    options.report(IndexProgress {
+        phase: IndexPhase::Done,
+        files_processed: files_total,
+        files_total,
+        current_file: None,
+    });
 
-    Ok(IndexResult {
+    IndexResult {
         graph,
         files_indexed,
         nodes_extracted,
         duration_ms: duration.as_millis() as u64,
         errors,
-    })
+        skipped,
+        transcoded,
+        generated,
+    }
+}
+
+/// Walks the tree and returns every file the config wants indexed.
+///
+/// Respects .gitignore and prunes ignored directories instead of
+/// descending into them.
+fn collect_files(root: &Path, filter: &FileFilter) -> Vec<PathBuf> {
+    let prune = filter.clone();
+    let walker = WalkBuilder::new(root)
+        .hidden(true) // Skip hidden files
+        .git_ignore(true) // Respect .gitignore
+        .git_global(true)
+        .git_exclude(true)
+        .require_git(false) // Honor .gitignore even outside a git checkout
+        .filter_entry(move |entry| !prune.is_ignored(entry.path()))
+        .build();
+
+    walker
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+        .map(|entry| entry.into_path())
+        .filter(|path| filter.should_index(path))
+        .collect()
+}
+
+/// Reads and parses one file, applying the size, encoding and
+/// generated-file rules.
+pub(crate) fn parse_with_filter(
+    path: &Path,
+    filter: &FileFilter,
+    timeout: Option<Duration>,
+) -> (FileOutcome, FileNotes) {
+    let notes = FileNotes::default();
+    match fs::metadata(path) {
+        Ok(meta) if filter.exceeds_size_limit(meta.len()) => {
+            let reason = format!("file too large ({} bytes)", meta.len());
+            return (FileOutcome::Skipped(reason), notes);
+        }
+        Ok(_) => {}
+        Err(e) => {
+            return (
+                FileOutcome::Failed(ParseError::io(path, e).to_string()),
+                notes,
+            )
+        }
+    }
+
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            return (
+                FileOutcome::Failed(ParseError::io(path, e).to_string()),
+                notes,
+            )
+        }
+    };
+
+    parse_bytes(path, &filter.stored_path(path), &bytes, filter, timeout)
+}
+
+/// Decodes and parses file contents already in memory.
+///
+/// `path` drives language and generated-file detection; `stored` is the
+/// path written into the nodes. Size limits are the caller's job.
+pub(crate) fn parse_bytes(
+    path: &Path,
+    stored: &str,
+    bytes: &[u8],
+    filter: &FileFilter,
+    timeout: Option<Duration>,
+) -> (FileOutcome, FileNotes) {
+    let mut notes = FileNotes::default();
+    let Some(parser) = filter.parser_for(path) else {
+        let e = ParseError::UnsupportedLanguage(path.to_path_buf());
+        return (FileOutcome::Failed(e.to_string()), notes);
+    };
+
+    let Some(decoded) = filter.decode(bytes) else {
+        return (FileOutcome::Skipped("not valid UTF-8".to_string()), notes);
+    };
+    if decoded.was_transcoded() {
+        debug!("Decoded {} as {}", stored, decoded.encoding);
+        notes.transcoded = Some((decoded.encoding, decoded.lossy));
+    }
+    let source = decoded.text;
+
+    // An emptied file has no symbols; its old nodes must go
+    if source.is_empty() {
+        return (FileOutcome::Parsed(Vec::new()), notes);
+    }
+
+    let generated = filter.generated_reason(path, &source);
+    if let Some(reason) = &generated {
+        notes.generated = Some(reason.clone());
+        if filter.generated_policy() == GeneratedPolicy::Skip {
+            return (FileOutcome::Skipped(reason.to_string()), notes);
+        }
+    }
+
+    debug!("Parsing {}", stored);
+
+    let outcome = match parse_source_with_timeout(&source, stored, parser.as_ref(), timeout) {
+        Ok(mut nodes) => {
+            let tag_generated =
+                generated.is_some() && filter.generated_policy() == GeneratedPolicy::Tag;
+            for node in &mut nodes {
+                node.generated = tag_generated;
+                node.root = filter.namespace().map(str::to_string);
+            }
+            if filter.body_index() {
+                notes.source = Some(source);
+            }
+            FileOutcome::Parsed(nodes)
+        }
+        Err(e) => FileOutcome::Failed(e.to_string()),
+    };
+    (outcome, notes)
+}
+
+fn invalid_config(e: arbor_core::ConfigError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
 }
 
 /// Parses a single file and returns its nodes.
@@ -118,6 +488,8 @@ pub fn parse_single_file(path: &Path) -> Result<Vec<CodeNode>, arbor_core::Parse
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::options::CancellationToken;
+    use arbor_core::EncodingPolicy;
     use std::fs;
     use tempfile::tempdir;
 
@@ -148,4 +520,186 @@ mod tests {
         assert_eq!(result.files_indexed, 1);
         assert!(result.nodes_extracted > 0);
     }
+
+    #[test]
+    fn test_index_respects_config() {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("vendor")).unwrap();
+        fs::write(dir.path().join("lib.rs"), "pub fn kept() {}").unwrap();
+        fs::write(dir.path().join("vendor/dep.rs"), "pub fn dropped() {}").unwrap();
+        fs::write(dir.path().join("script.py"), "def dropped():\n    pass\n").unwrap();
+        fs::write(
+            dir.path().join("gen.rs"),
+            "// @generated by build.rs\npub fn dropped() {}",
+        )
+        .unwrap();
+
+        let config = ArborConfig {
+            languages: vec!["rust".to_string()],
+            ignore: vec!["vendor".to_string()],
+            ..Default::default()
+        };
+        config.save(dir.path()).unwrap();
+
+        let result = index_directory(dir.path()).unwrap();
+        assert_eq!(result.files_indexed, 1);
+        assert!(!result.graph.find_by_name("kept").is_empty());
+        assert!(result.graph.find_by_name("dropped").is_empty());
+        assert_eq!(result.skipped.len(), 1);
+    }
+
+    #[test]
+    fn test_index_options_progress_and_cancel() {
+        use std::sync::{Arc, Mutex};
+
+        let dir = tempdir().unwrap();
+        for i in 0..4 {
+            fs::write(
+                dir.path().join(format!("m{}.py", i)),
+                "def f():\n    pass\n",
+            )
+            .unwrap();
+        }
+        fs::write(dir.path().join("big.py"), "x = 1\n".repeat(100)).unwrap();
+
+        let phases = Arc::new(Mutex::new(Vec::new()));
+        let sink = phases.clone();
+        let options =
+            IndexOptions::new()
+                .with_max_file_size(100)
+                .with_progress(move |p: &IndexProgress| {
+                    sink.lock().unwrap().push((p.phase, p.files_processed))
+                });
+        let result =
+            index_directory_with_options(dir.path(), &ArborConfig::default(), &options).unwrap();
+        assert_eq!(result.files_indexed, 4);
+        assert_eq!(result.skipped.len(), 1);
+
+        let phases = phases.lock().unwrap();
+        assert_eq!(phases.first(), Some(&(IndexPhase::Scanning, 0)));
+        assert_eq!(phases.last(), Some(&(IndexPhase::Done, 5)));
+        let parsed = phases
+            .iter()
+            .filter(|(p, _)| *p == IndexPhase::Parsing)
+            .count();
+        assert_eq!(parsed, 5);
+
+        let token = CancellationToken::new();
+        token.cancel();
+        let options = IndexOptions::new().with_cancellation(token);
+        let err = index_directory_with_options(dir.path(), &ArborConfig::default(), &options)
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::Interrupted);
+    }
+
+    #[test]
+    fn test_index_decodes_and_tags_generated() {
+        let dir = tempdir().unwrap();
+        // Latin-1 comment, and UTF-16 with a BOM
+        fs::write(
+            dir.path().join("latin.py"),
+            b"# caf\xE9\ndef latin():\n    pass\n",
+        )
+        .unwrap();
+        let mut utf16 = vec![0xFF, 0xFE];
+        utf16.extend(
+            "def wide():\n    pass\n"
+                .encode_utf16()
+                .flat_map(|u| u.to_le_bytes()),
+        );
+        fs::write(dir.path().join("wide.py"), utf16).unwrap();
+        fs::write(dir.path().join("app.min.js"), "function bundled(){}").unwrap();
+
+        let result = index_directory(dir.path()).unwrap();
+        assert!(!result.graph.find_by_name("latin").is_empty());
+        assert!(!result.graph.find_by_name("wide").is_empty());
+        assert_eq!(result.transcoded.len(), 2);
+        assert!(result.graph.find_by_name("bundled").is_empty());
+        assert_eq!(result.generated.len(), 1);
+
+        let config = ArborConfig {
+            generated: GeneratedPolicy::Tag,
+            ..Default::default()
+        };
+        let result = index_directory_with_config(dir.path(), &config).unwrap();
+        let bundled = result.graph.find_by_name("bundled");
+        assert!(bundled[0].generated);
+        assert!(!result.graph.find_by_name("latin")[0].generated);
+    }
+
+    #[test]
+    fn test_index_workspace_across_roots() {
+        let dir = tempdir().unwrap();
+        for (ns, body) in [
+            ("api", "def handler():\n    shared_helper()\n"),
+            ("lib", "def shared_helper():\n    return 1\n"),
+            ("tool", "def run():\n    shared_helper()\n"),
+        ] {
+            fs::create_dir_all(dir.path().join(ns)).unwrap();
+            fs::write(dir.path().join(ns).join("main.py"), body).unwrap();
+        }
+        fs::create_dir_all(dir.path().join(".arbor")).unwrap();
+        fs::write(
+            Workspace::path_for(dir.path()),
+            r#"{"roots": [
+                {"namespace": "api", "path": "api", "depends_on": ["lib"]},
+                {"namespace": "lib", "path": "lib"},
+                {"namespace": "tool", "path": "tool", "depends_on": []}
+            ]}"#,
+        )
+        .unwrap();
+
+        let workspace = Workspace::load(dir.path()).unwrap().unwrap();
+        let result = index_workspace(&workspace, &IndexOptions::default()).unwrap();
+        let graph = result.graph;
+        assert_eq!(result.files_indexed, 3);
+
+        // Same relative path in every root, but distinct files and IDs
+        let helper = &graph.find_by_name("shared_helper")[0];
+        assert_eq!(helper.file, "lib/main.py");
+        assert_eq!(helper.root.as_deref(), Some("lib"));
+        assert_eq!(graph.find_by_file("api/main.py").len(), 1);
+        assert_eq!(
+            graph.absolute_path("lib/main.py"),
+            workspace.roots[1].path.join("main.py")
+        );
+
+        // api may call into lib; tool has no dependencies
+        let callers = graph.get_callers(graph.get_index(&helper.id).unwrap());
+        let callers: Vec<_> = callers.iter().map(|n| n.name.as_str()).collect();
+        assert_eq!(callers, vec!["handler"]);
+        assert!(graph.find_node("run", Some("api")).is_none());
+        assert!(graph.find_node("run", Some("tool")).is_some());
+    }
+
+    #[test]
+    fn test_workspace_roots_keep_their_encoding() {
+        let dir = tempdir().unwrap();
+        for ns in ["app", "legacy"] {
+            fs::create_dir_all(dir.path().join(ns).join(".arbor")).unwrap();
+            fs::write(dir.path().join(ns).join("main.py"), "def f():\n    pass\n").unwrap();
+        }
+        fs::create_dir_all(dir.path().join(".arbor")).unwrap();
+        fs::write(
+            ArborConfig::path_for(&dir.path().join("legacy")),
+            r#"{"encoding": "strict"}"#,
+        )
+        .unwrap();
+        fs::write(
+            Workspace::path_for(dir.path()),
+            r#"{"roots": [
+                {"namespace": "app", "path": "app"},
+                {"namespace": "legacy", "path": "legacy"}
+            ]}"#,
+        )
+        .unwrap();
+
+        let workspace = Workspace::load(dir.path()).unwrap().unwrap();
+        let graph = index_workspace(&workspace, &IndexOptions::default())
+            .unwrap()
+            .graph;
+        assert_eq!(graph.encoding_for("app/main.py"), EncodingPolicy::Auto);
+        assert_eq!(graph.encoding_for("legacy/main.py"), EncodingPolicy::Strict);
+    }
 }
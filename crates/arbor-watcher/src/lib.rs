@@ -2,13 +2,30 @@
 //!
 //! This crate handles the file system side of things:
 //! - Walking directories to find source files
-//! - Watching for changes
-//! - Triggering incremental re-indexing
+//! - Watching for changes, debounced into batches
+//! - Applying changes to the graph and store incrementally
+//! - Indexing git revisions straight from the object database
 //!
-//! It respects .gitignore and other ignore patterns.
+//! It respects .gitignore and the project's `.arbor/config.json`.
 
+mod git;
+mod ignore_rules;
+mod incremental;
 mod indexer;
+mod options;
 mod watcher;
 
-pub use indexer::{index_directory, IndexResult};
-pub use watcher::{FileChange, FileWatcher};
+pub use git::{
+    index_revision, load_or_index_revision, GitError, GitRepo, RevisionIndex, StoredRevision,
+    TreeFile,
+};
+pub use ignore_rules::IgnoreRules;
+pub use incremental::{BatchSummary, IncrementalIndexer, IndexEvent, IndexerError, PreparedBatch};
+pub use indexer::{
+    index_directory, index_directory_with_config, index_directory_with_options, index_workspace,
+    IndexResult, TranscodedFile,
+};
+pub use options::{
+    CancellationToken, IndexOptions, IndexPhase, IndexProgress, ProgressSink, DEFAULT_PARSE_TIMEOUT,
+};
+pub use watcher::{ChangeBatch, FileChange, FileWatcher, DEFAULT_DEBOUNCE};
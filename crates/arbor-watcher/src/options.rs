@@ -0,0 +1,175 @@
+//! Knobs for a directory index: progress reporting, cancellation,
+//! parse timeouts and file size limits.
+
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Default time Tree-sitter gets per file before we give up on it.
+pub const DEFAULT_PARSE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where an index run currently is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexPhase {
+    /// Walking the tree to find files.
+    Scanning,
+    /// Parsing files.
+    Parsing,
+    /// Resolving references into edges.
+    Linking,
+    /// Finished.
+    Done,
+    /// Stopped by a cancellation token.
+    Cancelled,
+}
+
+impl IndexPhase {
+    /// Returns the phase name used in `IndexerStatus` messages.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Scanning => "scanning",
+            Self::Parsing => "parsing",
+            Self::Linking => "linking",
+            Self::Done => "done",
+            Self::Cancelled => "cancelled",
+        }
+    }
+}
+
+impl fmt::Display for IndexPhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A progress report.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexProgress {
+    pub phase: IndexPhase,
+    pub files_processed: usize,
+    pub files_total: usize,
+    /// The file just parsed, during `Parsing`.
+    pub current_file: Option<PathBuf>,
+}
+
+/// Receives progress reports. Called from the parser threads.
+pub trait ProgressSink: Send + Sync {
+    fn report(&self, progress: &IndexProgress);
+}
+
+impl<F> ProgressSink for F
+where
+    F: Fn(&IndexProgress) + Send + Sync,
+{
+    fn report(&self, progress: &IndexProgress) {
+        self(progress)
+    }
+}
+
+/// Cooperative cancellation for an index run.
+///
+/// Clones share state, so keep one and hand the other to the indexer.
+/// Parsing stops before the next file once it's cancelled.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Creates a token that isn't cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Asks the index run to stop.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns true once `cancel` has been called.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Options for `index_directory_with_options`.
+#[derive(Clone)]
+pub struct IndexOptions {
+    /// Receives progress reports.
+    pub progress: Option<Arc<dyn ProgressSink>>,
+    /// Stops the run early when cancelled.
+    pub cancel: CancellationToken,
+    /// Per-file Tree-sitter timeout. `None` waits forever.
+    pub parse_timeout: Option<Duration>,
+    /// Overrides the config's `max_file_size`.
+    pub max_file_size: Option<u64>,
+    /// Keeps file text for body search even if the config leaves it off.
+    pub body_index: bool,
+}
+
+impl Default for IndexOptions {
+    fn default() -> Self {
+        Self {
+            progress: None,
+            cancel: CancellationToken::new(),
+            parse_timeout: Some(DEFAULT_PARSE_TIMEOUT),
+            max_file_size: None,
+            body_index: false,
+        }
+    }
+}
+
+impl fmt::Debug for IndexOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IndexOptions")
+            .field("progress", &self.progress.is_some())
+            .field("cancel", &self.cancel)
+            .field("parse_timeout", &self.parse_timeout)
+            .field("max_file_size", &self.max_file_size)
+            .field("body_index", &self.body_index)
+            .finish()
+    }
+}
+
+impl IndexOptions {
+    /// Creates the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sends progress reports to `sink`.
+    pub fn with_progress(mut self, sink: impl ProgressSink + 'static) -> Self {
+        self.progress = Some(Arc::new(sink));
+        self
+    }
+
+    /// Stops the run when `token` is cancelled.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancel = token;
+        self
+    }
+
+    /// Sets the per-file parse timeout.
+    pub fn with_parse_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.parse_timeout = timeout;
+        self
+    }
+
+    /// Skips files larger than `bytes`, whatever the config says.
+    pub fn with_max_file_size(mut self, bytes: u64) -> Self {
+        self.max_file_size = Some(bytes);
+        self
+    }
+
+    /// Keeps file text for body search, whatever the config says.
+    pub fn with_body_index(mut self) -> Self {
+        self.body_index = true;
+        self
+    }
+
+    pub(crate) fn report(&self, progress: IndexProgress) {
+        if let Some(sink) = &self.progress {
+            sink.report(&progress);
+        }
+    }
+}
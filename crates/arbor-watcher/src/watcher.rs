@@ -1,96 +1,383 @@
 //! File watcher for real-time updates.
 //!
-//! Uses the notify crate to watch for file changes and trigger
-//! incremental re-indexing.
+//! Uses notify's full debouncer to collect file system events, then
+//! coalesces each debounce window into a single [`ChangeBatch`]. Renames
+//! are reported as renames rather than a delete plus a create, and the
+//! same ignore rules as `index_directory` keep build output out.
 
-use notify::{Event, RecursiveMode, Watcher};
+use crate::ignore_rules::IgnoreRules;
+use arbor_core::ArborConfig;
+use notify::event::{ModifyKind, RenameMode};
+use notify::{EventKind, RecursiveMode, Watcher};
+use notify_debouncer_full::{new_debouncer, DebounceEventResult, Debouncer, FileIdMap};
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::mpsc::{channel, Receiver};
+use std::sync::{Arc, RwLock};
 use std::time::Duration;
 use tracing::{debug, info, warn};
+use walkdir::WalkDir;
+
+/// Default quiet period before a batch of changes is emitted.
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(200);
 
 /// Type of file change detected.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum FileChange {
     Created(PathBuf),
     Modified(PathBuf),
+    /// A file, or a directory and everything indexed under it.
     Deleted(PathBuf),
+    /// A file moved. Both paths are inside the watched tree.
+    Renamed {
+        from: PathBuf,
+        to: PathBuf,
+    },
+}
+
+impl FileChange {
+    /// Returns the path the file has after the change.
+    pub fn path(&self) -> &Path {
+        match self {
+            Self::Created(p) | Self::Modified(p) | Self::Deleted(p) => p,
+            Self::Renamed { to, .. } => to,
+        }
+    }
+}
+
+/// All changes from one debounce window, coalesced per path.
+///
+/// Within a batch each path appears at most once, so consumers can
+/// apply the changes in any order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChangeBatch {
+    pub changes: Vec<FileChange>,
+}
+
+impl ChangeBatch {
+    /// Returns true if the batch has no changes.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Returns the number of changes.
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
 }
 
 /// Watches a directory for file changes.
 pub struct FileWatcher {
-    #[allow(dead_code)]
-    watcher: notify::RecommendedWatcher,
-    receiver: Receiver<FileChange>,
+    _debouncer: Debouncer<notify::RecommendedWatcher, FileIdMap>,
+    receiver: Receiver<ChangeBatch>,
 }
 
 impl FileWatcher {
     /// Creates a new file watcher for the given directory.
     ///
-    /// Returns a watcher that produces FileChange events when
-    /// source files are modified.
+    /// Returns a watcher that produces change batches when source
+    /// files are modified. Which files count is decided by the same
+    /// rules `index_directory` uses.
     pub fn new(root: &Path) -> Result<Self, notify::Error> {
+        let config = ArborConfig::load(root).map_err(|e| notify::Error::generic(&e.to_string()))?;
+        Self::with_config(root, &config, DEFAULT_DEBOUNCE)
+    }
+
+    /// Creates a file watcher using an already-loaded config.
+    pub fn with_config(
+        root: &Path,
+        config: &ArborConfig,
+        debounce: Duration,
+    ) -> Result<Self, notify::Error> {
+        let rules =
+            IgnoreRules::new(root, config).map_err(|e| notify::Error::generic(&e.to_string()))?;
+        let rules = Arc::new(RwLock::new(rules));
         let (tx, rx) = channel();
 
-        let mut watcher = notify::recommended_watcher(move |res: Result<Event, notify::Error>| {
+        let handler_rules = rules.clone();
+        let mut debouncer = new_debouncer(debounce, None, move |res: DebounceEventResult| {
             match res {
-                Ok(event) => {
-                    for path in event.paths {
-                        // Only care about supported source files
-                        let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+                Ok(events) => {
+                    let raw: Vec<FileChange> = events
+                        .iter()
+                        .flat_map(|event| to_raw_changes(&event.kind, &event.paths))
+                        .collect();
 
-                        if !arbor_core::languages::is_supported(ext) {
-                            continue;
+                    // A changed .gitignore changes what we should report
+                    if raw.iter().any(|c| is_gitignore(c.path())) {
+                        if let Ok(mut rules) = handler_rules.write() {
+                            debug!("Reloading gitignore rules");
+                            rules.reload_gitignores();
                         }
+                    }
+
+                    let batch = match handler_rules.read() {
+                        Ok(rules) => coalesce(raw, &rules),
+                        Err(_) => return,
+                    };
 
-                        let change = match event.kind {
-                            notify::EventKind::Create(_) => {
-                                debug!("File created: {}", path.display());
-                                Some(FileChange::Created(path))
-                            }
-                            notify::EventKind::Modify(_) => {
-                                debug!("File modified: {}", path.display());
-                                Some(FileChange::Modified(path))
-                            }
-                            notify::EventKind::Remove(_) => {
-                                debug!("File deleted: {}", path.display());
-                                Some(FileChange::Deleted(path))
-                            }
-                            _ => None,
-                        };
-
-                        if let Some(change) = change {
-                            if tx.send(change).is_err() {
-                                warn!("Failed to send file change event");
-                            }
+                    if !batch.is_empty() {
+                        debug!("Emitting batch of {} changes", batch.len());
+                        if tx.send(batch).is_err() {
+                            warn!("Failed to send file change batch");
                         }
                     }
                 }
-                Err(e) => warn!("Watch error: {}", e),
+                Err(errors) => {
+                    for e in errors {
+                        warn!("Watch error: {}", e);
+                    }
+                }
             }
         })?;
 
-        watcher.watch(root, RecursiveMode::Recursive)?;
+        let watch_root = rules
+            .read()
+            .map(|r| r.root().to_path_buf())
+            .unwrap_or_else(|_| root.to_path_buf());
+        debouncer
+            .watcher()
+            .watch(&watch_root, RecursiveMode::Recursive)?;
+        debouncer
+            .cache()
+            .add_root(&watch_root, RecursiveMode::Recursive);
 
         info!("Watching {} for changes", root.display());
 
         Ok(Self {
-            watcher,
+            _debouncer: debouncer,
             receiver: rx,
         })
     }
 
     /// Polls for file changes.
     ///
-    /// Returns immediately with any pending changes.
+    /// Returns immediately with the changes from every pending batch.
     pub fn poll(&self) -> Vec<FileChange> {
-        self.receiver.try_iter().collect()
+        self.receiver
+            .try_iter()
+            .flat_map(|batch| batch.changes)
+            .collect()
     }
 
-    /// Waits for the next file change with a timeout.
-    pub fn recv_timeout(&self, timeout: Duration) -> Option<FileChange> {
+    /// Waits for the next batch of changes with a timeout.
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<ChangeBatch> {
         self.receiver.recv_timeout(timeout).ok()
     }
+
+    /// Waits for the next batch of changes.
+    ///
+    /// Returns None once the watcher has shut down.
+    pub fn recv(&self) -> Option<ChangeBatch> {
+        self.receiver.recv().ok()
+    }
+}
+
+fn is_gitignore(path: &Path) -> bool {
+    path.file_name().is_some_and(|n| n == ".gitignore")
+}
+
+/// Translates one notify event into raw changes, before filtering.
+fn to_raw_changes(kind: &EventKind, paths: &[PathBuf]) -> Vec<FileChange> {
+    match kind {
+        EventKind::Create(_) => paths.iter().cloned().map(FileChange::Created).collect(),
+        EventKind::Remove(_) => paths.iter().cloned().map(FileChange::Deleted).collect(),
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if paths.len() == 2 => {
+            vec![FileChange::Renamed {
+                from: paths[0].clone(),
+                to: paths[1].clone(),
+            }]
+        }
+        EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+            paths.iter().cloned().map(FileChange::Deleted).collect()
+        }
+        EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+            paths.iter().cloned().map(FileChange::Created).collect()
+        }
+        EventKind::Modify(ModifyKind::Name(_)) => paths
+            .iter()
+            .map(|p| {
+                if p.exists() {
+                    FileChange::Created(p.clone())
+                } else {
+                    FileChange::Deleted(p.clone())
+                }
+            })
+            .collect(),
+        EventKind::Modify(ModifyKind::Metadata(_)) => Vec::new(),
+        EventKind::Modify(_) => paths.iter().cloned().map(FileChange::Modified).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// The net effect of all events on one path within a window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NetChange {
+    Created,
+    Modified,
+    Deleted,
+}
+
+/// Folds a window of raw changes into one change per path.
+///
+/// Renames are followed through the window, so `a → b → c` becomes a
+/// single `a → c`, and a file created then deleted disappears entirely.
+/// Ignore rules are applied last: a rename from an ignored path into
+/// the project is reported as a create, and the reverse as a delete.
+/// A deleted directory is reported as a delete of the directory itself.
+fn coalesce(raw: Vec<FileChange>, rules: &IgnoreRules) -> ChangeBatch {
+    let raw = expand_directories(raw, rules);
+    let mut net: HashMap<PathBuf, NetChange> = HashMap::new();
+    // Maps current path → original path for files moved in this window.
+    let mut moved: HashMap<PathBuf, PathBuf> = HashMap::new();
+    let mut order: Vec<PathBuf> = Vec::new();
+
+    fn touch(path: &PathBuf, order: &mut Vec<PathBuf>) {
+        if !order.contains(path) {
+            order.push(path.clone());
+        }
+    }
+
+    for change in raw {
+        match change {
+            FileChange::Created(path) => {
+                touch(&path, &mut order);
+                let next = match net.get(&path) {
+                    Some(NetChange::Deleted) => NetChange::Modified,
+                    Some(existing) => *existing,
+                    None => NetChange::Created,
+                };
+                net.insert(path, next);
+            }
+            FileChange::Modified(path) => {
+                touch(&path, &mut order);
+                net.entry(path).or_insert(NetChange::Modified);
+            }
+            FileChange::Deleted(path) => {
+                touch(&path, &mut order);
+                if let Some(origin) = moved.remove(&path) {
+                    // Moved then deleted: the original is what's gone.
+                    touch(&origin, &mut order);
+                    net.insert(origin, NetChange::Deleted);
+                    net.remove(&path);
+                    continue;
+                }
+                match net.get(&path) {
+                    Some(NetChange::Created) => {
+                        net.remove(&path);
+                    }
+                    _ => {
+                        net.insert(path, NetChange::Deleted);
+                    }
+                }
+            }
+            FileChange::Renamed { from, to } => {
+                touch(&to, &mut order);
+                let origin = moved.remove(&from).unwrap_or_else(|| from.clone());
+                let from_state = net.remove(&from);
+                if from_state == Some(NetChange::Created) {
+                    // Created in this window, so it's simply a new file at `to`.
+                    net.insert(to, NetChange::Created);
+                } else {
+                    net.remove(&to);
+                    moved.insert(to, origin);
+                }
+            }
+        }
+    }
+
+    let mut changes = Vec::new();
+    for path in order {
+        if let Some(origin) = moved.get(&path) {
+            let keep_from = rules.should_index(origin);
+            let keep_to = rules.should_index(&path);
+            match (keep_from, keep_to) {
+                (true, true) if origin != &path => changes.push(FileChange::Renamed {
+                    from: origin.clone(),
+                    to: path.clone(),
+                }),
+                (true, true) => changes.push(FileChange::Modified(path.clone())),
+                (false, true) => changes.push(FileChange::Created(path.clone())),
+                (true, false) => changes.push(FileChange::Deleted(origin.clone())),
+                // A directory that moved on again before we could list it
+                (false, false) if may_be_source_dir(origin, rules) => {
+                    changes.push(FileChange::Deleted(origin.clone()))
+                }
+                (false, false) => {}
+            }
+            continue;
+        }
+
+        let Some(state) = net.get(&path) else {
+            continue;
+        };
+        let keep = match state {
+            NetChange::Deleted => rules.should_index(&path) || may_be_source_dir(&path, rules),
+            _ => rules.should_index(&path),
+        };
+        if !keep {
+            continue;
+        }
+        changes.push(match state {
+            NetChange::Created => FileChange::Created(path),
+            NetChange::Modified => FileChange::Modified(path),
+            NetChange::Deleted => FileChange::Deleted(path),
+        });
+    }
+
+    ChangeBatch { changes }
+}
+
+/// Replaces directory creates and renames with one change per source
+/// file inside, since notify reports nothing for a moved directory's
+/// contents. Deletes are left alone: there's nothing left to list.
+fn expand_directories(raw: Vec<FileChange>, rules: &IgnoreRules) -> Vec<FileChange> {
+    let mut expanded = Vec::with_capacity(raw.len());
+    for change in raw {
+        match change {
+            FileChange::Created(dir) if dir.is_dir() => {
+                expanded.extend(
+                    source_files(&dir, rules)
+                        .into_iter()
+                        .map(FileChange::Created),
+                );
+            }
+            FileChange::Renamed { from, to } if to.is_dir() => {
+                if rules.is_ignored(&to, true) {
+                    expanded.push(FileChange::Deleted(from));
+                    continue;
+                }
+                for file in source_files(&to, rules) {
+                    let Ok(rel) = file.strip_prefix(&to) else {
+                        continue;
+                    };
+                    expanded.push(FileChange::Renamed {
+                        from: from.join(rel),
+                        to: file,
+                    });
+                }
+            }
+            other => expanded.push(other),
+        }
+    }
+    expanded
+}
+
+/// Lists the files under a directory that should be indexed.
+fn source_files(dir: &Path, rules: &IgnoreRules) -> Vec<PathBuf> {
+    WalkDir::new(dir)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|e| e.depth() == 0 || !rules.is_ignored(e.path(), e.file_type().is_dir()))
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_file() && rules.should_index(e.path()))
+        .map(|e| e.into_path())
+        .collect()
+}
+
+/// Whether a path that's gone could have been a directory of sources.
+fn may_be_source_dir(path: &Path, rules: &IgnoreRules) -> bool {
+    rules.filter().language_for(path).is_none() && !rules.is_ignored(path, true)
 }
 
 #[cfg(test)]
@@ -99,6 +386,10 @@ mod tests {
     use std::fs;
     use tempfile::tempdir;
 
+    fn rules_for(root: &Path) -> IgnoreRules {
+        IgnoreRules::new(root, &ArborConfig::default()).unwrap()
+    }
+
     #[test]
     fn test_watcher_creation() {
         let dir = tempdir().unwrap();
@@ -109,7 +400,12 @@ mod tests {
     #[test]
     fn test_watcher_detects_change() {
         let dir = tempdir().unwrap();
-        let watcher = FileWatcher::new(dir.path()).unwrap();
+        let watcher = FileWatcher::with_config(
+            dir.path(),
+            &ArborConfig::default(),
+            Duration::from_millis(50),
+        )
+        .unwrap();
 
         // Create a file
         let file_path = dir.path().join("test.rs");
@@ -132,4 +428,154 @@ mod tests {
             eprintln!("Warning: File change not detected - may be unsupported environment");
         }
     }
+
+    #[test]
+    fn test_coalesce_create_then_modify() {
+        let dir = tempdir().unwrap();
+        let rules = rules_for(dir.path());
+        let a = rules.root().join("a.rs");
+
+        let batch = coalesce(
+            vec![
+                FileChange::Created(a.clone()),
+                FileChange::Modified(a.clone()),
+                FileChange::Modified(a.clone()),
+            ],
+            &rules,
+        );
+        assert_eq!(batch.changes, vec![FileChange::Created(a)]);
+    }
+
+    #[test]
+    fn test_coalesce_create_then_delete_vanishes() {
+        let dir = tempdir().unwrap();
+        let rules = rules_for(dir.path());
+        let a = rules.root().join("a.rs");
+
+        let batch = coalesce(
+            vec![FileChange::Created(a.clone()), FileChange::Deleted(a)],
+            &rules,
+        );
+        assert!(batch.is_empty());
+    }
+
+    #[test]
+    fn test_coalesce_delete_then_create_is_modify() {
+        // Editors that save via write-to-temp-and-replace look like this
+        let dir = tempdir().unwrap();
+        let rules = rules_for(dir.path());
+        let a = rules.root().join("a.rs");
+
+        let batch = coalesce(
+            vec![
+                FileChange::Deleted(a.clone()),
+                FileChange::Created(a.clone()),
+            ],
+            &rules,
+        );
+        assert_eq!(batch.changes, vec![FileChange::Modified(a)]);
+    }
+
+    #[test]
+    fn test_coalesce_rename_chain() {
+        let dir = tempdir().unwrap();
+        let rules = rules_for(dir.path());
+        let a = rules.root().join("a.rs");
+        let b = rules.root().join("b.rs");
+        let c = rules.root().join("c.rs");
+
+        let batch = coalesce(
+            vec![
+                FileChange::Renamed {
+                    from: a.clone(),
+                    to: b.clone(),
+                },
+                FileChange::Modified(b.clone()),
+                FileChange::Renamed {
+                    from: b,
+                    to: c.clone(),
+                },
+            ],
+            &rules,
+        );
+        assert_eq!(batch.changes, vec![FileChange::Renamed { from: a, to: c }]);
+    }
+
+    #[test]
+    fn test_coalesce_rename_across_ignore_boundary() {
+        let dir = tempdir().unwrap();
+        let rules = rules_for(dir.path());
+        let src = rules.root().join("src/lib.rs");
+        let built = rules.root().join("target/lib.rs");
+
+        let into_project = coalesce(
+            vec![FileChange::Renamed {
+                from: built.clone(),
+                to: src.clone(),
+            }],
+            &rules,
+        );
+        assert_eq!(into_project.changes, vec![FileChange::Created(src.clone())]);
+
+        let out_of_project = coalesce(
+            vec![FileChange::Renamed {
+                from: src.clone(),
+                to: built,
+            }],
+            &rules,
+        );
+        assert_eq!(out_of_project.changes, vec![FileChange::Deleted(src)]);
+    }
+
+    #[test]
+    fn test_coalesce_directory_rename_and_delete() {
+        let dir = tempdir().unwrap();
+        let rules = rules_for(dir.path());
+        let root = rules.root().to_path_buf();
+        fs::create_dir_all(root.join("core/sub")).unwrap();
+        fs::write(root.join("core/a.rs"), "fn a() {}").unwrap();
+        fs::write(root.join("core/sub/b.rs"), "fn b() {}").unwrap();
+        fs::write(root.join("core/notes.txt"), "").unwrap();
+
+        // `src` was renamed to `core` on disk
+        let renamed = coalesce(
+            vec![FileChange::Renamed {
+                from: root.join("src"),
+                to: root.join("core"),
+            }],
+            &rules,
+        );
+        assert_eq!(
+            renamed.changes,
+            vec![
+                FileChange::Renamed {
+                    from: root.join("src/a.rs"),
+                    to: root.join("core/a.rs"),
+                },
+                FileChange::Renamed {
+                    from: root.join("src/sub/b.rs"),
+                    to: root.join("core/sub/b.rs"),
+                },
+            ]
+        );
+
+        let deleted = coalesce(vec![FileChange::Deleted(root.join("old"))], &rules);
+        assert_eq!(deleted.changes, vec![FileChange::Deleted(root.join("old"))]);
+    }
+
+    #[test]
+    fn test_coalesce_drops_ignored_paths() {
+        let dir = tempdir().unwrap();
+        let rules = rules_for(dir.path());
+
+        let batch = coalesce(
+            vec![
+                FileChange::Modified(rules.root().join("node_modules/x/index.js")),
+                FileChange::Modified(rules.root().join("README.md")),
+                FileChange::Modified(rules.root().join(".git/index")),
+            ],
+            &rules,
+        );
+        assert!(batch.is_empty());
+    }
 }
@@ -9,6 +9,11 @@ let spotlightDecorationType: vscode.TextEditorDecorationType;
 let currentSpotlightFile: string | null = null;
 let currentSpotlightLine: number | null = null;
 
+// Project root announced by the server; node paths are relative to it.
+// Workspace graphs list several roots, each mounted at its namespace.
+let projectRoot: string | null = null;
+let workspaceRoots: { namespace: string; path: string }[] = [];
+
 export function activate(context: vscode.ExtensionContext) {
     console.log('Arbor extension activated');
 
@@ -90,7 +95,10 @@ function connectToServer() {
 }
 
 function handleServerMessage(message: any) {
-    if (message.type === 'FocusNode') {
+    if (message.type === 'Hello') {
+        projectRoot = message.payload?.root ?? null;
+        workspaceRoots = message.payload?.roots ?? [];
+    } else if (message.type === 'FocusNode') {
         const payload = message.payload;
         if (payload.file && payload.line !== undefined) {
             highlightSpotlight(payload.file, payload.line);
@@ -106,11 +114,21 @@ async function highlightSpotlight(filePath: string, line: number) {
     const workspaceFolders = vscode.workspace.workspaceFolders;
     if (!workspaceFolders) return;
 
-    // Search for the file in workspace
-    const files = await vscode.workspace.findFiles(`**/${filePath.split(/[\\/]/).pop()}`);
-    if (files.length === 0) return;
+    let uri: vscode.Uri;
+    const workspaceRoot = workspaceRoots.find(r => filePath.startsWith(`${r.namespace}/`));
+    if (workspaceRoot) {
+        const relative = filePath.slice(workspaceRoot.namespace.length + 1);
+        uri = vscode.Uri.joinPath(vscode.Uri.file(workspaceRoot.path), relative);
+    } else if (projectRoot) {
+        uri = vscode.Uri.joinPath(vscode.Uri.file(projectRoot), filePath);
+    } else {
+        // Older servers don't send a root; search for the file in workspace
+        const files = await vscode.workspace.findFiles(`**/${filePath.split(/[\\/]/).pop()}`);
+        if (files.length === 0) return;
+        uri = files[0];
+    }
 
-    const document = await vscode.workspace.openTextDocument(files[0]);
+    const document = await vscode.workspace.openTextDocument(uri);
     const editor = await vscode.window.showTextDocument(document, { preview: true });
 
     // Highlight the line
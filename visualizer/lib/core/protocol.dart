@@ -38,10 +38,21 @@ class Hello extends BroadcastMessage {
   final int nodeCount;
   final int edgeCount;
 
+  /// Absolute project root; node file paths are relative to it.
+  final String? root;
+
+  /// Workspace roots by namespace, for multi-root graphs.
+  final Map<String, String> roots;
+
   Hello(Map<String, dynamic> json)
       : version = json['version'] as String,
         nodeCount = json['node_count'] as int,
         edgeCount = json['edge_count'] as int,
+        root = json['root'] as String?,
+        roots = {
+          for (final r in (json['roots'] as List<dynamic>? ?? const []))
+            r['namespace'] as String: r['path'] as String,
+        },
         super('Hello');
 }
 